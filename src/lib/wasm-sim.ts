@@ -5,6 +5,65 @@ import type { SimulationParams, SimulationState } from './precise-simulation';
 
 // Newer wasm-bindgen glue prefers an options object: { module_or_path: URL | Request | ... }
 type WasmInitFn = (options?: { module_or_path?: RequestInfo | URL | Response | BufferSource | WebAssembly.Module }) => Promise<unknown>;
+// Settings of the fit exports, read once per call (FitOptions in
+// wasm/src/wasm.rs); unset keys take the Rust defaults
+export type WasmFitOptions = {
+  interp_code?: number; // 0: linear, 1: PCHIP, 2: exact
+  loss_code?: number; // 0: sse, 1: Gaussian, 2: Poisson, 3: Huber, 4: soft-L1
+  loss_scale?: number;
+  prior_mean?: Float64Array | number[];
+  prior_sd?: Float64Array | number[];
+  constraints?: Float64Array | number[]; // rows of [kind, a, b, value]
+  rate_space?: number; // 0: rates, 1: macroscopic, 2: macroscopic with Keq
+  max_iter?: number;
+  tol?: number;
+  scale?: number;
+  x_tol?: number;
+  max_evals?: number;
+  progress_every?: number;
+  trace?: boolean;
+  crn_seed?: number;
+  warm_start?: Float64Array;
+  max_restarts?: number;
+  adaptive?: boolean;
+  oriented_restarts?: boolean;
+  outlier_threshold?: number;
+  outlier_rounds?: number;
+  lower?: Float64Array | number[]; // over the 17 fit parameters, NaN where unset
+  upper?: Float64Array | number[];
+  n_starts?: number;
+  n_polish?: number;
+  memory?: number;
+  population?: number;
+  generations?: number;
+  sigma?: number;
+  step?: number;
+  n_samples?: number;
+  burn_in?: number;
+  thin?: number;
+  n_chains?: number;
+};
+
+// Called as (iteration, objective, params); return true to stop the fit
+type WasmFitProgress = (iteration: number, objective: number, params: Float64Array) => boolean | void;
+
+// fit_auto, fit_surrogate, fit_powell and fit_lbfgs share fit_nelder_mead's arguments
+type WasmFitFn = (
+  e: number,
+  es: number,
+  ep: number,
+  s: number,
+  p: number,
+  tiempo: number,
+  params_in: Float64Array,
+  mask: Uint8Array,
+  times: Float64Array,
+  y_obs: Float64Array,
+  species: string | number,
+  options?: WasmFitOptions,
+  progress?: WasmFitProgress
+) => Float64Array;
+
 interface WasmModuleShape {
   default?: WasmInitFn;
   init?: WasmInitFn;
@@ -62,7 +121,15 @@ interface WasmModuleShape {
     dt: number,
     times: Float64Array,
     y_obs: Float64Array,
-    species_code: number
+    species: string | number,
+    interp_code: number,
+    t_shift: number,
+    signal_scale: number,
+    signal_offset: number,
+    drift: number,
+    drift_decay: number,
+    loss_code: number,
+    loss_scale: number
   ) => number;
   fit_nelder_mead?: (
     e: number,
@@ -71,16 +138,77 @@ interface WasmModuleShape {
     s: number,
     p: number,
     tiempo: number,
-    ns: number,
-    np: number,
-    params_in: Float64Array, // [k1,k-3,k-1,k2,k-2,k3,dt]
+    params_in: Float64Array, // [k1,k-3,k-1,k2,k-2,k3,dt], optionally the rest of the 17 fit parameters
     mask: Uint8Array, // 1 => optimize
     times: Float64Array,
     y_obs: Float64Array,
-    species_code: number,
-    max_iter: number,
-    tol: number,
-    scale: number
+    species: string | number,
+    options?: WasmFitOptions,
+    progress?: WasmFitProgress
+  ) => Float64Array;
+  fit_begin?: (
+    e: number,
+    es: number,
+    ep: number,
+    s: number,
+    p: number,
+    tiempo: number,
+    params_in: Float64Array,
+    mask: Uint8Array,
+    times: Float64Array,
+    y_obs: Float64Array,
+    species: string | number,
+    options?: WasmFitOptions
+  ) => unknown; // FitHandle, passed to fit_step and consumed by fit_finish
+  fit_step?: (handle: unknown, n_iters: number) => Float64Array;
+  fit_finish?: (handle: unknown) => Float64Array;
+  fit_auto?: WasmFitFn;
+  fit_surrogate?: WasmFitFn;
+  fit_powell?: WasmFitFn;
+  fit_lbfgs?: WasmFitFn;
+  fit_pareto?: (
+    e: number,
+    es: number,
+    ep: number,
+    s: number,
+    p: number,
+    tiempo: number,
+    params_in: Float64Array,
+    mask: Uint8Array,
+    times: Float64Array, // the datasets back to back
+    y_obs: Float64Array,
+    lengths: Uint32Array, // points per dataset
+    species: (string | number)[], // observable per dataset
+    options?: WasmFitOptions,
+    progress?: (generation: number, frontSize: number) => boolean | void
+  ) => Float64Array;
+  fit_report_json?: (
+    fit_output: Float64Array,
+    e: number,
+    es: number,
+    ep: number,
+    s: number,
+    p: number,
+    tiempo: number,
+    mask: Uint8Array,
+    times: Float64Array,
+    y_obs: Float64Array,
+    species: string | number,
+    options?: WasmFitOptions
+  ) => string;
+  mcmc_sample?: (
+    e: number,
+    es: number,
+    ep: number,
+    s: number,
+    p: number,
+    tiempo: number,
+    params_in: Float64Array,
+    mask: Uint8Array, // 1 => sample
+    times: Float64Array,
+    y_obs: Float64Array,
+    species: string | number,
+    options?: WasmFitOptions
   ) => Float64Array;
 }
 
@@ -143,6 +271,9 @@ export function isWasmReady(): boolean {
   );
 }
 
+// Observed quantity for the WASM objective: one species or a sum such as 'S+P'
+export type WasmObservable = 'S' | 'P' | 'E' | 'ES' | 'EP' | `${string}+${string}`;
+
 export type NumericState = {
   E: number;
   ES: number;
@@ -264,13 +395,12 @@ export async function wasmObjectiveSSE(
   params: ReturnType<typeof paramsToNumbers>,
   times: number[],
   yObs: number[],
-  species: 'S' | 'P' | 'E' | 'ES' | 'EP'
+  species: WasmObservable
 ): Promise<number | null> {
   const ok = await initWasm();
   if (!ok || !wasmMod || typeof wasmMod.objective_sse !== 'function') return null;
   const tArr = new Float64Array(times);
   const yArr = new Float64Array(yObs);
-  const code = species === 'S' ? 0 : species === 'P' ? 1 : species === 'E' ? 2 : species === 'ES' ? 3 : 4;
   const v = wasmMod.objective_sse!(
     current.E,
     current.ES,
@@ -289,7 +419,15 @@ export async function wasmObjectiveSSE(
     params.dt,
     tArr,
     yArr,
-    code
+    species,
+    0, // linear interpolation
+    0, // no dead time
+    1, // signal = [species]
+    0,
+    NaN, // no drift
+    NaN,
+    0, // sse
+    1
   ) as number;
   return v;
 }
@@ -310,7 +448,7 @@ export async function wasmFitNelderMead(
   params: ReturnType<typeof paramsToNumbers>,
   times: number[],
   yObs: number[],
-  species: 'S' | 'P' | 'E' | 'ES' | 'EP',
+  species: WasmObservable,
   mask: [number, number, number, number, number, number, number],
   opts?: { maxIter?: number; tol?: number; scale?: number }
 ): Promise<WasmFitResult | null> {
@@ -318,7 +456,6 @@ export async function wasmFitNelderMead(
   if (!ok || !wasmMod || typeof wasmMod.fit_nelder_mead !== 'function') return null;
   const tArr = new Float64Array(times);
   const yArr = new Float64Array(yObs);
-  const code = species === 'S' ? 0 : species === 'P' ? 1 : species === 'E' ? 2 : species === 'ES' ? 3 : 4;
   const paramArr = new Float64Array([
     params.k1,
     params.kMinus3,
@@ -339,16 +476,12 @@ export async function wasmFitNelderMead(
     current.S,
     current.P,
     current.TIEMPO,
-    params.NS,
-    params.NP,
     paramArr,
     maskArr,
     tArr,
     yArr,
-    code,
-    maxIter,
-    tol,
-    scale
+    species,
+    { max_iter: maxIter, tol, scale }
   ) as Float64Array;
   if (!out || out.length < 8) return null;
   return {
@@ -0,0 +1,95 @@
+// Self-profiling of the step loop and the samplers it leans on, timed inside
+// the module so the numbers exclude JS marshalling.
+
+use std::hint::black_box;
+
+use crate::ensemble;
+use crate::model::{Rates, State};
+use crate::rng::{rand_f64, rand_std_normal, sample_binomial};
+
+// Draws per sampler micro-benchmark
+const SAMPLER_DRAWS: u32 = 100_000;
+
+// Milliseconds on a monotonic-enough clock: Instant natively, Date.now in the
+// wasm build, and 0 (no clock) on bare wasm32.
+#[cfg(not(target_arch = "wasm32"))]
+fn now_ms() -> f64 {
+    static START: std::sync::OnceLock<std::time::Instant> = std::sync::OnceLock::new();
+    START.get_or_init(std::time::Instant::now).elapsed().as_secs_f64() * 1e3
+}
+
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+fn now_ms() -> f64 { js_sys::Date::now() }
+
+#[cfg(all(target_arch = "wasm32", not(feature = "wasm")))]
+fn now_ms() -> f64 { 0.0 }
+
+// Nanoseconds per call of f over SAMPLER_DRAWS calls
+fn ns_per_draw(mut f: impl FnMut() -> f64) -> f64 {
+    let t = now_ms();
+    let mut acc = 0.0;
+    for _ in 0..SAMPLER_DRAWS { acc += f(); }
+    black_box(acc);
+    (now_ms() - t) * 1e6 / SAMPLER_DRAWS as f64
+}
+
+pub struct Benchmark {
+    pub total_ms: f64, // wall time of the replicate ensemble
+    pub ms_per_replicate: f64,
+    pub ns_per_step: f64,
+    pub steps_per_second: f64,
+    pub ns_uniform: f64,
+    pub ns_normal: f64,
+    // One binomial regime each: Bernoulli sum, Poisson limit, inversion, BTPE
+    pub ns_binomial: [f64; 4],
+}
+
+impl Benchmark {
+    // [total_ms, ms_per_replicate, ns_per_step, steps_per_second, ns_uniform,
+    //  ns_normal, ns_binomial_bernoulli, ns_binomial_poisson,
+    //  ns_binomial_inversion, ns_binomial_btpe]
+    pub fn to_vec(&self) -> Vec<f64> {
+        let mut v = vec![
+            self.total_ms, self.ms_per_replicate, self.ns_per_step, self.steps_per_second,
+            self.ns_uniform, self.ns_normal,
+        ];
+        v.extend_from_slice(&self.ns_binomial);
+        v
+    }
+}
+
+// Time `replicates` runs of `steps` steps from st0 (through the same ensemble
+// path the exports use), then each sampler in isolation.
+pub fn benchmark(st0: State, k: &Rates, dt: f64, steps: u32, replicates: u32) -> Benchmark {
+    let replicates = replicates.max(1);
+    let t = now_ms();
+    black_box(ensemble::replicates_final(st0, k, dt, steps, replicates, f64::NAN, false));
+    let total_ms = now_ms() - t;
+    let n_steps = steps as f64 * replicates as f64;
+    let ns_per_step = if n_steps > 0.0 { total_ms * 1e6 / n_steps } else { 0.0 };
+
+    let binom = |n: i64, p: f64| ns_per_draw(|| sample_binomial(n, p) as f64);
+    Benchmark {
+        total_ms,
+        ms_per_replicate: total_ms / replicates as f64,
+        ns_per_step,
+        steps_per_second: if total_ms > 0.0 { n_steps / (total_ms * 1e-3) } else { 0.0 },
+        ns_uniform: ns_per_draw(rand_f64),
+        ns_normal: ns_per_draw(rand_std_normal),
+        ns_binomial: [binom(20, 0.3), binom(100_000, 1e-4), binom(1_000, 0.01), binom(100_000, 0.3)],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn breakdown_is_finite() {
+        let b = benchmark([100.0, 0.0, 0.0, 10_000.0, 0.0, 0.0], &[1e-3, 0.0, 0.1, 1.0, 0.0, 1.0], 0.01, 200, 2);
+        let v = b.to_vec();
+        assert_eq!(v.len(), 10);
+        assert!(v.iter().all(|x| x.is_finite() && *x >= 0.0), "{:?}", v);
+    }
+}
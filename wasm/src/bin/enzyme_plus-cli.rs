@@ -0,0 +1,575 @@
+// Batch simulation and fitting outside the browser.
+//
+//   enzyme_plus-cli <jobs.json | jobs.csv> [--out-dir DIR]
+//
+// A JSON job file is {"jobs": [...]} or a bare array of job objects; a CSV job
+// file has one job per row under a header naming the fields. Fields:
+//   name        output file stem (default job<N>)
+//   kind        "simulate" (default) or "fit"
+//   init        [E, ES, EP, S, P, t0], or columns E, ES, EP, S, P, t0
+//   rates       [k1, k-3, k-1, k2, k-2, k3], or columns k1 ... k3; fit:
+//               "guess" starts from rates guessed from the data (P or S
+//               observations, see guess::initial_rates)
+//   dt, steps   step size (default 0.01) and count (default 1000); dt
+//               "auto" takes model::suggest_dt at p_target (default 0.1);
+//               simulate: a list of {"until_time": t, "dt": h} steps through
+//               each piece until its (absolute) time instead of steps
+//   replicates  simulate: > 1 writes one final state per replicate
+//   antithetic  simulate: true (or 1) runs replicates in mirrored-draw pairs
+//   log_points  simulate: write this many rows at times log-spaced from
+//               t0 + t_first to t0 + t_end instead of one row per step
+//   t_first, t_end   simulate: range of the log grid (default dt, dt * steps)
+//   channels    simulate: derived columns for per-step series, a name or a
+//               list of names: "velocity" (dP/dt and -dS/dt over each step),
+//               "occupancy" (ES and EP as fractions of the enzyme total)
+//   integer     simulate: true keeps species as exact integer counts (init
+//               rounded once), for plain per-step series
+//   rounding    simulate: how fractional values become counts each step, for
+//               plain per-step series: "legacy" (default: enzyme rounded, S
+//               and P caps floored), "nearest", "floor" or "stochastic"
+//   overflow    simulate: binding draws beyond the S or P available, for plain
+//               per-step series, integer ones included: "reassign" (default:
+//               to the other binding channel), "drop" or "redraw" (re-split by
+//               the rates); the summary line of a non-integer series reports
+//               how many were reassigned
+//   p_max       simulate: split steps into substeps keeping every channel's
+//               reaction probability per substep <= p_max (default 0 = fixed dt)
+//   data        fit: CSV of (time, value) rows, relative to the job file
+//   times, y    fit: inline observations instead of data (JSON only)
+//   species     fitted observable: S, P, E, ES, EP or a sum like S+P (default P)
+//   mask        fit: flags over [k1,k-3,k-1,k2,k-2,k3,dt,t_shift,E0,ES0,EP0,S0,P0,
+//               signal_scale,signal_offset,drift,drift_decay] (default 1111110);
+//               E0..P0 start from init
+//   t_shift     fit: dead time added to the observation times (default 0)
+//   signal_scale, signal_offset   fit: data = scale * species + offset (1, 0)
+//   drift, drift_decay   fit: baseline drift, linear drift * t when decay is 0 (0, 0)
+//   interp      "linear" (default), "pchip", or "exact" (simulate onto the
+//               observation times instead of interpolating)
+//   loss        "sse" (default), "gaussian" or "poisson" (negative
+//               log-likelihoods; the report then adds aic, aicc and bic), or
+//               the robust "huber" or "soft_l1"
+//   loss_scale  residual scale of the robust losses, in data units (default 1)
+//   max_iter, tol, scale   Nelder–Mead settings (500, 1e-8, 0.1)
+//   restarts    Nelder–Mead restarts on convergence or stagnation (default 0)
+//   adaptive    fit: Gao–Han coefficients for the number of fitted parameters
+//   oriented_restarts   fit: restarts along the simplex gradient, also on a
+//               lack of sufficient decrease (with restarts > 0)
+//   x_tol       relative simplex-size stopping test (default 0 = off)
+//   max_evals   objective evaluation budget (default 0 = unlimited)
+//   trace       fit: true (or 1) adds the best sse and params of every iteration
+//   outlier_threshold   fit: refit without points whose |studentized
+//               residual| exceeds it (default 0 = off); the report then lists
+//               excluded indices and the residuals
+//   outlier_rounds      fit: refits allowed by the outlier screening (default 1)
+//   method      fit: "nelder_mead" (default), "surrogate" (quadratic model
+//               in a trust region; uses max_iter, max_evals, scale, x_tol),
+//               "powell" (line searches along conjugate directions; uses
+//               max_iter, max_evals, scale, tol, x_tol, crn_seed),
+//               "lbfgs" (L-BFGS-B on the deterministic model with sensitivity
+//               gradients; uses max_iter, max_evals, tol, x_tol, memory and
+//               bounds as a box; dt is not fitted), "auto" (Latin
+//               hypercube starts, then Nelder–Mead) or "pareto" (NSGA-II
+//               front over the datasets; uses bounds as search ranges)
+//   datasets    fit "pareto": [{"data" or "times" and "y", "species"}, ...]
+//               fitted with one parameter vector, in place of data
+//   population, generations   fit "pareto": members per generation
+//               (default 20 per fitted parameter) and generations (50)
+//   starts, polish   fit "auto": global starts (default 10 per fitted
+//               parameter) and how many of the best to polish (default 1)
+//   bounds      fit "auto": search ranges by name, {"k2": [0.01, 10], ...};
+//               unlisted parameters search a decade either side of the start;
+//               for "lbfgs" the box the fit stays in (unlisted: unbounded);
+//               "pareto" searches them as "auto" does
+//   constraints fit: parameters held by a relation, e.g. [{"kind": "haldane",
+//               "param": "k-2", "value": 5}] fixes Keq by solving k-2, and
+//               {"kind": "ratio", "param": "k1", "of": "k-1", "value": r}
+//               holds k1 = r * k-1, {"kind": "tie", "param": "k-1", "of":
+//               "k-2"} holds k-1 = k-2; the solved parameter leaves the mask
+//   rate_space  fit: what the optimizer moves in place of the six rates:
+//               "micro" (default), "macro" ([Km_S, Km_P, kcat_r, k2, k-2,
+//               kcat_f]) or "macro_keq" (Keq for kcat_r); the start still
+//               comes from rates (needs k-2 > 0), and mask, bounds and
+//               constraints refer to the space's names
+//   crn_seed    fit: common random numbers; every objective evaluation
+//               restarts the stream from this seed (default off)
+//   seed        restart the random stream before the job, for reproducible runs
+// Simulations write <name>.csv, fits write <name>.json (a report::fit_report
+// document under the job's name, or the front of a "pareto" fit), into DIR
+// (default .).
+// Non-fatal conditions met by a job (coarse steps, depleted species, ...) are
+// listed after its summary line and under "warnings" in fit reports.
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use enzyme_sim::fit::{self, NelderMead};
+use enzyme_sim::guess;
+use enzyme_sim::interp::Interp;
+use enzyme_sim::json::{self, Value};
+use enzyme_sim::lbfgs::{self, Lbfgs};
+use enzyme_sim::model::Observable;
+use enzyme_sim::objective::{self, Constraint, Loss, Problem, RateSpace, DRIFT, DRIFT_DECAY, PARAM_NAMES, SIGNAL_OFFSET, SIGNAL_SCALE, T_SHIFT};
+use enzyme_sim::pareto;
+use enzyme_sim::powell::{self, Powell};
+use enzyme_sim::surrogate::{self, Surrogate};
+use enzyme_sim::{ensemble, model, report, warn};
+
+const INIT_KEYS: [&str; 6] = ["E", "ES", "EP", "S", "P", "t0"];
+const RATE_KEYS: [&str; 6] = ["k1", "k-3", "k-1", "k2", "k-2", "k3"];
+
+// One CSV row per job: numeric cells become numbers, the rest strings
+fn csv_jobs(text: &str) -> Result<Vec<Value>, String> {
+    let mut lines = text.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#'));
+    let header: Vec<String> = lines.next().ok_or("empty job file")?.split(',').map(|h| h.trim().to_string()).collect();
+    Ok(lines.map(|line| {
+        let members = header.iter().zip(line.split(',')).filter(|(_, c)| !c.trim().is_empty()).map(|(h, c)| {
+            let c = c.trim();
+            (h.clone(), c.parse::<f64>().map_or_else(|_| Value::from(c), Value::Number))
+        }).collect();
+        Value::Object(members)
+    }).collect())
+}
+
+// Two-column (time, value) observations; a non-numeric first row is a header
+fn read_observations(path: &Path) -> Result<(Vec<f64>, Vec<f64>), String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let (mut t, mut y) = (Vec::new(), Vec::new());
+    for (i, line) in text.lines().map(str::trim).enumerate() {
+        if line.is_empty() || line.starts_with('#') { continue; }
+        let cells: Vec<&str> = line.split([',', '\t', ';']).map(str::trim).collect();
+        let parsed = (cells.first().and_then(|c| c.parse::<f64>().ok()), cells.get(1).and_then(|c| c.parse::<f64>().ok()));
+        match parsed {
+            (Some(a), Some(b)) => { t.push(a); y.push(b); }
+            _ if i == 0 => continue,
+            _ => return Err(format!("{}:{}: expected two numeric columns", path.display(), i + 1)),
+        }
+    }
+    Ok((t, y))
+}
+
+fn num(job: &Value, key: &str, default: f64) -> Result<f64, String> {
+    match job.get(key) {
+        None | Some(Value::Null) => Ok(default),
+        Some(v) => v.as_f64().ok_or_else(|| format!("'{}' must be a number", key)),
+    }
+}
+
+// Fitting constraints, in the layout of a fit report's model.constraints:
+// {"kind": "ratio", "param": "k1", "of": "k-1", "value": r} holds
+// k1 = r * k-1; {"kind": "haldane", "param": "k-2", "value": keq} solves
+// k-2 from the Haldane relation; {"kind": "tie", "param": "k-1", "of":
+// "k-2"} holds k-1 = k-2
+fn constraints(job: &Value, space: RateSpace) -> Result<Vec<Constraint>, String> {
+    let Some(list) = job.get("constraints") else { return Ok(Vec::new()) };
+    let list = list.as_array().ok_or("'constraints' must be an array")?;
+    let index = |c: &Value, key: &str| -> Result<usize, String> {
+        let name = c.get(key).and_then(Value::as_str).ok_or_else(|| format!("constraint needs a '{}' name", key))?;
+        (0..PARAM_NAMES.len()).find(|&i| space.param_name(i) == name).ok_or_else(|| format!("unknown parameter '{}'", name))
+    };
+    list.iter().map(|c| {
+        let value = || c.get("value").and_then(Value::as_f64).ok_or("constraint needs a numeric 'value'");
+        let constraint = match c.get("kind").and_then(Value::as_str) {
+            Some("ratio") => Constraint::Ratio { num: index(c, "param")?, den: index(c, "of")?, ratio: value()? },
+            Some("haldane") => Constraint::Haldane { keq: value()?, solve: index(c, "param")? },
+            Some("tie") => Constraint::Tie { param: index(c, "param")?, to: index(c, "of")? },
+            _ => return Err("constraint kind must be \"ratio\", \"haldane\" or \"tie\"".to_string()),
+        };
+        constraint.check()?;
+        Ok(constraint)
+    }).collect()
+}
+
+// Search range of a parameter from the job's "bounds" object; NaN when unlisted
+fn bound(job: &Value, name: &str) -> Result<(f64, f64), String> {
+    match job.get("bounds").and_then(|b| b.get(name)) {
+        None | Some(Value::Null) => Ok((f64::NAN, f64::NAN)),
+        Some(v) => match v.as_f64_vec().as_deref() {
+            Some(&[lo, hi]) if lo < hi => Ok((lo, hi)),
+            _ => Err(format!("bounds.{} must be [lower, upper] with lower < upper", name)),
+        },
+    }
+}
+
+// true/false, or a number (non-zero => true); absent => false
+fn flag(job: &Value, key: &str) -> Result<bool, String> {
+    match job.get(key) {
+        None | Some(Value::Null) => Ok(false),
+        Some(Value::Bool(b)) => Ok(*b),
+        Some(v) => Ok(v.as_f64().ok_or_else(|| format!("'{}' must be a boolean", key))? != 0.0),
+    }
+}
+
+// A fixed-length vector given as an array field or as one field per entry
+fn vector<const N: usize>(job: &Value, key: &str, keys: [&str; N], default: f64) -> Result<[f64; N], String> {
+    let mut out = [default; N];
+    if let Some(v) = job.get(key) {
+        let vals = v.as_f64_vec().ok_or_else(|| format!("'{}' must be an array of numbers", key))?;
+        if vals.len() != N { return Err(format!("'{}' needs {} entries", key, N)); }
+        out.copy_from_slice(&vals);
+    } else {
+        for (i, k) in keys.iter().enumerate() { out[i] = num(job, k, default)?; }
+    }
+    Ok(out)
+}
+
+fn mask(job: &Value) -> Result<Vec<u8>, String> {
+    match job.get("mask") {
+        None => Ok(vec![1, 1, 1, 1, 1, 1, 0]),
+        Some(Value::String(s)) => Ok(s.chars().filter(|c| !c.is_whitespace()).map(|c| u8::from(c == '1')).collect()),
+        // A CSV cell like 1111110 parses as a number
+        Some(Value::Number(n)) => Ok(format!("{:07}", *n as u64).chars().map(|c| u8::from(c == '1')).collect()),
+        Some(v) => v.as_f64_vec().map(|m| m.iter().map(|&x| u8::from(x != 0.0)).collect()).ok_or_else(|| "invalid 'mask'".to_string()),
+    }
+}
+
+fn interp(job: &Value) -> Result<Interp, String> {
+    Interp::parse(job.get("interp").and_then(Value::as_str).unwrap_or("linear"))
+}
+
+// Derived series columns by job name: flag and CSV headers
+const CHANNELS: [(&str, u32, &str); 2] = [
+    ("velocity", model::CHANNEL_VELOCITY, "dPdt,-dSdt"),
+    ("occupancy", model::CHANNEL_OCCUPANCY, "ES_frac,EP_frac"),
+];
+
+fn channels(job: &Value) -> Result<u32, String> {
+    let names: Vec<&str> = match job.get("channels") {
+        None | Some(Value::Null) => Vec::new(),
+        Some(Value::String(s)) => s.split('+').map(str::trim).collect(),
+        Some(Value::Array(a)) => a.iter().map(|v| v.as_str().ok_or("'channels' must hold names")).collect::<Result<_, _>>()?,
+        Some(_) => return Err("'channels' must be a name or a list of names".to_string()),
+    };
+    names.iter().try_fold(0, |acc, name| {
+        let (_, flag, _) = CHANNELS.iter().find(|c| c.0 == *name).ok_or_else(|| format!("unknown channel '{}'", name))?;
+        Ok(acc | flag)
+    })
+}
+
+// Step size: a number, or "auto" for the largest dt keeping every channel
+// p_tot at or below p_target over the run from init
+fn step_size(job: &Value, init: &[f64; 6], k: &[f64; 6]) -> Result<f64, String> {
+    match job.get("dt").and_then(Value::as_str) {
+        Some("auto") => {
+            let dt = model::suggest_dt(init, k, num(job, "p_target", 0.1)?);
+            Ok(if dt.is_finite() { dt } else { 1.0 }) // nothing reacts
+        }
+        Some(other) => Err(format!("'dt' must be a number or \"auto\", not \"{}\"", other)),
+        None => num(job, "dt", 0.01),
+    }
+}
+
+// A dt given as a list of {"until_time": t, "dt": h} pieces
+fn dt_schedule(job: &Value) -> Result<Option<model::DtSchedule>, String> {
+    let Some(pieces) = job.get("dt").and_then(Value::as_array) else { return Ok(None) };
+    let mut flat = Vec::with_capacity(2 * pieces.len());
+    for piece in pieces {
+        flat.push(num(piece, "until_time", f64::NAN)?);
+        flat.push(num(piece, "dt", f64::NAN)?);
+    }
+    model::dt_schedule(&flat).map(Some)
+}
+
+fn simulate(job: &Value, out: &Path) -> Result<String, String> {
+    let init = vector(job, "init", INIT_KEYS, 0.0)?;
+    let k = vector(job, "rates", RATE_KEYS, 0.0)?;
+    let schedule = dt_schedule(job)?;
+    let dt = if schedule.is_some() { 0.01 } else { step_size(job, &init, &k)? };
+    let steps = num(job, "steps", 1000.0)? as u32;
+    let replicates = num(job, "replicates", 1.0)? as u32;
+    let p_max = num(job, "p_max", 0.0)?;
+    let log_points = num(job, "log_points", 0.0)? as u32;
+    let channels = channels(job)?;
+    if channels != 0 && (replicates > 1 || log_points > 0) {
+        return Err("'channels' applies to per-step series only".to_string());
+    }
+    let integer = flag(job, "integer")?;
+    let name = |key: &str| job.get(key).map(|r| r.as_str().ok_or(format!("'{}' must be a name", key))).transpose();
+    let (rounding, overflow) = (name("rounding")?, name("overflow")?);
+    let custom = rounding.is_some() || overflow.is_some();
+    let plain = replicates <= 1 && log_points == 0 && channels == 0 && p_max <= 0.0;
+    if (integer || custom) && !plain {
+        return Err("'integer', 'rounding' and 'overflow' apply to plain per-step series only".to_string());
+    }
+    if integer && rounding.is_some() { return Err("'rounding' does not combine with 'integer'".to_string()); }
+    if schedule.is_some() && (replicates > 1 || log_points > 0 || channels != 0 || integer || custom) {
+        return Err("a 'dt' schedule applies to plain per-step series only".to_string());
+    }
+    let policy = model::StepPolicy {
+        rounding: rounding.map_or(Ok(model::Rounding::Legacy), model::Rounding::parse)?,
+        overflow: overflow.map_or(Ok(model::Overflow::Reassign), model::Overflow::parse)?,
+    };
+    let mut moved = None;
+    let width = model::channels_width(channels);
+    let mut csv = String::new();
+    let row = |csv: &mut String, prefix: Option<usize>, st: &[f64]| {
+        let mut cells: Vec<String> = prefix.map(|r| r.to_string()).into_iter().collect();
+        cells.push(st[5].to_string());
+        cells.extend(st[..5].iter().map(|v| v.to_string()));
+        // Derived columns are blank on the initial row
+        cells.extend((6..width).map(|i| st.get(i).map_or(String::new(), |v| v.to_string())));
+        csv.push_str(&cells.join(","));
+        csv.push('\n');
+    };
+    if replicates > 1 {
+        csv.push_str("replicate,t,E,ES,EP,S,P\n");
+        let antithetic = flag(job, "antithetic")?;
+        for (r, st) in ensemble::replicates_final(init, &k, dt, steps, replicates, p_max, antithetic).iter().enumerate() {
+            row(&mut csv, Some(r), st);
+        }
+    } else {
+        csv.push_str("t,E,ES,EP,S,P");
+        for (_, flag, header) in CHANNELS { if channels & flag != 0 { csv.push(','); csv.push_str(header); } }
+        csv.push('\n');
+        row(&mut csv, None, &init);
+        let data = if log_points > 0 {
+            let (first, last) = (num(job, "t_first", dt)?, num(job, "t_end", dt * steps as f64)?);
+            let times = model::log_spaced_times(init[5], first, last, log_points);
+            if times.is_empty() { return Err("log grid needs 0 < t_first < t_end".to_string()); }
+            model::run_at_times(init, &k, dt, &times, p_max)
+        } else if let Some(schedule) = &schedule {
+            model::run_series_scheduled(init, &k, schedule, p_max)
+        } else if integer {
+            model::run_series_integer(init, &k, dt, steps, policy.overflow)
+        } else if custom {
+            let (data, n) = model::run_series_with(init, &k, dt, steps, &policy);
+            moved = Some(n);
+            data
+        } else {
+            model::run_series_channels(init, &k, dt, steps, p_max, channels)
+        };
+        for st in data.chunks(width) { row(&mut csv, None, st); }
+    }
+    std::fs::write(out, csv).map_err(|e| format!("{}: {}", out.display(), e))?;
+    if log_points > 0 && replicates <= 1 { return Ok(format!("{} log-spaced points", log_points)); }
+    if let Some(schedule) = &schedule { return Ok(format!("{} steps over a {}-piece dt schedule", model::schedule_steps(init[5], schedule), schedule.len())); }
+    if let Some(n) = moved { return Ok(format!("{} steps, {} binding events reassigned", steps, n)); }
+    Ok(format!("{} steps x {} replicate(s)", steps, replicates.max(1)))
+}
+
+type Observations = (Vec<f64>, Vec<f64>, Observable);
+
+// Times, values and species of a fit job, or of one of its "datasets"
+fn observations(v: &Value, base: &Path) -> Result<Observations, String> {
+    let (times, y_obs) = match (v.get("data").and_then(Value::as_str), v.get("times"), v.get("y")) {
+        (Some(path), _, _) => read_observations(&base.join(path))?,
+        (None, Some(t), Some(y)) => (
+            t.as_f64_vec().ok_or("'times' must be an array of numbers")?,
+            y.as_f64_vec().ok_or("'y' must be an array of numbers")?,
+        ),
+        _ => return Err("fit needs 'data' or 'times' and 'y'".to_string()),
+    };
+    Ok((times, y_obs, Observable::parse(v.get("species").and_then(Value::as_str).unwrap_or("P"))?))
+}
+
+// Method "pareto": the front of problem's setup over every dataset, written
+// as {"name", "datasets": [species], "fitted": [names], "front": [{"objectives",
+// "params"}]}, sorted by the first dataset's objective
+fn pareto_job(job: &Value, problem: &Problem, params: objective::Params, optimize_idx: &[usize], datasets: &[Observations], out: &Path) -> Result<String, String> {
+    let problems: Vec<Problem> = datasets.iter().map(|(times, y_obs, observable)| Problem {
+        times: times.clone(),
+        y_obs: y_obs.clone(),
+        observable: *observable,
+        ..problem.clone()
+    }).collect();
+    let bounds = optimize_idx.iter().map(|&i| bound(job, problem.space.param_name(i))).collect::<Result<Vec<_>, _>>()?;
+    let opts = pareto::Pareto {
+        population: num(job, "population", 0.0)? as u32,
+        generations: num(job, "generations", 50.0)? as u32,
+    };
+    let front = pareto::pareto_fit(&problems, params, optimize_idx, &bounds, &opts, |_, _| false);
+    let name = |i: usize| problem.space.param_name(i);
+    let points = front.iter().map(|pt| Value::Object(vec![
+        ("objectives".to_string(), Value::from(&pt.objectives[..])),
+        ("params".to_string(), Value::Object(pt.params.iter().enumerate().map(|(i, &v)| (name(i).to_string(), Value::from(v))).collect())),
+    ])).collect();
+    let doc = Value::Object(vec![
+        ("name".to_string(), job.get("name").cloned().unwrap_or(Value::Null)),
+        ("datasets".to_string(), Value::Array(datasets.iter().map(|d| Value::String(d.2.to_string())).collect())),
+        ("fitted".to_string(), Value::Array(optimize_idx.iter().map(|&i| Value::from(name(i))).collect())),
+        ("front".to_string(), Value::Array(points)),
+    ]);
+    std::fs::write(out, format!("{}\n", doc)).map_err(|e| format!("{}: {}", out.display(), e))?;
+    Ok(format!("{} points on the front over {} datasets", front.len(), datasets.len()))
+}
+
+fn fit_job(job: &Value, base: &Path, out: &Path) -> Result<String, String> {
+    let init = vector(job, "init", INIT_KEYS, 0.0)?;
+    let datasets = match job.get("datasets") {
+        None | Some(Value::Null) => Vec::new(),
+        Some(v) => v.as_array().ok_or("'datasets' must be an array")?.iter().map(|d| observations(d, base)).collect::<Result<Vec<_>, _>>()?,
+    };
+    let (times, y_obs, observable) = match datasets.first() {
+        Some(first) => first.clone(),
+        None => observations(job, base)?,
+    };
+    let mut rates = [0.0; 6];
+    match job.get("rates").and_then(Value::as_str) {
+        Some("guess") => rates.copy_from_slice(&guess::initial_rates(&init, &times, &y_obs, observable)?[..6]),
+        Some(other) => return Err(format!("'rates' must be an array of numbers or \"guess\", not \"{}\"", other)),
+        None => rates = vector(job, "rates", RATE_KEYS, 0.0)?,
+    }
+    let space = RateSpace::parse(job.get("rate_space").and_then(Value::as_str).unwrap_or("micro"))?;
+    let mut params = objective::params_from_slice(&space.coordinates(&rates)?, &init);
+    params[6] = step_size(job, &init, &rates)?;
+    params[T_SHIFT] = num(job, "t_shift", 0.0)?;
+    params[SIGNAL_SCALE] = num(job, "signal_scale", 1.0)?;
+    params[SIGNAL_OFFSET] = num(job, "signal_offset", 0.0)?;
+    params[DRIFT] = num(job, "drift", 0.0)?;
+    params[DRIFT_DECAY] = num(job, "drift_decay", 0.0)?;
+    let problem = Problem {
+        init,
+        times,
+        y_obs,
+        observable,
+        interp: interp(job)?,
+        priors: None,
+        loss: Loss::parse(job.get("loss").and_then(Value::as_str).unwrap_or("sse"))?.with_scale(num(job, "loss_scale", 1.0)?),
+        constraints: constraints(job, space)?,
+        space,
+    };
+    space.check(&problem.constraints)?;
+    let opts = NelderMead {
+        max_iter: num(job, "max_iter", 500.0)? as u32,
+        tol: num(job, "tol", 1e-8)?,
+        scale: num(job, "scale", 0.1)?,
+        progress_every: 0,
+        warm_simplex: None,
+        max_restarts: num(job, "restarts", 0.0)? as u32,
+        x_tol: num(job, "x_tol", 0.0)?,
+        max_evals: num(job, "max_evals", 0.0)? as u32,
+        trace: flag(job, "trace")?,
+        crn_seed: match job.get("crn_seed") {
+            None | Some(Value::Null) => None,
+            Some(v) => Some(v.as_f64().filter(|s| *s >= 0.0).ok_or("'crn_seed' must be a non-negative number")? as u64),
+        },
+        adaptive: flag(job, "adaptive")?,
+        oriented_restarts: flag(job, "oriented_restarts")?,
+    };
+    let optimize_idx = objective::free_indices(&objective::mask_indices(&mask(job)?), &problem.constraints);
+    let outliers = fit::Outliers {
+        threshold: num(job, "outlier_threshold", 0.0)?,
+        max_rounds: num(job, "outlier_rounds", 1.0)? as u32,
+    };
+    let method = job.get("method").and_then(Value::as_str).unwrap_or("nelder_mead");
+    if method == "pareto" {
+        if datasets.len() < 2 { return Err("method \"pareto\" needs at least two 'datasets'".to_string()); }
+        return pareto_job(job, &problem, params, &optimize_idx, &datasets, out);
+    }
+    if !datasets.is_empty() { return Err("'datasets' needs method \"pareto\"".to_string()); }
+    let res = match method {
+        "nelder_mead" => fit::refit_without_outliers(&problem, params, &optimize_idx, &opts, &outliers, |_, _, _| false),
+        "surrogate" => {
+            if outliers.threshold > 0.0 { return Err("outlier screening needs method \"nelder_mead\"".to_string()); }
+            let opts = Surrogate {
+                max_iter: opts.max_iter, max_evals: opts.max_evals, scale: opts.scale, x_tol: opts.x_tol,
+                progress_every: 0, trace: opts.trace,
+            };
+            surrogate::surrogate_fit(&problem, params, &optimize_idx, &opts, |_, _, _| false)
+        }
+        "powell" => {
+            if outliers.threshold > 0.0 { return Err("outlier screening needs method \"nelder_mead\"".to_string()); }
+            let opts = Powell {
+                max_iter: opts.max_iter, max_evals: opts.max_evals, scale: opts.scale, tol: opts.tol, x_tol: opts.x_tol,
+                progress_every: 0, trace: opts.trace, crn_seed: opts.crn_seed,
+            };
+            powell::powell_fit(&problem, params, &optimize_idx, &opts, |_, _, _| false)
+        }
+        "lbfgs" => {
+            if outliers.threshold > 0.0 { return Err("outlier screening needs method \"nelder_mead\"".to_string()); }
+            let bounds = optimize_idx.iter().map(|&i| bound(job, space.param_name(i))).collect::<Result<Vec<_>, _>>()?;
+            let opts = Lbfgs {
+                max_iter: opts.max_iter, max_evals: opts.max_evals, memory: num(job, "memory", 0.0)? as u32,
+                tol: opts.tol, x_tol: opts.x_tol, progress_every: 0, trace: opts.trace,
+            };
+            lbfgs::lbfgs_fit(&problem, params, &optimize_idx, &bounds, &opts, |_, _, _| false)
+        }
+        "auto" => {
+            if outliers.threshold > 0.0 { return Err("outlier screening needs method \"nelder_mead\"".to_string()); }
+            let bounds = optimize_idx.iter().map(|&i| bound(job, space.param_name(i))).collect::<Result<Vec<_>, _>>()?;
+            let opts = fit::AutoFit {
+                n_starts: num(job, "starts", 0.0)? as u32,
+                n_polish: num(job, "polish", 1.0)? as u32,
+                polish: opts,
+            };
+            fit::fit_auto(&problem, params, &optimize_idx, &bounds, &opts, |_, _, _| false)
+        }
+        other => return Err(format!("unknown fit method '{}' (expected nelder_mead, surrogate, powell, lbfgs, auto or pareto)", other)),
+    };
+    let mut report = report::fit_report(&problem, &res, &optimize_idx);
+    if let Value::Object(m) = &mut report {
+        m.insert(0, ("name".to_string(), job.get("name").cloned().unwrap_or(Value::Null)));
+    }
+    std::fs::write(out, format!("{}\n", report)).map_err(|e| format!("{}: {}", out.display(), e))?;
+    Ok(format!("sse {:.6e}, {} ({} iterations)", res.sse, fit::reason_name(res.reason), res.iterations))
+}
+
+fn run(job: &Value, index: usize, base: &Path, out_dir: &Path) -> Result<String, String> {
+    let name = match job.get("name") {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Number(n)) => n.to_string(),
+        _ => format!("job{}", index + 1),
+    };
+    if let Some(seed) = job.get("seed") {
+        let seed = seed.as_f64().ok_or("'seed' must be a number")?;
+        enzyme_sim::rng::seed_rng(seed.max(0.0) as u64);
+    }
+    warn::take();
+    let summary = match job.get("kind").and_then(Value::as_str).unwrap_or("simulate") {
+        "simulate" => {
+            let out = out_dir.join(format!("{}.csv", name));
+            simulate(job, &out).map(|s| format!("{} -> {}", s, out.display()))
+        }
+        "fit" => {
+            let out = out_dir.join(format!("{}.json", name));
+            fit_job(job, base, &out).map(|s| format!("{} -> {}", s, out.display()))
+        }
+        other => Err(format!("unknown kind '{}'", other)),
+    }?;
+    let raised = warn::take();
+    Ok(if raised.is_empty() { summary } else { format!("{} (warnings: {})", summary, raised.names().join(", ")) })
+}
+
+fn load_jobs(path: &Path) -> Result<Vec<Value>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    if path.extension().is_some_and(|e| e.eq_ignore_ascii_case("csv")) { return csv_jobs(&text); }
+    let doc = json::parse(&text)?;
+    let jobs = doc.get("jobs").unwrap_or(&doc);
+    jobs.as_array().map(<[Value]>::to_vec).ok_or_else(|| "expected a 'jobs' array".to_string())
+}
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let mut job_file: Option<PathBuf> = None;
+    let mut out_dir = PathBuf::from(".");
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--out-dir" | "-o" => match args.next() {
+                Some(d) => out_dir = PathBuf::from(d),
+                None => { eprintln!("--out-dir needs a directory"); return ExitCode::from(2); }
+            },
+            "--help" | "-h" => { println!("usage: enzyme_plus-cli <jobs.json|jobs.csv> [--out-dir DIR]"); return ExitCode::SUCCESS; }
+            _ => job_file = Some(PathBuf::from(arg)),
+        }
+    }
+    let Some(job_file) = job_file else {
+        eprintln!("usage: enzyme_plus-cli <jobs.json|jobs.csv> [--out-dir DIR]");
+        return ExitCode::from(2);
+    };
+    let jobs = match load_jobs(&job_file) {
+        Ok(j) => j,
+        Err(e) => { eprintln!("error: {}", e); return ExitCode::from(2); }
+    };
+    if let Err(e) = std::fs::create_dir_all(&out_dir) {
+        eprintln!("error: {}: {}", out_dir.display(), e);
+        return ExitCode::from(2);
+    }
+    let base = job_file.parent().unwrap_or(Path::new("."));
+    let mut failed = 0;
+    for (i, job) in jobs.iter().enumerate() {
+        match run(job, i, base, &out_dir) {
+            Ok(msg) => println!("job {}: {}", i + 1, msg),
+            Err(e) => { eprintln!("job {}: error: {}", i + 1, e); failed += 1; }
+        }
+    }
+    if failed > 0 { ExitCode::FAILURE } else { ExitCode::SUCCESS }
+}
@@ -0,0 +1,69 @@
+// Branched variant: besides the ES <-> EP -> E + P route of model::step, ES
+// can release a second product P2 directly (ES -> E + P2, first order kb,
+// irreversible), as for a promiscuous enzyme. The branch is drawn from the
+// ES the reaction step left, like the transport of compartment::step.
+
+use crate::model::{self, clamp_dt, Clock, Rates};
+use crate::rng::sample_binomial;
+
+// [E, ES, EP, S, P, t, P2]; the first six match model::State
+pub type State = [f64; 7];
+
+// Series rows: the state, then the product ratio P / P2 (NaN before any P2)
+pub const ROW_LEN: usize = 8;
+
+// One dt step: the reaction step, then ES -> E + P2. dt must already be
+// clamped.
+pub fn step(st: &mut State, k: &Rates, kb: f64, dt: f64) {
+    let mut inner = [st[0], st[1], st[2], st[3], st[4], st[5]];
+    model::step(&mut inner, k, dt);
+    let p = if kb > 0.0 { 1.0 - (-(kb * dt)).exp() } else { 0.0 };
+    let n = sample_binomial(inner[1].round().max(0.0) as i64, p) as f64;
+    st[..6].copy_from_slice(&inner);
+    st[0] += n;
+    st[1] -= n;
+    st[6] += n;
+}
+
+// Flattened [E, ES, EP, S, P, t, P2, P / P2] rows, one per step
+pub fn run_series(mut st: State, k: &Rates, kb: f64, dt: f64, steps: u32) -> Vec<f64> {
+    let dt = clamp_dt(dt);
+    let mut data: Vec<f64> = Vec::with_capacity(ROW_LEN * steps as usize);
+    let mut clock = Clock::new(st[5]);
+    for _ in 0..steps {
+        step(&mut st, k, kb, dt);
+        st[5] = clock.tick(dt);
+        data.extend_from_slice(&st);
+        data.push(if st[6] > 0.0 { st[4] / st[6] } else { f64::NAN });
+    }
+    data
+}
+
+// Ratio P / P2 while neither product rebinds: ES commits to P at k2 times
+// the fraction k3 / (k-2 + k3) of EP released forward, and to P2 at kb
+pub fn partition_ratio(k: &Rates, kb: f64) -> f64 {
+    let [_, _, _, k2, k_minus2, k3] = *k;
+    k2 * k3 / ((k_minus2 + k3) * kb)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn products_split_by_the_partition_ratio() {
+        crate::rng::seed_rng(37);
+        let k = [1e-3, 0.0, 0.1, 1.0, 0.5, 1.0];
+        let st0 = [50.0, 0.0, 0.0, 20_000.0, 0.0, 0.0, 0.0];
+        let data = run_series(st0, &k, 0.25, 0.01, 5000);
+        for row in data.chunks(ROW_LEN) {
+            assert_eq!(row[0] + row[1] + row[2], 50.0);
+            assert_eq!(row[1] + row[2] + row[3] + row[4] + row[6], 20_000.0);
+        }
+        let last = &data[data.len() - ROW_LEN..];
+        let expected = partition_ratio(&k, 0.25);
+        assert!((expected - 8.0 / 3.0).abs() < 1e-12);
+        assert!((last[7] / expected - 1.0).abs() < 0.1, "{} vs {}", last[7], expected);
+    }
+}
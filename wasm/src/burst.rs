@@ -0,0 +1,77 @@
+// Pre-steady-state burst analysis. When product release is slower than the
+// chemistry, product measured after a quench (which frees the bound EP, so
+// the "EP+P" observable) appears in a first-order burst of about one turnover
+// per active site before settling into the steady rate:
+//
+//   P(t) = A (1 - e^(-kb t)) + v t
+//
+// Times are relative to mixing. Series at the needed resolution come from
+// model::run_series_dual.
+
+use crate::linalg;
+use crate::model::Rates;
+
+// [A, kb, v] of the network at saturating S with irreversible chemistry and
+// release (k-2 = k-3 = 0): kb = k2 + k3, A = E0 (k2 / (k2 + k3))^2 and
+// v = E0 k2 k3 / (k2 + k3)
+pub fn burst_constants(k: &Rates, e_total: f64) -> [f64; 3] {
+    let [_, _, _, k2, _, k3] = *k;
+    let sum = k2 + k3;
+    [e_total * (k2 / sum).powi(2), sum, e_total * k2 * k3 / sum]
+}
+
+// Width of the fit_burst output
+pub const BURST_LEN: usize = 7;
+
+// A, kb and v of the burst equation above by least squares to product p at
+// times, from the line through the later half of the time span (slope v,
+// intercept A) and the half-time of its approach. Output: [A, se, kb, se,
+// v, se, rms residual]; NaN with fewer than four points.
+pub fn fit_burst(times: &[f64], p: &[f64]) -> [f64; BURST_LEN] {
+    let mut pts: Vec<(f64, f64)> = times.iter().zip(p).filter(|(t, y)| t.is_finite() && y.is_finite() && **t >= 0.0).map(|(&t, &y)| (t, y)).collect();
+    if pts.len() < 4 { return [f64::NAN; BURST_LEN]; }
+    pts.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let t_mid = 0.5 * (pts[0].0 + pts[pts.len() - 1].0);
+    let late: Vec<(f64, f64)> = pts.iter().copied().filter(|q| q.0 >= t_mid).collect();
+    if late.len() < 2 { return [f64::NAN; BURST_LEN]; }
+    let n = late.len() as f64;
+    let (mt, my) = (late.iter().map(|q| q.0).sum::<f64>() / n, late.iter().map(|q| q.1).sum::<f64>() / n);
+    let sxx: f64 = late.iter().map(|q| (q.0 - mt).powi(2)).sum();
+    let v0 = if sxx > 0.0 { late.iter().map(|q| (q.0 - mt) * (q.1 - my)).sum::<f64>() / sxx } else { 0.0 };
+    let a0 = (my - v0 * mt).max(1e-12 * my.abs().max(1e-300));
+    // Where the curve has closed half the gap to the line
+    let t_half = pts.iter().find(|q| a0 + v0 * q.0 - q.1 <= 0.5 * a0).map_or(pts[pts.len() / 4].0, |q| q.0);
+    let kb0 = std::f64::consts::LN_2 / t_half.max(1e-12 * pts[pts.len() - 1].0);
+    let fit = linalg::levenberg_marquardt(&[a0.ln(), kb0.ln(), v0], 300, |q| {
+        let (a, kb) = (q[0].exp(), q[1].exp());
+        pts.iter().map(|&(t, y)| y - a * (1.0 - (-kb * t).exp()) - q[2] * t).collect()
+    });
+    let se = fit.standard_errors(pts.len());
+    let (a, kb) = (fit.q[0].exp(), fit.q[1].exp());
+    [a, a * se[0], kb, kb * se[1], fit.q[2], se[2], (fit.ssr / pts.len() as f64).sqrt()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn burst_recovered_from_a_dual_resolution_run() {
+        crate::rng::seed_rng(41);
+        let k = [1e-3, 0.0, 0.0, 10.0, 0.0, 0.5];
+        let e0 = 10_000.0;
+        let data = model::run_series_dual([e0, 0.0, 0.0, 1e6, 0.0, 0.0], &k, 1e-4, 5000, 0.01, 250, f64::NAN);
+        assert_eq!(data.len(), 6 * 5250);
+        assert!((data[data.len() - 1] - 3.0).abs() < 1e-9);
+        // Every 50th fine row, then every coarse one
+        let rows = data.chunks(6).enumerate().filter(|(i, _)| *i >= 5000 || i % 50 == 49);
+        let (times, p): (Vec<f64>, Vec<f64>) = rows.map(|(_, r)| (r[5], r[2] + r[4])).unzip();
+        let out = fit_burst(&times, &p);
+        let [a, kb, v] = burst_constants(&k, e0);
+        assert!((out[0] / a - 1.0).abs() < 0.05, "A {} vs {}", out[0], a);
+        assert!((out[2] / kb - 1.0).abs() < 0.05, "kb {} vs {}", out[2], kb);
+        assert!((out[4] / v - 1.0).abs() < 0.05, "v {} vs {}", out[4], v);
+    }
+}
@@ -0,0 +1,94 @@
+// Compact output (feature "compact-output"): the same competing-risks scheme
+// as model::step under the default rounding, with the state kept in f32 and
+// half-size series rows for live previews. The event draws and their
+// probabilities stay in f64 through model::draw_events, so a step costs about
+// what the f64 one does: the mode saves memory and transfer, not time. Counts
+// are exact only up to 2^24 and time drifts off the step grid sooner, so
+// fitting and anything reported stays on the f64 path.
+
+use crate::model::{self, Overflow, Rates};
+use crate::warn::{self, Warnings};
+
+pub type State32 = [f32; 6];
+
+fn channel_p_tot(st: &State32, k: &[f32; 6], dt: f32) -> [f64; 3] {
+    let [k1, k_minus3, k_minus1, k2, k_minus2, k3] = *k;
+    [
+        (k1 * st[3]).max(0.0) + (k_minus3 * st[4]).max(0.0),
+        k_minus1.max(0.0) + k2.max(0.0),
+        k_minus2.max(0.0) + k3.max(0.0),
+    ]
+    .map(|lambda| if lambda > 0.0 { -(-(lambda * dt)).exp_m1() as f64 } else { 0.0 })
+}
+
+// One dt step in f32; k is the rate vector already narrowed to f32. Binding
+// draws beyond the S or P available follow overflow.
+pub fn step_f32(st: &mut State32, k: &[f32; 6], dt: f32, overflow: Overflow) {
+    for v in st[..5].iter_mut() { *v = if *v > 0.0 { *v } else { 0.0 }; }
+    let p_tot = channel_p_tot(st, k, dt);
+    if p_tot[0].max(p_tot[1]).max(p_tot[2]) > warn::P_TOT_WARN { warn::raise(Warnings::P_TOT_HIGH); }
+    let [e, es, ep, s, p, t] = *st;
+    let pools = [e.round() as i64, es.round() as i64, ep.round() as i64];
+    let k64: Rates = k.map(f64::from);
+    let (fl, _, _) = model::draw_events(pools, s.floor() as i64, p.floor() as i64, s as f64, p as f64, &k64, p_tot, overflow);
+    let [n_es, n_ep, to_el, to_ep, to_es, to_e] = fl.map(|n| n as f32);
+    *st = [
+        e - n_es - n_ep + to_el + to_e,
+        es + n_es - to_el - to_ep + to_es,
+        ep + n_ep + to_ep - to_es - to_e,
+        s - n_es + to_el,
+        p - n_ep + to_e,
+        t + dt,
+    ];
+    for v in st[..5].iter_mut() { *v = v.max(0.0); }
+}
+
+// Flattened [E, ES, EP, S, P, t] f32 rows, one per step. Time is kept in f64
+// and narrowed per row so long previews stay on the step grid.
+pub fn run_series_f32(st0: &model::State, k: &Rates, dt: f64, steps: u32, overflow: Overflow) -> Vec<f32> {
+    let dt = model::clamp_dt(dt);
+    let mut st: State32 = st0.map(|v| v as f32);
+    let k32 = k.map(|v| v as f32);
+    let mut clock = model::Clock::new(st0[5]);
+    let mut data = Vec::with_capacity(6 * steps as usize);
+    for _ in 0..steps {
+        step_f32(&mut st, &k32, dt as f32, overflow);
+        st[5] = clock.tick(dt) as f32;
+        data.extend_from_slice(&st);
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn f32_series_conserves_and_tracks_f64() {
+        crate::rng::seed_rng(5);
+        let st0 = [100.0, 0.0, 0.0, 5000.0, 0.0, 0.0];
+        let k = [1e-3, 0.0, 0.1, 1.0, 0.0, 2.0];
+        let rows = run_series_f32(&st0, &k, 0.01, 2000, Overflow::default());
+        let last = &rows[rows.len() - 6..];
+        assert_eq!(last[0] + last[1] + last[2], 100.0);
+        assert_eq!(last[1] + last[2] + last[3] + last[4], 5000.0);
+        assert!((last[5] - 20.0).abs() < 1e-5);
+        let p64 = model::run_final(st0, &k, 0.01, 2000)[4];
+        assert!((last[4] as f64 - p64).abs() < 0.1 * p64, "{} vs {}", last[4], p64);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn f32_steps_follow_the_overflow_policy() {
+        // Far more enzyme than S, with P present: most S-binding draws overflow
+        let k = [1.0, 0.001, 0.0, 0.0, 0.0, 0.0];
+        let first = |overflow| {
+            crate::rng::seed_rng(8);
+            let mut st: State32 = [1000.0, 0.0, 0.0, 5.0, 500.0, 0.0];
+            step_f32(&mut st, &k, 0.5, overflow);
+            assert_eq!(st[0] + st[1] + st[2], 1000.0);
+            st[2]
+        };
+        assert!(first(Overflow::Reassign) > first(Overflow::Drop));
+    }
+}
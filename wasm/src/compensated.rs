@@ -0,0 +1,31 @@
+// Compensated (Kahan–Babuška–Neumaier) summation, for long accumulations
+// whose naive rounding error grows with the number of terms: the step clock
+// and the objective's sums of squares.
+
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Sum {
+    sum: f64,
+    c: f64, // running compensation: the low-order bits lost from sum
+}
+
+impl Sum {
+    pub(crate) fn new(start: f64) -> Sum { Sum { sum: start, c: 0.0 } }
+
+    #[inline]
+    pub(crate) fn add(&mut self, x: f64) {
+        let t = self.sum + x;
+        self.c += if self.sum.abs() >= x.abs() { (self.sum - t) + x } else { (x - t) + self.sum };
+        self.sum = t;
+    }
+
+    #[inline]
+    pub(crate) fn value(&self) -> f64 { self.sum + self.c }
+}
+
+impl FromIterator<f64> for Sum {
+    fn from_iter<I: IntoIterator<Item = f64>>(iter: I) -> Sum {
+        let mut s = Sum::default();
+        for x in iter { s.add(x); }
+        s
+    }
+}
@@ -0,0 +1,136 @@
+// Secondary analyses of constants fitted under a series of conditions, such
+// as kcat at several temperatures or pH values. Temperatures are in K and
+// rate constants in s^-1; energies come out in J/mol.
+
+use crate::linalg;
+
+// J mol^-1 K^-1
+pub const GAS_CONSTANT: f64 = 8.314462618;
+// ln(kB / h) in ln(s^-1 K^-1)
+const LN_KB_OVER_H: f64 = 23.759_977_810_504_218;
+// Temperature of the reported activation free energy
+pub const EYRING_T_REF: f64 = 298.15;
+
+// Width of the eyring output
+pub const EYRING_LEN: usize = 7;
+
+// Weighted least-squares line y = a + b x: [a, b, var a, var b, cov(a, b),
+// weighted sum of squared residuals], None with fewer than two distinct x.
+// The variances use the weights as inverse variances when absolute is set,
+// else are scaled by the residual variance (NaN with only two points).
+fn weighted_line(x: &[f64], y: &[f64], w: &[f64], absolute: bool) -> Option<[f64; 6]> {
+    let (mut sw, mut sx, mut sy, mut sxx, mut sxy) = (0.0, 0.0, 0.0, 0.0, 0.0);
+    for i in 0..x.len() {
+        sw += w[i];
+        sx += w[i] * x[i];
+        sy += w[i] * y[i];
+        sxx += w[i] * x[i] * x[i];
+        sxy += w[i] * x[i] * y[i];
+    }
+    let det = sw * sxx - sx * sx;
+    if x.len() < 2 || det.is_nan() || det <= 1e-12 * sw * sxx { return None; }
+    let b = (sw * sxy - sx * sy) / det;
+    let a = (sxx * sy - sx * sxy) / det;
+    let ssr: f64 = (0..x.len()).map(|i| w[i] * (y[i] - a - b * x[i]).powi(2)).sum();
+    let s2 = if absolute { 1.0 } else if x.len() > 2 { ssr / (x.len() - 2) as f64 } else { f64::NAN };
+    Some([a, b, s2 * sxx / det, s2 * sw / det, -s2 * sx / det, ssr])
+}
+
+// Eyring fit of ln(k / T) = ln(kB / h) + dS / R - dH / (R T) to rate
+// constants k at temperatures t, by least squares in ln(k / T); sd, when
+// given (same length), holds the standard errors of k, weighting each point
+// by (k / sd)^2 and taking the uncertainties from them, else the scatter
+// about the line sets them. Output: [dH, se, dS, se, dG at EYRING_T_REF, se,
+// rms residual in ln(k / T)]. Points with a non-positive k or T (or sd) are
+// skipped; NaN throughout with fewer than two distinct temperatures.
+pub fn eyring(t: &[f64], k: &[f64], sd: Option<&[f64]>) -> [f64; EYRING_LEN] {
+    let mut x = Vec::new();
+    let mut y = Vec::new();
+    let mut w = Vec::new();
+    for i in 0..t.len().min(k.len()) {
+        let weight = match sd {
+            Some(sd) => sd.get(i).map_or(f64::NAN, |s| (k[i] / s).powi(2)),
+            None => 1.0,
+        };
+        if t[i] > 0.0 && k[i] > 0.0 && weight > 0.0 && weight.is_finite() && t[i].is_finite() && k[i].is_finite() {
+            x.push(1.0 / t[i]);
+            y.push((k[i] / t[i]).ln());
+            w.push(weight);
+        }
+    }
+    let Some([a, b, var_a, var_b, cov, ssr]) = weighted_line(&x, &y, &w, sd.is_some()) else { return [f64::NAN; EYRING_LEN] };
+    let r = GAS_CONSTANT;
+    let t0 = EYRING_T_REF;
+    let (dh, ds) = (-r * b, r * (a - LN_KB_OVER_H));
+    let var_dg = r * r * (var_b + t0 * t0 * var_a + 2.0 * t0 * cov);
+    let rms = (ssr / w.iter().sum::<f64>()).sqrt();
+    [dh, r * var_b.sqrt(), ds, r * var_a.sqrt(), dh - t0 * ds, var_dg.max(0.0).sqrt(), rms]
+}
+
+// Width of the ph_profile output
+pub const PH_PROFILE_LEN: usize = 7;
+
+// ln of the two-pKa model y_lim / (1 + 10^(pKa1 - pH) + 10^(pH - pKa2)) at
+// q = [ln y_lim, pKa1, pKa2]
+fn ln_bell(q: &[f64], ph: f64) -> f64 {
+    q[0] - (1.0 + 10f64.powf(q[1] - ph) + 10f64.powf(ph - q[2])).ln()
+}
+
+// Bell-shaped pH profile: least squares in ln y of the two-pKa model above
+// to values y (kcat or kcat/Km) at each pH, by Levenberg–Marquardt from
+// pKa1 and pKa2 half a unit either side of the optimum's pH. Output: [pKa1,
+// se, pKa2, se, y_lim (the pH-independent limit), se, rms residual in ln
+// y]; the se come from the scatter and are NaN with only three points.
+// Non-positive values are skipped; NaN throughout with fewer than three.
+pub fn ph_profile(ph: &[f64], y: &[f64]) -> [f64; PH_PROFILE_LEN] {
+    let pts: Vec<(f64, f64)> = ph.iter().zip(y)
+        .filter(|(p, v)| p.is_finite() && v.is_finite() && **v > 0.0)
+        .map(|(&p, &v)| (p, v.ln()))
+        .collect();
+    let n = pts.len();
+    if n < 3 { return [f64::NAN; PH_PROFILE_LEN]; }
+    let top = pts.iter().copied().fold(pts[0], |a, b| if b.1 > a.1 { b } else { a });
+    // At pH = (pKa1 + pKa2) / 2 with the pKas 1 apart the bell is 1 + 2 10^-0.5 below y_lim
+    let start = [top.1 + (1.0 + 2.0 * 10f64.powf(-0.5)).ln(), top.0 - 0.5, top.0 + 0.5];
+    let fit = linalg::levenberg_marquardt(&start, 200, |q| pts.iter().map(|&(p, v)| v - ln_bell(q, p)).collect());
+    let (q, se) = (&fit.q, fit.standard_errors(n));
+    let y_lim = q[0].exp();
+    [q[1], se[1], q[2], se[2], y_lim, y_lim * se[0], (fit.ssr / n as f64).sqrt()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn eyring_recovers_activation_parameters() {
+        let (dh, ds) = (55e3, -30.0);
+        let t = [278.15, 288.15, 298.15, 308.15, 318.15];
+        let k: Vec<f64> = t.iter().map(|&t| t * (LN_KB_OVER_H + ds / GAS_CONSTANT - dh / (GAS_CONSTANT * t)).exp()).collect();
+        let out = eyring(&t, &k, None);
+        assert!((out[0] - dh).abs() < 1e-6 * dh, "{:?}", out);
+        assert!((out[2] - ds).abs() < 1e-6, "{:?}", out);
+        assert!((out[4] - (dh - EYRING_T_REF * ds)).abs() < 1e-3);
+        assert!(out[1] < 1e-3 && out[6] < 1e-9);
+        // With 5% errors the scatter-free points keep dH, and the errors set a finite se
+        let sd: Vec<f64> = k.iter().map(|v| 0.05 * v).collect();
+        let weighted = eyring(&t, &k, Some(&sd));
+        assert!((weighted[0] - dh).abs() < 1e-6 * dh);
+        assert!(weighted[1] > 100.0 && weighted[1] < 10e3, "{:?}", weighted);
+        assert!(eyring(&[300.0], &[1.0], None)[0].is_nan());
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn ph_profile_recovers_both_pkas() {
+        let q = [50f64.ln(), 5.2, 8.1];
+        let ph: Vec<f64> = (0..13).map(|i| 4.0 + 0.5 * i as f64).collect();
+        // +-2% alternating scatter
+        let y: Vec<f64> = ph.iter().enumerate().map(|(i, &p)| ln_bell(&q, p).exp() * if i % 2 == 0 { 1.02 } else { 0.98 }).collect();
+        let out = ph_profile(&ph, &y);
+        assert!((out[0] - 5.2).abs() < 0.05 && (out[2] - 8.1).abs() < 0.05, "{:?}", out);
+        assert!((out[4] / 50.0 - 1.0).abs() < 0.05, "{:?}", out);
+        assert!(out[1] > 0.0 && out[1] < 0.1, "{:?}", out);
+        assert!(ph_profile(&ph[..2], &y[..2])[0].is_nan());
+    }
+}
@@ -0,0 +1,81 @@
+// Coupled-assay variant: the product P of model::step is turned into the
+// detected Q by a coupling enzyme in excess, at the Michaelis–Menten rate
+// vmax P / (km + P) in the state's units. The coupling system is fast
+// enough when Q follows the primary rate after a short lag with P held low.
+
+use crate::model::{self, clamp_dt, Clock, Rates};
+use crate::rng::sample_binomial;
+
+// [E, ES, EP, S, P, t, Q]; the first six match model::State
+pub type State = [f64; 7];
+
+// Coupling enzyme [vmax, km]
+pub type Coupling = [f64; 2];
+
+// One dt step: the reaction step, then P -> Q drawn from the P it left at
+// the per-molecule rate vmax / (km + P). dt must already be clamped.
+pub fn step(st: &mut State, k: &Rates, c: &Coupling, dt: f64) {
+    let mut inner = [st[0], st[1], st[2], st[3], st[4], st[5]];
+    model::step(&mut inner, k, dt);
+    let p = inner[4].round().max(0.0);
+    let rate = if c[0] > 0.0 && p > 0.0 { c[0] / (c[1].max(0.0) + p) } else { 0.0 };
+    let n = sample_binomial(p as i64, 1.0 - (-(rate * dt)).exp()) as f64;
+    st[..6].copy_from_slice(&inner);
+    st[4] -= n;
+    st[6] += n;
+}
+
+// Flattened [E, ES, EP, S, P, t, Q] rows, one per step
+pub fn run_series(mut st: State, k: &Rates, c: &Coupling, dt: f64, steps: u32) -> Vec<f64> {
+    let dt = clamp_dt(dt);
+    let mut data: Vec<f64> = Vec::with_capacity(7 * steps as usize);
+    let mut clock = Clock::new(st[5]);
+    for _ in 0..steps {
+        step(&mut st, k, c, dt);
+        st[5] = clock.tick(dt);
+        data.extend_from_slice(&st);
+    }
+    data
+}
+
+// Width of the coupling_check output
+pub const CHECK_LEN: usize = 4;
+
+// Whether a coupling enzyme keeps up with a primary rate v: [P held at steady
+// state v km / (vmax - v), lag km / vmax, time for the rate of Q to reach
+// fraction of v, v / vmax]. The lag and time treat the coupling reaction as
+// first order (P well below km); all but the last are infinite when vmax <= v.
+pub fn coupling_check(v: f64, c: &Coupling, fraction: f64) -> [f64; CHECK_LEN] {
+    let [vmax, km] = *c;
+    let load = v / vmax;
+    if vmax.is_nan() || vmax <= v { return [f64::INFINITY, f64::INFINITY, f64::INFINITY, load]; }
+    let lag = km / vmax;
+    let fraction = if fraction > 0.0 && fraction < 1.0 { fraction } else { 0.99 };
+    [v * km / (vmax - v), lag, -(-fraction).ln_1p() * lag, load]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn fast_coupling_tracks_the_primary_rate() {
+        crate::rng::seed_rng(23);
+        let k = [1e-3, 0.0, 0.1, 1.0, 0.0, 1.0];
+        let st0 = [20.0, 0.0, 0.0, 100_000.0, 0.0, 0.0, 0.0];
+        let c = [400.0, 50.0];
+        let data = run_series(st0, &k, &c, 0.01, 3000);
+        for row in data.chunks(7) {
+            assert_eq!(row[1] + row[2] + row[3] + row[4] + row[6], 100_000.0);
+        }
+        // Saturated primary rate: 20 * kcat = 10 per unit time
+        let check = coupling_check(10.0, &c, 0.99);
+        assert!((check[0] - 10.0 * 50.0 / 390.0).abs() < 1e-12 && check[1] == 0.125);
+        let last = &data[data.len() - 7..];
+        assert!(last[4] < 5.0 * check[0] + 5.0, "P {}", last[4]);
+        let (q1, q2) = (data[1000 * 7 - 1], last[6]);
+        assert!(((q2 - q1) / 20.0 / 10.0 - 1.0).abs() < 0.1, "{}", (q2 - q1) / 20.0);
+        assert!(coupling_check(10.0, &[5.0, 50.0], 0.99)[0].is_infinite());
+    }
+}
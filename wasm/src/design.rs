@@ -0,0 +1,220 @@
+// Local and global sensitivity analyses and experimental-design criteria,
+// mostly on the deterministic RK4 model.
+
+use crate::model::{clamp_dt, run_final, Observable, Rates};
+use crate::{gsa, linalg, ode};
+
+// dY/dk of the deterministic model's observable, exact by dual numbers
+// (ode::integrate_dual). Output: n_times rows of 6 derivatives.
+pub fn ode_jacobian(y0: &ode::State, t0: f64, k: &Rates, dt: f64, times: &[f64], obs: Observable) -> Vec<f64> {
+    ode::integrate_dual(y0, t0, k, dt, times).iter()
+        .flat_map(|(_, sens)| sens.map(|d| obs.value(&d)))
+        .collect()
+}
+
+// ode_jacobian, optionally scaled to d ln Y / d ln k (0 where Y is 0).
+// Output: n_times rows of 6 derivatives.
+pub fn sensitivities(
+    y0: &ode::State, t0: f64, k: &Rates, dt: f64, times: &[f64], obs: Observable, relative: bool,
+) -> Vec<f64> {
+    let mut data = ode_jacobian(y0, t0, k, dt, times, obs);
+    if relative {
+        let base = ode::integrate(y0, t0, k, dt, times);
+        for (i, y) in base.iter().enumerate() {
+            for j in 0..6 {
+                let d = &mut data[i * 6 + j];
+                let v = obs.value(y);
+                *d = if v != 0.0 { *d * k[j] / v } else { 0.0 };
+            }
+        }
+    }
+    data
+}
+
+// Fisher information J^T J / sigma^2 over the rate constants in idx for one
+// sampling schedule. log_params differentiates w.r.t. ln k, which makes the
+// determinant comparable across parameter scales.
+#[allow(clippy::too_many_arguments)]
+pub fn fim_for_schedule(
+    y0: &ode::State, t0: f64, k: &Rates, dt: f64, times: &[f64], obs: Observable,
+    idx: &[usize], sigma: f64, log_params: bool,
+) -> Vec<f64> {
+    let full = ode_jacobian(y0, t0, k, dt, times, obs);
+    let m = idx.len();
+    let mut j = vec![0.0; times.len() * m];
+    for i in 0..times.len() {
+        for (c, &p) in idx.iter().enumerate() {
+            let scale = if log_params { k[p] } else { 1.0 };
+            j[i * m + c] = full[i * 6 + p] * scale;
+        }
+    }
+    let s2 = if sigma.is_finite() && sigma > 0.0 { sigma * sigma } else { 1.0 };
+    linalg::gram(&j, times.len(), m).iter().map(|v| v / s2).collect()
+}
+
+// m x m FIM (row-major) followed by log det(FIM)
+#[allow(clippy::too_many_arguments)]
+pub fn fisher_information(
+    y0: &ode::State, t0: f64, k: &Rates, dt: f64, times: &[f64], obs: Observable,
+    idx: &[usize], sigma: f64, log_params: bool,
+) -> Vec<f64> {
+    let mut data = fim_for_schedule(y0, t0, k, dt, times, obs, idx, sigma, log_params);
+    data.push(linalg::log_det_spd(&data, idx.len()));
+    data
+}
+
+// log det(FIM) for each schedule; schedules are concatenated with their lengths given
+#[allow(clippy::too_many_arguments)]
+pub fn d_optimal_scores(
+    y0: &ode::State, t0: f64, k: &Rates, dt: f64, schedules: &[f64], lengths: &[u32],
+    obs: Observable, idx: &[usize], sigma: f64, log_params: bool,
+) -> Vec<f64> {
+    let mut scores = Vec::new();
+    let mut off = 0usize;
+    for &len in lengths {
+        let end = (off + len as usize).min(schedules.len());
+        let fim = fim_for_schedule(y0, t0, k, dt, &schedules[off..end], obs, idx, sigma, log_params);
+        scores.push(linalg::log_det_spd(&fim, idx.len()));
+        off = end;
+    }
+    scores
+}
+
+// Parameter correlations from the asymptotic covariance sigma^2 (J^T J)^-1,
+// with sigma^2 = SSE / (n - m). Pairs with |r| above threshold (default 0.95)
+// are flagged. Output: [m x m correlation][m standard errors][n_flagged]
+// [i, j, r] * n_flagged; empty if J^T J is singular.
+#[allow(clippy::too_many_arguments)]
+pub fn parameter_correlations(
+    y0: &ode::State, t0: f64, k: &Rates, dt: f64, times: &[f64], y_obs: &[f64],
+    obs: Observable, idx: &[usize], threshold: f64,
+) -> Vec<f64> {
+    let m = idx.len();
+    let n = times.len().min(y_obs.len());
+    let pred = ode::integrate(y0, t0, k, dt, &times[..n]);
+    let sse: f64 = (0..n).map(|i| (y_obs[i] - obs.value(&pred[i])).powi(2)).sum();
+    let sigma2 = if n > m { sse / (n - m) as f64 } else { 1.0 };
+
+    let fim = fim_for_schedule(y0, t0, k, dt, &times[..n], obs, idx, 1.0, false);
+    let cov = match linalg::invert(&fim, m) {
+        Some(inv) => inv.iter().map(|v| v * sigma2).collect::<Vec<f64>>(),
+        None => return Vec::new(),
+    };
+    let se: Vec<f64> = (0..m).map(|i| cov[i * m + i].max(0.0).sqrt()).collect();
+    let mut corr = vec![0.0; m * m];
+    for a in 0..m {
+        for b in 0..m {
+            let d = se[a] * se[b];
+            corr[a * m + b] = if d > 0.0 { cov[a * m + b] / d } else { 0.0 };
+        }
+    }
+    let thr = if threshold.is_finite() && threshold > 0.0 { threshold } else { 0.95 };
+    let mut flagged = Vec::new();
+    for a in 0..m {
+        for b in (a + 1)..m {
+            let r = corr[a * m + b];
+            if r.abs() > thr { flagged.extend_from_slice(&[idx[a] as f64, idx[b] as f64, r]); }
+        }
+    }
+
+    let mut data = corr;
+    data.extend_from_slice(&se);
+    data.push((flagged.len() / 3) as f64);
+    data.extend_from_slice(&flagged);
+    data
+}
+
+// Sobol (S, ST) per rate constant for an observable at t0 + steps*dt, from the
+// deterministic model or a single stochastic run per sample. Empty unless
+// both bounds have 6 entries.
+#[allow(clippy::too_many_arguments)]
+pub fn sobol_indices(
+    y0: &ode::State, t0: f64, lower: &[f64], upper: &[f64], dt: f64, steps: u32,
+    obs: Observable, n_base: u32, log_scale: bool, stochastic: bool,
+) -> Vec<f64> {
+    if lower.len() < 6 || upper.len() < 6 { return Vec::new(); }
+    let dt = clamp_dt(dt);
+    let t_end = t0 + steps as f64 * dt;
+    let [e0, es0, ep0, s0, p0] = *y0;
+    let st0 = [e0, es0, ep0, s0, p0, t0];
+    let observe = |x: &[f64]| -> f64 {
+        let mut k = [0.0f64; 6];
+        k.copy_from_slice(&x[..6]);
+        if stochastic {
+            obs.value(&run_final(st0, &k, dt, steps))
+        } else {
+            obs.value(&ode::integrate(y0, t0, &k, dt, &[t_end])[0])
+        }
+    };
+    let idx = gsa::sobol_indices(observe, &lower[..6], &upper[..6], log_scale, n_base as usize);
+    idx.iter().flat_map(|&(s1, st)| [s1, st]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    const Y0: ode::State = [1.0, 0.0, 0.0, 50.0, 0.0];
+    const K: Rates = [0.2, 0.0, 1.0, 2.0, 0.0, 3.0];
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn fim_is_the_scaled_gram_of_the_jacobian() {
+        let times: Vec<f64> = (1..=8).map(|i| 2.0 * i as f64).collect();
+        let jac = ode_jacobian(&Y0, 0.0, &K, 0.01, &times, Observable::P);
+        let fim = fisher_information(&Y0, 0.0, &K, 0.01, &times, Observable::P, &[3, 5], 0.5, false);
+        let dot = |a: usize, b: usize| (0..8).map(|i| jac[i * 6 + a] * jac[i * 6 + b]).sum::<f64>() / 0.25;
+        let expected = [dot(3, 3), dot(3, 5), dot(5, 3), dot(5, 5)];
+        for (f, e) in fim[..4].iter().zip(&expected) { assert!((f - e).abs() < 1e-9 * e.abs().max(1.0)); }
+        let det = expected[0] * expected[3] - expected[1] * expected[2];
+        assert!((fim[4] - det.ln()).abs() < 1e-6);
+        // In ln k each column picks up its k: log det grows by 2 ln(k2 k3)
+        let log = fisher_information(&Y0, 0.0, &K, 0.01, &times, Observable::P, &[3, 5], 0.5, true);
+        assert!((log[4] - fim[4] - 2.0 * (K[3] * K[5]).ln()).abs() < 1e-6);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn more_samples_never_lose_information() {
+        let early = [1.0, 2.0, 3.0];
+        let both = [1.0, 2.0, 3.0, 20.0, 40.0, 60.0];
+        let schedules: Vec<f64> = early.iter().chain(&both).copied().collect();
+        let scores = d_optimal_scores(&Y0, 0.0, &K, 0.01, &schedules, &[3, 6], Observable::P, &[0, 3, 5], 1.0, true);
+        assert_eq!(scores.len(), 2);
+        assert!(scores[1] > scores[0], "{:?}", scores);
+        // One point cannot identify three rates
+        let single = d_optimal_scores(&Y0, 0.0, &K, 0.01, &[5.0], &[1], Observable::P, &[0, 3, 5], 1.0, true);
+        assert_eq!(single, [f64::NEG_INFINITY]);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn correlations_come_from_the_scaled_inverse_fim() {
+        let times: Vec<f64> = (1..=12).map(|i| 1.5 * i as f64).collect();
+        let clean: Vec<ode::State> = ode::integrate(&Y0, 0.0, &K, 0.01, &times);
+        let y: Vec<f64> = clean.iter().enumerate().map(|(i, s)| s[4] + if i % 2 == 0 { 0.3 } else { -0.3 }).collect();
+        let idx = [0, 3, 5];
+        let out = parameter_correlations(&Y0, 0.0, &K, 0.01, &times, &y, Observable::P, &idx, 0.0);
+        let (corr, se) = (&out[..9], &out[9..12]);
+        // sigma^2 from the residuals over n - m, then se = sqrt(diag(sigma^2 FIM^-1))
+        let sse: f64 = clean.iter().zip(&y).map(|(s, v)| (v - s[4]).powi(2)).sum();
+        let fim = fim_for_schedule(&Y0, 0.0, &K, 0.01, &times, Observable::P, &idx, 1.0, false);
+        let cov: Vec<f64> = linalg::invert(&fim, 3).unwrap().iter().map(|v| v * sse / 9.0).collect();
+        for a in 0..3 {
+            assert!((se[a] - cov[a * 3 + a].sqrt()).abs() < 1e-9 * se[a]);
+            assert!((corr[a * 3 + a] - 1.0).abs() < 1e-12);
+            for b in 0..3 {
+                assert!((corr[a * 3 + b] - corr[b * 3 + a]).abs() < 1e-12 && corr[a * 3 + b].abs() <= 1.0 + 1e-12);
+                assert!((corr[a * 3 + b] - cov[a * 3 + b] / (se[a] * se[b])).abs() < 1e-9);
+            }
+        }
+        // Pairs above the threshold (0 => 0.95) are listed as [i, j, r]
+        let n_flagged = out[12] as usize;
+        let expected: Vec<[f64; 3]> = [(0, 1), (0, 2), (1, 2)].iter()
+            .filter(|&&(a, b)| corr[a * 3 + b].abs() > 0.95)
+            .map(|&(a, b)| [idx[a] as f64, idx[b] as f64, corr[a * 3 + b]])
+            .collect();
+        assert_eq!(out[13..], expected.concat()[..]);
+        assert_eq!(n_flagged, expected.len());
+        let all = parameter_correlations(&Y0, 0.0, &K, 0.01, &times, &y, Observable::P, &idx, 1e-9);
+        assert_eq!(all[12], 3.0);
+    }
+}
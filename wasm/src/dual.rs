@@ -0,0 +1,103 @@
+// Forward-mode automatic differentiation: a value carried with its
+// derivatives along N seed directions. The deterministic model is generic
+// over Scalar, so running it on Dual numbers gives exact derivatives of its
+// predictions alongside the values.
+
+use std::ops::{Add, Mul, Neg, Sub};
+
+// Number type the deterministic model computes in
+pub trait Scalar: Copy + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> + Neg<Output = Self> {
+    fn constant(v: f64) -> Self;
+    fn value(self) -> f64;
+}
+
+impl Scalar for f64 {
+    #[inline]
+    fn constant(v: f64) -> f64 { v }
+    #[inline]
+    fn value(self) -> f64 { self }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Dual<const N: usize> {
+    pub v: f64,
+    pub d: [f64; N],
+}
+
+impl<const N: usize> Dual<N> {
+    // v varying along seed direction i (derivative 1 there, 0 elsewhere)
+    pub fn variable(v: f64, i: usize) -> Dual<N> {
+        let mut d = [0.0; N];
+        d[i] = 1.0;
+        Dual { v, d }
+    }
+}
+
+impl<const N: usize> Add for Dual<N> {
+    type Output = Dual<N>;
+    #[inline]
+    fn add(self, o: Dual<N>) -> Dual<N> {
+        let mut d = self.d;
+        for (a, b) in d.iter_mut().zip(&o.d) { *a += b; }
+        Dual { v: self.v + o.v, d }
+    }
+}
+
+impl<const N: usize> Sub for Dual<N> {
+    type Output = Dual<N>;
+    #[inline]
+    fn sub(self, o: Dual<N>) -> Dual<N> {
+        let mut d = self.d;
+        for (a, b) in d.iter_mut().zip(&o.d) { *a -= b; }
+        Dual { v: self.v - o.v, d }
+    }
+}
+
+impl<const N: usize> Mul for Dual<N> {
+    type Output = Dual<N>;
+    #[inline]
+    fn mul(self, o: Dual<N>) -> Dual<N> {
+        let mut d = [0.0; N];
+        for (i, di) in d.iter_mut().enumerate() { *di = self.d[i] * o.v + self.v * o.d[i]; }
+        Dual { v: self.v * o.v, d }
+    }
+}
+
+impl<const N: usize> Neg for Dual<N> {
+    type Output = Dual<N>;
+    #[inline]
+    fn neg(self) -> Dual<N> { Dual { v: -self.v, d: self.d.map(|x| -x) } }
+}
+
+impl<const N: usize> Scalar for Dual<N> {
+    #[inline]
+    fn constant(v: f64) -> Dual<N> { Dual { v, d: [0.0; N] } }
+    #[inline]
+    fn value(self) -> f64 { self.v }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ode;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn dual_model_derivatives_match_sensitivities() {
+        let (x, y) = (Dual::<2>::variable(3.0, 0), Dual::<2>::variable(-2.0, 1));
+        let f = x * x * y - Dual::constant(4.0) * y + -x;
+        assert_eq!(f, Dual { v: -13.0, d: [2.0 * 3.0 * -2.0 - 1.0, 9.0 - 4.0] });
+
+        let k = [2e-3, 1e-3, 0.3, 1.5, 0.4, 0.8];
+        let y0 = [10.0, 0.0, 0.0, 500.0, 20.0];
+        let times = [0.5, 3.0, 20.0];
+        let dual = ode::integrate_dual(&y0, 0.0, &k, 0.01, &times);
+        let forward = ode::integrate_sensitivities(&y0, 0.0, &k, 0.01, &times);
+        assert_eq!(dual.iter().map(|r| r.0).collect::<Vec<_>>(), ode::integrate(&y0, 0.0, &k, 0.01, &times));
+        for ((_, a), (_, b)) in dual.iter().zip(&forward) {
+            for j in 0..6 {
+                for i in 0..5 { assert!((a[j][i] - b[j][i]).abs() <= 1e-9 * b[j][i].abs().max(1e-6), "{} {}: {} vs {}", j, i, a[j][i], b[j][i]); }
+            }
+        }
+    }
+}
@@ -0,0 +1,379 @@
+// Replicate ensembles of the stochastic engine and summaries over them.
+
+use crate::model::{channel_p_tot, clamp_dt, Clock, run_final, run_final_adaptive, run_series, step, Observable, Rates, State};
+use crate::parallel;
+use crate::rng::{self, seed_rng};
+use crate::summary::conversion_times;
+use crate::warn;
+
+// n runs of `run`, independent, or with antithetic set as pairs whose second
+// run replays the first's uniforms mirrored (rng::mirror_draws), so their
+// noise is negatively correlated and pair means vary less. Draws beyond the
+// first run's come from the live stream. An odd n ends with an unpaired run.
+fn replicate_runs<T: Send>(n: u32, antithetic: bool, run: impl Fn() -> T + Sync) -> Vec<T> {
+    if !antithetic { return parallel::par_map(n as usize, |_| run()); }
+    let pairs = parallel::par_map(n.div_ceil(2) as usize, |i| {
+        rng::start_recording();
+        let first = run();
+        let mut draws = rng::finish_recording();
+        if 2 * i + 1 >= n as usize { return vec![first]; }
+        rng::mirror_draws(&mut draws);
+        rng::start_replay(draws);
+        let second = run();
+        rng::stop_replay();
+        vec![first, second]
+    });
+    pairs.into_iter().flatten().collect()
+}
+
+// Final states of replicate runs from the same initial state, with steps
+// refined to keep p_tot <= p_max (NaN => fixed steps); independent, or in
+// antithetic pairs (see replicate_runs)
+pub fn replicates_final(
+    st0: State, k: &Rates, dt: f64, steps: u32, n_replicates: u32, p_max: f64, antithetic: bool,
+) -> Vec<State> {
+    replicate_runs(n_replicates, antithetic, || run_final_adaptive(st0, k, dt, steps, p_max))
+}
+
+// Initial amount a titration varies
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Titrant {
+    Enzyme, // E, with ES and EP cleared
+    Substrate, // S
+}
+
+impl Titrant {
+    // 0: enzyme, 1: substrate
+    pub fn from_code(code: u32) -> Result<Titrant, String> {
+        match code {
+            0 => Ok(Titrant::Enzyme),
+            1 => Ok(Titrant::Substrate),
+            _ => Err(format!("unknown titrant {} (expected 0: enzyme or 1: substrate)", code)),
+        }
+    }
+}
+
+// One run per amount of a titration from st0, with the titrant set to the
+// amount and everything else kept: `steps` [E, ES, EP, S, P, t] rows per run
+// stacked run after run when series is set, else one final state per run
+pub fn titration(st0: State, k: &Rates, dt: f64, steps: u32, amounts: &[f64], titrant: Titrant, series: bool) -> Vec<f64> {
+    let runs = parallel::par_map(amounts.len(), |i| {
+        let mut st = st0;
+        match titrant {
+            Titrant::Enzyme => st[..3].copy_from_slice(&[amounts[i], 0.0, 0.0]),
+            Titrant::Substrate => st[3] = amounts[i],
+        }
+        if series { run_series(st, k, dt, steps) } else { run_final(st, k, dt, steps).to_vec() }
+    });
+    runs.concat()
+}
+
+// Width of a replicate_band row
+pub const BAND_ROW: usize = 11;
+
+// Mean and spread of replicate series, one row per step: [t, mean E, ES, EP,
+// S, P, sample sd of each]. With antithetic runs (see replicate_runs) the
+// mean varies less than over as many independent runs, by more the longer a
+// pair's draws stay in step; the sd is still the spread of single runs.
+pub fn replicate_band(st0: State, k: &Rates, dt: f64, steps: u32, n_replicates: u32, antithetic: bool) -> Vec<f64> {
+    let runs = replicate_runs(n_replicates.max(1), antithetic, || run_series(st0, k, dt, steps));
+    let n = runs.len() as f64;
+    let mut out = vec![0.0; BAND_ROW * steps as usize];
+    for (r, row) in out.chunks_exact_mut(BAND_ROW).enumerate() {
+        row[0] = runs[0][6 * r + 5];
+        for c in 0..5 {
+            let mean = runs.iter().map(|s| s[6 * r + c]).sum::<f64>() / n;
+            let ss = runs.iter().map(|s| (s[6 * r + c] - mean).powi(2)).sum::<f64>();
+            row[1 + c] = mean;
+            row[6 + c] = if n > 1.0 { (ss / (n - 1.0)).sqrt() } else { 0.0 };
+        }
+    }
+    out
+}
+
+// Width of a dt_convergence row
+pub const DT_STUDY_ROW: usize = 13;
+
+// Reruns to t_end at dt, dt/2, ..., dt/2^(levels-1), each level from the same
+// seed and averaged over n_replicates sequential runs (so the study is
+// reproducible with or without threads). One row per level, coarsest first:
+// [dt, mean E, ES, EP, S, P at t_end, |difference| of each from the finest
+// level, largest channel p_tot seen, 1 if it exceeded p_warn (NaN =>
+// warn::P_TOT_WARN)].
+#[allow(clippy::too_many_arguments)]
+pub fn dt_convergence(
+    st0: State, k: &Rates, dt: f64, t_end: f64, levels: u32, n_replicates: u32, seed: u64, p_warn: f64,
+) -> Vec<f64> {
+    let dt = clamp_dt(dt);
+    let p_warn = if p_warn.is_finite() { p_warn } else { warn::P_TOT_WARN };
+    let span = if t_end.is_finite() { (t_end - st0[5]).max(0.0) } else { 0.0 };
+    let n_rep = n_replicates.max(1);
+    let mut rows: Vec<[f64; DT_STUDY_ROW]> = Vec::new();
+    for level in 0..levels.max(1) {
+        let h = dt / 2f64.powi(level as i32);
+        let steps = (span / h).ceil() as u64;
+        seed_rng(seed);
+        let mut row = [0.0; DT_STUDY_ROW];
+        row[0] = h;
+        for _ in 0..n_rep {
+            let mut st = st0;
+            for _ in 0..steps {
+                row[11] = channel_p_tot(&st, k, h).into_iter().fold(row[11], f64::max);
+                step(&mut st, k, h);
+            }
+            for i in 0..5 { row[1 + i] += st[i] / n_rep as f64; }
+        }
+        row[12] = if row[11] > p_warn { 1.0 } else { 0.0 };
+        rows.push(row);
+    }
+    let finest = rows[rows.len() - 1];
+    for row in rows.iter_mut() {
+        for i in 0..5 { row[6 + i] = (row[1 + i] - finest[1 + i]).abs(); }
+    }
+    rows.concat()
+}
+
+// First-passage times of an observable across a threshold, one per replicate.
+// falling => first time value <= threshold (e.g. S at 50% conversion),
+// otherwise first time value >= threshold. Crossing times are interpolated
+// within the step; NaN marks replicates that never crossed within max_steps.
+#[allow(clippy::too_many_arguments)]
+pub fn first_passage_times(
+    st0: State, k: &Rates, dt: f64, max_steps: u32,
+    observable: Observable, threshold: f64, falling: bool, n_replicates: u32,
+) -> Vec<f64> {
+    let dt = clamp_dt(dt);
+    let crossed = |v: f64| if falling { v <= threshold } else { v >= threshold };
+    parallel::par_map(n_replicates as usize, |_| {
+        let mut st = st0;
+        let mut clock = Clock::new(st[5]);
+        let mut hit = if crossed(observable.value(&st)) { st[5] } else { f64::NAN };
+        let mut i = 0;
+        while hit.is_nan() && i < max_steps {
+            let prev = st;
+            step(&mut st, k, dt);
+            st[5] = clock.tick(dt);
+            let (v0, v1) = (observable.value(&prev), observable.value(&st));
+            if crossed(v1) {
+                let dv = v1 - v0;
+                let w = if dv != 0.0 { ((threshold - v0) / dv).clamp(0.0, 1.0) } else { 1.0 };
+                hit = prev[5] + w * (st[5] - prev[5]);
+            }
+            i += 1;
+        }
+        hit
+    })
+}
+
+// Width of a conversion_time_stats row
+pub const CONVERSION_ROW: usize = 4;
+
+// Times to substrate conversion fractions (1 - S / S0, see
+// summary::conversion_times) across replicates run until every fraction is
+// reached or max_steps pass. One row per fraction: [fraction, mean time,
+// sample sd, replicates that reached it], over the replicates that did (NaN
+// mean / sd when too few did).
+pub fn conversion_time_stats(
+    st0: State, k: &Rates, dt: f64, max_steps: u32, fractions: &[f64], n_replicates: u32,
+) -> Vec<f64> {
+    let dt = clamp_dt(dt);
+    let times = parallel::par_map(n_replicates as usize, |_| {
+        let mut st = st0;
+        let mut clock = Clock::new(st[5]);
+        let mut series = st.to_vec();
+        // Rows only need to cover the deepest fraction asked for
+        let deepest = fractions.iter().fold(f64::INFINITY, |a, &f| a.min((1.0 - f) * st0[3]));
+        let mut i = 0;
+        while i < max_steps && st[3] > deepest {
+            step(&mut st, k, dt);
+            st[5] = clock.tick(dt);
+            series.extend_from_slice(&st);
+            i += 1;
+        }
+        conversion_times(&series, 0, fractions)
+    });
+    let mut out = Vec::with_capacity(CONVERSION_ROW * fractions.len());
+    for (j, &f) in fractions.iter().enumerate() {
+        let hit: Vec<f64> = times.iter().map(|t| t[j]).filter(|t| t.is_finite()).collect();
+        let n = hit.len() as f64;
+        let mean = if n > 0.0 { hit.iter().sum::<f64>() / n } else { f64::NAN };
+        let sd = if n > 1.0 { (hit.iter().map(|t| (t - mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt() } else { f64::NAN };
+        out.extend_from_slice(&[f, mean, sd, n]);
+    }
+    out
+}
+
+// Equal-width histogram over the sample range: (n_bins + 1 edges, n_bins counts).
+// Non-finite samples are skipped; a degenerate range is widened by 0.5 each side.
+pub fn histogram(samples: &[f64], n_bins: usize) -> (Vec<f64>, Vec<f64>) {
+    let n_bins = n_bins.max(1);
+    let finite: Vec<f64> = samples.iter().copied().filter(|v| v.is_finite()).collect();
+    let (mut lo, mut hi) = finite.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(a, b), &v| (a.min(v), b.max(v)));
+    if finite.is_empty() { lo = 0.0; hi = 1.0; }
+    if hi <= lo { lo -= 0.5; hi += 0.5; }
+    let width = (hi - lo) / n_bins as f64;
+    let edges: Vec<f64> = (0..=n_bins).map(|i| lo + i as f64 * width).collect();
+    let mut counts = vec![0.0; n_bins];
+    for v in finite {
+        let b = (((v - lo) / width) as usize).min(n_bins - 1);
+        counts[b] += 1.0;
+    }
+    (edges, counts)
+}
+
+// Histogram of an observable after `steps` steps across independent replicates
+pub fn final_state_histogram(
+    st0: State, k: &Rates, dt: f64, steps: u32, n_replicates: u32, observable: Observable, n_bins: u32,
+) -> (Vec<f64>, Vec<f64>) {
+    let samples = parallel::par_map(n_replicates as usize, |_| observable.value(&run_final(st0, k, dt, steps)));
+    histogram(&samples, n_bins as usize)
+}
+
+// Conservation audit of a series buffer whose rows start with [E, ES, EP, S, P].
+// Checks E+ES+EP and S+P+ES+EP against the given totals (NaN => first row).
+// stride is the row width (0 => 6, use 12 for flux series).
+// Returns [max |enzyme drift|, row of max, max |substrate drift|, row of max].
+pub fn audit_conservation(data: &[f64], enzyme_total: f64, substrate_total: f64, stride: u32) -> [f64; 4] {
+    let w = if stride == 0 { 6 } else { (stride as usize).max(5) };
+    let rows = data.len() / w;
+    let enzyme = |r: usize| data[r * w] + data[r * w + 1] + data[r * w + 2];
+    let substrate = |r: usize| data[r * w + 3] + data[r * w + 4] + data[r * w + 1] + data[r * w + 2];
+    let mut out = [0.0; 4];
+    if rows == 0 { return out; }
+    let e_ref = if enzyme_total.is_finite() { enzyme_total } else { enzyme(0) };
+    let s_ref = if substrate_total.is_finite() { substrate_total } else { substrate(0) };
+    for r in 0..rows {
+        let de = (enzyme(r) - e_ref).abs();
+        let ds = (substrate(r) - s_ref).abs();
+        if de > out[0] { out[0] = de; out[1] = r as f64; }
+        if ds > out[2] { out[2] = ds; out[3] = r as f64; }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::{next_seed, rand_f64};
+    use wasm_bindgen_test::*;
+
+    const ST0: State = [100.0, 0.0, 0.0, 10_000.0, 0.0, 2.0];
+    const K: Rates = [1e-3, 0.0, 0.1, 1.0, 0.0, 1.0];
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn first_passage_interpolates_within_the_crossing_step() {
+        // A single replicate runs on the first stream seeded off the caller's
+        seed_rng(31);
+        seed_rng(next_seed());
+        let series = run_series(ST0, &K, 0.1, 400);
+        seed_rng(31);
+        let hit = first_passage_times(ST0, &K, 0.1, 400, Observable::S, 9000.0, true, 1)[0];
+        let r = series.chunks(6).position(|row| row[3] <= 9000.0).unwrap();
+        let (prev, row) = (if r == 0 { &ST0[..] } else { &series[6 * (r - 1)..6 * r] }, &series[6 * r..6 * r + 6]);
+        assert!(hit > prev[5] && hit <= row[5], "{} not in ({}, {}]", hit, prev[5], row[5]);
+        let w = (9000.0 - prev[3]) / (row[3] - prev[3]);
+        assert!((hit - (prev[5] + w * (row[5] - prev[5]))).abs() < 1e-9);
+        // Already past the threshold: t0; never reaching it: NaN
+        let early = first_passage_times(ST0, &K, 0.1, 10, Observable::S, 20_000.0, true, 3);
+        assert_eq!(early, [2.0; 3]);
+        let never = first_passage_times(ST0, &[0.0; 6], 0.1, 50, Observable::P, 1.0, false, 2);
+        assert!(never.iter().all(|t| t.is_nan()));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn histograms_cover_every_finite_sample() {
+        let (edges, counts) = histogram(&[0.0, 1.0, 1.5, f64::NAN, 4.0, 4.0, f64::INFINITY], 4);
+        assert_eq!(edges, [0.0, 1.0, 2.0, 3.0, 4.0]);
+        // The maximum falls in the last bin
+        assert_eq!(counts, [1.0, 2.0, 0.0, 2.0]);
+        assert_eq!(histogram(&[3.0, 3.0], 2), (vec![2.5, 3.0, 3.5], vec![0.0, 2.0]));
+        assert_eq!(histogram(&[], 0), (vec![0.0, 1.0], vec![0.0]));
+        let (edges, counts) = final_state_histogram(ST0, &K, 0.1, 100, 40, Observable::P, 8);
+        assert_eq!((edges.len(), counts.len()), (9, 8));
+        assert_eq!(counts.iter().sum::<f64>(), 40.0);
+        assert!(edges[0] > 0.0 && counts[0] > 0.0 && counts[7] > 0.0);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn conservation_audit_finds_the_worst_row() {
+        let mut data = run_series(ST0, &K, 0.1, 50);
+        assert_eq!(audit_conservation(&data, 100.0, 10_000.0, 0), [0.0; 4]);
+        // Leak enzyme at row 12 and substrate at row 30
+        data[6 * 12 + 1] += 2.0;
+        data[6 * 30 + 4] -= 3.0;
+        data[6 * 31 + 4] -= 1.0;
+        assert_eq!(audit_conservation(&data, 100.0, 10_000.0, 6), [2.0, 12.0, 3.0, 30.0]);
+        // NaN totals take the first row's; flux rows have 12 columns
+        let fluxes = crate::model::run_series_fluxes(ST0, &K, 0.1, 50);
+        assert_eq!(audit_conservation(&fluxes, f64::NAN, f64::NAN, 12), [0.0; 4]);
+        assert_eq!(audit_conservation(&fluxes, 99.0, 10_000.0, 12)[..2], [1.0, 0.0]);
+        assert_eq!(audit_conservation(&[], 1.0, 1.0, 0), [0.0; 4]);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn dt_study_halves_the_step_per_level() {
+        let study = dt_convergence(ST0, &K, 0.02, 12.0, 3, 4, 5, f64::NAN);
+        let rows: Vec<&[f64]> = study.chunks(DT_STUDY_ROW).collect();
+        assert_eq!(rows.len(), 3);
+        assert_eq!([rows[0][0], rows[1][0], rows[2][0]], [0.02, 0.01, 0.005]);
+        for row in &rows {
+            // Means of conserved totals stay exact
+            assert!((row[1] + row[2] + row[3] - 100.0).abs() < 1e-9);
+            for i in 0..5 { assert_eq!(row[6 + i], (row[1 + i] - rows[2][1 + i]).abs()); }
+        }
+        assert_eq!(rows[2][6..11], [0.0; 5]);
+        // p_tot scales with the step: 0.02 s is coarse, 0.005 s is not
+        assert!(rows[0][11] > rows[1][11] && rows[1][11] > rows[2][11]);
+        assert_eq!([rows[0][12], rows[2][12]], [1.0, 0.0], "{} {}", rows[0][11], rows[2][11]);
+        // Seeded, so independent of the caller's stream
+        seed_rng(99);
+        assert_eq!(dt_convergence(ST0, &K, 0.02, 12.0, 3, 4, 5, f64::NAN), study);
+        assert!(dt_convergence(ST0, &K, 0.02, 12.0, 3, 4, 5, 1.0).chunks(DT_STUDY_ROW).all(|r| r[12] == 0.0));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn antithetic_pairs_mirror_their_uniforms() {
+        seed_rng(37);
+        let runs = replicate_runs(5, true, || [rand_f64(), rand_f64()]);
+        for pair in runs[..4].chunks(2) {
+            assert_eq!(pair[1], pair[0].map(|u| 1.0 - f64::EPSILON / 2.0 - u));
+        }
+        assert_ne!(runs[4], runs[0]);
+        // Mirrored noise anticorrelates the substrate a pair has consumed
+        // while its draws stay in step (a few steps; it fades over longer runs)
+        let st0 = [100.0, 0.0, 0.0, 10_000.0, 0.0, 0.0];
+        let finals = replicates_final(st0, &K, 0.01, 3, 400, f64::NAN, true);
+        let used: Vec<f64> = finals.iter().map(|st| 10_000.0 - st[3]).collect();
+        let mean = used.iter().sum::<f64>() / used.len() as f64;
+        let var = used.iter().map(|v| (v - mean).powi(2)).sum::<f64>();
+        let corr = 2.0 * used.chunks(2).map(|w| (w[0] - mean) * (w[1] - mean)).sum::<f64>() / var;
+        assert!(corr < -0.3, "{}", corr);
+        // Bands over pairs still report single-run spread; one run has none
+        let band = replicate_band(st0, &K, 0.01, 20, 1, true);
+        assert_eq!(band.len(), BAND_ROW * 20);
+        assert!(band.chunks(BAND_ROW).all(|row| row[6..] == [0.0; 5]));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn titrations_set_only_the_titrant() {
+        let st0 = [7.0, 3.0, 2.0, 10_000.0, 50.0, 0.0];
+        let amounts = [0.0, 20.0, 200.0];
+        let finals = titration(st0, &K, 0.1, 100, &amounts, Titrant::Enzyme, false);
+        assert_eq!(finals.len(), 18);
+        for (st, &e) in finals.chunks(6).zip(&amounts) {
+            assert_eq!(st[0] + st[1] + st[2], e);
+            assert_eq!(st[1] + st[2] + st[3] + st[4], 10_050.0);
+            assert!((st[5] - 10.0).abs() < 1e-9);
+        }
+        // Without enzyme nothing turns over; more enzyme converts more
+        assert_eq!(finals[3..5], [10_000.0, 50.0]);
+        assert!(finals[16] > finals[10] && finals[10] > 50.0);
+        // One run of `steps` rows per amount, each from its own substrate level
+        let series = titration(st0, &K, 0.1, 30, &[500.0, 5000.0], Titrant::Substrate, true);
+        assert_eq!(series.len(), 2 * 30 * 6);
+        for (run, s0) in series.chunks(30 * 6).zip([500.0, 5000.0]) {
+            assert!(run.chunks(6).all(|row| row[0] + row[1] + row[2] == 12.0 && row[1] + row[2] + row[3] + row[4] == s0 + 55.0));
+        }
+        assert!(Titrant::from_code(2).is_err());
+    }
+}
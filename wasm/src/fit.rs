@@ -0,0 +1,1141 @@
+// Parameter estimation: Nelder–Mead over the masked parameters, outlier
+// screening with refits, and random-walk Metropolis–Hastings posterior sampling.
+
+use std::borrow::Cow;
+
+use crate::linalg;
+use crate::parallel;
+use crate::objective::{lower_bound, Params, Problem, Readout, N_PARAMS};
+use crate::rng::{next_seed, rand_f64, rand_std_normal, seed_rng};
+
+// Termination reasons reported in the fit output
+pub const FIT_CONVERGED: f64 = 0.0; // f-value spread fell below tol
+pub const FIT_MAX_ITER: f64 = 1.0; // iteration budget exhausted
+pub const FIT_CANCELLED: f64 = 2.0; // progress callback asked to stop
+pub const FIT_NOTHING_TO_DO: f64 = 3.0; // empty mask
+pub const FIT_X_CONVERGED: f64 = 4.0; // simplex diameter fell below x_tol
+pub const FIT_MAX_EVALS: f64 = 5.0; // evaluation budget could not cover another iteration
+
+// Name of a termination reason, for reports
+pub fn reason_name(reason: f64) -> &'static str {
+    match reason as i64 {
+        0 => "converged",
+        1 => "max_iter",
+        2 => "cancelled",
+        3 => "nothing_to_do",
+        4 => "x_converged",
+        5 => "max_evals",
+        _ => "unknown",
+    }
+}
+
+pub struct FitResult {
+    pub params: Params,
+    pub sse: f64,
+    pub iterations: u32,
+    pub evaluations: u32,
+    pub spread: f64,
+    pub reason: f64,
+    pub restarts: u32,
+    pub simplex: Vec<Params>, // final vertices as full parameter vectors, best first
+    // Best (objective, params) entering each iteration, when NelderMead::trace is set
+    pub trace: Vec<(f64, Params)>,
+    // Observation indices left out of the final fit by refit_without_outliers,
+    // ascending, and the studentized residual of every observation (each
+    // excluded point keeps the value that flagged it); both empty otherwise
+    pub excluded: Vec<usize>,
+    pub studentized: Vec<f64>,
+}
+
+// Offsets in FitResult::to_vec of the nuisance parameters (Params entries
+// after dt) and of the simplex block
+const NUISANCE_OFFSET: usize = 13;
+const SIMPLEX_OFFSET: usize = NUISANCE_OFFSET + N_PARAMS - 7;
+
+impl FitResult {
+    // Result of a fitter that tracks one point rather than a simplex; the
+    // simplex block holds that point
+    pub fn single_point(params: Params, sse: f64, iterations: u32, evaluations: u32, spread: f64, reason: f64, trace: Vec<(f64, Params)>) -> FitResult {
+        FitResult {
+            params, sse, iterations, evaluations, spread, reason, restarts: 0,
+            simplex: vec![params], trace, excluded: Vec::new(), studentized: Vec::new(),
+        }
+    }
+
+    // [k1,k-3,k-1,k2,k-2,k3,dt, sse, iterations, evaluations, spread, reason,
+    //  restarts, t_shift, E0..P0, signal_scale, signal_offset, drift,
+    //  drift_decay, n_vertices,
+    //  n_vertices rows of Params, n_trace, n_trace rows of [objective, Params],
+    //  n_excluded, excluded indices, n_studentized, studentized residuals].
+    //  The leading fields keep the layout of the original 7-parameter output.
+    pub fn to_vec(&self) -> Vec<f64> {
+        let mut v = self.params[..7].to_vec();
+        v.extend_from_slice(&[self.sse, self.iterations as f64, self.evaluations as f64, self.spread, self.reason]);
+        v.push(self.restarts as f64);
+        v.extend_from_slice(&self.params[7..]);
+        v.push(self.simplex.len() as f64);
+        for vertex in &self.simplex { v.extend_from_slice(vertex); }
+        v.push(self.trace.len() as f64);
+        for (f, p) in &self.trace {
+            v.push(*f);
+            v.extend_from_slice(p);
+        }
+        v.push(self.excluded.len() as f64);
+        v.extend(self.excluded.iter().map(|&i| i as f64));
+        v.push(self.studentized.len() as f64);
+        v.extend_from_slice(&self.studentized);
+        v
+    }
+}
+
+// Best parameters from a previous FitResult::to_vec
+pub fn params_from_output(v: &[f64]) -> Option<Params> {
+    let mut p = [0.0; N_PARAMS];
+    p[..7].copy_from_slice(v.get(..7)?);
+    p[7..].copy_from_slice(v.get(NUISANCE_OFFSET..SIMPLEX_OFFSET)?);
+    Some(p)
+}
+
+// FitResult back from its to_vec layout; None if the buffer is truncated
+pub fn result_from_output(v: &[f64]) -> Option<FitResult> {
+    let params = params_from_output(v)?;
+    let simplex = simplex_from_output(v)?;
+    let mut at = SIMPLEX_OFFSET + 1 + N_PARAMS * simplex.len();
+    let n_trace = *v.get(at)? as usize;
+    let trace = v.get(at + 1..at + 1 + (N_PARAMS + 1) * n_trace)?.chunks_exact(N_PARAMS + 1).map(|r| {
+        let mut p = [0.0; N_PARAMS];
+        p.copy_from_slice(&r[1..]);
+        (r[0], p)
+    }).collect();
+    at += 1 + (N_PARAMS + 1) * n_trace;
+    let n_excluded = *v.get(at)? as usize;
+    let excluded = v.get(at + 1..at + 1 + n_excluded)?.iter().map(|&i| i as usize).collect();
+    at += 1 + n_excluded;
+    let n_studentized = *v.get(at)? as usize;
+    let studentized = v.get(at + 1..at + 1 + n_studentized)?.to_vec();
+    Some(FitResult {
+        params,
+        sse: v[7],
+        iterations: v[8] as u32,
+        evaluations: v[9] as u32,
+        spread: v[10],
+        reason: v[11],
+        restarts: v[12] as u32,
+        simplex,
+        trace,
+        excluded,
+        studentized,
+    })
+}
+
+// Simplex vertices from a previous FitResult::to_vec, for warm starts
+pub fn simplex_from_output(v: &[f64]) -> Option<Vec<Params>> {
+    let n = *v.get(SIMPLEX_OFFSET)? as usize;
+    let rows = v.get(SIMPLEX_OFFSET + 1..SIMPLEX_OFFSET + 1 + N_PARAMS * n)?;
+    Some(rows.chunks_exact(N_PARAMS).map(|r| { let mut p = [0.0; N_PARAMS]; p.copy_from_slice(r); p }).collect())
+}
+
+#[derive(Clone)]
+pub struct NelderMead {
+    pub max_iter: u32,
+    pub tol: f64,
+    pub scale: f64, // relative size of the initial simplex
+    pub progress_every: u32, // 0 => every iteration
+    // Vertices of a previous fit's simplex; used when the count matches the
+    // mask (n + 1), otherwise the fit starts from a fresh simplex
+    pub warm_simplex: Option<Vec<Params>>,
+    // Fresh simplexes around the best point after convergence or stagnation;
+    // stops early once a restart fails to improve the best value
+    pub max_restarts: u32,
+    // Stop once every vertex is within x_tol of the best, relative per
+    // coordinate; <= 0 disables the test
+    pub x_tol: f64,
+    // Objective evaluations allowed in total, including the initial simplex;
+    // the fit stops before an iteration (up to n + 2 evaluations) or restart
+    // (n) that could exceed it. 0 means no limit.
+    pub max_evals: u32,
+    // Record the best objective and parameters at every iteration
+    pub trace: bool,
+    // Common random numbers: every objective evaluation restarts the rng
+    // from this seed, so vertices are compared on the same noise rather
+    // than on independent draws. The fit then minimizes one realization of
+    // the stochastic objective; the caller's stream moves on afterwards.
+    pub crn_seed: Option<u64>,
+    // Gao–Han coefficients for n fitted parameters: expansion 1 + 2/n,
+    // contraction 0.75 - 1/(2n) and shrink 1 - 1/n, which keep steps from
+    // collapsing as n grows; the classical 2, 0.5, 0.5 otherwise and for n < 2
+    pub adaptive: bool,
+    // Restarts (up to max_restarts) lay Kelley's oriented simplex along the
+    // simplex gradient instead of the axes, and an iteration without
+    // sufficient decrease of the mean objective also triggers one
+    pub oriented_restarts: bool,
+}
+
+// Sort simplex vertices by objective value, best first
+fn order_simplex(simplex: &mut Vec<Vec<f64>>, fvals: &mut Vec<f64>) {
+    let mut idxs: Vec<usize> = (0..fvals.len()).collect();
+    idxs.sort_by(|&a, &b| fvals[a].partial_cmp(&fvals[b]).unwrap_or(std::cmp::Ordering::Equal));
+    *simplex = idxs.iter().map(|&i| simplex[i].clone()).collect();
+    *fvals = idxs.iter().map(|&i| fvals[i]).collect();
+}
+
+// Axis-aligned simplex around x0 with relative step scale (absolute where x0 is 0)
+fn fresh_simplex(x0: &[f64], scale: f64) -> Vec<Vec<f64>> {
+    let sc = if scale.is_finite() && scale > 0.0 { scale } else { 0.1 };
+    let mut simplex = vec![x0.to_vec()];
+    for i in 0..x0.len() {
+        let mut xi = x0.to_vec();
+        let base = xi[i].abs();
+        let delta = if base > 0.0 { base * sc } else { sc };
+        xi[i] += delta;
+        simplex.push(xi);
+    }
+    simplex
+}
+
+// Largest relative coordinate distance from the best vertex (simplex[0])
+fn simplex_diameter(simplex: &[Vec<f64>]) -> f64 {
+    let best = &simplex[0];
+    simplex[1..].iter().flat_map(|v| v.iter().zip(best).map(|(a, b)| (a - b).abs() / b.abs().max(1.0e-12)))
+        .fold(0.0, f64::max)
+}
+
+// Standard deviation of the simplex objective values
+fn simplex_spread(fvals: &[f64]) -> f64 {
+    let m = fvals.len() as f64;
+    let mean = fvals.iter().sum::<f64>() / m;
+    let var = fvals.iter().map(|v| (v - mean) * (v - mean)).sum::<f64>() / m;
+    var.sqrt()
+}
+
+// Minimize the problem objective over params[optimize_idx], starting from params.
+// progress is called as (iteration, best objective, best params) every
+// opts.progress_every iterations; returning true stops the fit. With the
+// `threads` feature, the initial, restart and shrink vertices are evaluated
+// in parallel; each worker then draws from its own rng stream.
+pub fn nelder_mead(
+    problem: &Problem,
+    params: Params,
+    optimize_idx: &[usize],
+    opts: &NelderMead,
+    progress: impl FnMut(u32, f64, &Params) -> bool,
+) -> FitResult {
+    let mut run = NelderMeadRun::new(Cow::Borrowed(problem), params, optimize_idx, opts);
+    run.step(opts.max_iter, progress);
+    run.finish()
+}
+
+// A nelder_mead fit taken a few iterations at a time, so a caller can yield
+// between them: new evaluates the starting simplex, step runs further
+// iterations and finish gives the FitResult nelder_mead would have. With
+// crn_seed set, the caller's rng stream is put back after every call.
+pub struct NelderMeadRun<'a> {
+    problem: Cow<'a, Problem>,
+    params: Params,
+    optimize_idx: Vec<usize>,
+    opts: NelderMead,
+    simplex: Vec<Vec<f64>>,
+    fvals: Vec<f64>,
+    n_evals: u32,
+    iter: u32,
+    reason: Option<f64>, // set once the fit has stopped
+    restarts: u32,
+    best_seen: f64,
+    since_improve: u32,
+    f_at_restart: f64,
+    trace: Vec<(f64, Params)>,
+    resume: Option<u64>,
+    coef: [f64; 4],
+    // Mean objective entering the previous iteration, for the oriented
+    // restarts' sufficient-decrease test
+    prev_mean: f64,
+}
+
+// Reflection, expansion, contraction and shrink coefficients for n fitted
+// parameters
+fn coefficients(n: usize, adaptive: bool) -> [f64; 4] {
+    if !adaptive || n < 2 { return [1.0, 2.0, 0.5, 0.5]; }
+    let n = n as f64;
+    [1.0, 1.0 + 2.0 / n, 0.75 - 0.5 / n, 1.0 - 1.0 / n]
+}
+
+// Simplex gradient: g solving (x_i - x_0) . g = f_i - f_0 over the edges
+// from the best vertex; None when the simplex is degenerate
+fn simplex_gradient(simplex: &[Vec<f64>], fvals: &[f64]) -> Option<Vec<f64>> {
+    let n = simplex.len() - 1;
+    let mut v = vec![0.0; n * n];
+    for i in 0..n { for j in 0..n { v[i * n + j] = simplex[i + 1][j] - simplex[0][j]; } }
+    let inv = linalg::invert(&v, n)?;
+    Some((0..n).map(|i| (0..n).map(|j| inv[i * n + j] * (fvals[j + 1] - fvals[0])).sum()).collect())
+}
+
+// Kelley's oriented simplex at the best vertex: edges of half the shortest
+// current edge, along -sign(g) per coordinate
+fn oriented_simplex(simplex: &[Vec<f64>], g: &[f64]) -> Vec<Vec<f64>> {
+    let best = &simplex[0];
+    let shortest = simplex[1..].iter()
+        .map(|v| v.iter().zip(best).map(|(a, b)| (a - b) * (a - b)).sum::<f64>().sqrt())
+        .fold(f64::INFINITY, f64::min);
+    let mut out = vec![best.clone()];
+    for i in 0..best.len() {
+        let mut x = best.clone();
+        x[i] += if g[i] > 0.0 { -0.5 * shortest } else { 0.5 * shortest };
+        out.push(x);
+    }
+    out
+}
+
+impl<'a> NelderMeadRun<'a> {
+    pub fn new(problem: Cow<'a, Problem>, params: Params, optimize_idx: &[usize], opts: &NelderMead) -> NelderMeadRun<'a> {
+        let n = optimize_idx.len();
+        let mut run = NelderMeadRun {
+            problem, params, optimize_idx: optimize_idx.to_vec(), opts: opts.clone(),
+            simplex: Vec::new(), fvals: vec![0.0; n + 1], n_evals: 0, iter: 0, reason: None, restarts: 0,
+            best_seen: f64::INFINITY, since_improve: 0, f_at_restart: f64::INFINITY, trace: Vec::new(),
+            resume: None, coef: coefficients(n, opts.adaptive), prev_mean: f64::NAN,
+        };
+        if n == 0 {
+            run.reason = Some(FIT_NOTHING_TO_DO);
+            return run;
+        }
+        run.resume = opts.crn_seed.map(|_| next_seed());
+        // Build initial simplex around current params in the subspace, or
+        // reuse a previous fit's vertices
+        let x0: Vec<f64> = optimize_idx.iter().map(|&i| params[i]).collect();
+        run.simplex = match opts.warm_simplex.as_ref().filter(|w| w.len() == n + 1) {
+            Some(warm) => warm.iter().map(|v| optimize_idx.iter().map(|&i| v[i]).collect()).collect(),
+            None => fresh_simplex(&x0, opts.scale),
+        };
+        run.eval_all(0);
+        run.put_back_rng();
+        run
+    }
+
+    // params with x at optimize_idx
+    fn trial_at(&self, x: &[f64]) -> Params {
+        let mut trial = self.params;
+        for (j, &idx) in self.optimize_idx.iter().enumerate() { trial[idx] = x[j].max(lower_bound(idx)); }
+        trial[6] = trial[6].max(1e-12);
+        self.problem.constrained(&trial)
+    }
+
+    fn objective(&self, x: &[f64]) -> f64 {
+        if let Some(seed) = self.opts.crn_seed { seed_rng(seed); }
+        self.problem.objective(&self.trial_at(x))
+    }
+
+    fn eval(&mut self, x: &[f64]) -> f64 {
+        self.n_evals += 1;
+        self.objective(x)
+    }
+
+    // Independent vertices (initial simplex, restarts, shrinks) from index
+    // `from` on fan out across parallel::par_map workers; they dominate the
+    // cost of a fit
+    fn eval_all(&mut self, from: usize) {
+        let xs = &self.simplex[from..];
+        let f = parallel::par_map(xs.len(), |i| self.objective(&xs[i]));
+        self.n_evals += f.len() as u32;
+        self.fvals[from..].copy_from_slice(&f);
+    }
+
+    fn affordable(&self, evals: u32) -> bool { self.opts.max_evals == 0 || self.n_evals + evals <= self.opts.max_evals }
+
+    fn put_back_rng(&self) {
+        if let Some(seed) = self.resume { seed_rng(seed); }
+    }
+
+    // Up to n_iters further iterations (never past opts.max_iter); progress
+    // as in nelder_mead. Returns true once the fit has stopped.
+    pub fn step(&mut self, n_iters: u32, mut progress: impl FnMut(u32, f64, &Params) -> bool) -> bool {
+        let stop = self.iter.saturating_add(n_iters).min(self.opts.max_iter);
+        while self.reason.is_none() && self.iter < stop {
+            self.reason = self.iterate(&mut progress);
+        }
+        if self.reason.is_none() && self.iter >= self.opts.max_iter { self.reason = Some(FIT_MAX_ITER); }
+        self.put_back_rng();
+        self.reason.is_some()
+    }
+
+    pub fn is_done(&self) -> bool { self.reason.is_some() }
+
+    pub fn iterations(&self) -> u32 { self.iter }
+
+    pub fn evaluations(&self) -> u32 { self.n_evals }
+
+    // Best objective and parameters so far
+    pub fn best(&self) -> (f64, Params) {
+        if self.simplex.is_empty() { return (f64::NAN, self.problem.constrained(&self.params)); }
+        let b = (0..self.fvals.len()).fold(0, |b, i| if self.fvals[i] < self.fvals[b] { i } else { b });
+        (self.fvals[b], self.trial_at(&self.simplex[b]))
+    }
+
+    // One iteration; Some(reason) when the fit stops instead
+    fn iterate(&mut self, progress: &mut impl FnMut(u32, f64, &Params) -> bool) -> Option<f64> {
+        let n = self.optimize_idx.len();
+        // Order simplex by f
+        order_simplex(&mut self.simplex, &mut self.fvals);
+        if self.fvals[0] < self.best_seen { self.best_seen = self.fvals[0]; self.since_improve = 0; } else { self.since_improve += 1; }
+
+        let reporting = self.iter.is_multiple_of(self.opts.progress_every.max(1));
+        if self.opts.trace || reporting {
+            let mut best = self.params;
+            for (j, &idx) in self.optimize_idx.iter().enumerate() { best[idx] = self.simplex[0][j].max(lower_bound(idx)); }
+            let best = self.problem.constrained(&best);
+            if self.opts.trace { self.trace.push((self.fvals[0], best)); }
+            // Report progress with the current best vertex. Cooperative
+            // cancellation: keep the best vertex found so far
+            if reporting && progress(self.iter, self.fvals[0], &best) { return Some(FIT_CANCELLED); }
+        }
+
+        // Check convergence: stddev of fvals. With restarts enabled, a best
+        // value that has not improved for stall_limit iterations also counts.
+        // The x-tolerance test is robust to a noisy objective whose f spread
+        // never falls below tol.
+        let stall_limit = 10 * (n as u32 + 1);
+        let x_converged = self.opts.x_tol > 0.0 && simplex_diameter(&self.simplex) < self.opts.x_tol;
+        let converged = simplex_spread(&self.fvals) < self.opts.tol || x_converged;
+        // Kelley's test: the mean objective must fall by 1e-4 |g|^2
+        let gradient = if self.opts.oriented_restarts { simplex_gradient(&self.simplex, &self.fvals) } else { None };
+        let mean = self.fvals.iter().sum::<f64>() / self.fvals.len() as f64;
+        let stalled = gradient.as_ref().is_some_and(|g| mean - self.prev_mean >= -1e-4 * g.iter().map(|v| v * v).sum::<f64>());
+        self.prev_mean = mean;
+        let stopping = converged || (self.opts.max_restarts > 0 && self.since_improve >= stall_limit);
+        if stopping || stalled {
+            // Restart around the best vertex while restarts keep paying off
+            if self.restarts < self.opts.max_restarts && self.fvals[0] < self.f_at_restart && self.affordable(n as u32) {
+                self.restarts += 1;
+                self.f_at_restart = self.fvals[0];
+                self.simplex = match &gradient {
+                    Some(g) => oriented_simplex(&self.simplex, g),
+                    None => fresh_simplex(&self.simplex[0], self.opts.scale),
+                };
+                self.prev_mean = f64::NAN;
+                self.eval_all(1);
+                self.since_improve = 0;
+                self.iter += 1;
+                return None;
+            }
+            // Without a restart left, a lack of sufficient decrease alone carries on
+            if stopping { return Some(if x_converged { FIT_X_CONVERGED } else { FIT_CONVERGED }); }
+        }
+        if !self.affordable(n as u32 + 2) { return Some(FIT_MAX_EVALS); }
+
+        let [alpha, gamma, rho, sigma] = self.coef;
+        // Centroid of all but worst
+        let simplex = &self.simplex;
+        let mut centroid = vec![0.0; n];
+        for v in &simplex[..n] { for (c, x) in centroid.iter_mut().zip(v) { *c += x; } }
+        for c in centroid.iter_mut() { *c /= n as f64; }
+
+        // Reflection
+        let mut xr = vec![0.0; n];
+        for j in 0..n { xr[j] = centroid[j] + alpha * (centroid[j] - simplex[n][j]); }
+        let fr = self.eval(&xr);
+        if fr < self.fvals[0] {
+            // Expansion
+            let mut xe = vec![0.0; n];
+            for j in 0..n { xe[j] = centroid[j] + gamma * (xr[j] - centroid[j]); }
+            let fe = self.eval(&xe);
+            if fe < fr { self.simplex[n] = xe; self.fvals[n] = fe; }
+            else { self.simplex[n] = xr; self.fvals[n] = fr; }
+        } else if fr < self.fvals[n - 1] {
+            self.simplex[n] = xr; self.fvals[n] = fr;
+        } else {
+            // Contraction
+            let mut xc = vec![0.0; n];
+            for j in 0..n { xc[j] = centroid[j] + rho * (self.simplex[n][j] - centroid[j]); }
+            let fc = self.eval(&xc);
+            if fc < self.fvals[n] { self.simplex[n] = xc; self.fvals[n] = fc; }
+            else {
+                // Shrink
+                for i in 1..(n + 1) {
+                    for j in 0..n { self.simplex[i][j] = self.simplex[0][j] + sigma * (self.simplex[i][j] - self.simplex[0][j]); }
+                }
+                self.eval_all(1);
+            }
+        }
+        self.iter += 1;
+        None
+    }
+
+    // The fit's result; a run stopped before its end reports the iteration
+    // budget as the reason
+    pub fn finish(self) -> FitResult {
+        let NelderMeadRun { problem, mut params, optimize_idx, mut simplex, mut fvals, n_evals, iter, reason, restarts, trace, .. } = self;
+        if optimize_idx.is_empty() {
+            // Nothing to optimize, just return input and its objective
+            params = problem.constrained(&params);
+            let mut k = [0.0f64; 6];
+            k.copy_from_slice(&params[..6]);
+            let sse = crate::objective::loss(
+                problem.init_at(&params), &k, params[6], Readout::from_params(&params), &problem.times, &problem.y_obs,
+                problem.observable, problem.interp, problem.loss,
+            ) + problem.prior_term(&params);
+            return FitResult {
+                params, sse, iterations: 0, evaluations: 1, spread: 0.0, reason: FIT_NOTHING_TO_DO, restarts: 0,
+                simplex: vec![params],
+                trace: Vec::new(),
+                excluded: Vec::new(),
+                studentized: Vec::new(),
+            };
+        }
+
+        // Best point (the last update may have left the simplex unordered)
+        order_simplex(&mut simplex, &mut fvals);
+        let best_x = &simplex[0];
+        for (j, &idx) in optimize_idx.iter().enumerate() { params[idx] = best_x[j].max(lower_bound(idx)); }
+        params[6] = params[6].max(1e-12);
+        params = problem.constrained(&params);
+
+        let vertices = simplex.iter().map(|x| {
+            let mut v = params;
+            for (j, &idx) in optimize_idx.iter().enumerate() { v[idx] = x[j].max(lower_bound(idx)); }
+            problem.constrained(&v)
+        }).collect();
+
+        FitResult {
+            params,
+            sse: fvals[0],
+            iterations: iter,
+            evaluations: n_evals,
+            spread: simplex_spread(&fvals),
+            reason: reason.unwrap_or(FIT_MAX_ITER),
+            restarts,
+            simplex: vertices,
+            trace,
+            excluded: Vec::new(),
+            studentized: Vec::new(),
+        }
+    }
+}
+
+// Jacobian of the deterministic predictions at the `used` observations over
+// params[cols], by adaptive central differences (the rates from the forward
+// sensitivities where Problem::ode_rate_jacobian applies); row-major
+// used.len() x cols.len()
+pub(crate) fn prediction_jacobian(problem: &Problem, params: &Params, cols: &[usize], used: &[usize]) -> Vec<f64> {
+    let m = cols.len();
+    let mut jac = vec![0.0; used.len() * m];
+    // Rate columns from the forward sensitivities where they apply, the rest
+    // by Problem::prediction_jacobian
+    let analytic = if cols.iter().any(|&j| j < 6) { problem.ode_rate_jacobian(params) } else { None };
+    let fd_cols: Vec<usize> = cols.iter().copied().filter(|&j| analytic.is_none() || j >= 6).collect();
+    let fd = problem.prediction_jacobian(params, &fd_cols);
+    for (c, &j) in cols.iter().enumerate() {
+        if let Some((_, grad)) = analytic.as_ref().filter(|_| j < 6) {
+            for (row, &i) in used.iter().enumerate() { jac[row * m + c] = grad[i][j]; }
+        } else {
+            let f = fd_cols.iter().position(|&k| k == j).unwrap_or(0);
+            for (row, &i) in used.iter().enumerate() { jac[row * m + c] = fd[i * fd_cols.len() + f]; }
+        }
+    }
+    jac
+}
+
+// Asymptotic standard errors sqrt(s^2 [(J^T J)^-1]_jj) of params[idx], with
+// s^2 = SSE / (n - m) from the residuals of one stochastic run and J the
+// deterministic model's Jacobian (as in studentized_residuals). NaN for dt,
+// and for every entry when J^T J is singular or fewer than m + 1 points have
+// a finite residual.
+pub fn standard_errors(problem: &Problem, params: &Params, idx: &[usize]) -> Vec<f64> {
+    let r = problem.residuals(params);
+    let used: Vec<usize> = (0..r.len()).filter(|&i| r[i].is_finite()).collect();
+    let cols: Vec<usize> = idx.iter().copied().filter(|&j| j != 6).collect();
+    let (n, m) = (used.len(), cols.len());
+    let mut out = vec![f64::NAN; idx.len()];
+    if m == 0 || n <= m { return out; }
+    let jac = prediction_jacobian(problem, params, &cols, &used);
+    let Some(inv) = linalg::invert(&linalg::gram(&jac, n, m), m) else { return out };
+    let s2 = used.iter().map(|&i| r[i] * r[i]).sum::<f64>() / (n - m) as f64;
+    for (slot, &j) in out.iter_mut().zip(idx) {
+        if let Some(c) = cols.iter().position(|&cj| cj == j) { *slot = (s2 * inv[c * m + c]).max(0.0).sqrt(); }
+    }
+    out
+}
+
+// Externally studentized residuals r_i / (s_(i) sqrt(1 - h_ii)) at params, s_(i)
+// being the residual sd of the other points (the fit is not redone without
+// point i, so a nonlinear, noisy model keeps this well defined). Leverages come from the
+// deterministic model's Jacobian over params[idx] (dt excluded), by central
+// differences. NaN for points without a finite time or residual, and for all
+// points when fewer than m + 2 remain.
+pub fn studentized_residuals(problem: &Problem, params: &Params, idx: &[usize]) -> Vec<f64> {
+    let r = problem.residuals(params);
+    let n_t = r.len();
+    let used: Vec<usize> = (0..n_t).filter(|&i| r[i].is_finite()).collect();
+    let cols: Vec<usize> = idx.iter().copied().filter(|&j| j != 6).collect();
+    let (n, m) = (used.len(), cols.len());
+    let mut out = vec![f64::NAN; n_t];
+    if n < m + 2 { return out; }
+
+    let jac = prediction_jacobian(problem, params, &cols, &used);
+    // Leverages h_ii = J_i (J^T J)^-1 J_i^T; 0 if J^T J is singular
+    let inv = linalg::invert(&linalg::gram(&jac, n, m), m);
+    let dof = (n - m) as f64;
+    let sse: f64 = used.iter().map(|&i| r[i] * r[i]).sum();
+    for (row, &i) in used.iter().enumerate() {
+        let lev = inv.as_ref().map_or(0.0, |inv| {
+            let ji = &jac[row * m..(row + 1) * m];
+            (0..m).map(|a| (0..m).map(|b| ji[a] * inv[a * m + b] * ji[b]).sum::<f64>()).sum::<f64>()
+        }).clamp(0.0, 1.0 - 1e-12);
+        let s2_del = (sse - r[i] * r[i]) / (dof - 1.0);
+        out[i] = if s2_del > 0.0 { r[i] / (s2_del.sqrt() * (1.0 - lev).sqrt()) } else { f64::NAN };
+    }
+    out
+}
+
+// Outlier screening between fits
+pub struct Outliers {
+    pub threshold: f64, // |studentized residual| above which a point is excluded; <= 0 or NaN disables
+    pub max_rounds: u32, // refits after the first fit, each excluding the newly flagged points (0 => 1)
+}
+
+// nelder_mead, then up to max_rounds refits from the previous best with the
+// points whose studentized residuals exceed the threshold excluded. A round
+// that flags nothing, a cancelled fit, or too few points left (m + 2) ends the
+// screening. opts.max_evals bounds all fits together; evaluations and the
+// trace cover every fit, with the reason and simplex those of the last one.
+pub fn refit_without_outliers(
+    problem: &Problem,
+    params: Params,
+    optimize_idx: &[usize],
+    opts: &NelderMead,
+    outliers: &Outliers,
+    mut progress: impl FnMut(u32, f64, &Params) -> bool,
+) -> FitResult {
+    let mut res = nelder_mead(problem, params, optimize_idx, opts, &mut progress);
+    let thr = outliers.threshold;
+    if !(thr.is_finite() && thr > 0.0) { return res; }
+
+    let mut work = problem.clone();
+    let mut studentized = studentized_residuals(&work, &res.params, optimize_idx);
+    let mut excluded: Vec<usize> = Vec::new();
+    let mut evaluations = res.evaluations;
+    let mut trace = std::mem::take(&mut res.trace);
+    for _ in 0..outliers.max_rounds.max(1) {
+        let flagged: Vec<usize> = (0..studentized.len())
+            .filter(|&i| studentized[i].abs() > thr && !excluded.contains(&i)).collect();
+        let remaining = (0..studentized.len())
+            .filter(|&i| studentized[i].is_finite() && !excluded.contains(&i) && !flagged.contains(&i)).count();
+        if flagged.is_empty() || res.reason == FIT_CANCELLED || remaining < optimize_idx.len() + 2 { break; }
+        if opts.max_evals > 0 && evaluations >= opts.max_evals { break; }
+        for &i in &flagged { work.times[i] = f64::NAN; }
+        excluded.extend_from_slice(&flagged);
+
+        let round_opts = NelderMead {
+            warm_simplex: None,
+            max_evals: if opts.max_evals > 0 { opts.max_evals - evaluations } else { 0 },
+            ..opts.clone()
+        };
+        res = nelder_mead(&work, res.params, optimize_idx, &round_opts, &mut progress);
+        evaluations += res.evaluations;
+        trace.append(&mut res.trace);
+        let fresh = studentized_residuals(&work, &res.params, optimize_idx);
+        for (i, v) in fresh.into_iter().enumerate() { if !excluded.contains(&i) { studentized[i] = v; } }
+    }
+    excluded.sort_unstable();
+    res.evaluations = evaluations;
+    res.trace = trace;
+    res.excluded = excluded;
+    res.studentized = studentized;
+    res
+}
+
+// Global-then-local fitting: Latin hypercube starts over the bounds, then
+// Nelder–Mead from the best of them
+pub struct AutoFit {
+    pub n_starts: u32, // Latin hypercube starts; 0 => 10 per fitted parameter
+    pub n_polish: u32, // best starts polished, best first (0 => 1)
+    // Settings of every polishing run; max_evals bounds them together, on
+    // top of the n_starts global evaluations
+    pub polish: NelderMead,
+}
+
+// Global search range of params[i]: the given (lower, upper) when finite
+// with lower < upper, else a decade either side of a positive start and
+// start +- 1 otherwise, floored at the parameter's lower bound
+pub(crate) fn search_range(params: &Params, i: usize, bound: Option<&(f64, f64)>) -> (f64, f64) {
+    let x = params[i];
+    let (lo, hi) = match bound {
+        Some(&(lo, hi)) if lo.is_finite() && hi.is_finite() && lo < hi => (lo, hi),
+        _ if x > 0.0 => (x / 10.0, x * 10.0),
+        _ => (x - 1.0, x + 1.0),
+    };
+    (lo.max(lower_bound(i)), hi.max(lo.max(lower_bound(i))))
+}
+
+// One-call fit: opts.n_starts Latin hypercube points over bounds (one
+// (lower, upper) per optimize_idx entry, missing or invalid pairs as in
+// search_range; log-uniform where lower > 0), then nelder_mead from the
+// opts.n_polish best, keeping the best polished result. The trace (with
+// opts.polish.trace) is the running best over the starts followed by each
+// polishing run's; evaluations count both phases. A cancelled polish stops
+// the pipeline and the result reports FIT_CANCELLED.
+pub fn fit_auto(
+    problem: &Problem,
+    params: Params,
+    optimize_idx: &[usize],
+    bounds: &[(f64, f64)],
+    opts: &AutoFit,
+    mut progress: impl FnMut(u32, f64, &Params) -> bool,
+) -> FitResult {
+    let n = optimize_idx.len();
+    if n == 0 { return nelder_mead(problem, params, optimize_idx, &opts.polish, progress); }
+    let n_starts = if opts.n_starts > 0 { opts.n_starts as usize } else { 10 * n };
+    let mut starts = vec![params; n_starts];
+    for (j, &i) in optimize_idx.iter().enumerate() {
+        let (lo, hi) = search_range(&params, i, bounds.get(j));
+        let mut strata: Vec<usize> = (0..n_starts).collect();
+        for s in (1..n_starts).rev() { strata.swap(s, (rand_f64() * (s + 1) as f64) as usize); }
+        for (start, &stratum) in starts.iter_mut().zip(&strata) {
+            let u = (stratum as f64 + rand_f64()) / n_starts as f64;
+            start[i] = if lo > 0.0 { (lo.ln() + u * (hi / lo).ln()).exp() } else { lo + u * (hi - lo) };
+        }
+    }
+    let fs = parallel::par_map(n_starts, |s| problem.objective(&starts[s]));
+
+    let mut trace = Vec::new();
+    if opts.polish.trace {
+        let mut best = (f64::INFINITY, params);
+        for (f, start) in fs.iter().zip(&starts) {
+            if *f < best.0 { best = (*f, *start); }
+            trace.push(best);
+        }
+    }
+    let mut order: Vec<usize> = (0..n_starts).collect();
+    order.sort_by(|&a, &b| fs[a].partial_cmp(&fs[b]).unwrap_or(std::cmp::Ordering::Equal));
+
+    let max_evals = opts.polish.max_evals;
+    let mut polished = 0u32;
+    let mut best: Option<FitResult> = None;
+    let mut cancelled = false;
+    for &s in order.iter().take(opts.n_polish.max(1) as usize) {
+        if max_evals > 0 && polished >= max_evals { break; }
+        let run_opts = NelderMead {
+            warm_simplex: None,
+            max_evals: if max_evals > 0 { max_evals - polished } else { 0 },
+            ..opts.polish.clone()
+        };
+        let mut res = nelder_mead(problem, starts[s], optimize_idx, &run_opts, &mut progress);
+        polished += res.evaluations;
+        trace.append(&mut res.trace);
+        cancelled = res.reason == FIT_CANCELLED;
+        if best.as_ref().is_none_or(|b| res.sse < b.sse) { best = Some(res); }
+        if cancelled { break; }
+    }
+    let mut res = best.expect("at least one polishing run");
+    if cancelled { res.reason = FIT_CANCELLED; }
+    res.evaluations = n_starts as u32 + polished;
+    res.trace = trace;
+    res
+}
+
+pub struct Mcmc {
+    pub sigma: f64, // noise sd; <= 0 estimates it from the starting SSE
+    pub step: f64, // proposal sd in log space
+    pub n_samples: u32,
+    pub burn_in: u32,
+    pub thin: u32,
+    pub n_chains: u32,
+}
+
+// Random-walk Metropolis–Hastings over log(k) for params[sample_idx], under
+// Gaussian noise: log L = -SSE / (2 sigma^2), or log L = -loss when the
+// problem's loss is a likelihood (sigma unused). Flat prior on log(k) unless
+// the problem has log-normal priors; signal_offset and drift take additive
+// steps of step times their starting size (at least 1), fixed so the walk
+// stays symmetric. Each chain starts at params. Returns (acceptance rate per
+// chain, n_chains * n_samples rows of [k1,k-3,k-1,k2,k-2,k3,dt, loss,
+// t_shift, E0..P0, signal_scale, signal_offset, drift, drift_decay],
+// chain-major).
+pub fn mcmc(problem: &Problem, params: Params, sample_idx: &[usize], opts: &Mcmc) -> (Vec<f64>, Vec<f64>) {
+    let n_use = problem.n_obs().max(1);
+    let n_chains = opts.n_chains.max(1) as usize;
+    let thin = opts.thin.max(1);
+    let burn_in = opts.burn_in;
+    let step = if opts.step.is_finite() && opts.step > 0.0 { opts.step } else { 0.05 };
+    let additive: Params = std::array::from_fn(|i| step * params[i].abs().max(1.0));
+
+    let sse0 = problem.loss_value(&params);
+    let sigma2 = if opts.sigma.is_finite() && opts.sigma > 0.0 {
+        opts.sigma * opts.sigma
+    } else {
+        (sse0 / n_use as f64).max(1e-300)
+    };
+    let likelihood = problem.loss.is_likelihood();
+    let log_post = |k: &Params, loss: f64| -> f64 {
+        if !loss.is_finite() { return f64::NEG_INFINITY; }
+        let log_l = if likelihood { -loss } else { -loss / (2.0 * sigma2) };
+        log_l - 0.5 * problem.penalty(k)
+    };
+
+    let mut acceptance: Vec<f64> = Vec::with_capacity(n_chains);
+    let mut rows: Vec<f64> = Vec::with_capacity(n_chains * opts.n_samples as usize * (N_PARAMS + 1));
+    for _ in 0..n_chains {
+        let mut cur = problem.constrained(&params);
+        let mut cur_sse = sse0;
+        let mut cur_lp = log_post(&cur, cur_sse);
+        let mut accepted = 0u64;
+        let mut proposed = 0u64;
+        let total = burn_in as u64 + opts.n_samples as u64 * thin as u64;
+        for it in 0..total {
+            if !sample_idx.is_empty() {
+                let mut prop = cur;
+                for &idx in sample_idx {
+                    if lower_bound(idx) < 0.0 {
+                        // Baseline terms can cross zero: additive step
+                        prop[idx] = cur[idx] + additive[idx] * rand_std_normal();
+                        continue;
+                    }
+                    // Multiplicative step keeps rate constants positive
+                    let base = if cur[idx] > 0.0 { cur[idx] } else { 1e-12 };
+                    prop[idx] = base * (step * rand_std_normal()).exp();
+                }
+                let prop = problem.constrained(&prop);
+                let prop_sse = problem.loss_value(&prop);
+                let prop_lp = log_post(&prop, prop_sse);
+                proposed += 1;
+                if prop_lp - cur_lp >= rand_f64().max(1e-300).ln() {
+                    cur = prop;
+                    cur_sse = prop_sse;
+                    cur_lp = prop_lp;
+                    accepted += 1;
+                }
+            }
+            if it >= burn_in as u64 && (it - burn_in as u64).is_multiple_of(thin as u64) {
+                rows.extend_from_slice(&cur[..7]);
+                rows.push(cur_sse);
+                rows.extend_from_slice(&cur[7..]);
+            }
+        }
+        acceptance.push(if proposed > 0 { accepted as f64 / proposed as f64 } else { 0.0 });
+    }
+    (acceptance, rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objective::tests::{problem, synthetic_problem};
+    use crate::objective::{free_indices, params_from_slice, Constraint, Priors, SIGNAL_OFFSET};
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn empty_mask_returns_input() {
+        let problem = problem([10.0, 0.0, 0.0, 1000.0, 0.0, 0.0], vec![1.0, 2.0], vec![0.0, 0.0]);
+        let params = params_from_slice(&[1e-3, 0.0, 0.1, 1.0, 0.0, 1.0, 0.1], &problem.init);
+        let opts = NelderMead { max_iter: 50, tol: 1e-9, scale: 0.1, progress_every: 0, warm_simplex: None, max_restarts: 0, x_tol: 0.0, max_evals: 0, trace: false, crn_seed: None, adaptive: false, oriented_restarts: false };
+        let res = nelder_mead(&problem, params, &[], &opts, |_, _, _| false);
+        assert_eq!(res.params, params);
+        assert_eq!(res.reason, FIT_NOTHING_TO_DO);
+        assert_eq!((res.iterations, res.evaluations), (0, 1));
+        assert!(res.sse.is_finite() && res.sse >= 0.0);
+        assert_eq!(simplex_from_output(&res.to_vec()), Some(vec![params]));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn trace_is_monotone_within_budget() {
+        let problem = problem([10.0, 0.0, 0.0, 1000.0, 0.0, 0.0], vec![1.0, 2.0], vec![5.0, 10.0]);
+        let params = params_from_slice(&[1e-3, 0.0, 0.1, 1.0, 0.0, 1.0, 0.1], &problem.init);
+        let opts = NelderMead { max_iter: 500, tol: 0.0, scale: 0.1, progress_every: 0, warm_simplex: None, max_restarts: 0, x_tol: 0.0, max_evals: 40, trace: true, crn_seed: None, adaptive: false, oriented_restarts: false };
+        let res = nelder_mead(&problem, params, &[0, 3], &opts, |_, _, _| false);
+        assert_eq!(res.reason, FIT_MAX_EVALS);
+        assert!(res.evaluations <= 40);
+        assert_eq!(res.trace.len(), res.iterations as usize + 1);
+        assert!(res.trace.windows(2).all(|w| w[1].0 <= w[0].0));
+        assert_eq!(simplex_from_output(&res.to_vec()).map(|s| s.len()), Some(3));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn refit_excludes_injected_outlier() {
+        let init = [10.0, 0.0, 0.0, 1000.0, 0.0, 0.0];
+        let p0 = params_from_slice(&[1e-3, 0.0, 0.1, 1.0, 0.0, 1.0, 0.1], &init);
+        let mut problem = synthetic_problem(init, &p0, (1..=20).map(|i| i as f64).collect());
+        problem.y_obs[7] += 300.0;
+        let opts = NelderMead { max_iter: 200, tol: 1e-6, scale: 0.1, progress_every: 0, warm_simplex: None, max_restarts: 0, x_tol: 1e-4, max_evals: 150, trace: false, crn_seed: None, adaptive: false, oriented_restarts: false };
+        let res = refit_without_outliers(&problem, p0, &[3], &opts, &Outliers { threshold: 4.0, max_rounds: 2 }, |_, _, _| false);
+        assert!(res.excluded.contains(&7), "{:?} {:?}", res.excluded, res.studentized);
+        assert!(res.studentized[7] > 4.0);
+        assert!(res.evaluations <= 150);
+        let v = res.to_vec();
+        let tail = 1 + res.excluded.len() + 1 + 20;
+        assert_eq!(v[v.len() - tail], res.excluded.len() as f64);
+        // Disabled screening is a plain fit
+        let plain = refit_without_outliers(&problem, p0, &[], &opts, &Outliers { threshold: 0.0, max_rounds: 2 }, |_, _, _| false);
+        assert!(plain.excluded.is_empty() && plain.studentized.is_empty());
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn auto_fit_escapes_a_poor_start() {
+        crate::rng::seed_rng(8);
+        let init = [100.0, 0.0, 0.0, 5000.0, 0.0, 0.0];
+        let truth = params_from_slice(&[1e-3, 0.0, 0.1, 1.0, 0.0, 1.0, 0.05], &init);
+        let problem = synthetic_problem(init, &truth, (1..=20).map(|i| 2.0 * i as f64).collect());
+        let mut start = truth;
+        start[3] = 0.02;
+        let polish = NelderMead { max_iter: 40, tol: 1e-9, scale: 0.1, progress_every: 0, warm_simplex: None, max_restarts: 0, x_tol: 1e-3, max_evals: 0, trace: true, crn_seed: None, adaptive: false, oriented_restarts: false };
+        let opts = AutoFit { n_starts: 20, n_polish: 2, polish };
+        let res = fit_auto(&problem, start, &[3], &[(0.01, 10.0)], &opts, |_, _, _| false);
+        assert!((res.params[3] - 1.0).abs() < 0.2, "k2 = {}", res.params[3]);
+        assert!(res.trace[..20].windows(2).all(|w| w[1].0 <= w[0].0));
+        assert!(res.evaluations > 20 && res.trace.len() > 20);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn haldane_constraint_holds_keq() {
+        let init = [10.0, 0.0, 0.0, 1000.0, 0.0, 0.0];
+        let keq = 50.0;
+        let problem = Problem {
+            constraints: vec![Constraint::Haldane { keq, solve: 4 }],
+            ..problem(init, vec![1.0, 2.0, 3.0], vec![5.0, 10.0, 15.0])
+        };
+        let params = params_from_slice(&[1e-3, 1e-4, 0.1, 1.0, 0.5, 1.0, 0.1], &init);
+        let idx = free_indices(&[0, 3, 4], &problem.constraints);
+        assert_eq!(idx, [0, 3]);
+        let opts = NelderMead { max_iter: 20, tol: 0.0, scale: 0.1, progress_every: 0, warm_simplex: None, max_restarts: 0, x_tol: 0.0, max_evals: 0, trace: false, crn_seed: None, adaptive: false, oriented_restarts: false };
+        let res = nelder_mead(&problem, params, &idx, &opts, |_, _, _| false);
+        let p = res.params;
+        let ratio = p[0] * p[3] * p[5] / (p[2] * p[4] * p[1]);
+        assert!((ratio / keq - 1.0).abs() < 1e-12, "Keq = {}", ratio);
+        assert!(res.simplex.iter().all(|v| (v[0] * v[3] * v[5] / (v[2] * v[4] * v[1]) / keq - 1.0).abs() < 1e-12));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn common_random_numbers_fix_the_noise() {
+        let problem = problem([10.0, 0.0, 0.0, 1000.0, 0.0, 0.0], vec![1.0, 2.0, 3.0], vec![5.0, 10.0, 15.0]);
+        let params = params_from_slice(&[1e-3, 0.0, 0.1, 1.0, 0.0, 1.0, 0.1], &problem.init);
+        let opts = NelderMead { max_iter: 30, tol: 0.0, scale: 0.1, progress_every: 0, warm_simplex: None, max_restarts: 0, x_tol: 0.0, max_evals: 0, trace: false, crn_seed: Some(9), adaptive: false, oriented_restarts: false };
+        crate::rng::seed_rng(1);
+        let a = nelder_mead(&problem, params, &[0, 3], &opts, |_, _, _| false);
+        crate::rng::seed_rng(2);
+        let b = nelder_mead(&problem, params, &[0, 3], &opts, |_, _, _| false);
+        assert_eq!((a.params, a.sse), (b.params, b.sse));
+        seed_rng(9);
+        assert_eq!(problem.objective(&a.params), a.sse);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn chunked_run_matches_one_call() {
+        let problem = problem([10.0, 0.0, 0.0, 1000.0, 0.0, 0.0], vec![1.0, 2.0, 3.0], vec![5.0, 10.0, 15.0]);
+        let params = params_from_slice(&[1e-3, 0.0, 0.1, 1.0, 0.0, 1.0, 0.1], &problem.init);
+        let opts = NelderMead { max_iter: 40, tol: 0.0, scale: 0.1, progress_every: 0, warm_simplex: None, max_restarts: 1, x_tol: 0.0, max_evals: 0, trace: true, crn_seed: Some(4), adaptive: false, oriented_restarts: false };
+        let whole = nelder_mead(&problem, params, &[0, 3], &opts, |_, _, _| false);
+        let mut run = NelderMeadRun::new(Cow::Owned(problem.clone()), params, &[0, 3], &opts);
+        let mut calls = 0;
+        while !run.step(7, |_, _, _| false) { calls += 1; }
+        assert_eq!(calls, 5);
+        assert!(run.best().0 <= whole.trace[0].0);
+        let chunked = run.finish();
+        assert_eq!(chunked.to_vec(), whole.to_vec());
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn progress_can_cancel_a_fit() {
+        let problem = problem([10.0, 0.0, 0.0, 1000.0, 0.0, 0.0], vec![1.0, 2.0, 3.0], vec![5.0, 10.0, 15.0]);
+        let params = params_from_slice(&[1e-3, 0.0, 0.1, 1.0, 0.0, 1.0, 0.1], &problem.init);
+        let opts = NelderMead { max_iter: 100, tol: 0.0, scale: 0.1, progress_every: 2, warm_simplex: None, max_restarts: 0, x_tol: 0.0, max_evals: 0, trace: false, crn_seed: Some(6), adaptive: false, oriented_restarts: false };
+        let mut calls = Vec::new();
+        let res = nelder_mead(&problem, params, &[0, 3], &opts, |iter, f, p| { calls.push((iter, f, *p)); iter >= 6 });
+        assert_eq!(calls.iter().map(|c| c.0).collect::<Vec<_>>(), [0, 2, 4, 6]);
+        assert_eq!((res.reason, res.iterations), (FIT_CANCELLED, 6));
+        // The fit keeps the best vertex it reported
+        let (_, f, p) = calls[3];
+        assert_eq!((res.sse, res.params), (f, p));
+        assert!(calls.windows(2).all(|w| w[1].1 <= w[0].1));
+        // A cancelled polish stops the auto fit
+        let auto = AutoFit { n_starts: 8, n_polish: 3, polish: NelderMead { progress_every: 0, crn_seed: None, ..opts } };
+        let mut polishes = 0;
+        let res = fit_auto(&problem, params, &[0, 3], &[], &auto, |iter, _, _| { if iter == 0 { polishes += 1; } iter >= 3 });
+        assert_eq!((res.reason, polishes), (FIT_CANCELLED, 1));
+        assert_eq!(reason_name(res.reason), "cancelled");
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn warm_start_resumes_from_the_previous_simplex() {
+        let problem = problem([10.0, 0.0, 0.0, 1000.0, 0.0, 0.0], vec![1.0, 2.0, 3.0], vec![5.0, 10.0, 15.0]);
+        let params = params_from_slice(&[1e-3, 0.0, 0.1, 1.0, 0.0, 1.0, 0.1], &problem.init);
+        let opts = NelderMead { max_iter: 25, tol: 0.0, scale: 0.1, progress_every: 0, warm_simplex: None, max_restarts: 0, x_tol: 0.0, max_evals: 0, trace: false, crn_seed: Some(7), adaptive: false, oriented_restarts: false };
+        let first = nelder_mead(&problem, params, &[0, 3], &opts, |_, _, _| false);
+        let previous = result_from_output(&first.to_vec()).unwrap();
+        assert_eq!(previous.simplex, first.simplex);
+        let warm = NelderMead { warm_simplex: Some(previous.simplex), ..opts.clone() };
+        // No further iterations: the same best point and vertices back
+        let same = nelder_mead(&problem, previous.params, &[0, 3], &NelderMead { max_iter: 0, ..warm.clone() }, |_, _, _| false);
+        assert_eq!((same.sse, same.params, &same.simplex), (first.sse, first.params, &first.simplex));
+        assert_eq!(same.evaluations, 3);
+        // 25 warm iterations continue the first 25 rather than restarting
+        let both = nelder_mead(&problem, params, &[0, 3], &NelderMead { max_iter: 50, ..opts.clone() }, |_, _, _| false);
+        let resumed = nelder_mead(&problem, previous.params, &[0, 3], &warm, |_, _, _| false);
+        assert!(resumed.sse <= first.sse);
+        assert_eq!((resumed.sse, resumed.params), (both.sse, both.params));
+        // A simplex that does not match the mask is ignored
+        let mismatched = NelderMead { warm_simplex: Some(vec![params; 2]), ..opts.clone() };
+        let cold = nelder_mead(&problem, params, &[0, 3], &mismatched, |_, _, _| false);
+        assert_eq!(cold.to_vec(), first.to_vec());
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn restarts_are_bounded_and_never_lose_ground() {
+        let init = [10.0, 0.0, 0.0, 1000.0, 0.0, 0.0];
+        let p0 = params_from_slice(&[1e-3, 0.0, 0.1, 1.0, 0.0, 1.0, 0.1], &init);
+        let problem = synthetic_problem(init, &p0, (1..=10).map(|i| i as f64).collect());
+        let mut start = p0;
+        start[0] = 3e-3;
+        start[3] = 0.4;
+        let opts = NelderMead { max_iter: 2000, tol: 1e-3, scale: 0.1, progress_every: 0, warm_simplex: None, max_restarts: 0, x_tol: 0.0, max_evals: 0, trace: false, crn_seed: Some(3), adaptive: false, oriented_restarts: false };
+        let plain = nelder_mead(&problem, start, &[0, 3], &opts, |_, _, _| false);
+        assert_eq!((plain.reason, plain.restarts), (FIT_CONVERGED, 0));
+        let restarted = nelder_mead(&problem, start, &[0, 3], &NelderMead { max_restarts: 4, ..opts.clone() }, |_, _, _| false);
+        assert!((1..=4).contains(&restarted.restarts), "{}", restarted.restarts);
+        assert!(restarted.sse <= plain.sse && restarted.evaluations > plain.evaluations);
+        assert_eq!(restarted.to_vec()[12], restarted.restarts as f64);
+        // No restart once the budget cannot cover its n fresh vertices
+        let budget = NelderMead { max_restarts: 4, max_evals: plain.evaluations + 1, ..opts.clone() };
+        assert_eq!(nelder_mead(&problem, start, &[0, 3], &budget, |_, _, _| false).restarts, 0);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn seeded_fits_repeat_with_vertices_fanned_out() {
+        // Stochastic objective without common random numbers: the initial and
+        // shrink vertices go through parallel::par_map
+        let problem = problem([10.0, 0.0, 0.0, 1000.0, 0.0, 0.0], vec![1.0, 2.0, 3.0], vec![5.0, 10.0, 15.0]);
+        let params = params_from_slice(&[1e-3, 0.0, 0.1, 1.0, 0.0, 1.0, 0.1], &problem.init);
+        let opts = NelderMead { max_iter: 30, tol: 0.0, scale: 0.1, progress_every: 0, warm_simplex: None, max_restarts: 0, x_tol: 0.0, max_evals: 0, trace: false, crn_seed: None, adaptive: false, oriented_restarts: false };
+        let fit = || nelder_mead(&problem, params, &[0, 2, 3], &opts, |_, _, _| false).to_vec();
+        seed_rng(41);
+        let a = fit();
+        seed_rng(41);
+        assert_eq!(fit(), a);
+        seed_rng(42);
+        assert_ne!(fit(), a);
+        // The sequential and threaded par_map paths seed vertices alike
+        for workers in [1, 2, 4] {
+            seed_rng(41);
+            assert_eq!(crate::parallel::with_workers(workers, fit), a, "{workers} workers");
+        }
+        // Recorded and replayed draws give the same fit
+        crate::rng::start_recording();
+        let recorded = fit();
+        let tape = crate::rng::finish_recording();
+        seed_rng(43);
+        crate::rng::start_replay(tape);
+        assert_eq!(fit(), recorded);
+        crate::rng::stop_replay();
+        let start = nelder_mead(&problem, params, &[0, 2, 3], &NelderMead { max_iter: 0, ..opts.clone() }, |_, _, _| false);
+        assert_eq!(start.evaluations, 4);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn mcmc_recovers_a_gaussian_offset_posterior() {
+        crate::rng::seed_rng(13);
+        // No rates, so [P] stays at P0 = 0: observations 20 + N(0, 1)-like
+        // scatter give offset | y ~ N(mean y, 1 / n) with sigma = 1
+        let init = [10.0, 0.0, 0.0, 1000.0, 0.0, 0.0];
+        let y: Vec<f64> = (0..10).map(|i| 20.0 + [0.3, -1.2, 0.8, 0.1, -0.4, 1.5, -0.9, 0.2, -0.6, 0.7][i]).collect();
+        let problem = problem(init, (1..=10).map(|i| i as f64).collect(), y.clone());
+        let mut start = params_from_slice(&[0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.1], &init);
+        start[SIGNAL_OFFSET] = 25.0;
+        let opts = Mcmc { sigma: 1.0, step: 0.05, n_samples: 20_000, burn_in: 1000, thin: 1, n_chains: 1 };
+        let (_, rows) = mcmc(&problem, start, &[SIGNAL_OFFSET], &opts);
+        let draws: Vec<f64> = rows.chunks(N_PARAMS + 1).map(|r| r[SIGNAL_OFFSET + 1]).collect();
+        let mean = draws.iter().sum::<f64>() / draws.len() as f64;
+        let var = draws.iter().map(|d| (d - mean).powi(2)).sum::<f64>() / draws.len() as f64;
+        let target = y.iter().sum::<f64>() / 10.0;
+        assert!((mean - target).abs() < 0.03, "mean {} vs {}", mean, target);
+        assert!((var / 0.1 - 1.0).abs() < 0.1, "variance {}", var);
+    }
+
+    // No enzyme, so nothing reacts and every loss is exact: [P] stays at P0 = 0
+    fn idle_problem(y: Vec<f64>) -> (Problem, Params) {
+        let init = [0.0, 0.0, 0.0, 1000.0, 0.0, 0.0];
+        let problem = problem(init, (1..=y.len()).map(|i| i as f64).collect(), y);
+        let start = params_from_slice(&[1.0, 0.0, 0.1, 1.0, 0.0, 1.0, 0.1], &init);
+        (problem, start)
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn mcmc_rows_follow_the_chain_layout() {
+        crate::rng::seed_rng(5);
+        let (problem, mut start) = idle_problem(vec![3.0; 5]);
+        start[SIGNAL_OFFSET] = 1.0;
+        let opts = Mcmc { sigma: 1.0, step: 0.2, n_samples: 40, burn_in: 15, thin: 3, n_chains: 2 };
+        let (acceptance, rows) = mcmc(&problem, start, &[SIGNAL_OFFSET], &opts);
+        assert_eq!(acceptance.len(), 2);
+        assert_eq!(rows.len(), 2 * 40 * (N_PARAMS + 1));
+        for row in rows.chunks(N_PARAMS + 1) {
+            // The loss sits after dt and matches the row's own parameters
+            let mut p = [0.0; N_PARAMS];
+            p[..7].copy_from_slice(&row[..7]);
+            p[7..].copy_from_slice(&row[8..]);
+            assert!((problem.loss_value(&p) - row[7]).abs() < 1e-9);
+            assert_eq!(p[..SIGNAL_OFFSET], start[..SIGNAL_OFFSET]);
+        }
+        // Burn-in and thinning pick states 15, 18, .. of the unthinned chain
+        crate::rng::seed_rng(5);
+        let full = Mcmc { n_samples: 15 + 40 * 3, burn_in: 0, thin: 1, n_chains: 1, ..opts };
+        let (_, all) = mcmc(&problem, start, &[SIGNAL_OFFSET], &full);
+        let kept: Vec<&[f64]> = all.chunks(N_PARAMS + 1).skip(15).step_by(3).collect();
+        assert_eq!(kept.concat(), rows[..40 * (N_PARAMS + 1)]);
+        assert!(kept.windows(2).filter(|w| w[0] != w[1]).count() > 10);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn mcmc_acceptance_falls_with_the_step() {
+        crate::rng::seed_rng(6);
+        let (problem, start) = idle_problem(vec![0.0; 5]);
+        let mut sampled = problem.clone();
+        sampled.priors = Some(Priors::new(&[1.0], &[0.5]));
+        let rate = |step| {
+            let opts = Mcmc { sigma: 1.0, step, n_samples: 2000, burn_in: 0, thin: 1, n_chains: 1 };
+            mcmc(&sampled, start, &[0], &opts).0[0]
+        };
+        let (small, large) = (rate(0.01), rate(3.0));
+        assert!(small > 0.95 && small <= 1.0, "acceptance {}", small);
+        assert!(large > 0.0 && large < 0.3, "acceptance {}", large);
+        // Nothing sampled: nothing proposed
+        let opts = Mcmc { sigma: 1.0, step: 0.1, n_samples: 5, burn_in: 0, thin: 1, n_chains: 1 };
+        assert_eq!(mcmc(&problem, start, &[], &opts).0, vec![0.0]);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn mcmc_recovers_a_log_normal_prior() {
+        crate::rng::seed_rng(8);
+        // k1 has no effect without enzyme, so the posterior is its prior:
+        // ln k1 ~ N(ln 2, 0.5^2)
+        let (mut problem, start) = idle_problem(vec![0.0; 5]);
+        problem.priors = Some(Priors::new(&[2.0], &[0.5]));
+        let opts = Mcmc { sigma: 1.0, step: 0.5, n_samples: 20_000, burn_in: 500, thin: 2, n_chains: 2 };
+        let (_, rows) = mcmc(&problem, start, &[0], &opts);
+        let logs: Vec<f64> = rows.chunks(N_PARAMS + 1).map(|r| r[0].ln()).collect();
+        let mean = logs.iter().sum::<f64>() / logs.len() as f64;
+        let var = logs.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / logs.len() as f64;
+        assert!((mean - 2f64.ln()).abs() < 0.03, "mean {}", mean);
+        assert!((var / 0.25 - 1.0).abs() < 0.1, "variance {}", var);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn diameter_is_relative_to_best() {
+        let simplex = vec![vec![2.0, 0.5], vec![2.2, 0.5], vec![2.0, 0.51]];
+        assert!((simplex_diameter(&simplex) - 0.1).abs() < 1e-12);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn adaptive_coefficients_and_oriented_simplex() {
+        assert_eq!(coefficients(1, true), coefficients(10, false));
+        let [alpha, gamma, rho, sigma] = coefficients(10, true);
+        assert_eq!(alpha, 1.0);
+        assert!((gamma - 1.2).abs() < 1e-12 && (rho - 0.7).abs() < 1e-12 && (sigma - 0.9).abs() < 1e-12);
+        // f = 3 x - 2 y + 1 has simplex gradient [3, -2] on any simplex
+        let simplex = vec![vec![1.0, 1.0], vec![1.5, 1.0], vec![1.2, 1.4]];
+        let fvals: Vec<f64> = simplex.iter().map(|v| 3.0 * v[0] - 2.0 * v[1] + 1.0).collect();
+        let g = simplex_gradient(&simplex, &fvals).unwrap();
+        assert!((g[0] - 3.0).abs() < 1e-12 && (g[1] + 2.0).abs() < 1e-12, "{:?}", g);
+        // Edges of half the shortest one (0.4472), downhill on each axis
+        let oriented = oriented_simplex(&simplex, &g);
+        assert_eq!(oriented[0], simplex[0]);
+        assert!((oriented[1][0] - (1.0 - 0.2f64.sqrt() / 2.0)).abs() < 1e-12 && oriented[1][1] == 1.0);
+        assert!(oriented[2][1] > 1.0 && oriented[2][0] == 1.0);
+        assert!(simplex_gradient(&[vec![0.0, 0.0], vec![1.0, 1.0], vec![2.0, 2.0]], &[0.0, 1.0, 2.0]).is_none());
+    }
+}
@@ -0,0 +1,115 @@
+// Starting rates from a progress curve, for seeding a fit. The observations
+// are turned into product formed, the end level gives Keq, the integrated
+// Michaelis–Menten rate and its decline over the early curve give V and
+// Km_S, and
+// rates with those constants follow from steady::rates_from_mm. It is a
+// rough guess: a fit started from it still has to do the work.
+
+use crate::model::{Observable, Rates, State};
+use crate::steady;
+
+// Fraction of the end level below which points enter the initial-rate
+// regression (at least the first three points do)
+const EARLY: f64 = 0.15;
+// An end level within this fraction of full conversion counts as complete
+const COMPLETE: f64 = 0.98;
+
+// [k1, k-3, k-1, k2, k-2, k3, kcat_f, Km_S, Keq] guessed from observations
+// y at times of P (product) or S (substrate) amounts, started from init.
+// Keq is the end ratio P/S when the curve levels off short of full
+// conversion, else ten times the ratio at the last point. The isomerization
+// rates are set to 3 kcat_f (k-2 raised where the reverse turnover needs it)
+// and Km_P to Km_S.
+pub fn initial_rates(init: &State, times: &[f64], y: &[f64], observable: Observable) -> Result<[f64; 9], String> {
+    let [e0, es0, ep0, s0, p0, t0] = *init;
+    let e_total = e0 + es0 + ep0;
+    let s_total = s0 + es0 + ep0;
+    if !(e_total > 0.0 && s_total > 0.0) { return Err("initial guess needs enzyme and substrate".to_string()); }
+    // Product formed since t0 at each finite observation
+    let formed = |v: f64| if observable == Observable::P { v - p0 } else { s0 - v };
+    if observable != Observable::P && observable != Observable::S {
+        return Err("initial guess needs observations of P or S".to_string());
+    }
+    let pts: Vec<(f64, f64)> = times.iter().zip(y)
+        .filter(|(t, v)| t.is_finite() && v.is_finite() && **t > t0)
+        .map(|(&t, &v)| (t - t0, formed(v).clamp(0.0, s_total)))
+        .collect();
+    if pts.len() < 3 { return Err("initial guess needs at least 3 observations after t0".to_string()); }
+
+    // End level: mean of the last three points; levelled off when the last
+    // fifth of the time span moved it by under 2%
+    let p_end = pts[pts.len() - 3..].iter().map(|p| p.1).sum::<f64>() / 3.0;
+    let (t_last, p_last) = pts[pts.len() - 1];
+    let p_before = pts.iter().rev().find(|p| p.0 <= 0.8 * t_last).map_or(0.0, |p| p.1);
+    let levelled = (p_last - p_before).abs() < 0.02 * p_end.max(1e-300);
+    let keq = if levelled && p_end < COMPLETE * s_total {
+        p_end / (s_total - p_end)
+    } else {
+        10.0 * (p_last / (s_total - p_last).max(1e-3 * s_total)).max(1.0)
+    };
+
+    // Early curve p = v0 t + v'(0) t^2 / 2. For Michaelis–Menten
+    // v'(0) = -v0^2 Km / (S0 (Km + S0)), so r = -v'(0) / v0^2 gives Km.
+    let mut early: Vec<(f64, f64)> = pts.iter().copied().filter(|p| p.1 < EARLY * p_end).collect();
+    if early.len() < 3 { early = pts[..3].to_vec(); }
+    // A lag (or noise) can bend the start the other way: then the slope of
+    // a line through the origin, with Km = S0
+    let (v0, curv) = match origin_quadratic(&early) {
+        Some((v0, curv)) if v0 > 0.0 => (v0, curv),
+        _ => (early.iter().map(|p| p.0 * p.1).sum::<f64>() / early.iter().map(|p| p.0 * p.0).sum::<f64>(), 0.0),
+    };
+    if v0.is_nan() || v0 <= 0.0 { return Err("initial guess needs a rising product curve".to_string()); }
+    let r = -curv / (v0 * v0);
+    let km = if r > 0.0 && r * s_total < 0.9 { r * s_total * s_total / (1.0 - r * s_total) } else { s_total };
+    let v_max = v0 * (km + s_total) / s_total;
+    let kcat_f = v_max / e_total;
+    let kcat_r = kcat_f / keq; // Haldane with Km_P = Km_S
+    let k2 = 3.0 * kcat_f;
+    let k_minus2 = k2.max(3.0 * kcat_r);
+    let k: Rates = steady::rates_from_mm(&[kcat_f, km, kcat_r, km], k2, k_minus2);
+    if !k.iter().all(|v| v.is_finite()) { return Err("initial guess found no usable rates in the data".to_string()); }
+    Ok([k[0], k[1], k[2], k[3], k[4], k[5], kcat_f, km, keq])
+}
+
+// Least-squares (b, 2c) of y = b t + c t^2 through pts (t, y); None when
+// singular
+fn origin_quadratic(pts: &[(f64, f64)]) -> Option<(f64, f64)> {
+    let (mut s2, mut s3, mut s4, mut sy1, mut sy2) = (0.0, 0.0, 0.0, 0.0, 0.0);
+    for &(t, y) in pts {
+        s2 += t * t;
+        s3 += t * t * t;
+        s4 += t * t * t * t;
+        sy1 += t * y;
+        sy2 += t * t * y;
+    }
+    let det = s2 * s4 - s3 * s3;
+    if det.is_nan() || det.abs() <= 1e-12 * s2 * s4 { return None; }
+    let b = (sy1 * s4 - sy2 * s3) / det;
+    let c = (s2 * sy2 - s3 * sy1) / det;
+    Some((b, 2.0 * c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ode;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn guess_recovers_the_curve_constants() {
+        let k = [1e-3, 0.0222, 0.1, 5.0, 0.5, 2.0];
+        let init = [10.0, 0.0, 0.0, 1000.0, 0.0, 0.0];
+        let times: Vec<f64> = (1..=1000).map(|i| 10.0 * i as f64).collect();
+        let y: Vec<f64> = ode::integrate(&[10.0, 0.0, 0.0, 1000.0, 0.0], 0.0, &k, 0.01, &times).iter().map(|s| s[4]).collect();
+        let g = initial_rates(&init, &times, &y, Observable::P).unwrap();
+        let [kcat_f, km_s, ..] = steady::mm_constants(&k);
+        // Product binding bends the early curve too, so kcat and Km are rough
+        assert!(g[6] > 0.5 * kcat_f && g[6] < 2.0 * kcat_f, "kcat {} vs {}", g[6], kcat_f);
+        assert!(g[7] > km_s / 3.0 && g[7] < 3.0 * km_s, "Km {} vs {}", g[7], km_s);
+        assert!((g[8] / steady::keq(&k) - 1.0).abs() < 0.1, "Keq {} vs {}", g[8], steady::keq(&k));
+        let mut gk = [0.0; 6];
+        gk.copy_from_slice(&g[..6]);
+        assert!((steady::mm_constants(&gk)[0] - g[6]).abs() < 1e-9 * g[6]);
+        assert!(initial_rates(&init, &times, &y, Observable::ES).is_err());
+    }
+}
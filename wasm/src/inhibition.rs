@@ -0,0 +1,394 @@
+// Closed-form inhibition analyses on initial rates. The stochastic engine has
+// no inhibitor species, so these work from the steady-state rate laws: v
+// measured over a grid of substrate and inhibitor concentrations, fitted in
+// one global least-squares problem instead of per-[I] replots,
+// dose-response IC50s converted to Ki, the Morrison equation for
+// tight-binding inhibitors that deplete free I, slow-onset inhibition and
+// irreversible (covalent) inactivation.
+
+use crate::linalg;
+
+// Reversible inhibition pattern of v = V S / (Km (1 + I/Ki) + S (1 + I/(alpha Ki)))
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Inhibition {
+    Competitive, // alpha infinite
+    Uncompetitive, // binds ES only: v = V S / (Km + S (1 + I/Ki))
+    Noncompetitive, // alpha = 1
+    Mixed, // alpha fitted
+}
+
+impl Inhibition {
+    // 0: competitive, 1: uncompetitive, 2: noncompetitive, 3: mixed
+    pub fn from_code(code: u32) -> Result<Inhibition, String> {
+        match code {
+            0 => Ok(Inhibition::Competitive),
+            1 => Ok(Inhibition::Uncompetitive),
+            2 => Ok(Inhibition::Noncompetitive),
+            3 => Ok(Inhibition::Mixed),
+            _ => Err(format!("unknown inhibition {} (expected 0: competitive, 1: uncompetitive, 2: noncompetitive or 3: mixed)", code)),
+        }
+    }
+
+    // Initial rate at substrate s and inhibitor i with c = [V, Km, Ki, alpha]
+    // (alpha only read by Mixed)
+    pub fn rate(self, c: &[f64; 4], s: f64, i: f64) -> f64 {
+        let [v_max, km, ki, alpha] = *c;
+        let (free, bound) = match self {
+            Inhibition::Competitive => (1.0 + i / ki, 1.0),
+            Inhibition::Uncompetitive => (1.0, 1.0 + i / ki),
+            Inhibition::Noncompetitive => (1.0 + i / ki, 1.0 + i / ki),
+            Inhibition::Mixed => (1.0 + i / ki, 1.0 + i / (alpha * ki)),
+        };
+        v_max * s / (km * free + s * bound)
+    }
+}
+
+// Width of the fit_inhibition output
+pub const INHIBITION_FIT_LEN: usize = 9;
+
+// Global fit of initial rates v at substrate s and inhibitor i (same length)
+// to an inhibition pattern, by least squares on v over the logs of the
+// constants (so they stay positive). Output: [V, se, Km, se, Ki, se, alpha,
+// se, rms residual]; alpha is NaN unless Mixed. Standard errors come from
+// the scatter (NaN without points to spare). NaN throughout with too few
+// finite points, no inhibited ones or no substrate.
+pub fn fit_inhibition(s: &[f64], i: &[f64], v: &[f64], mode: Inhibition) -> [f64; INHIBITION_FIT_LEN] {
+    let mut out = [f64::NAN; INHIBITION_FIT_LEN];
+    let pts: Vec<(f64, f64, f64)> = (0..s.len().min(i.len()).min(v.len()))
+        .map(|j| (s[j], i[j], v[j]))
+        .filter(|p| p.0.is_finite() && p.1.is_finite() && p.2.is_finite() && p.0 >= 0.0 && p.1 >= 0.0)
+        .collect();
+    let n_params = if mode == Inhibition::Mixed { 4 } else { 3 };
+    if pts.len() < n_params || !pts.iter().any(|p| p.1 > 0.0) || !pts.iter().any(|p| p.0 > 0.0) { return out; }
+
+    // Start: V a little above the fastest rate, Km and Ki at the median
+    // substrate and (nonzero) inhibitor concentrations
+    let median = |mut xs: Vec<f64>| { xs.sort_by(f64::total_cmp); xs[xs.len() / 2] };
+    let v_top = pts.iter().map(|p| p.2).fold(0.0, f64::max);
+    let km0 = median(pts.iter().map(|p| p.0).filter(|&x| x > 0.0).collect::<Vec<_>>()).max(1e-12);
+    let ki0 = median(pts.iter().map(|p| p.1).filter(|&x| x > 0.0).collect());
+    let mut start = vec![(1.5 * v_top.max(1e-12)).ln(), km0.ln(), ki0.ln()];
+    if n_params == 4 { start.push(0.0); }
+    let constants = |q: &[f64]| [q[0].exp(), q[1].exp(), q[2].exp(), if n_params == 4 { q[3].exp() } else { 1.0 }];
+    let fit = linalg::levenberg_marquardt(&start, 200, |q| {
+        let c = constants(q);
+        pts.iter().map(|&(s, i, v)| v - mode.rate(&c, s, i)).collect()
+    });
+    let c = constants(&fit.q);
+    let se = fit.standard_errors(pts.len());
+    for j in 0..n_params {
+        out[2 * j] = c[j];
+        out[2 * j + 1] = c[j] * se[j];
+    }
+    out[8] = (fit.ssr / pts.len() as f64).sqrt();
+    out
+}
+
+// Four-parameter logistic bottom + (top - bottom) / (1 + (x / ic50)^hill)
+pub fn logistic4(x: f64, bottom: f64, top: f64, ic50: f64, hill: f64) -> f64 {
+    bottom + (top - bottom) / (1.0 + (x / ic50).powf(hill))
+}
+
+// Width of the fit_dose_response output
+pub const DOSE_RESPONSE_LEN: usize = 9;
+
+// Four-parameter logistic fit of responses y at inhibitor concentrations x
+// (x = 0 controls count as the top plateau), by least squares on y over
+// [bottom, top, ln IC50, hill] from the data's extremes and the
+// concentration nearest half-way. Output: [IC50, se, hill, se, top, se,
+// bottom, se, rms residual]; NaN throughout with fewer than four points or
+// no positive concentration.
+pub fn fit_dose_response(x: &[f64], y: &[f64]) -> [f64; DOSE_RESPONSE_LEN] {
+    let pts: Vec<(f64, f64)> = x.iter().zip(y)
+        .filter(|(c, v)| c.is_finite() && v.is_finite() && **c >= 0.0)
+        .map(|(&c, &v)| (c, v))
+        .collect();
+    if pts.len() < 4 || !pts.iter().any(|p| p.0 > 0.0) { return [f64::NAN; DOSE_RESPONSE_LEN]; }
+    let lowest = pts.iter().copied().fold(pts[0], |a, b| if b.0 < a.0 { b } else { a });
+    let highest = pts.iter().copied().fold(pts[0], |a, b| if b.0 > a.0 { b } else { a });
+    let (top, bottom) = (lowest.1, highest.1);
+    let half = 0.5 * (top + bottom);
+    let mid = pts.iter().filter(|p| p.0 > 0.0)
+        .fold((f64::INFINITY, highest.0), |best, p| if (p.1 - half).abs() < best.0 { ((p.1 - half).abs(), p.0) } else { best }).1;
+    let fit = linalg::levenberg_marquardt(&[bottom, top, mid.ln(), 1.0], 200, |q| {
+        pts.iter().map(|&(c, v)| v - logistic4(c, q[0], q[1], q[2].exp(), q[3])).collect()
+    });
+    let (q, se) = (&fit.q, fit.standard_errors(pts.len()));
+    let ic50 = q[2].exp();
+    [ic50, ic50 * se[2], q[3], se[3], q[1], se[1], q[0], se[0], (fit.ssr / pts.len() as f64).sqrt()]
+}
+
+// Ki from an IC50 measured at substrate s with the given Km (Cheng–Prusoff
+// generalized to the inhibition pattern; alpha only read by Mixed). e_total
+// > 0 applies the tight-binding correction IC50 = Ki_app + E_t / 2 (Morrison),
+// for inhibitors potent enough to deplete; 0 is the classical relation.
+pub fn cheng_prusoff(ic50: f64, s: f64, km: f64, e_total: f64, mode: Inhibition, alpha: f64) -> f64 {
+    // Ki_app / Ki = (Km + S) / (Km + S / alpha), the I-free and I-bound terms of the rate law
+    let (free, bound) = match mode {
+        Inhibition::Competitive => (km, 0.0),
+        Inhibition::Uncompetitive => (0.0, s),
+        Inhibition::Noncompetitive => (km, s),
+        Inhibition::Mixed => (km, s / alpha),
+    };
+    (ic50 - 0.5 * e_total.max(0.0)) * (free + bound) / (km + s)
+}
+
+// Morrison's quadratic: rate at total inhibitor i for enzyme total e_total,
+// uninhibited rate v0 and apparent Ki ki_app, with the inhibitor bound to
+// enzyme taken out of the free pool: v = v0 (1 - EI / E_t) where EI solves
+// EI^2 - (E_t + I + Ki_app) EI + E_t I = 0 (the smaller root)
+pub fn morrison_rate(v0: f64, e_total: f64, i: f64, ki_app: f64) -> f64 {
+    let b = e_total + i + ki_app;
+    let disc = (b * b - 4.0 * e_total * i).max(0.0).sqrt();
+    // EI / E_t = 2 I / (b + disc), without the cancellation of (b - disc) / (2 E_t)
+    v0 * (1.0 - 2.0 * i / (b + disc))
+}
+
+// Width of the fit_morrison output
+pub const MORRISON_LEN: usize = 7;
+
+// Morrison fit of rates v at total inhibitor i by least squares on v over
+// [v0, ln Ki_app] and, when fit_enzyme is set, ln E_t (active enzyme,
+// often below the nominal amount) from e_total. Output: [Ki_app, se, v0,
+// se, E_t, se (NaN when fixed), rms residual]; Ki follows from Ki_app as in
+// cheng_prusoff with e_total 0. NaN throughout with too few points.
+pub fn fit_morrison(i: &[f64], v: &[f64], e_total: f64, fit_enzyme: bool) -> [f64; MORRISON_LEN] {
+    let pts: Vec<(f64, f64)> = i.iter().zip(v)
+        .filter(|(c, r)| c.is_finite() && r.is_finite() && **c >= 0.0)
+        .map(|(&c, &r)| (c, r))
+        .collect();
+    let n = if fit_enzyme { 3 } else { 2 };
+    if pts.len() < n || e_total.is_nan() || e_total <= 0.0 || !pts.iter().any(|p| p.0 > 0.0) { return [f64::NAN; MORRISON_LEN]; }
+    let lowest = pts.iter().copied().fold(pts[0], |a, b| if b.0 < a.0 { b } else { a });
+    // Start Ki_app where the rate is nearest half of v0, less the titrated E_t / 2
+    let mid = pts.iter().filter(|p| p.0 > 0.0)
+        .fold((f64::INFINITY, lowest.0), |best, p| if (p.1 - 0.5 * lowest.1).abs() < best.0 { ((p.1 - 0.5 * lowest.1).abs(), p.0) } else { best }).1;
+    let mut start = vec![lowest.1, (mid - 0.5 * e_total).max(1e-3 * mid).ln()];
+    if fit_enzyme { start.push(e_total.ln()); }
+    let enzyme = |q: &[f64]| if fit_enzyme { q[2].exp() } else { e_total };
+    let fit = linalg::levenberg_marquardt(&start, 200, |q| {
+        pts.iter().map(|&(c, r)| r - morrison_rate(q[0], enzyme(q), c, q[1].exp())).collect()
+    });
+    let (q, se) = (&fit.q, fit.standard_errors(pts.len()));
+    let ki_app = q[1].exp();
+    let e = enzyme(q);
+    let se_e = if fit_enzyme { e * se[2] } else { f64::NAN };
+    [ki_app, ki_app * se[1], q[0], se[0], e, se_e, (fit.ssr / pts.len() as f64).sqrt()]
+}
+
+// Product formed under slow-binding inhibition E + I <-> EI (rapid, Ki_app
+// in the presence of substrate) <-> EI* (k5 forward, k6 back), enzyme added
+// last to substrate and inhibitor: P = p0 + vs t + (v0 - vs)(1 - e^(-kobs t))
+// / kobs with v_u the uninhibited rate, v0 = v_u / (1 + I/Ki_app), vs = v_u
+// / (1 + I/Ki_app (1 + k5/k6)) and kobs = k6 + k5 (I/Ki_app) / (1 + I/Ki_app).
+// Substrate depletion is neglected, as in the initial-rate analyses.
+// Returns [v0, vs, kobs] and P at each time.
+pub fn slow_binding_curve(v_u: f64, ki_app: f64, k5: f64, k6: f64, i: f64, p0: f64, times: &[f64]) -> ([f64; 3], Vec<f64>) {
+    let x = i / ki_app;
+    let v0 = v_u / (1.0 + x);
+    let vs = v_u / (1.0 + x * (1.0 + k5 / k6));
+    let kobs = k6 + k5 * x / (1.0 + x);
+    ([v0, vs, kobs], times.iter().map(|&t| progress_curve(p0, v0, vs, kobs, t)).collect())
+}
+
+// Slow-onset progress curve p0 + vs t + (v0 - vs)(1 - e^(-kobs t)) / kobs
+pub fn progress_curve(p0: f64, v0: f64, vs: f64, kobs: f64, t: f64) -> f64 {
+    let burst = if kobs * t > 1e-8 { -(-kobs * t).exp_m1() / kobs } else { t };
+    p0 + vs * t + (v0 - vs) * burst
+}
+
+// Width of the fit_slow_onset output
+pub const SLOW_ONSET_LEN: usize = 9;
+
+// Fit of one slow-onset progress curve (p at times) to progress_curve by
+// least squares on p over [p0, v0, vs, ln kobs], from the slopes of the
+// first and last quarters and kobs = 4 / the time span. Output: [kobs, se,
+// v0, se, vs, se, p0, se, rms residual]; NaN with fewer than five points.
+pub fn fit_slow_onset(times: &[f64], p: &[f64]) -> [f64; SLOW_ONSET_LEN] {
+    let mut pts: Vec<(f64, f64)> = times.iter().zip(p).filter(|(t, v)| t.is_finite() && v.is_finite()).map(|(&t, &v)| (t, v)).collect();
+    if pts.len() < 5 { return [f64::NAN; SLOW_ONSET_LEN]; }
+    pts.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let m = pts.len();
+    let slope = |a: (f64, f64), b: (f64, f64)| if b.0 > a.0 { (b.1 - a.1) / (b.0 - a.0) } else { 0.0 };
+    let q = (m / 4).max(1);
+    let (v0, vs) = (slope(pts[0], pts[q]), slope(pts[m - 1 - q], pts[m - 1]));
+    let span = (pts[m - 1].0 - pts[0].0).max(1e-300);
+    let fit = linalg::levenberg_marquardt(&[pts[0].1, v0, vs, (4.0 / span).ln()], 300, |q| {
+        pts.iter().map(|&(t, v)| v - progress_curve(q[0], q[1], q[2], q[3].exp(), t)).collect()
+    });
+    let (q, se) = (&fit.q, fit.standard_errors(m));
+    let kobs = q[3].exp();
+    [kobs, kobs * se[3], q[1], se[1], q[2], se[2], q[0], se[0], (fit.ssr / m as f64).sqrt()]
+}
+
+// Width of the fit_kobs output
+pub const KOBS_LEN: usize = 7;
+
+// kobs against inhibitor concentration i. two_step fits the hyperbola k6 +
+// k5 I / (Ki_app + I) of an initial complex isomerizing slowly, else the
+// line k6 + k5 I of one-step slow binding (k5 then a second-order constant
+// and Ki_app NaN). Least squares on kobs over the logs of the constants.
+// Output: [k5, se, k6, se, Ki_app, se, rms residual]; NaN with too few points.
+pub fn fit_kobs(i: &[f64], kobs: &[f64], two_step: bool) -> [f64; KOBS_LEN] {
+    let pts: Vec<(f64, f64)> = i.iter().zip(kobs).filter(|(c, k)| c.is_finite() && k.is_finite() && **c >= 0.0).map(|(&c, &k)| (c, k)).collect();
+    let n = if two_step { 3 } else { 2 };
+    let mut out = [f64::NAN; KOBS_LEN];
+    if pts.len() < n { return out; }
+    let i_max = pts.iter().map(|p| p.0).fold(0.0, f64::max);
+    let k_min = pts.iter().map(|p| p.1).fold(f64::INFINITY, f64::min).max(1e-12);
+    let k_max = pts.iter().map(|p| p.1).fold(0.0, f64::max);
+    if i_max <= 0.0 { return out; }
+    let model = |q: &[f64], c: f64| {
+        let (k5, k6) = (q[0].exp(), q[1].exp());
+        if two_step { k6 + k5 * c / (q[2].exp() + c) } else { k6 + k5 * c }
+    };
+    let start = if two_step {
+        vec![(2.0 * (k_max - k_min)).max(k_min).ln(), (0.5 * k_min).ln(), (0.5 * i_max).ln()]
+    } else {
+        vec![((k_max - k_min).max(1e-3 * k_max) / i_max).ln(), (0.5 * k_min).ln()]
+    };
+    let fit = linalg::levenberg_marquardt(&start, 300, |q| pts.iter().map(|&(c, k)| k - model(q, c)).collect());
+    let se = fit.standard_errors(pts.len());
+    for j in 0..n {
+        let v = fit.q[j].exp();
+        out[2 * j] = v;
+        out[2 * j + 1] = v * se[j];
+    }
+    out[6] = (fit.ssr / pts.len() as f64).sqrt();
+    out
+}
+
+// Product formed under irreversible inactivation E + I <-> EI (rapid, KI
+// in the presence of substrate) -> E-I (kinact), enzyme added last, with I
+// in excess: P = v0 (1 - e^(-kobs t)) / kobs, v0 = v_u / (1 + I/KI) and kobs
+// = kinact I / (KI + I). Returns [v0, kobs] and P at each time; the kobs of
+// a measured curve come from fit_slow_onset (its vs then near 0).
+pub fn inactivation_curve(v_u: f64, kinact: f64, ki: f64, i: f64, times: &[f64]) -> ([f64; 2], Vec<f64>) {
+    let v0 = v_u / (1.0 + i / ki);
+    let kobs = kinact * i / (ki + i);
+    ([v0, kobs], times.iter().map(|&t| progress_curve(0.0, v0, 0.0, kobs, t)).collect())
+}
+
+// Width of the fit_inactivation output
+pub const INACTIVATION_LEN: usize = 7;
+
+// kinact and KI from kobs at inhibitor concentrations i, by least squares
+// of kinact I / (KI + I) over their logs. Output: [kinact, se, KI, se,
+// kinact/KI, se, rms residual]; the ratio's se includes the correlation
+// of the two, which is strong when no I comes near KI. NaN with fewer than
+// two points.
+pub fn fit_inactivation(i: &[f64], kobs: &[f64]) -> [f64; INACTIVATION_LEN] {
+    let pts: Vec<(f64, f64)> = i.iter().zip(kobs).filter(|(c, k)| c.is_finite() && k.is_finite() && **c > 0.0).map(|(&c, &k)| (c, k)).collect();
+    if pts.len() < 2 { return [f64::NAN; INACTIVATION_LEN]; }
+    let i_max = pts.iter().map(|p| p.0).fold(0.0, f64::max);
+    let k_max = pts.iter().map(|p| p.1).fold(0.0, f64::max).max(1e-12);
+    let fit = linalg::levenberg_marquardt(&[(2.0 * k_max).ln(), i_max.ln()], 300, |q| {
+        let (kinact, ki) = (q[0].exp(), q[1].exp());
+        pts.iter().map(|&(c, k)| k - kinact * c / (ki + c)).collect()
+    });
+    let cov = fit.covariance(pts.len());
+    let (kinact, ki) = (fit.q[0].exp(), fit.q[1].exp());
+    let ratio = kinact / ki;
+    let se_ln_ratio = (cov[0] + cov[3] - 2.0 * cov[1]).max(0.0).sqrt();
+    [kinact, kinact * cov[0].sqrt(), ki, ki * cov[3].sqrt(), ratio, ratio * se_ln_ratio, (fit.ssr / pts.len() as f64).sqrt()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn global_fit_recovers_mixed_inhibition() {
+        let truth = [12.0, 40.0, 5.0, 3.0];
+        let (mut s, mut i, mut v) = (Vec::new(), Vec::new(), Vec::new());
+        for (n, &sc) in [5.0, 10.0, 20.0, 40.0, 80.0, 160.0].iter().enumerate() {
+            for (m, &ic) in [0.0, 2.5, 5.0, 10.0, 20.0].iter().enumerate() {
+                s.push(sc);
+                i.push(ic);
+                // +-1% alternating scatter
+                v.push(Inhibition::Mixed.rate(&truth, sc, ic) * if (n + m) % 2 == 0 { 1.01 } else { 0.99 });
+            }
+        }
+        let out = fit_inhibition(&s, &i, &v, Inhibition::Mixed);
+        for (j, &t) in truth.iter().enumerate() {
+            assert!((out[2 * j] / t - 1.0).abs() < 0.1, "param {}: {:?}", j, out);
+            assert!(out[2 * j + 1] > 0.0 && out[2 * j + 1] < 0.5 * t, "param {}: {:?}", j, out);
+        }
+        // A competitive fit of the same data misses by more
+        assert!(fit_inhibition(&s, &i, &v, Inhibition::Competitive)[8] > 2.0 * out[8]);
+        assert!(fit_inhibition(&s, &[0.0; 30], &v, Inhibition::Mixed)[0].is_nan());
+        // Inhibited points without substrate have no Km to start from
+        assert!(fit_inhibition(&[0.0; 30], &i, &v, Inhibition::Mixed).iter().all(|x| x.is_nan()));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn ic50_converts_back_to_ki() {
+        let (km, s, ki) = (40.0, 100.0, 5.0);
+        let c = [12.0, km, ki, 3.0];
+        let doses: Vec<f64> = (0..12).map(|j| 0.1 * 2f64.powi(j)).collect();
+        for mode in [Inhibition::Competitive, Inhibition::Uncompetitive, Inhibition::Noncompetitive, Inhibition::Mixed] {
+            let v: Vec<f64> = doses.iter().map(|&i| mode.rate(&c, s, i)).collect();
+            let fit = fit_dose_response(&doses, &v);
+            // Hyperbolic inhibition is a Hill slope of 1
+            assert!((fit[2] - 1.0).abs() < 1e-3, "{:?}: {:?}", mode, fit);
+            let back = cheng_prusoff(fit[0], s, km, 0.0, mode, 3.0);
+            assert!((back / ki - 1.0).abs() < 1e-3, "{:?}: Ki {} from IC50 {}", mode, back, fit[0]);
+        }
+        assert_eq!(cheng_prusoff(10.0, 0.0, 1.0, 4.0, Inhibition::Competitive, 0.0), 8.0);
+        assert!(fit_dose_response(&[1.0, 2.0, 3.0], &[3.0, 2.0, 1.0])[0].is_nan());
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn morrison_fit_handles_depletion() {
+        // Ki_app 2 nM against 10 nM enzyme: free I is far below total
+        let (v0, e_t, ki_app) = (8.0, 10.0, 2.0);
+        let doses: Vec<f64> = (0..12).map(|j| 0.5 * 1.6f64.powi(j)).collect();
+        let v: Vec<f64> = doses.iter().map(|&i| morrison_rate(v0, e_t, i, ki_app)).collect();
+        assert!((morrison_rate(v0, e_t, 0.0, ki_app) - v0).abs() < 1e-12);
+        assert!(morrison_rate(v0, e_t, 1e6, ki_app) < 1e-3 * v0);
+        let fixed = fit_morrison(&doses, &v, e_t, false);
+        assert!((fixed[0] / ki_app - 1.0).abs() < 1e-4 && (fixed[2] / v0 - 1.0).abs() < 1e-4, "{:?}", fixed);
+        // Active enzyme fitted from a nominal 15
+        let fitted = fit_morrison(&doses, &v, 15.0, true);
+        assert!((fitted[4] / e_t - 1.0).abs() < 1e-3, "{:?}", fitted);
+        // The classical hyperbola puts IC50 near Ki_app + E_t / 2, far above Ki_app
+        let ic50 = fit_dose_response(&doses, &v)[0];
+        assert!(ic50 > 2.0 * ki_app, "IC50 {}", ic50);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn slow_binding_constants_from_progress_curves() {
+        let (v_u, ki_app, k5, k6) = (2.0, 50.0, 0.05, 0.005);
+        let times: Vec<f64> = (0..=120).map(|j| 5.0 * j as f64).collect();
+        let doses = [10.0, 25.0, 50.0, 100.0, 200.0, 400.0];
+        let mut kobs = Vec::new();
+        for &i in &doses {
+            let (truth, p) = slow_binding_curve(v_u, ki_app, k5, k6, i, 0.0, &times);
+            let fit = fit_slow_onset(&times, &p);
+            assert!((fit[0] / truth[2] - 1.0).abs() < 1e-3, "I {}: {:?} vs {:?}", i, fit, truth);
+            assert!((fit[2] / truth[0] - 1.0).abs() < 1e-3 && (fit[4] / truth[1] - 1.0).abs() < 1e-3);
+            kobs.push(fit[0]);
+        }
+        let two = fit_kobs(&doses, &kobs, true);
+        assert!((two[0] / k5 - 1.0).abs() < 0.01 && (two[2] / k6 - 1.0).abs() < 0.01 && (two[4] / ki_app - 1.0).abs() < 0.01, "{:?}", two);
+        // One-step binding only fits the hyperbola poorly
+        assert!(fit_kobs(&doses, &kobs, false)[6] > 10.0 * two[6]);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn kinact_and_ki_from_inactivation_curves() {
+        let (kinact, ki) = (0.02, 5.0);
+        let times: Vec<f64> = (0..=100).map(|j| 3.0 * j as f64).collect();
+        let doses = [1.0, 2.5, 5.0, 10.0, 25.0, 50.0];
+        let kobs: Vec<f64> = doses.iter().map(|&i| {
+            let (truth, p) = inactivation_curve(1.0, kinact, ki, i, &times);
+            let fit = fit_slow_onset(&times, &p);
+            assert!((fit[0] / truth[1] - 1.0).abs() < 1e-3 && fit[4].abs() < 1e-3, "I {}: {:?}", i, fit);
+            fit[0]
+        }).collect();
+        let out = fit_inactivation(&doses, &kobs);
+        assert!((out[0] / kinact - 1.0).abs() < 1e-3 && (out[2] / ki - 1.0).abs() < 1e-3, "{:?}", out);
+        assert!((out[4] - kinact / ki).abs() < 1e-6);
+    }
+}
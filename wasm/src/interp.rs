@@ -0,0 +1,137 @@
+// Interpolation of a simulated series at observation times.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Interp {
+    Linear,
+    // Monotone piecewise cubic Hermite (Fritsch–Carlson)
+    Pchip,
+    // No interpolation: the objective simulates straight onto each
+    // observation time (model::run_at_times); eval treats it as Linear
+    Exact,
+}
+
+impl Interp {
+    pub fn from_code(code: u32) -> Interp {
+        match code { 1 => Interp::Pchip, 2 => Interp::Exact, _ => Interp::Linear }
+    }
+
+    pub fn parse(name: &str) -> Result<Interp, String> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "linear" => Ok(Interp::Linear),
+            "pchip" => Ok(Interp::Pchip),
+            "exact" => Ok(Interp::Exact),
+            _ => Err(format!("unknown interp '{}' (expected linear, pchip or exact)", name)),
+        }
+    }
+
+    // Name accepted by parse
+    pub fn name(self) -> &'static str {
+        match self { Interp::Linear => "linear", Interp::Pchip => "pchip", Interp::Exact => "exact" }
+    }
+}
+
+// PCHIP node derivative at i from the neighbouring secants
+fn pchip_slope(t: &[f64], v: &[f64], i: usize) -> f64 {
+    let m = t.len();
+    let secant = |a: usize| -> f64 {
+        let h = t[a + 1] - t[a];
+        if h > 0.0 { (v[a + 1] - v[a]) / h } else { 0.0 }
+    };
+    if m < 2 { return 0.0; }
+    if i == 0 || i == m - 1 {
+        // One-sided three-point estimate, limited to preserve monotonicity
+        let d0 = if i == 0 { secant(0) } else { secant(m - 2) };
+        if m < 3 { return d0; }
+        let (d1, h0, h1) = if i == 0 {
+            (secant(1), t[1] - t[0], t[2] - t[1])
+        } else {
+            (secant(m - 3), t[m - 1] - t[m - 2], t[m - 2] - t[m - 3])
+        };
+        let d = ((2.0 * h0 + h1) * d0 - h0 * d1) / (h0 + h1);
+        if d.signum() != d0.signum() { return 0.0; }
+        if d0.signum() != d1.signum() && d.abs() > 3.0 * d0.abs() { return 3.0 * d0; }
+        return d;
+    }
+    let (d0, d1) = (secant(i - 1), secant(i));
+    if d0 == 0.0 || d1 == 0.0 || d0.signum() != d1.signum() { return 0.0; }
+    let (h0, h1) = (t[i] - t[i - 1], t[i + 1] - t[i]);
+    let w1 = 2.0 * h1 + h0;
+    let w2 = h1 + 2.0 * h0;
+    (w1 + w2) / (w1 / d0 + w2 / d1)
+}
+
+// Value at x of the series (t ascending, values v); clamps outside the range
+pub fn eval(t: &[f64], v: &[f64], x: f64, method: Interp) -> f64 {
+    let m = t.len();
+    if m == 0 { return f64::NAN; }
+    if x <= t[0] { return v[0]; }
+    if x >= t[m - 1] { return v[m - 1]; }
+    // binary search for bracket
+    let mut lo: usize = 0;
+    let mut hi: usize = m - 1;
+    while lo + 1 < hi {
+        let mid = (lo + hi) / 2;
+        if t[mid] <= x { lo = mid; } else { hi = mid; }
+    }
+    let h = t[hi] - t[lo];
+    if h <= 0.0 { return v[lo]; }
+    let w = (x - t[lo]) / h;
+    match method {
+        Interp::Linear | Interp::Exact => v[lo] + w * (v[hi] - v[lo]),
+        Interp::Pchip => {
+            let (d0, d1) = (pchip_slope(t, v, lo), pchip_slope(t, v, hi));
+            let w2 = w * w;
+            let w3 = w2 * w;
+            (2.0 * w3 - 3.0 * w2 + 1.0) * v[lo]
+                + (w3 - 2.0 * w2 + w) * h * d0
+                + (-2.0 * w3 + 3.0 * w2) * v[hi]
+                + (w3 - w2) * h * d1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn pchip_is_exact_on_lines_and_keeps_steps_monotone() {
+        // Linear data on an uneven grid: every slope is the line's
+        let t = [0.0, 0.5, 2.0, 2.2, 5.0];
+        let v: Vec<f64> = t.iter().map(|x| 3.0 - 2.0 * x).collect();
+        for i in 0..=50 {
+            let x = 0.1 * i as f64;
+            assert!((eval(&t, &v, x, Interp::Pchip) - (3.0 - 2.0 * x)).abs() < 1e-12, "x={}", x);
+        }
+        // A step: no overshoot, flat at the plateaus, non-decreasing between
+        let (t, v) = ([0.0, 1.0, 2.0, 3.0, 4.0], [0.0, 0.0, 1.0, 1.0, 1.0]);
+        let ys: Vec<f64> = (0..=400).map(|i| eval(&t, &v, 0.01 * i as f64, Interp::Pchip)).collect();
+        assert!(ys.iter().all(|y| (0.0..=1.0).contains(y)));
+        assert!(ys.windows(2).all(|w| w[1] >= w[0]));
+        assert_eq!(pchip_slope(&t, &v, 1), 0.0);
+        // A peak: the limiter flattens the interior extremum
+        let peak = [0.0, 1.0, 0.0];
+        assert_eq!(pchip_slope(&[0.0, 1.0, 2.0], &peak, 1), 0.0);
+        assert!((0..=200).all(|i| eval(&[0.0, 1.0, 2.0], &peak, 0.01 * i as f64, Interp::Pchip) <= 1.0));
+        // Outside the range the end values hold
+        assert_eq!(eval(&t, &v, -1.0, Interp::Pchip), 0.0);
+        assert!(eval(&[], &[], 1.0, Interp::Linear).is_nan());
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn pchip_end_slopes_are_one_sided_and_limited() {
+        let t = [0.0, 1.0, 3.0];
+        // The three-point estimate is exact for a quadratic (here t^2 at 0 and 3)
+        let sq = [0.0, 1.0, 9.0];
+        assert_eq!(pchip_slope(&t, &sq, 0), 0.0);
+        assert!((pchip_slope(&t, &sq, 2) - 6.0).abs() < 1e-12);
+        let t = [0.0, 1.0, 2.0];
+        // Estimate against the end secant's sign: 0
+        assert_eq!(pchip_slope(&t, &[0.0, 1.0, 10.0], 0), 0.0);
+        // Secants of opposite sign: capped at 3 times the end secant
+        assert_eq!(pchip_slope(&t, &[0.0, 1.0, -10.0], 0), 3.0);
+        // Two points: the secant
+        assert_eq!(pchip_slope(&[0.0, 2.0], &[1.0, 5.0], 1), 2.0);
+    }
+}
@@ -0,0 +1,290 @@
+// Minimal JSON reader/writer for job files and reports (std only).
+// Objects keep key order; non-finite numbers are written as null.
+
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    // Member of an object by key
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(m) => m.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self { Value::Number(n) => Some(*n), _ => None }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self { Value::String(s) => Some(s), _ => None }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self { Value::Bool(b) => Some(*b), _ => None }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self { Value::Array(a) => Some(a), _ => None }
+    }
+
+    // Array of numbers (null entries become NaN)
+    pub fn as_f64_vec(&self) -> Option<Vec<f64>> {
+        self.as_array()?.iter().map(|v| match v {
+            Value::Null => Some(f64::NAN),
+            _ => v.as_f64(),
+        }).collect()
+    }
+}
+
+impl From<f64> for Value {
+    fn from(v: f64) -> Value { Value::Number(v) }
+}
+
+impl From<&str> for Value {
+    fn from(v: &str) -> Value { Value::String(v.to_string()) }
+}
+
+impl From<&[f64]> for Value {
+    fn from(v: &[f64]) -> Value { Value::Array(v.iter().map(|&x| Value::Number(x)).collect()) }
+}
+
+fn write_str(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    f.write_str("\"")
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Null => f.write_str("null"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Number(n) if n.is_finite() => write!(f, "{}", n),
+            Value::Number(_) => f.write_str("null"),
+            Value::String(s) => write_str(f, s),
+            Value::Array(a) => {
+                f.write_str("[")?;
+                for (i, v) in a.iter().enumerate() {
+                    if i > 0 { f.write_str(",")?; }
+                    write!(f, "{}", v)?;
+                }
+                f.write_str("]")
+            }
+            Value::Object(m) => {
+                f.write_str("{")?;
+                for (i, (k, v)) in m.iter().enumerate() {
+                    if i > 0 { f.write_str(",")?; }
+                    write_str(f, k)?;
+                    write!(f, ":{}", v)?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+// Arrays and objects nested deeper than this are rejected rather than
+// recursed into, so hostile input cannot overflow the stack
+const MAX_DEPTH: usize = 256;
+
+struct Parser<'a> {
+    src: &'a [u8],
+    pos: usize,
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn err<T>(&self, msg: &str) -> Result<T, String> { Err(format!("{} at byte {}", msg, self.pos)) }
+
+    fn skip_ws(&mut self) {
+        while self.pos < self.src.len() && self.src[self.pos].is_ascii_whitespace() { self.pos += 1; }
+    }
+
+    fn peek(&self) -> Option<u8> { self.src.get(self.pos).copied() }
+
+    fn expect(&mut self, lit: &str) -> Result<(), String> {
+        if self.src[self.pos..].starts_with(lit.as_bytes()) {
+            self.pos += lit.len();
+            Ok(())
+        } else {
+            self.err(&format!("expected '{}'", lit))
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        self.skip_ws();
+        match self.peek() {
+            Some(b'n') => self.expect("null").map(|_| Value::Null),
+            Some(b't') => self.expect("true").map(|_| Value::Bool(true)),
+            Some(b'f') => self.expect("false").map(|_| Value::Bool(false)),
+            Some(b'"') => self.string().map(Value::String),
+            Some(open @ (b'[' | b'{')) => {
+                if self.depth == MAX_DEPTH { return self.err(&format!("nesting deeper than {} levels", MAX_DEPTH)); }
+                self.depth += 1;
+                let v = if open == b'[' { self.array() } else { self.object() };
+                self.depth -= 1;
+                v
+            }
+            Some(c) if c == b'-' || c.is_ascii_digit() => self.number(),
+            Some(_) => self.err("unexpected character"),
+            None => self.err("unexpected end of input"),
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, String> {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if c.is_ascii_digit() || matches!(c, b'-' | b'+' | b'.' | b'e' | b'E') { self.pos += 1; } else { break; }
+        }
+        let text = std::str::from_utf8(&self.src[start..self.pos]).unwrap_or("");
+        match text.parse::<f64>() {
+            Ok(n) => Ok(Value::Number(n)),
+            Err(_) => { self.pos = start; self.err("invalid number") }
+        }
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let text = self.src.get(self.pos..self.pos + 4).and_then(|b| std::str::from_utf8(b).ok());
+        match text.and_then(|t| u32::from_str_radix(t, 16).ok()) {
+            Some(v) => { self.pos += 4; Ok(v) }
+            None => self.err("invalid \\u escape"),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.pos += 1; // opening quote
+        let mut out = String::new();
+        loop {
+            let start = self.pos;
+            while let Some(c) = self.peek() {
+                if c == b'"' || c == b'\\' { break; }
+                self.pos += 1;
+            }
+            out.push_str(std::str::from_utf8(&self.src[start..self.pos]).map_err(|_| "invalid UTF-8".to_string())?);
+            match self.peek() {
+                Some(b'"') => { self.pos += 1; return Ok(out); }
+                Some(b'\\') => {
+                    self.pos += 1;
+                    let c = self.peek();
+                    self.pos += 1;
+                    match c {
+                        Some(b'"') => out.push('"'),
+                        Some(b'\\') => out.push('\\'),
+                        Some(b'/') => out.push('/'),
+                        Some(b'b') => out.push('\u{8}'),
+                        Some(b'f') => out.push('\u{c}'),
+                        Some(b'n') => out.push('\n'),
+                        Some(b'r') => out.push('\r'),
+                        Some(b't') => out.push('\t'),
+                        Some(b'u') => {
+                            let mut cp = self.hex4()?;
+                            // Surrogate pair
+                            if (0xd800..0xdc00).contains(&cp) && self.src[self.pos..].starts_with(b"\\u") {
+                                self.pos += 2;
+                                let lo = self.hex4()?;
+                                cp = 0x10000 + ((cp - 0xd800) << 10) + (lo.wrapping_sub(0xdc00) & 0x3ff);
+                            }
+                            out.push(char::from_u32(cp).unwrap_or('\u{fffd}'));
+                        }
+                        _ => return self.err("invalid escape"),
+                    }
+                }
+                _ => return self.err("unterminated string"),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, String> {
+        self.pos += 1;
+        let mut items = Vec::new();
+        self.skip_ws();
+        if self.peek() == Some(b']') { self.pos += 1; return Ok(Value::Array(items)); }
+        loop {
+            items.push(self.value()?);
+            self.skip_ws();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => { self.pos += 1; return Ok(Value::Array(items)); }
+                _ => return self.err("expected ',' or ']'"),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Value, String> {
+        self.pos += 1;
+        let mut members = Vec::new();
+        self.skip_ws();
+        if self.peek() == Some(b'}') { self.pos += 1; return Ok(Value::Object(members)); }
+        loop {
+            self.skip_ws();
+            if self.peek() != Some(b'"') { return self.err("expected object key"); }
+            let key = self.string()?;
+            self.skip_ws();
+            self.expect(":")?;
+            members.push((key, self.value()?));
+            self.skip_ws();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => { self.pos += 1; return Ok(Value::Object(members)); }
+                _ => return self.err("expected ',' or '}'"),
+            }
+        }
+    }
+}
+
+pub fn parse(text: &str) -> Result<Value, String> {
+    let mut p = Parser { src: text.as_bytes(), pos: 0, depth: 0 };
+    let v = p.value()?;
+    p.skip_ws();
+    if p.pos != p.src.len() { return p.err("trailing characters"); }
+    Ok(v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn round_trip() {
+        let text = r#"{"name":"run \"1\"","k":[0.001,-2.5e-3,null],"ok":true,"nested":{"a":[]}}"#;
+        let v = parse(text).unwrap();
+        assert_eq!(v.get("name").and_then(Value::as_str), Some("run \"1\""));
+        let k = v.get("k").and_then(Value::as_f64_vec).unwrap();
+        assert_eq!(&k[..2], &[0.001, -2.5e-3]);
+        assert!(k[2].is_nan());
+        assert_eq!(parse(&v.to_string()).unwrap(), v);
+        assert!(parse("[1, 2").is_err());
+        assert!(parse("{} x").is_err());
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn nesting_is_capped() {
+        let nested = |n: usize| "[".repeat(n) + &"]".repeat(n);
+        assert!(parse(&nested(MAX_DEPTH)).is_ok());
+        let err = parse(&nested(MAX_DEPTH + 1)).unwrap_err();
+        assert!(err.starts_with("nesting deeper than 256 levels"), "{}", err);
+        assert!(parse(&"{\"a\":".repeat(100_000)).is_err());
+    }
+}
@@ -0,0 +1,245 @@
+// Gradient-based fitting of the deterministic model by limited-memory BFGS
+// with box bounds (L-BFGS-B). The objective is Problem::ode_objective, whose
+// gradient comes from the forward sensitivities for the rates and adaptive
+// differences for the rest, so on smooth data a fit needs far fewer
+// evaluations than the simplex. Bounds are held by projection: a variable at
+// a bound that the gradient pushes outward is fixed for the iteration, the
+// others take the two-loop quasi-Newton direction, and the line search
+// projects every trial point back into the box.
+
+use std::collections::VecDeque;
+
+use crate::fit::{self, FitResult, FIT_CANCELLED, FIT_CONVERGED, FIT_MAX_EVALS, FIT_MAX_ITER, FIT_NOTHING_TO_DO, FIT_X_CONVERGED};
+use crate::objective::{lower_bound, Params, Problem};
+
+// Sufficient-decrease constant of the line search
+const ARMIJO: f64 = 1e-4;
+// Curvature constant of the (weak) Wolfe line search
+const CURVATURE: f64 = 0.9;
+// Trial steps before a line search gives up
+const MAX_TRIALS: u32 = 30;
+
+#[derive(Clone, Debug)]
+pub struct Lbfgs {
+    pub max_iter: u32,
+    // Objective plus gradient evaluations allowed in total (a gradient
+    // counts once); 0 => unlimited
+    pub max_evals: u32,
+    // Correction pairs kept for the inverse-Hessian model; 0 => 7
+    pub memory: u32,
+    // Stop once an iteration lowers the objective by less than tol,
+    // relative to its magnitude; <= 0 disables the test
+    pub tol: f64,
+    // Stop once no coordinate moves by more than x_tol, relative to its
+    // starting value (absolute where it is 0); <= 0 disables the test
+    pub x_tol: f64,
+    pub progress_every: u32, // 0 => every iteration
+    // Record the objective and parameters at every iteration
+    pub trace: bool,
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 { a.iter().zip(b).map(|(x, y)| x * y).sum() }
+
+// Gradient of Problem::ode_objective over params[cols]: the loss's
+// derivative through the prediction Jacobian, plus the prior's
+fn gradient(problem: &Problem, p: &Params, cols: &[usize]) -> Vec<f64> {
+    let pred = problem.ode_predictions(p);
+    let used: Vec<usize> = (0..pred.len()).filter(|&i| problem.times[i].is_finite()).collect();
+    let obs: Vec<f64> = used.iter().map(|&i| problem.y_obs[i]).collect();
+    let at: Vec<f64> = used.iter().map(|&i| pred[i]).collect();
+    let dl = problem.loss.derivative(&obs, &at);
+    let m = cols.len();
+    let jac = fit::prediction_jacobian(problem, p, cols, &used);
+    let prior = problem.prior_gradient(p);
+    cols.iter().enumerate()
+        .map(|(c, &j)| (0..used.len()).map(|r| dl[r] * jac[r * m + c]).sum::<f64>() + prior[j])
+        .collect()
+}
+
+// -H g by the two-loop recursion over the free coordinates, with the
+// newest pair's s.y / y.y as the initial scaling; 0 on the fixed ones
+fn two_loop(g: &[f64], free: &[bool], pairs: &VecDeque<(Vec<f64>, Vec<f64>)>) -> Vec<f64> {
+    let mask = |v: &[f64]| -> Vec<f64> { v.iter().zip(free).map(|(x, &f)| if f { *x } else { 0.0 }).collect() };
+    let mut q = mask(g);
+    let masked: Vec<(Vec<f64>, Vec<f64>, f64)> = pairs.iter()
+        .map(|(s, y)| { let (s, y) = (mask(s), mask(y)); let sy = dot(&s, &y); (s, y, sy) })
+        .filter(|(_, _, sy)| *sy > 0.0)
+        .collect();
+    let mut alpha = vec![0.0; masked.len()];
+    for (k, (s, y, sy)) in masked.iter().enumerate() {
+        alpha[k] = dot(s, &q) / sy;
+        for i in 0..q.len() { q[i] -= alpha[k] * y[i]; }
+    }
+    if let Some((_, y, sy)) = masked.first() {
+        let gamma = sy / dot(y, y);
+        for v in q.iter_mut() { *v *= gamma; }
+    }
+    for (k, (s, y, sy)) in masked.iter().enumerate().rev() {
+        let beta = dot(y, &q) / sy;
+        for i in 0..q.len() { q[i] += (alpha[k] - beta) * s[i]; }
+    }
+    q.iter().map(|v| -v).collect()
+}
+
+// Minimize Problem::ode_objective over params[optimize_idx] within bounds
+// (one (lower, upper) per optimize_idx entry, NaN for none; every entry is
+// also floored at the parameter's lower bound), starting from params. dt
+// sets the integration step rather than the model and is left out. progress
+// is called as (iteration, objective, params) every opts.progress_every
+// iterations; returning true stops the fit. Reasons are the fit::FIT_*
+// codes, FIT_X_CONVERGED also when no step along the steepest descent
+// lowers the objective; spread is the largest projected-gradient component
+// (in units of the starting values), simplex the final point.
+pub fn lbfgs_fit(
+    problem: &Problem,
+    params: Params,
+    optimize_idx: &[usize],
+    bounds: &[(f64, f64)],
+    opts: &Lbfgs,
+    mut progress: impl FnMut(u32, f64, &Params) -> bool,
+) -> FitResult {
+    let (idx, bounds): (Vec<usize>, Vec<(f64, f64)>) = optimize_idx.iter().enumerate()
+        .filter(|&(_, &i)| i != 6)
+        .map(|(j, &i)| (i, bounds.get(j).copied().unwrap_or((f64::NAN, f64::NAN))))
+        .unzip();
+    let n = idx.len();
+    if n == 0 {
+        let f = problem.ode_objective(&params);
+        return FitResult::single_point(params, f, 0, 1, 0.0, FIT_NOTHING_TO_DO, Vec::new());
+    }
+
+    // Coordinates z = x / w relative to the start, so one step length suits
+    // rates of any magnitude
+    let w: Vec<f64> = idx.iter().map(|&i| if params[i].abs() > 0.0 { params[i].abs() } else { 1.0 }).collect();
+    let lo: Vec<f64> = (0..n).map(|j| {
+        let b = if bounds[j].0.is_finite() { bounds[j].0 } else { f64::NEG_INFINITY };
+        b.max(lower_bound(idx[j])) / w[j]
+    }).collect();
+    let hi: Vec<f64> = (0..n).map(|j| {
+        let b = if bounds[j].1.is_finite() { bounds[j].1 } else { f64::INFINITY };
+        (b / w[j]).max(lo[j])
+    }).collect();
+    let project = |z: &[f64]| -> Vec<f64> { (0..n).map(|j| z[j].clamp(lo[j], hi[j])).collect() };
+    let at = |z: &[f64]| -> Params {
+        let mut trial = params;
+        for (j, &i) in idx.iter().enumerate() { trial[i] = w[j] * z[j]; }
+        trial
+    };
+    let grad_at = |z: &[f64]| -> Vec<f64> { gradient(problem, &at(z), &idx).iter().zip(&w).map(|(g, w)| g * w).collect() };
+    let budget = if opts.max_evals > 0 { opts.max_evals } else { u32::MAX };
+    let memory = if opts.memory > 0 { opts.memory as usize } else { 7 };
+
+    let mut z = project(&idx.iter().enumerate().map(|(j, &i)| params[i] / w[j]).collect::<Vec<f64>>());
+    let mut f = problem.ode_objective(&at(&z));
+    let mut g = grad_at(&z);
+    let mut evals: u32 = 2;
+    let mut pairs: VecDeque<(Vec<f64>, Vec<f64>)> = VecDeque::new();
+    let mut trace = Vec::new();
+    let mut iter = 0;
+    let mut reason = FIT_MAX_ITER;
+    let mut pg_norm;
+    loop {
+        // Variables held at a bound this iteration, and the projected gradient
+        let free: Vec<bool> = (0..n).map(|j| !((z[j] <= lo[j] && g[j] > 0.0) || (z[j] >= hi[j] && g[j] < 0.0))).collect();
+        pg_norm = (0..n).filter(|&j| free[j]).map(|j| g[j].abs()).fold(0.0, f64::max);
+        if iter >= opts.max_iter { break; }
+        if opts.trace { trace.push((f, at(&z))); }
+        if iter % opts.progress_every.max(1) == 0 && progress(iter, f, &at(&z)) { reason = FIT_CANCELLED; break; }
+        if pg_norm == 0.0 { reason = FIT_CONVERGED; break; }
+        if evals.saturating_add(2) > budget { reason = FIT_MAX_EVALS; break; }
+
+        let mut d = two_loop(&g, &free, &pairs);
+        let descent = dot(&d, &g);
+        if descent.is_nan() || descent >= 0.0 {
+            pairs.clear();
+            d = (0..n).map(|j| if free[j] { -g[j] } else { 0.0 }).collect();
+        }
+        // Without curvature pairs the first trial moves a tenth of the start
+        let slope = dot(&d, &g);
+        let mut step = if pairs.is_empty() { (0.1 / d.iter().fold(0.0, |m, v| f64::max(m, v.abs()))).min(1.0) } else { 1.0 };
+        let (mut lo_step, mut hi_step) = (0.0, f64::INFINITY);
+        let mut accepted = None;
+        let mut sufficient = None;
+        for _ in 0..MAX_TRIALS {
+            if evals.saturating_add(2) > budget { break; }
+            let zt = project(&(0..n).map(|j| z[j] + step * d[j]).collect::<Vec<f64>>());
+            let s: Vec<f64> = (0..n).map(|j| zt[j] - z[j]).collect();
+            if s.iter().all(|v| *v == 0.0) { break; }
+            let ft = problem.ode_objective(&at(&zt));
+            evals += 1;
+            if ft.is_nan() || ft > f + ARMIJO * dot(&g, &s) {
+                // Too far: interpolate the quadratic through f, the slope
+                // and ft until a sufficient step is known, then bisect
+                hi_step = step;
+                let quad = -slope * step * step / (2.0 * (ft - f - slope * step));
+                step = if lo_step > 0.0 { 0.5 * (lo_step + hi_step) }
+                    else if quad.is_finite() { quad.clamp(0.1 * step, 0.5 * step) }
+                    else { 0.1 * step };
+                continue;
+            }
+            let gt = grad_at(&zt);
+            evals += 1;
+            // Curvature condition, where the box did not clip the step
+            let clipped = (0..n).any(|j| s[j] != step * d[j]);
+            if !clipped && dot(&gt, &d) < CURVATURE * slope {
+                lo_step = step;
+                step = if hi_step.is_finite() { 0.5 * (lo_step + hi_step) } else { 2.0 * step };
+                sufficient = Some((zt, s, ft, gt));
+                continue;
+            }
+            accepted = Some((zt, s, ft, gt));
+            break;
+        }
+        let Some((zt, s, ft, gt)) = accepted.or(sufficient) else {
+            // A stale curvature model can point nowhere useful: retry once
+            // along the steepest descent before giving up
+            if !pairs.is_empty() {
+                pairs.clear();
+                iter += 1;
+                continue;
+            }
+            reason = if evals.saturating_add(2) > budget { FIT_MAX_EVALS } else { FIT_X_CONVERGED };
+            break;
+        };
+        let y: Vec<f64> = (0..n).map(|j| gt[j] - g[j]).collect();
+        if dot(&s, &y) > f64::EPSILON * dot(&y, &y) {
+            pairs.push_front((s.clone(), y));
+            pairs.truncate(memory);
+        }
+        let decrease = f - ft;
+        z = zt;
+        f = ft;
+        g = gt;
+        iter += 1;
+        if opts.tol > 0.0 && decrease <= opts.tol * f.abs().max(f64::MIN_POSITIVE) { reason = FIT_CONVERGED; break; }
+        if opts.x_tol > 0.0 && s.iter().all(|v| v.abs() <= opts.x_tol) { reason = FIT_X_CONVERGED; break; }
+    }
+    FitResult::single_point(at(&z), f, iter, evals, pg_norm, reason, trace)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objective::params_from_slice;
+    use crate::objective::tests::synthetic_problem;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn lbfgs_recovers_rates_and_holds_bounds() {
+        let init = [10.0, 0.0, 0.0, 1000.0, 0.0, 0.0];
+        let truth = params_from_slice(&[1e-3, 0.0, 0.1, 1.0, 0.0, 0.5, 0.2], &init);
+        let problem = synthetic_problem(init, &truth, (1..=40).map(|i| 10.0 * i as f64).collect());
+        let mut start = truth;
+        start[3] = 0.7;
+        start[5] = 0.8;
+        let opts = Lbfgs { max_iter: 200, max_evals: 0, memory: 0, tol: 1e-14, x_tol: 1e-9, progress_every: 0, trace: false };
+        let res = lbfgs_fit(&problem, start, &[3, 5, 6], &[], &opts, |_, _, _| false);
+        assert!((res.params[3] - 1.0).abs() < 1e-3 && (res.params[5] - 0.5).abs() < 1e-3, "{:?} {}", &res.params[..7], fit::reason_name(res.reason));
+        assert_eq!(res.params[6], start[6]);
+        assert!(res.evaluations < 200, "{} evaluations", res.evaluations);
+        // An upper bound below the truth holds k2 on it
+        let bounded = lbfgs_fit(&problem, start, &[3, 5], &[(f64::NAN, 0.8), (f64::NAN, f64::NAN)], &opts, |_, _, _| false);
+        assert_eq!(bounded.params[3], 0.8);
+        assert!(bounded.sse > 0.0 && bounded.sse < problem.ode_objective(&start));
+    }
+}
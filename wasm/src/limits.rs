@@ -0,0 +1,75 @@
+// Caps on run length and output size, checked by the exports before they
+// allocate a result, so an oversized request fails with an error the caller
+// can catch instead of aborting the WASM instance on a failed allocation.
+// Set per thread, like the rng stream and warn flags.
+
+use std::cell::Cell;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limits {
+    pub max_steps: u64, // steps of one run; 0 => unlimited
+    pub max_output_bytes: u64, // f64 output of one call; 0 => unlimited
+}
+
+// No step cap; 1 GiB of output, a quarter of the wasm32 address space
+pub const DEFAULT_LIMITS: Limits = Limits { max_steps: 0, max_output_bytes: 1 << 30 };
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LimitError {
+    TooManySteps { requested: u64, max: u64 },
+    OutputTooLarge { bytes: u64, max: u64 },
+}
+
+impl fmt::Display for LimitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LimitError::TooManySteps { requested, max } => write!(f, "{} steps requested, limit is {}", requested, max),
+            LimitError::OutputTooLarge { bytes, max } => write!(f, "output of {} bytes requested, limit is {}", bytes, max),
+        }
+    }
+}
+
+impl std::error::Error for LimitError {}
+
+thread_local! {
+    static LIMITS: Cell<Limits> = const { Cell::new(DEFAULT_LIMITS) };
+}
+
+pub fn set(limits: Limits) { LIMITS.with(|l| l.set(limits)); }
+
+pub fn get() -> Limits { LIMITS.with(Cell::get) }
+
+// A run of `steps` steps returning `values` f64s, against the current limits
+pub fn check(steps: u64, values: u64) -> Result<(), LimitError> {
+    let l = get();
+    if l.max_steps > 0 && steps > l.max_steps {
+        return Err(LimitError::TooManySteps { requested: steps, max: l.max_steps });
+    }
+    let bytes = values.saturating_mul(8);
+    if l.max_output_bytes > 0 && bytes > l.max_output_bytes {
+        return Err(LimitError::OutputTooLarge { bytes, max: l.max_output_bytes });
+    }
+    Ok(())
+}
+
+// One row of `width` values per step
+pub fn check_series(steps: u32, width: usize) -> Result<(), LimitError> {
+    check(steps as u64, steps as u64 * width as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn limits_reject_oversized_runs() {
+        assert!(check_series(u32::MAX, 12).is_err());
+        set(Limits { max_steps: 100, max_output_bytes: 0 });
+        assert_eq!(check_series(101, 6), Err(LimitError::TooManySteps { requested: 101, max: 100 }));
+        assert!(check_series(100, 1_000_000).is_ok());
+        set(DEFAULT_LIMITS);
+        assert!(check_series(1_000_000, 6).is_ok());
+    }
+}
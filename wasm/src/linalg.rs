@@ -0,0 +1,180 @@
+// Small dense linear algebra on row-major n x n matrices stored in Vec<f64>,
+// and a Levenberg–Marquardt solver for the closed-form secondary fits.
+
+// J^T J for a row-major rows x cols matrix
+pub(crate) fn gram(j: &[f64], rows: usize, cols: usize) -> Vec<f64> {
+    let mut g = vec![0.0; cols * cols];
+    for r in 0..rows {
+        let row = &j[r * cols..(r + 1) * cols];
+        for a in 0..cols {
+            for b in a..cols { g[a * cols + b] += row[a] * row[b]; }
+        }
+    }
+    for a in 0..cols {
+        for b in 0..a { g[a * cols + b] = g[b * cols + a]; }
+    }
+    g
+}
+
+// Lower Cholesky factor, or None if the matrix is not positive definite
+pub(crate) fn cholesky(a: &[f64], n: usize) -> Option<Vec<f64>> {
+    let mut l = vec![0.0; n * n];
+    for i in 0..n {
+        for j in 0..=i {
+            let mut sum = a[i * n + j];
+            for k in 0..j { sum -= l[i * n + k] * l[j * n + k]; }
+            if i == j {
+                if !(sum.is_finite() && sum > 0.0) { return None; }
+                l[i * n + i] = sum.sqrt();
+            } else {
+                l[i * n + j] = sum / l[j * n + j];
+            }
+        }
+    }
+    Some(l)
+}
+
+// log det of a symmetric positive definite matrix, -inf if singular
+pub(crate) fn log_det_spd(a: &[f64], n: usize) -> f64 {
+    match cholesky(a, n) {
+        Some(l) => (0..n).map(|i| 2.0 * l[i * n + i].ln()).sum(),
+        None => f64::NEG_INFINITY,
+    }
+}
+
+// Inverse by Gauss-Jordan elimination with partial pivoting
+pub(crate) fn invert(a: &[f64], n: usize) -> Option<Vec<f64>> {
+    let mut m = a.to_vec();
+    let mut inv = vec![0.0; n * n];
+    for i in 0..n { inv[i * n + i] = 1.0; }
+    for c in 0..n {
+        let piv = (c..n).max_by(|&x, &y| m[x * n + c].abs().partial_cmp(&m[y * n + c].abs()).unwrap_or(std::cmp::Ordering::Equal))?;
+        let pv = m[piv * n + c];
+        if !(pv.is_finite() && pv.abs() > 1e-300) { return None; }
+        if piv != c {
+            for k in 0..n { m.swap(piv * n + k, c * n + k); inv.swap(piv * n + k, c * n + k); }
+        }
+        for k in 0..n { m[c * n + k] /= pv; inv[c * n + k] /= pv; }
+        for r in 0..n {
+            if r == c { continue; }
+            let f = m[r * n + c];
+            if f == 0.0 { continue; }
+            for k in 0..n {
+                m[r * n + k] -= f * m[c * n + k];
+                inv[r * n + k] -= f * inv[c * n + k];
+            }
+        }
+    }
+    Some(inv)
+}
+
+// Nonlinear least-squares solution: parameters, sum of squared residuals and
+// (J^T J)^-1 at the solution (None if singular); scale it by ssr / (m - n)
+// for the covariance
+pub(crate) struct LeastSquares {
+    pub q: Vec<f64>,
+    pub ssr: f64,
+    pub inv_jtj: Option<Vec<f64>>,
+}
+
+impl LeastSquares {
+    // Covariance of q from the scatter of m residuals; NaN without degrees
+    // of freedom or with a singular J^T J
+    pub fn covariance(&self, m: usize) -> Vec<f64> {
+        let n = self.q.len();
+        let s2 = if m > n { self.ssr / (m - n) as f64 } else { f64::NAN };
+        match &self.inv_jtj {
+            Some(c) => c.iter().map(|v| s2 * v).collect(),
+            None => vec![f64::NAN; n * n],
+        }
+    }
+
+    // Standard errors of q from the scatter of m residuals
+    pub fn standard_errors(&self, m: usize) -> Vec<f64> {
+        let n = self.q.len();
+        let cov = self.covariance(m);
+        (0..n).map(|j| cov[j * n + j].sqrt()).collect()
+    }
+}
+
+// Levenberg–Marquardt minimization of the squared residuals(q) from q0, with
+// a forward-difference Jacobian; trial points with non-finite residuals are
+// turned down
+pub(crate) fn levenberg_marquardt(q0: &[f64], max_iter: usize, residuals: impl Fn(&[f64]) -> Vec<f64>) -> LeastSquares {
+    let n = q0.len();
+    let ssr = |r: &[f64]| r.iter().map(|v| v * v).sum::<f64>();
+    let jacobian = |q: &[f64], r: &[f64]| -> Vec<f64> {
+        let m = r.len();
+        let mut jac = vec![0.0; m * n];
+        for j in 0..n {
+            let h = 1e-7 * q[j].abs().max(1e-3);
+            let mut qh = q.to_vec();
+            qh[j] += h;
+            let rh = residuals(&qh);
+            // d(model)/dq = -d(residual)/dq
+            for i in 0..m { jac[i * n + j] = (r[i] - rh[i]) / h; }
+        }
+        jac
+    };
+    let mut q = q0.to_vec();
+    let mut r = residuals(&q);
+    let mut f = ssr(&r);
+    let mut lambda = 1e-3;
+    for _ in 0..max_iter {
+        if !f.is_finite() { break; }
+        let m = r.len();
+        let jac = jacobian(&q, &r);
+        let jtj = gram(&jac, m, n);
+        let g: Vec<f64> = (0..n).map(|j| (0..m).map(|i| jac[i * n + j] * r[i]).sum()).collect();
+        let mut improved = false;
+        while lambda < 1e12 {
+            let mut a = jtj.clone();
+            for j in 0..n { a[j * n + j] *= 1.0 + lambda; }
+            let Some(inv) = invert(&a, n) else { lambda *= 10.0; continue };
+            let trial: Vec<f64> = (0..n).map(|j| q[j] + (0..n).map(|l| inv[j * n + l] * g[l]).sum::<f64>()).collect();
+            let rt = residuals(&trial);
+            let ft = ssr(&rt);
+            if ft < f {
+                improved = f - ft > 1e-14 * f.max(1e-300);
+                (q, r, f) = (trial, rt, ft);
+                lambda = (lambda / 10.0).max(1e-12);
+                break;
+            }
+            lambda *= 10.0;
+        }
+        if !improved { break; }
+    }
+    let inv_jtj = if f.is_finite() { invert(&gram(&jacobian(&q, &r), r.len(), n), n) } else { None };
+    LeastSquares { q, ssr: f, inv_jtj }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn gram_and_log_det_of_a_known_matrix() {
+        // J = [[1, 2], [0, 1], [1, 0]]: J^T J = [[2, 2], [2, 5]], det 6
+        let g = gram(&[1.0, 2.0, 0.0, 1.0, 1.0, 0.0], 3, 2);
+        assert_eq!(g, [2.0, 2.0, 2.0, 5.0]);
+        let l = cholesky(&g, 2).unwrap();
+        assert!((l[0] * l[0] - 2.0).abs() < 1e-12 && l[1] == 0.0 && (l[2] * l[2] + l[3] * l[3] - 5.0).abs() < 1e-12);
+        assert!((log_det_spd(&g, 2) - 6f64.ln()).abs() < 1e-12);
+        assert_eq!(log_det_spd(&[1.0, 1.0, 1.0, 1.0], 2), f64::NEG_INFINITY);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn inverse_times_matrix_is_identity() {
+        // Needs a row swap: the first pivot is 0
+        let a = [0.0, 2.0, 1.0, 1.0, 1.0, 0.0, 3.0, 0.0, 4.0];
+        let inv = invert(&a, 3).unwrap();
+        for i in 0..3 {
+            for j in 0..3 {
+                let v: f64 = (0..3).map(|k| inv[i * 3 + k] * a[k * 3 + j]).sum();
+                assert!((v - if i == j { 1.0 } else { 0.0 }).abs() < 1e-12);
+            }
+        }
+        assert!(invert(&[1.0, 2.0, 2.0, 4.0], 2).is_none());
+    }
+}
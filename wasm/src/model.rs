@@ -0,0 +1,1083 @@
+// Stochastic competing-risks engine on a [E, ES, EP, S, P, t] state.
+// Rates are [k1, k-3, k-1, k2, k-2, k3], matching the fit vector.
+
+use crate::compensated;
+use crate::rng::{rand_f64, sample_binomial};
+use crate::warn::{self, Warnings};
+use crate::volume;
+
+pub type State = [f64; 6];
+pub type Rates = [f64; 6];
+
+// Events per reaction channel in one step: [E->ES, E->EP, ES->E, ES->EP, EP->ES, EP->E]
+pub type Fluxes = [i64; 6];
+
+// Exact species counts [E, ES, EP, S, P] for the integer bookkeeping mode
+pub type Counts = [i64; 5];
+
+// Per-molecule rates of leaving E, ES and EP at state st
+pub fn channel_rates(st: &State, k: &Rates) -> [f64; 3] {
+    let [k1, k_minus3, k_minus1, k2, k_minus2, k3] = *k;
+    [
+        (k1 * st[3].max(0.0)).max(0.0) + (k_minus3 * st[4].max(0.0)).max(0.0),
+        k_minus1.max(0.0) + k2.max(0.0),
+        k_minus2.max(0.0) + k3.max(0.0),
+    ]
+}
+
+// Per-molecule probability 1 - exp(-lambda dt) of leaving E, ES and EP within
+// one step at state st. Values near 1 mean most molecules would react more
+// than once per step and the step is too coarse.
+pub fn channel_p_tot(st: &State, k: &Rates, dt: f64) -> [f64; 3] {
+    channel_rates(st, k).map(|lambda| if lambda > 0.0 { 1.0 - (-(lambda * dt)).exp() } else { 0.0 })
+}
+
+// Largest dt keeping every channel p_tot at or below target (NaN or outside
+// (0, 1) => warn::P_TOT_WARN) over the whole run from st: binding is bounded
+// by max(k1, k-3) times all of S + ES + EP + P, since S and P only trade
+// places. Infinite when no channel can react.
+pub fn suggest_dt(st: &State, k: &Rates, target: f64) -> f64 {
+    let target = if target > 0.0 && target < 1.0 { target } else { warn::P_TOT_WARN };
+    let [k1, k_minus3, k_minus1, k2, k_minus2, k3] = k.map(|v| v.max(0.0));
+    let pool = st[1].max(0.0) + st[2].max(0.0) + st[3].max(0.0) + st[4].max(0.0);
+    let lambda = (k1.max(k_minus3) * pool).max(k_minus1 + k2).max(k_minus2 + k3);
+    if lambda > 0.0 { -(-target).ln_1p() / lambda } else { f64::INFINITY }
+}
+
+// Substeps allowed per adaptive step
+const MAX_SUBSTEPS: f64 = 1024.0;
+
+// One dt step taken as substeps short enough that every channel p_tot stays
+// at or below p_max, re-evaluated after each substep as S and P change (but
+// never shorter than dt / 1024). Events are summed and time lands exactly on
+// t + dt. p_max outside (0, 1) takes the single fixed step. dt must already
+// be clamped.
+pub fn step_adaptive(st: &mut State, k: &Rates, dt: f64, p_max: f64) -> Fluxes {
+    if !(p_max > 0.0 && p_max < 1.0) { return step(st, k, dt); }
+    let t_end = st[5] + dt;
+    let lambda_h_max = -(-p_max).ln_1p(); // lambda * h at which p_tot = p_max
+    let mut total: Fluxes = [0; 6];
+    let mut left = dt;
+    while left > 0.0 {
+        let lambda = channel_rates(st, k).into_iter().fold(0.0, f64::max);
+        let mut h = if lambda > 0.0 { (lambda_h_max / lambda).max(dt / MAX_SUBSTEPS) } else { left };
+        // No sliver of a substep at the end
+        if h >= left * (1.0 - 1e-9) { h = left; }
+        let fl = step(st, k, h);
+        for c in 0..6 { total[c] += fl[c]; }
+        left = if h == left { 0.0 } else { left - h };
+    }
+    st[5] = t_end;
+    total
+}
+
+// Events of one step drawn from the integer pools [E, ES, EP], the S and P
+// available to bind (s_avail, p_avail) and the S and P setting the binding
+// rates, at the per-channel p_tot of channel_p_tot. Also returns the binding
+// events moved to the other channel by the overflow policy, and the binding
+// draws cut by the S or P available before it placed them.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn draw_events(
+    pools: [i64; 3], s_avail: i64, p_avail: i64, s: f64, p: f64, k: &Rates, p_tot: [f64; 3], overflow: Overflow,
+) -> (Fluxes, i64, i64) {
+    let [k1, k_minus3, k_minus1, k2, k_minus2, k3] = *k;
+    let [nel, nes_c, nep_c] = pools;
+    let [p_tot, p_tot_es, p_tot_ep] = p_tot;
+
+    // ---------- Competing-risks aggregated transitions for free E ----------
+    // Rates per molecule
+    let lambda1 = (k1 * s.max(0.0)).max(0.0);
+    let lambda2 = (k_minus3 * p.max(0.0)).max(0.0);
+    let lambda_sum = lambda1 + lambda2;
+    let n_react = sample_binomial(nel, p_tot);
+    let frac1 = if lambda_sum > 0.0 { (lambda1 / lambda_sum).clamp(0.0, 1.0) } else { 0.0 };
+    let n_es_raw = sample_binomial(n_react, frac1);
+    let n_ep_raw = n_react - n_es_raw;
+    // Cap by resources, then place the overflow per the policy
+    let mut n_es = n_es_raw.min(s_avail);
+    let mut n_ep = n_ep_raw.min(p_avail);
+    let s_left = s_avail - n_es;
+    let p_left = p_avail - n_ep;
+    let overflow_es = n_es_raw - n_es; // ES wanted but no S
+    let overflow_ep = n_ep_raw - n_ep; // EP wanted but no P
+    if overflow_es > 0 || overflow_ep > 0 { warn::raise(Warnings::RESOURCE_CAPPED); }
+    let (mut add_ep, mut add_es) = (0, 0);
+    match overflow {
+        Overflow::Reassign => {
+            if overflow_es > 0 && p_left > 0 { add_ep = overflow_es.min(p_left); }
+            if overflow_ep > 0 && s_left > 0 { add_es = overflow_ep.min(s_left); }
+        }
+        Overflow::Drop => {}
+        Overflow::Redraw if overflow_es + overflow_ep > 0 => {
+            // Each overflowing event picks a channel again by the rate split;
+            // a pick of a full channel stays a free E
+            let extra = overflow_es + overflow_ep;
+            let again_es = sample_binomial(extra, frac1);
+            add_es = again_es.min(s_left);
+            add_ep = (extra - again_es).min(p_left);
+        }
+        Overflow::Redraw => {}
+    }
+    n_es += add_es;
+    n_ep += add_ep;
+
+    // ---------- Competing-risks for ES complexes ----------
+    let lambda1_es = k_minus1.max(0.0);
+    let lambda2_es = k2.max(0.0);
+    let lambda_sum_es = lambda1_es + lambda2_es;
+    let n_react_es = sample_binomial(nes_c, p_tot_es);
+    let frac1_es = if lambda_sum_es > 0.0 { (lambda1_es / lambda_sum_es).clamp(0.0, 1.0) } else { 0.0 };
+    let to_el = sample_binomial(n_react_es, frac1_es);
+    let to_ep = n_react_es - to_el;
+
+    // ---------- Competing-risks for EP complexes ----------
+    let lambda1_ep = k_minus2.max(0.0);
+    let lambda2_ep = k3.max(0.0);
+    let lambda_sum_ep = lambda1_ep + lambda2_ep;
+    let n_react_ep = sample_binomial(nep_c, p_tot_ep);
+    let frac1_ep = if lambda_sum_ep > 0.0 { (lambda1_ep / lambda_sum_ep).clamp(0.0, 1.0) } else { 0.0 };
+    let to_es = sample_binomial(n_react_ep, frac1_ep);
+    let to_e = n_react_ep - to_es;
+    ([n_es, n_ep, to_el, to_ep, to_es, to_e], add_es + add_ep, overflow_es + overflow_ep)
+}
+
+// How fractional species values become the integer counts a step draws from
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Rounding {
+    // Enzyme pools rounded to nearest, S and P caps floored (the original scheme)
+    #[default]
+    Legacy,
+    // Everything rounded to nearest; a cap rounded up can bind S or P that is
+    // not there, and the clamp to 0 then loses the difference
+    Nearest,
+    // Everything floored: never draws more than is present, biased low
+    Floor,
+    // floor(x) + 1 with probability frac(x): unbiased on average, one extra
+    // random draw per fractional value
+    Stochastic,
+}
+
+impl Rounding {
+    // 0: legacy, 1: nearest, 2: floor, 3: stochastic
+    pub fn from_code(code: u32) -> Result<Rounding, String> {
+        match code {
+            0 => Ok(Rounding::Legacy),
+            1 => Ok(Rounding::Nearest),
+            2 => Ok(Rounding::Floor),
+            3 => Ok(Rounding::Stochastic),
+            _ => Err(format!("unknown rounding {} (expected 0: legacy, 1: nearest, 2: floor or 3: stochastic)", code)),
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Rounding, String> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "legacy" => Ok(Rounding::Legacy),
+            "nearest" => Ok(Rounding::Nearest),
+            "floor" => Ok(Rounding::Floor),
+            "stochastic" => Ok(Rounding::Stochastic),
+            _ => Err(format!("unknown rounding '{}' (expected legacy, nearest, floor or stochastic)", name)),
+        }
+    }
+
+    // Count of a non-negative value; cap marks the S and P resource caps
+    fn count(self, x: f64, cap: bool) -> i64 {
+        let x = x.max(0.0);
+        let v = match self {
+            Rounding::Legacy if cap => x.floor(),
+            Rounding::Legacy | Rounding::Nearest => x.round(),
+            Rounding::Floor => x.floor(),
+            Rounding::Stochastic => {
+                let f = x.floor();
+                if x > f && rand_f64() < x - f { f + 1.0 } else { f }
+            }
+        };
+        v as i64
+    }
+}
+
+// What happens to binding draws beyond the S or P available
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Overflow {
+    // Moved to the other binding channel while it has substrate left (the original scheme)
+    #[default]
+    Reassign,
+    // Left as free E
+    Drop,
+    // Redrawn across both channels by their rate split; picks of the full
+    // channel stay free E, so the other channel gets only its share
+    Redraw,
+}
+
+impl Overflow {
+    // 0: reassign, 1: drop, 2: redraw
+    pub fn from_code(code: u32) -> Result<Overflow, String> {
+        match code {
+            0 => Ok(Overflow::Reassign),
+            1 => Ok(Overflow::Drop),
+            2 => Ok(Overflow::Redraw),
+            _ => Err(format!("unknown overflow policy {} (expected 0: reassign, 1: drop or 2: redraw)", code)),
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Overflow, String> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "reassign" => Ok(Overflow::Reassign),
+            "drop" => Ok(Overflow::Drop),
+            "redraw" => Ok(Overflow::Redraw),
+            _ => Err(format!("unknown overflow policy '{}' (expected reassign, drop or redraw)", name)),
+        }
+    }
+}
+
+// Modelling choices of a step; the default is the original scheme
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StepPolicy {
+    pub rounding: Rounding,
+    pub overflow: Overflow,
+}
+
+// Clamp the five species of a [E, ES, EP, S, P, t] state at zero, raising
+// COUNT_ZERO if a negative value was discarded
+#[inline]
+fn clamp_species(st: &mut State) {
+    let mut clamped = false;
+    for v in st.iter_mut().take(5) { if *v < 0.0 { *v = 0.0; clamped = true; } }
+    if clamped { warn::raise(Warnings::COUNT_ZERO); }
+}
+
+fn warn_p_tot(p_tot: [f64; 3]) {
+    if p_tot[0].max(p_tot[1]).max(p_tot[2]) > warn::P_TOT_WARN { warn::raise(Warnings::P_TOT_HIGH); }
+}
+
+// One dt step of the competing-risks scheme. dt must already be clamped.
+pub fn step(st: &mut State, k: &Rates, dt: f64) -> Fluxes {
+    step_with(st, k, dt, &StepPolicy::default()).0
+}
+
+// step under an explicit policy; also returns the binding events the
+// overflow policy moved to the other channel
+pub fn step_with(st: &mut State, k: &Rates, dt: f64, policy: &StepPolicy) -> (Fluxes, i64) {
+    // Ensure non-negative
+    clamp_species(st);
+    let p_tot = channel_p_tot(st, k, dt);
+    warn_p_tot(p_tot);
+    let [mut e, mut es, mut ep, mut s, mut p, tiempo] = *st;
+
+    // Compute NEL/NES/NEP as current counts (rounded, like the TS engine, by default)
+    let r = policy.rounding;
+    let pools = [r.count(e, false), r.count(es, false), r.count(ep, false)];
+    let (s_avail, p_avail) = (r.count(s, true), r.count(p, true));
+    let (fl, moved, _) = draw_events(pools, s_avail, p_avail, s, p, k, p_tot, policy.overflow);
+    let [n_es, n_ep, to_el, to_ep, to_es, to_e] = fl;
+
+    // Apply updates: binding, then the ES and EP channels
+    e -= (n_es + n_ep) as f64;
+    es += n_es as f64;
+    ep += n_ep as f64;
+    s -= n_es as f64;
+    p -= n_ep as f64;
+
+    e += to_el as f64;
+    es -= (to_el + to_ep) as f64;
+    s += to_el as f64;
+    ep += to_ep as f64;
+
+    es += to_es as f64;
+    ep -= (to_es + to_e) as f64;
+    e += to_e as f64;
+    p += to_e as f64;
+
+    // Clamp and increment time by dt
+    *st = [e, es, ep, s, p, tiempo + dt];
+    clamp_species(st);
+    (fl, moved)
+}
+
+// Counts of a state, rounded to nearest (negative and non-finite values => 0)
+pub fn counts_from_state(st: &State) -> Counts {
+    let mut n = [0i64; 5];
+    for i in 0..5 { n[i] = if st[i].is_finite() { st[i].round().max(0.0) as i64 } else { 0 }; }
+    n
+}
+
+pub fn state_from_counts(n: &Counts, t: f64) -> State {
+    [n[0] as f64, n[1] as f64, n[2] as f64, n[3] as f64, n[4] as f64, t]
+}
+
+// step on exact integer counts: the same events (and random draws) as step on
+// an integral state, applied without any float rounding, so E + ES + EP and
+// S + ES + EP + P are conserved exactly however long the run. Binding draws
+// beyond the S or P available follow overflow. dt must already be clamped.
+pub fn step_counts(n: &mut Counts, t: &mut f64, k: &Rates, dt: f64, overflow: Overflow) -> Fluxes {
+    let p_tot = channel_p_tot(&state_from_counts(n, *t), k, dt);
+    warn_p_tot(p_tot);
+    let (fl, _, capped) = draw_events([n[0], n[1], n[2]], n[3], n[4], n[3] as f64, n[4] as f64, k, p_tot, overflow);
+    let [n_es, n_ep, to_el, to_ep, to_es, to_e] = fl;
+    n[0] += to_el + to_e - n_es - n_ep;
+    n[1] += n_es + to_es - to_el - to_ep;
+    n[2] += n_ep + to_ep - to_es - to_e;
+    n[3] += to_el - n_es;
+    n[4] += to_e - n_ep;
+    *t += dt;
+    // Counts cannot go negative here; the pools truncating a draw is the analogue
+    if capped > 0 { warn::raise(Warnings::COUNT_ZERO); }
+    fl
+}
+
+// Run time t0 + dt + dt + ... with compensated summation: 10^7 steps of a
+// small dt would otherwise drift off the grid by thousands of ulps and
+// misalign interpolation against observation times. Runners keep one and
+// overwrite the time each step sets.
+pub struct Clock(compensated::Sum);
+
+impl Clock {
+    pub fn new(t0: f64) -> Clock { Clock(compensated::Sum::new(t0)) }
+
+    // Time after a further dt
+    #[inline]
+    pub fn tick(&mut self, dt: f64) -> f64 {
+        self.0.add(dt);
+        self.0.value()
+    }
+}
+
+// dt itself when finite and positive, otherwise 1 (raising DT_CLAMPED)
+pub fn clamp_dt(dt: f64) -> f64 {
+    if dt.is_finite() && dt > 0.0 { return dt; }
+    warn::raise(Warnings::DT_CLAMPED);
+    1.0
+}
+
+// Species names in [E, ES, EP, S, P, t] row order
+const SPECIES_NAMES: [&str; 5] = ["E", "ES", "EP", "S", "P"];
+
+// Observed quantity: one species or a sum of species of a [E, ES, EP, S, P, ..]
+// row, e.g. "P", "S+P" or "ES+EP" (bound enzyme). Bit i selects row index i.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Observable(u8);
+
+impl Observable {
+    pub const E: Observable = Observable(1);
+    pub const ES: Observable = Observable(1 << 1);
+    pub const EP: Observable = Observable(1 << 2);
+    pub const S: Observable = Observable(1 << 3);
+    pub const P: Observable = Observable(1 << 4);
+
+    // Species names joined by '+' (case-insensitive, each at most once)
+    pub fn parse(text: &str) -> Result<Observable, String> {
+        let mut bits = 0u8;
+        for part in text.split('+') {
+            let name = part.trim().to_ascii_uppercase();
+            let i = SPECIES_NAMES.iter().position(|&s| s == name).ok_or_else(|| {
+                format!("unknown species '{}' in '{}' (expected S, P, E, ES or EP, or a sum like S+P)", part.trim(), text)
+            })?;
+            if bits & (1 << i) != 0 { return Err(format!("species '{}' repeated in '{}'", name, text)); }
+            bits |= 1 << i;
+        }
+        Ok(Observable(bits))
+    }
+
+    // Numeric codes of the original JS API: 0:S, 1:P, 2:E, 3:ES, 4:EP
+    pub fn from_code(code: u32) -> Result<Observable, String> {
+        match code {
+            0 => Ok(Observable::S),
+            1 => Ok(Observable::P),
+            2 => Ok(Observable::E),
+            3 => Ok(Observable::ES),
+            4 => Ok(Observable::EP),
+            _ => Err(format!("unknown species code {} (expected 0:S, 1:P, 2:E, 3:ES or 4:EP)", code)),
+        }
+    }
+
+    // Value on a row starting [E, ES, EP, S, P]
+    pub fn value(self, row: &[f64]) -> f64 {
+        (0..5).filter(|i| self.0 & (1 << i) != 0).map(|i| row[i]).sum()
+    }
+}
+
+impl std::fmt::Display for Observable {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let names: Vec<&str> = (0..5).filter(|i| self.0 & (1 << i) != 0).map(|i| SPECIES_NAMES[i]).collect();
+        f.write_str(&names.join("+"))
+    }
+}
+
+// Final [E, ES, EP, S, P, t] after `steps` steps
+pub fn run_final(st: State, k: &Rates, dt: f64, steps: u32) -> State {
+    run_final_adaptive(st, k, dt, steps, f64::NAN)
+}
+
+// run_final with each step refined by step_adaptive to keep p_tot <= p_max
+pub fn run_final_adaptive(mut st: State, k: &Rates, dt: f64, steps: u32, p_max: f64) -> State {
+    let dt = clamp_dt(dt);
+    let mut clock = Clock::new(st[5]);
+    for _ in 0..steps {
+        step_adaptive(&mut st, k, dt, p_max);
+        st[5] = clock.tick(dt);
+    }
+    st
+}
+
+// Final state plus cumulative events per channel over the run
+pub fn run_final_counts(mut st: State, k: &Rates, dt: f64, steps: u32) -> (State, Fluxes) {
+    let dt = clamp_dt(dt);
+    let mut total: Fluxes = [0; 6];
+    let mut clock = Clock::new(st[5]);
+    for _ in 0..steps {
+        let fl = step(&mut st, k, dt);
+        st[5] = clock.tick(dt);
+        for c in 0..6 { total[c] += fl[c]; }
+    }
+    (st, total)
+}
+
+// Integer bookkeeping run: the initial state is rounded to counts once, and
+// counts become f64 only in the output. Flattened [E, ES, EP, S, P, t] rows,
+// one per step.
+pub fn run_series_integer(st0: State, k: &Rates, dt: f64, steps: u32, overflow: Overflow) -> Vec<f64> {
+    let dt = clamp_dt(dt);
+    let (mut n, mut t) = (counts_from_state(&st0), st0[5]);
+    let mut clock = Clock::new(t);
+    let mut data: Vec<f64> = Vec::with_capacity(6 * steps as usize);
+    for _ in 0..steps {
+        step_counts(&mut n, &mut t, k, dt, overflow);
+        t = clock.tick(dt);
+        data.extend_from_slice(&state_from_counts(&n, t));
+    }
+    data
+}
+
+// Final state of run_series_integer
+pub fn run_final_integer(st0: State, k: &Rates, dt: f64, steps: u32, overflow: Overflow) -> State {
+    let dt = clamp_dt(dt);
+    let (mut n, mut t) = (counts_from_state(&st0), st0[5]);
+    let mut clock = Clock::new(t);
+    for _ in 0..steps {
+        step_counts(&mut n, &mut t, k, dt, overflow);
+        t = clock.tick(dt);
+    }
+    state_from_counts(&n, t)
+}
+
+// run_series with every step taken under policy, plus the total binding
+// events its overflow policy moved between channels
+pub fn run_series_with(mut st: State, k: &Rates, dt: f64, steps: u32, policy: &StepPolicy) -> (Vec<f64>, u64) {
+    let dt = clamp_dt(dt);
+    let mut data: Vec<f64> = Vec::with_capacity(6 * steps as usize);
+    let mut moved = 0u64;
+    let mut clock = Clock::new(st[5]);
+    for _ in 0..steps {
+        moved += step_with(&mut st, k, dt, policy).1 as u64;
+        st[5] = clock.tick(dt);
+        data.extend_from_slice(&st);
+    }
+    (data, moved)
+}
+
+// Flattened [E, ES, EP, S, P, t] rows, one per step
+pub fn run_series(st: State, k: &Rates, dt: f64, steps: u32) -> Vec<f64> {
+    run_series_adaptive(st, k, dt, steps, f64::NAN)
+}
+
+// run_series on the same dt grid, each step refined by step_adaptive to keep
+// p_tot <= p_max
+pub fn run_series_adaptive(mut st: State, k: &Rates, dt: f64, steps: u32, p_max: f64) -> Vec<f64> {
+    let dt = clamp_dt(dt);
+    let mut data: Vec<f64> = Vec::with_capacity(6 * steps as usize);
+    let mut clock = Clock::new(st[5]);
+    for _ in 0..steps {
+        step_adaptive(&mut st, k, dt, p_max);
+        st[5] = clock.tick(dt);
+        data.extend_from_slice(&st);
+    }
+    data
+}
+
+// run_series_adaptive at two resolutions: fine_steps of dt_fine for the
+// pre-steady-state window, then coarse_steps of dt_coarse, one row per step
+// at either resolution
+pub fn run_series_dual(st: State, k: &Rates, dt_fine: f64, fine_steps: u32, dt_coarse: f64, coarse_steps: u32, p_max: f64) -> Vec<f64> {
+    let mut data = run_series_adaptive(st, k, dt_fine, fine_steps, p_max);
+    let mut mid = st;
+    if fine_steps > 0 { mid.copy_from_slice(&data[data.len() - 6..]); }
+    data.extend(run_series_adaptive(mid, k, dt_coarse, coarse_steps, p_max));
+    data
+}
+
+// Piecewise step sizes: steps of dt until each until_time, in order
+pub type DtSchedule = Vec<(f64, f64)>;
+
+// DtSchedule from flat [until_time, dt, ..] pairs; the times must increase
+// and every dt be finite and positive
+pub fn dt_schedule(flat: &[f64]) -> Result<DtSchedule, String> {
+    if flat.is_empty() || !flat.len().is_multiple_of(2) { return Err("a dt schedule needs [until_time, dt] pairs".to_string()); }
+    let mut out = Vec::with_capacity(flat.len() / 2);
+    for pair in flat.chunks(2) {
+        let (until, dt) = (pair[0], pair[1]);
+        if !until.is_finite() || out.last().is_some_and(|&(u, _): &(f64, f64)| until <= u) {
+            return Err(format!("dt schedule times must be finite and increasing (at {})", until));
+        }
+        if !dt.is_finite() || dt <= 0.0 { return Err(format!("dt schedule step until {} must be positive, not {}", until, dt)); }
+        out.push((until, dt));
+    }
+    Ok(out)
+}
+
+// Steps a schedule takes from t0, counting each shortened landing step
+pub fn schedule_steps(t0: f64, schedule: &[(f64, f64)]) -> u64 {
+    let mut t = t0;
+    let mut steps = 0u64;
+    for &(until, dt) in schedule {
+        if until > t {
+            steps = steps.saturating_add(((until - t) / dt).ceil() as u64);
+            t = until;
+        }
+    }
+    steps
+}
+
+// run_series_adaptive over a dt schedule: steps of each piece's dt until its
+// until_time, the last shortened to land on it exactly, one row per step.
+// Pieces ending at or before the current time are skipped.
+pub fn run_series_scheduled(mut st: State, k: &Rates, schedule: &[(f64, f64)], p_max: f64) -> Vec<f64> {
+    let mut data: Vec<f64> = Vec::with_capacity(6 * schedule_steps(st[5], schedule).min(1 << 24) as usize);
+    let mut clock = Clock::new(st[5]);
+    for &(until, dt) in schedule {
+        while st[5] < until {
+            let gap = until - st[5];
+            let h = if gap <= dt * (1.0 + 1e-9) { gap } else { dt };
+            step_adaptive(&mut st, k, h, p_max);
+            if h == gap {
+                clock = Clock::new(until);
+                st[5] = until;
+            } else {
+                st[5] = clock.tick(h);
+            }
+            data.extend_from_slice(&st);
+        }
+    }
+    data
+}
+
+// [E, ES, EP, S, P, t] rows at each requested absolute time, in request order.
+// Steps of dt (refined by step_adaptive to keep p_tot <= p_max), the last
+// before each time shortened to land on it exactly. Times before the start
+// (or non-finite) report the initial state.
+pub fn run_at_times(st0: State, k: &Rates, dt: f64, times: &[f64], p_max: f64) -> Vec<f64> {
+    let dt = clamp_dt(dt);
+    let mut order: Vec<usize> = (0..times.len()).collect();
+    order.sort_by(|&a, &b| times[a].total_cmp(&times[b]));
+    let mut data: Vec<f64> = st0.repeat(times.len());
+    let mut st = st0;
+    let mut clock = Clock::new(st[5]);
+    for &i in &order {
+        let target = times[i];
+        if !target.is_finite() { continue; }
+        while st[5] < target {
+            let gap = target - st[5];
+            let landing = gap <= dt;
+            step_adaptive(&mut st, k, gap.min(dt), p_max);
+            st[5] = clock.tick(gap.min(dt));
+            if landing {
+                st[5] = target;
+                clock = Clock::new(target);
+            }
+        }
+        data[6 * i..6 * i + 6].copy_from_slice(&st);
+    }
+    data
+}
+
+// n times from t0 + first to t0 + last, equally spaced in log(t - t0): dense
+// early, sparse late, as progress curves are sampled. Empty unless
+// 0 < first < last; a single point is t0 + last.
+pub fn log_spaced_times(t0: f64, first: f64, last: f64, n: u32) -> Vec<f64> {
+    if !(first > 0.0 && last > first && last.is_finite()) || n == 0 { return Vec::new(); }
+    if n == 1 { return vec![t0 + last]; }
+    let ratio = (last / first).ln() / (n - 1) as f64;
+    (0..n).map(|i| if i + 1 == n { t0 + last } else { t0 + first * (ratio * i as f64).exp() }).collect()
+}
+
+// run_series into a caller-provided buffer. Runs min(steps, out.len() / 6)
+// steps; returns the number of rows written.
+pub fn run_series_into(out: &mut [f64], mut st: State, k: &Rates, dt: f64, steps: u32) -> usize {
+    let dt = clamp_dt(dt);
+    let rows = (steps as usize).min(out.len() / 6);
+    let mut clock = Clock::new(st[5]);
+    for row in out.chunks_exact_mut(6).take(rows) {
+        step(&mut st, k, dt);
+        st[5] = clock.tick(dt);
+        row.copy_from_slice(&st);
+    }
+    rows
+}
+
+// Flattened [E, ES, EP, S, P, t, E->ES, E->EP, ES->E, ES->EP, EP->ES, EP->E]
+// rows, one per step, with the event counts of that step
+pub fn run_series_fluxes(mut st: State, k: &Rates, dt: f64, steps: u32) -> Vec<f64> {
+    let dt = clamp_dt(dt);
+    let mut data: Vec<f64> = Vec::with_capacity(12 * steps as usize);
+    let mut clock = Clock::new(st[5]);
+    for _ in 0..steps {
+        let fl = step(&mut st, k, dt);
+        st[5] = clock.tick(dt);
+        data.extend_from_slice(&st);
+        data.extend(fl.iter().map(|&v| v as f64));
+    }
+    data
+}
+
+// Derived columns run_series_channels can append to each row, as bit flags,
+// in this order
+pub const CHANNEL_VELOCITY: u32 = 1; // [dP/dt, -dS/dt]: net P formed and S consumed in the step, per unit time
+pub const CHANNEL_OCCUPANCY: u32 = 1 << 1; // [ES, EP] / (E + ES + EP), 0 without enzyme
+
+// Row width of a run_series_channels buffer: 6 plus two per channel
+pub fn channels_width(channels: u32) -> usize {
+    6 + 2 * (channels & (CHANNEL_VELOCITY | CHANNEL_OCCUPANCY)).count_ones() as usize
+}
+
+// run_series_adaptive with the derived columns selected by channels appended
+// to each [E, ES, EP, S, P, t] row (see channels_width)
+pub fn run_series_channels(mut st: State, k: &Rates, dt: f64, steps: u32, p_max: f64, channels: u32) -> Vec<f64> {
+    let dt = clamp_dt(dt);
+    let mut data: Vec<f64> = Vec::with_capacity(channels_width(channels) * steps as usize);
+    let mut clock = Clock::new(st[5]);
+    for _ in 0..steps {
+        let fl = step_adaptive(&mut st, k, dt, p_max);
+        st[5] = clock.tick(dt);
+        data.extend_from_slice(&st);
+        if channels & CHANNEL_VELOCITY != 0 {
+            // EP->E releases P, E->EP binds it; E->ES binds S, ES->E releases it
+            data.push((fl[5] - fl[1]) as f64 / dt);
+            data.push((fl[0] - fl[2]) as f64 / dt);
+        }
+        if channels & CHANNEL_OCCUPANCY != 0 {
+            let total = st[0] + st[1] + st[2];
+            let frac = |v: f64| if total > 0.0 { v / total } else { 0.0 };
+            data.push(frac(st[1]));
+            data.push(frac(st[2]));
+        }
+    }
+    data
+}
+
+// A long run simulated in bounded-memory chunks
+pub struct SeriesStream {
+    st: State,
+    k: Rates,
+    dt: f64,
+    remaining: u32,
+    clock: Clock,
+}
+
+impl SeriesStream {
+    pub fn new(st: State, k: Rates, dt: f64, steps: u32) -> SeriesStream {
+        SeriesStream { st, k, dt: clamp_dt(dt), remaining: steps, clock: Clock::new(st[5]) }
+    }
+
+    // Up to n further rows of [E, ES, EP, S, P, t]; empty once the run is done
+    pub fn next_chunk(&mut self, n: u32) -> Vec<f64> {
+        let mut data = vec![0.0; 6 * n.min(self.remaining) as usize];
+        self.fill(&mut data);
+        data
+    }
+
+    // Further rows into out, up to out.len() / 6; returns the number written
+    pub fn fill(&mut self, out: &mut [f64]) -> usize {
+        let rows = (self.remaining as usize).min(out.len() / 6);
+        for row in out.chunks_exact_mut(6).take(rows) {
+            step(&mut self.st, &self.k, self.dt);
+            self.st[5] = self.clock.tick(self.dt);
+            row.copy_from_slice(&self.st);
+        }
+        self.remaining -= rows as u32;
+        rows
+    }
+
+    // Steps not yet simulated
+    pub fn remaining(&self) -> u32 { self.remaining }
+
+    // Current [E, ES, EP, S, P, t]
+    pub fn state(&self) -> State { self.st }
+}
+
+// Run either in molecule counts (concentration_mode = false, inputs passed
+// through) or in concentrations: species in µM, k1 and k-3 in µM^-1 s^-1,
+// converted to counts for the stochastic engine and back on output.
+// Empty on an invalid volume in concentration mode.
+pub fn run_mode(
+    st: State, k: Rates, dt: f64, steps: u32, concentration_mode: bool, volume_l: f64, series: bool,
+) -> Vec<f64> {
+    let vol = if concentration_mode { volume::Volume::litres(volume_l) } else { None };
+    if concentration_mode && vol.is_none() { return Vec::new(); }
+    let (st, k) = match vol {
+        Some(v) => (v.state_to_counts(&st), v.rates_to_stochastic(&k)),
+        None => (st, k),
+    };
+    let data = if series { run_series(st, &k, dt, steps) } else { run_final(st, &k, dt, steps).to_vec() };
+    match vol {
+        Some(v) => data.chunks(6).flat_map(|row| v.state_to_conc(row)).collect(),
+        None => data,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    fn assert_finite_nonneg(v: f64) {
+        assert!(v.is_finite(), "value is not finite: {}", v);
+        assert!(v >= 0.0, "value is negative: {}", v);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn suggested_dt_keeps_p_tot_below_target() {
+        let k = [2e-3, 5e-4, 0.5, 3.0, 0.2, 8.0];
+        let st = [100.0, 0.0, 0.0, 5000.0, 0.0, 0.0];
+        let dt = suggest_dt(&st, &k, 0.05);
+        // Fastest channel at start: E binding S
+        assert!((channel_p_tot(&st, &k, dt)[0] - 0.05).abs() < 1e-12);
+        let converted = [100.0, 0.0, 0.0, 0.0, 5000.0, 0.0];
+        assert!(channel_p_tot(&converted, &k, dt).iter().all(|&p| p <= 0.05 + 1e-12));
+        warn::take();
+        crate::rng::seed_rng(1);
+        run_final(st, &k, dt, 2000);
+        assert!(!warn::take().contains(Warnings::P_TOT_HIGH));
+        assert_eq!(suggest_dt(&st, &[0.0; 6], 0.05), f64::INFINITY);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn dt_schedule_lands_on_each_piece() {
+        crate::rng::seed_rng(5);
+        let schedule = dt_schedule(&[0.1, 0.001, 10.0, 0.25]).unwrap();
+        assert_eq!(schedule_steps(0.0, &schedule), 100 + 40);
+        let k = [1e-3, 0.0, 0.1, 1.0, 0.0, 1.0];
+        let data = run_series_scheduled([10.0, 0.0, 0.0, 1000.0, 0.0, 0.0], &k, &schedule, f64::NAN);
+        let rows: Vec<&[f64]> = data.chunks(6).collect();
+        assert_eq!(rows.len(), 140);
+        assert_eq!(rows[99][5], 0.1);
+        assert!((rows[100][5] - 0.35).abs() < 1e-12);
+        assert_eq!(rows[139][5], 10.0);
+        for r in &rows { assert_eq!(r[0] + r[1] + r[2], 10.0); }
+        assert!(dt_schedule(&[1.0, 0.1, 0.5, 0.1]).is_err());
+        assert!(dt_schedule(&[1.0, 0.0]).is_err());
+        assert!(dt_schedule(&[1.0]).is_err());
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn observables_parse_and_sum() {
+        let row = [1.0, 2.0, 4.0, 8.0, 16.0, 0.5];
+        assert_eq!(Observable::parse("p").unwrap(), Observable::P);
+        assert_eq!(Observable::parse(" S + P ").unwrap().value(&row), 24.0);
+        assert_eq!(Observable::parse("ES+EP").unwrap().to_string(), "ES+EP");
+        assert_eq!(Observable::from_code(0).unwrap(), Observable::S);
+        assert_eq!(Observable::from_code(4).unwrap().value(&row), 4.0);
+        assert!(Observable::from_code(5).is_err());
+        assert!(Observable::parse("S+S").is_err());
+        assert!(Observable::parse("X").is_err());
+        assert!(Observable::parse("").is_err());
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn adaptive_steps_follow_fast_kinetics() {
+        crate::rng::seed_rng(11);
+        // p_tot of ES and EP is ~0.99 at dt = 0.1
+        let st0 = [100.0, 0.0, 0.0, 10_000.0, 0.0, 0.0];
+        let k = [1e-3, 0.0, 0.0, 50.0, 0.0, 50.0];
+        let exact = crate::ode::integrate(&[100.0, 0.0, 0.0, 10_000.0, 0.0], 0.0, &k, 1e-4, &[1.0])[0][4];
+        warn::take();
+        let fixed = run_final(st0, &k, 0.1, 10);
+        assert!(warn::take().contains(Warnings::P_TOT_HIGH));
+        let adaptive = run_series_adaptive(st0, &k, 0.1, 10, 0.05);
+        assert!(!warn::take().contains(Warnings::P_TOT_HIGH));
+        clamp_dt(f64::NAN);
+        assert_eq!(warn::take().names(), ["dt_clamped"]);
+        let last = &adaptive[adaptive.len() - 6..];
+        assert!((last[5] - 1.0).abs() < 1e-12);
+        assert!((last[0] + last[1] + last[2] - 100.0).abs() < 1e-9);
+        assert!((last[4] - exact).abs() < 0.05 * exact, "{} vs {}", last[4], exact);
+        assert!((last[4] - exact).abs() < (fixed[4] - exact).abs(), "{} {} {}", last[4], fixed[4], exact);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn requested_times_match_the_step_grid() {
+        let st0 = [100.0, 0.0, 0.0, 10_000.0, 0.0, 0.0];
+        let k = [1e-3, 0.0, 0.1, 1.0, 0.0, 1.0];
+        crate::rng::seed_rng(11);
+        let grid = run_series(st0, &k, 0.1, 5);
+        crate::rng::seed_rng(11);
+        let at = run_at_times(st0, &k, 0.1, &[0.3, f64::NAN, 0.1, 0.5, -1.0], f64::NAN);
+        assert_eq!(at[..5], grid[2 * 6..2 * 6 + 5]);
+        assert_eq!(at[5], 0.3);
+        assert_eq!(at[6..12], st0);
+        assert_eq!(at[12..17], grid[..5]);
+        assert_eq!(at[18..23], grid[4 * 6..4 * 6 + 5]);
+        assert_eq!(at[24..30], st0);
+        // Off-grid times land exactly
+        assert_eq!(run_at_times(st0, &k, 0.1, &[0.25], f64::NAN)[5], 0.25);
+        let log = log_spaced_times(2.0, 0.01, 100.0, 5);
+        assert_eq!((log[0], log[4]), (2.01, 102.0));
+        assert!((log[2] - 3.0).abs() < 1e-12);
+        // Equal ratios of the offsets from t0: x10 per point here
+        assert!(log.windows(2).all(|w| ((w[1] - 2.0) / (w[0] - 2.0) - 10.0).abs() < 1e-9), "{:?}", log);
+        assert_eq!(log_spaced_times(1.0, 0.5, 4.0, 1), [5.0]);
+        assert!(log_spaced_times(0.0, 0.0, 1.0, 5).is_empty());
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn derived_channels_follow_the_state() {
+        let st0 = [100.0, 0.0, 0.0, 10_000.0, 0.0, 0.0];
+        let k = [1e-3, 0.0, 0.1, 1.0, 0.0, 1.0];
+        let all = CHANNEL_VELOCITY | CHANNEL_OCCUPANCY;
+        assert_eq!(channels_width(all), 10);
+        crate::rng::seed_rng(5);
+        let plain = run_series(st0, &k, 0.1, 20);
+        crate::rng::seed_rng(5);
+        let data = run_series_channels(st0, &k, 0.1, 20, f64::NAN, all);
+        let mut prev = st0;
+        for (r, row) in data.chunks(10).enumerate() {
+            assert_eq!(row[..6], plain[6 * r..6 * r + 6]);
+            assert!((row[6] * 0.1 - (row[4] - prev[4])).abs() < 1e-9);
+            assert!((row[7] * 0.1 + (row[3] - prev[3])).abs() < 1e-9);
+            assert!((row[8] - row[1] / 100.0).abs() < 1e-12 && (row[9] - row[2] / 100.0).abs() < 1e-12);
+            prev.copy_from_slice(&row[..6]);
+        }
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn flux_rows_account_for_each_step() {
+        let st0 = [50.0, 0.0, 0.0, 2000.0, 100.0, 0.0];
+        let k = [2e-3, 1e-3, 0.5, 2.0, 0.3, 1.5];
+        crate::rng::seed_rng(17);
+        let plain = run_series(st0, &k, 0.05, 200);
+        crate::rng::seed_rng(17);
+        let data = run_series_fluxes(st0, &k, 0.05, 200);
+        let mut prev = st0;
+        for (r, row) in data.chunks(12).enumerate() {
+            assert_eq!(row[..6], plain[6 * r..6 * r + 6]);
+            let fl = &row[6..];
+            assert!(fl.iter().all(|&v| v >= 0.0 && v.fract() == 0.0));
+            // Each species moves by its channels' events
+            let change = [
+                fl[2] + fl[5] - fl[0] - fl[1],
+                fl[0] + fl[4] - fl[2] - fl[3],
+                fl[1] + fl[3] - fl[4] - fl[5],
+                fl[2] - fl[0],
+                fl[5] - fl[1],
+            ];
+            for i in 0..5 { assert_eq!(row[i] - prev[i], change[i], "row {} species {}", r, i); }
+            prev.copy_from_slice(&row[..6]);
+        }
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn final_counts_total_the_flux_rows() {
+        let st0 = [50.0, 0.0, 0.0, 2000.0, 100.0, 0.0];
+        let k = [2e-3, 1e-3, 0.5, 2.0, 0.3, 1.5];
+        crate::rng::seed_rng(19);
+        let data = run_series_fluxes(st0, &k, 0.05, 300);
+        crate::rng::seed_rng(19);
+        let (st, total) = run_final_counts(st0, &k, 0.05, 300);
+        assert_eq!(st[..], data[data.len() - 12..data.len() - 6]);
+        for c in 0..6 {
+            assert_eq!(total[c] as f64, data.chunks(12).map(|row| row[6 + c]).sum::<f64>(), "channel {}", c);
+        }
+        // Net turnover: P released minus P rebound is the P formed
+        assert_eq!((total[5] - total[1]) as f64, st[4] - st0[4]);
+        assert!(total[5] > 0);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn concentration_mode_runs_in_molecules() {
+        // 0.05 µM enzyme and 5 µM substrate in 1 fL, k1 in µM^-1 s^-1
+        let st0 = [0.05, 0.0, 0.0, 5.0, 0.0, 0.0];
+        let k = [2.0, 0.0, 0.5, 3.0, 0.0, 4.0];
+        let v = volume::Volume::litres(1e-15).unwrap();
+        crate::rng::seed_rng(23);
+        let counts = run_series(v.state_to_counts(&st0), &v.rates_to_stochastic(&k), 0.01, 100);
+        crate::rng::seed_rng(23);
+        let conc = run_mode(st0, k, 0.01, 100, true, 1e-15, true);
+        assert_eq!(conc.len(), counts.len());
+        for (c, n) in conc.chunks(6).zip(counts.chunks(6)) {
+            assert_eq!(c, v.state_to_conc(n));
+            assert!((c[0] + c[1] + c[2] - v.to_conc(30.0)).abs() < 1e-12);
+        }
+        // Final mode is the last row; count mode passes the inputs through
+        crate::rng::seed_rng(23);
+        assert_eq!(run_mode(st0, k, 0.01, 100, true, 1e-15, false), conc[conc.len() - 6..]);
+        let st = [30.0, 0.0, 0.0, 3011.0, 0.0, 0.0];
+        crate::rng::seed_rng(23);
+        let plain = run_final(st, &k, 0.01, 10);
+        crate::rng::seed_rng(23);
+        assert_eq!(run_mode(st, k, 0.01, 10, false, f64::NAN, false), plain);
+        assert!(run_mode(st0, k, 0.01, 10, true, -1.0, true).is_empty());
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn streamed_chunks_join_into_the_series() {
+        let st0 = [100.0, 0.0, 0.0, 10_000.0, 0.0, 0.0];
+        let k = [1e-3, 0.0, 0.1, 1.0, 0.0, 1.0];
+        crate::rng::seed_rng(29);
+        let whole = run_series(st0, &k, 0.1, 250);
+        crate::rng::seed_rng(29);
+        let mut stream = SeriesStream::new(st0, k, 0.1, 250);
+        let mut joined = Vec::new();
+        for n in [1, 64, 0, 100, 1000] {
+            let chunk = stream.next_chunk(n);
+            assert_eq!(chunk.len(), 6 * n.min(250 - joined.len() as u32 / 6) as usize);
+            joined.extend(chunk);
+            assert_eq!(stream.remaining(), 250 - joined.len() as u32 / 6);
+        }
+        assert_eq!(joined, whole);
+        assert_eq!(stream.state()[..], whole[whole.len() - 6..]);
+        assert!(stream.next_chunk(10).is_empty());
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn large_ep_simulate_steps_final() {
+        // Initial conditions with very large EP
+        let e0 = 10.0;
+        let es0 = 0.0;
+        let ep0 = 1_000_000.0;
+        let s0 = 1_000_000.0;
+        let p0 = 1_000_000.0;
+        let t0 = 0.0;
+        // Moderate rates and dt
+        let k1 = 1e-3;
+        let k_minus3 = 1e-3;
+        let k_minus1 = 1e-3;
+        let k2 = 1e-3;
+        let k_minus2 = 1e-3;
+        let k3 = 1e-3;
+        let dt = 0.01;
+        let steps = 10u32;
+
+        let total_e0 = e0 + es0 + ep0;
+        let v = run_final([e0, es0, ep0, s0, p0, t0], &[k1, k_minus3, k_minus1, k2, k_minus2, k3], dt, steps);
+        assert_eq!(v.len(), 6);
+        let (e, es, ep, s, p, t) = (v[0], v[1], v[2], v[3], v[4], v[5]);
+        assert_finite_nonneg(e);
+        assert_finite_nonneg(es);
+        assert_finite_nonneg(ep);
+        assert_finite_nonneg(s);
+        assert_finite_nonneg(p);
+        assert!(t.is_finite() && t > 0.0);
+        // Mass conservation of E
+        let total_e = e + es + ep;
+        assert!((total_e - total_e0).abs() < 1e-6, "E mass not conserved: {} vs {}", total_e, total_e0);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn large_ep_simulate_steps_series() {
+        let e0 = 10.0;
+        let es0 = 0.0;
+        let ep0 = 1_000_000.0;
+        let s0 = 1_000_000.0;
+        let p0 = 1_000_000.0;
+        let t0 = 0.0;
+        let k1 = 5e-4;
+        let k_minus3 = 5e-4;
+        let k_minus1 = 5e-4;
+        let k2 = 5e-4;
+        let k_minus2 = 5e-4;
+        let k3 = 5e-4;
+        let dt = 0.02;
+        let steps = 20u32;
+
+        let total_e0 = e0 + es0 + ep0;
+        let data = run_series([e0, es0, ep0, s0, p0, t0], &[k1, k_minus3, k_minus1, k2, k_minus2, k3], dt, steps);
+        assert_eq!(data.len(), (6 * steps as usize));
+        for i in 0..steps as usize {
+            let base = 6 * i;
+            let e = data[base];
+            let es = data[base + 1];
+            let ep = data[base + 2];
+            let s = data[base + 3];
+            let p = data[base + 4];
+            let t = data[base + 5];
+            assert_finite_nonneg(e);
+            assert_finite_nonneg(es);
+            assert_finite_nonneg(ep);
+            assert_finite_nonneg(s);
+            assert_finite_nonneg(p);
+            let total_e = e + es + ep;
+            assert!((total_e - total_e0).abs() < 1e-6, "E mass not conserved at step {}: {} vs {}", i+1, total_e, total_e0);
+            let expected_t = dt * ((i as f64) + 1.0);
+            assert!((t - expected_t).abs() < 1e-9, "time mismatch at step {}: got {}, expected {}", i+1, t, expected_t);
+        }
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn integer_mode_conserves_exactly() {
+        let st0 = [40.0, 0.0, 0.0, 3000.0, 0.0, 0.0];
+        let k = [2e-3, 1e-3, 0.5, 2.0, 0.3, 1.5];
+        crate::rng::seed_rng(21);
+        let int = run_series_integer(st0, &k, 0.02, 5000, Overflow::default());
+        crate::rng::seed_rng(21);
+        let float = run_series(st0, &k, 0.02, 5000);
+        // Same draws: integral f64 states take the same events
+        assert_eq!(int[int.len() - 6..int.len() - 1], float[float.len() - 6..float.len() - 1]);
+        for row in int.chunks(6) {
+            assert_eq!(row[0] + row[1] + row[2], 40.0);
+            assert_eq!(row[1] + row[2] + row[3] + row[4], 3000.0);
+        }
+        assert_eq!(counts_from_state(&[2.5, -1.0, f64::NAN, 7.4, 0.6, 0.0]), [3, 0, 0, 7, 1]);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn rounding_policies() {
+        assert_eq!((Rounding::Legacy.count(2.5, false), Rounding::Legacy.count(2.5, true)), (3, 2));
+        assert_eq!((Rounding::Nearest.count(2.5, true), Rounding::Floor.count(2.7, false)), (3, 2));
+        crate::rng::seed_rng(2);
+        let mean = (0..20_000).map(|_| Rounding::Stochastic.count(0.3, true) as f64).sum::<f64>() / 20_000.0;
+        assert!((mean - 0.3).abs() < 0.02, "{}", mean);
+        // The default policy is the original step
+        let st0 = [20.4, 0.0, 0.0, 500.5, 0.0, 0.0];
+        let k = [1e-3, 0.0, 0.1, 1.0, 0.0, 1.0];
+        crate::rng::seed_rng(3);
+        let (a, _) = run_series_with(st0, &k, 0.1, 200, &StepPolicy::default());
+        crate::rng::seed_rng(3);
+        assert_eq!(a, run_series(st0, &k, 0.1, 200));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn overflow_policies_place_capped_binding() {
+        // Far more enzyme than S, with P present: most S-binding draws overflow
+        let st0 = [1000.0, 0.0, 0.0, 5.0, 500.0, 0.0];
+        let k = [1.0, 0.001, 0.0, 0.0, 0.0, 0.0];
+        let first = |overflow| {
+            crate::rng::seed_rng(8);
+            let mut st = st0;
+            let (fl, moved) = step_with(&mut st, &k, 0.5, &StepPolicy { overflow, ..StepPolicy::default() });
+            (fl[1], moved)
+        };
+        let (reassign_ep, reassigned) = first(Overflow::Reassign);
+        let (drop_ep, dropped) = first(Overflow::Drop);
+        let (redraw_ep, redrawn) = first(Overflow::Redraw);
+        assert_eq!(dropped, 0);
+        assert!(reassigned > 0 && reassign_ep == drop_ep + reassigned);
+        // The redraw hands P binding only its (small) rate share of the overflow
+        assert!(redrawn < reassigned / 2 && redraw_ep == drop_ep + redrawn, "{} {}", redrawn, reassigned);
+        // Integer bookkeeping places the overflow the same way
+        let counted = |overflow| {
+            crate::rng::seed_rng(8);
+            let (mut n, mut t) = (counts_from_state(&st0), 0.0);
+            step_counts(&mut n, &mut t, &k, 0.5, overflow)[1]
+        };
+        assert_eq!([counted(Overflow::Reassign), counted(Overflow::Drop), counted(Overflow::Redraw)], [reassign_ep, drop_ep, redraw_ep]);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn clock_stays_on_the_step_grid() {
+        let (mut clock, mut naive) = (Clock::new(0.0), 0.0f64);
+        for _ in 0..10_000_000 {
+            clock.tick(1e-4);
+            naive += 1e-4;
+        }
+        let t = clock.tick(0.0);
+        assert!((t - 1000.0).abs() < 1e-9, "{}", t);
+        assert!((naive - 1000.0).abs() > (t - 1000.0).abs());
+    }
+}
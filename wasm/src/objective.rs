@@ -0,0 +1,902 @@
+// Fit objective: SSE of the stochastic model against an observed trace,
+// plus optional log-normal parameter priors.
+
+use crate::interp::{self, Interp};
+use crate::model::{run_at_times, run_series, Observable, Rates, State};
+use crate::rng::ln_gamma;
+use crate::{compensated, ode, steady};
+
+// [k1, k-3, k-1, k2, k-2, k3, dt, t_shift, E0, ES0, EP0, S0, P0,
+//  signal_scale, signal_offset, drift, drift_decay]
+pub type Params = [f64; N_PARAMS];
+pub const N_PARAMS: usize = 17;
+// Names of the Params entries, for reports
+pub const PARAM_NAMES: [&str; N_PARAMS] = [
+    "k1", "k-3", "k-1", "k2", "k-2", "k3", "dt", "t_shift", "E0", "ES0", "EP0", "S0", "P0",
+    "signal_scale", "signal_offset", "drift", "drift_decay",
+];
+// Dead time between the start of the reaction and the observation clock: the
+// model is compared at t_obs + t_shift
+pub const T_SHIFT: usize = 7;
+// Initial [E, ES, EP, S, P], so loading concentrations can be fitted
+pub const INIT: usize = 8;
+// Measured signal = signal_scale * observable + signal_offset (e.g.
+// absorbance = epsilon * l * [P] + blank)
+pub const SIGNAL_SCALE: usize = 13;
+pub const SIGNAL_OFFSET: usize = 14;
+// Baseline drift added to the signal at observation time t:
+// drift * (1 - exp(-drift_decay t)) / drift_decay, i.e. linear drift * t when
+// drift_decay is 0 and an exponential approach to a plateau otherwise
+pub const DRIFT: usize = 15;
+pub const DRIFT_DECAY: usize = 16;
+
+// Lowest value of each parameter: the baseline and its drift may be negative,
+// everything else is a rate, time or amount
+pub fn lower_bound(i: usize) -> f64 {
+    if i == SIGNAL_OFFSET || i == DRIFT { f64::NEG_INFINITY } else { 0.0 }
+}
+
+// Full parameter vector from one in the original [k1..k3, dt] layout or any
+// longer prefix; missing nuisance parameters take their neutral values and
+// missing initial amounts come from init
+pub fn params_from_slice(v: &[f64], init: &State) -> Params {
+    let mut p = [0.0; N_PARAMS];
+    p[INIT..INIT + 5].copy_from_slice(&init[..5]);
+    p[SIGNAL_SCALE] = 1.0;
+    let n = v.len().min(N_PARAMS);
+    p[..n].copy_from_slice(&v[..n]);
+    p
+}
+
+// How the model maps onto the recorded trace: readings taken t_shift after the
+// model clock, of the signal scale * observable + offset plus baseline drift
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Readout {
+    pub t_shift: f64,
+    pub scale: f64,
+    pub offset: f64,
+    pub drift: f64,
+    pub drift_decay: f64,
+}
+
+impl Readout {
+    // No drift; non-finite entries take the neutral values (0, 1, 0)
+    pub fn new(t_shift: f64, scale: f64, offset: f64) -> Readout {
+        let or = |v: f64, d: f64| if v.is_finite() { v } else { d };
+        Readout { t_shift: or(t_shift, 0.0), scale: or(scale, 1.0), offset: or(offset, 0.0), drift: 0.0, drift_decay: 0.0 }
+    }
+
+    // Add baseline drift (non-finite => none, decay floored at 0)
+    pub fn with_drift(mut self, drift: f64, decay: f64) -> Readout {
+        self.drift = if drift.is_finite() { drift } else { 0.0 };
+        self.drift_decay = if decay.is_finite() { decay.max(0.0) } else { 0.0 };
+        self
+    }
+
+    pub fn from_params(p: &Params) -> Readout {
+        Readout::new(p[T_SHIFT], p[SIGNAL_SCALE], p[SIGNAL_OFFSET]).with_drift(p[DRIFT], p[DRIFT_DECAY])
+    }
+
+    // Drift at observation time t
+    pub fn baseline(&self, t: f64) -> f64 {
+        let x = self.drift_decay * t;
+        // (1 - e^-x) / x -> 1 as x -> 0; exp_m1 keeps it accurate there
+        let factor = if x.abs() > 1e-12 { -(-x).exp_m1() / x } else { 1.0 };
+        self.drift * t * factor
+    }
+}
+
+impl Default for Readout {
+    fn default() -> Readout { Readout::new(0.0, 1.0, 0.0) }
+}
+
+// Goodness of fit of predictions to observations. The likelihood losses are
+// negative log-likelihoods, so AIC/BIC and likelihood-ratio tests apply.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Loss {
+    Sse,
+    // Gaussian noise with sigma profiled out: n/2 (ln(2 pi SSE / n) + 1)
+    Gaussian,
+    // Counting noise for low-copy data: sum of mu - y ln mu + ln y!, with the
+    // predicted signal as mu (floored at 1e-300) and y >= 0 counts
+    Poisson,
+    // Robust SSE variants that grow linearly past |residual| = delta, so
+    // isolated spikes cannot dominate: r^2 inside, 2 delta |r| - delta^2 outside
+    Huber(f64),
+    // 2 delta^2 (sqrt(1 + (r / delta)^2) - 1), a smooth Huber
+    SoftL1(f64),
+}
+
+impl Loss {
+    // 0: sse, 1: gaussian, 2: poisson, 3: huber, 4: soft_l1 (robust losses
+    // with delta = 1 until with_scale)
+    pub fn from_code(code: u32) -> Result<Loss, String> {
+        match code {
+            0 => Ok(Loss::Sse),
+            1 => Ok(Loss::Gaussian),
+            2 => Ok(Loss::Poisson),
+            3 => Ok(Loss::Huber(1.0)),
+            4 => Ok(Loss::SoftL1(1.0)),
+            _ => Err(format!("unknown loss code {} (expected 0: sse, 1: gaussian, 2: poisson, 3: huber, 4: soft_l1)", code)),
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Loss, String> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "sse" => Ok(Loss::Sse),
+            "gaussian" => Ok(Loss::Gaussian),
+            "poisson" => Ok(Loss::Poisson),
+            "huber" => Ok(Loss::Huber(1.0)),
+            "soft_l1" => Ok(Loss::SoftL1(1.0)),
+            _ => Err(format!("unknown loss '{}' (expected sse, gaussian, poisson, huber or soft_l1)", name)),
+        }
+    }
+
+    // Residual scale delta of a robust loss, in signal units; ignored unless
+    // finite and positive, and by the other losses
+    pub fn with_scale(self, delta: f64) -> Loss {
+        if !(delta.is_finite() && delta > 0.0) { return self; }
+        match self {
+            Loss::Huber(_) => Loss::Huber(delta),
+            Loss::SoftL1(_) => Loss::SoftL1(delta),
+            other => other,
+        }
+    }
+
+    pub fn is_likelihood(self) -> bool { matches!(self, Loss::Gaussian | Loss::Poisson) }
+
+    // Name accepted by parse
+    pub fn name(self) -> &'static str {
+        match self {
+            Loss::Sse => "sse",
+            Loss::Gaussian => "gaussian",
+            Loss::Poisson => "poisson",
+            Loss::Huber(_) => "huber",
+            Loss::SoftL1(_) => "soft_l1",
+        }
+    }
+
+    // Residual scale of a robust loss, NaN for the others
+    pub fn scale(self) -> f64 {
+        match self { Loss::Huber(d) | Loss::SoftL1(d) => d, _ => f64::NAN }
+    }
+
+    pub fn value(self, obs: &[f64], pred: &[f64]) -> f64 {
+        match self {
+            Loss::Sse => sum_sq_diff(obs, pred),
+            Loss::Gaussian => gaussian_nll(sum_sq_diff(obs, pred), obs.len()),
+            Loss::Poisson => obs.iter().zip(pred).map(|(&y, &mu)| {
+                let (y, mu) = (y.max(0.0), mu.max(1e-300));
+                mu - y * mu.ln() + ln_gamma(y + 1.0)
+            }).sum(),
+            Loss::Huber(d) => obs.iter().zip(pred).map(|(y, p)| {
+                let r = (y - p).abs();
+                if r <= d { r * r } else { 2.0 * d * r - d * d }
+            }).sum(),
+            Loss::SoftL1(d) => obs.iter().zip(pred).map(|(y, p)| {
+                let z = (y - p) / d;
+                2.0 * d * d * ((1.0 + z * z).sqrt() - 1.0)
+            }).sum(),
+        }
+    }
+
+    // Derivative of value over each prediction
+    pub fn derivative(self, obs: &[f64], pred: &[f64]) -> Vec<f64> {
+        let r = obs.iter().zip(pred).map(|(y, p)| y - p);
+        match self {
+            Loss::Sse => r.map(|r| -2.0 * r).collect(),
+            Loss::Gaussian => {
+                let sse = sum_sq_diff(obs, pred).max(1e-300 * obs.len() as f64);
+                r.map(|r| -(obs.len() as f64) * r / sse).collect()
+            }
+            Loss::Poisson => obs.iter().zip(pred).map(|(&y, &mu)| if mu > 1e-300 { 1.0 - y.max(0.0) / mu } else { 0.0 }).collect(),
+            Loss::Huber(d) => r.map(|r| -2.0 * r.clamp(-d, d)).collect(),
+            Loss::SoftL1(d) => r.map(|r| -2.0 * r / (1.0 + (r / d).powi(2)).sqrt()).collect(),
+        }
+    }
+}
+
+// Compensated sum of squared differences over the common length
+fn sum_sq_diff(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).collect::<compensated::Sum>().value()
+}
+
+// Profile negative log-likelihood of n residuals with sum of squares sse
+// under Gaussian noise of unknown sigma (the MLE sigma^2 = sse / n)
+pub fn gaussian_nll(sse: f64, n: usize) -> f64 {
+    if n == 0 { return 0.0; }
+    let n = n as f64;
+    0.5 * n * ((2.0 * std::f64::consts::PI * (sse / n).max(1e-300)).ln() + 1.0)
+}
+
+// [AIC, AICc, BIC] for a negative log-likelihood with n_params fitted
+// parameters over n_obs points (AICc is infinite when n_obs <= n_params + 1)
+pub fn information_criteria(nll: f64, n_params: usize, n_obs: usize) -> [f64; 3] {
+    let (k, n) = (n_params as f64, n_obs as f64);
+    let aic = 2.0 * nll + 2.0 * k;
+    let aicc = if n > k + 1.0 { aic + 2.0 * k * (k + 1.0) / (n - k - 1.0) } else { f64::INFINITY };
+    [aic, aicc, 2.0 * nll + k * n.max(1.0).ln()]
+}
+
+// SSE of an observable's signal against observations, simulating over
+// ceil(max_t / dt) steps
+#[allow(clippy::too_many_arguments)]
+pub fn sse(
+    init: State, k: &Rates, dt: f64, readout: Readout,
+    times: &[f64], y_obs: &[f64], observable: Observable, interp: Interp,
+) -> f64 {
+    loss(init, k, dt, readout, times, y_obs, observable, interp, Loss::Sse)
+}
+
+// sse under any loss
+#[allow(clippy::too_many_arguments)]
+pub fn loss(
+    init: State, k: &Rates, dt: f64, readout: Readout,
+    times: &[f64], y_obs: &[f64], observable: Observable, interp: Interp, loss: Loss,
+) -> f64 {
+    let n_use = times.len().min(y_obs.len());
+    let Some(pred) = predict(init, k, dt, readout, &times[..n_use], observable, interp) else { return 0.0 };
+    let (obs, pred): (Vec<f64>, Vec<f64>) =
+        (0..n_use).filter(|&i| times[i].is_finite()).map(|i| (y_obs[i], pred[i])).unzip();
+    loss.value(&obs, &pred)
+}
+
+// Signal of one stochastic run at each observation time (NaN where the time is
+// not finite); None when no time lies after the start. Interp::Exact steps
+// onto the (shifted) times instead of interpolating a dt grid.
+pub fn predict(
+    init: State, k: &Rates, dt: f64, readout: Readout,
+    times: &[f64], observable: Observable, interp: Interp,
+) -> Option<Vec<f64>> {
+    if times.is_empty() { return None; }
+    let dt_clamped = if dt.is_finite() && dt > 0.0 { dt } else { 1.0 };
+    let shift = readout.t_shift;
+    let mut max_t = 0.0;
+    for &x in times { if x.is_finite() && x + shift > max_t { max_t = x + shift; } }
+    if max_t <= 0.0 { return None; }
+    if interp == Interp::Exact {
+        let shifted: Vec<f64> = times.iter().map(|&tt| tt + shift).collect();
+        let rows = run_at_times(init, k, dt_clamped, &shifted, f64::NAN);
+        return Some(times.iter().zip(rows.chunks_exact(6)).map(|(&tt, row)| {
+            if tt.is_finite() { readout.scale * observable.value(row) + readout.offset + readout.baseline(tt) } else { f64::NAN }
+        }).collect());
+    }
+    let steps = ((max_t / dt_clamped).ceil() as i64).max(1) as u32;
+
+    let data = run_series(init, k, dt_clamped, steps);
+    let m = data.len() / 6;
+    if m == 0 { return Some(vec![f64::NAN; times.len()]); }
+    let t_series: Vec<f64> = (0..m).map(|i| data[6*i + 5]).collect();
+    let vals: Vec<f64> = data.chunks_exact(6).map(|row| readout.scale * observable.value(row) + readout.offset).collect();
+    Some(times.iter().map(|&tt| {
+        if !tt.is_finite() { return f64::NAN; }
+        interp::eval(&t_series, &vals, tt + shift, interp) + readout.baseline(tt)
+    }).collect())
+}
+
+// Indices of the parameter vector selected by a fit mask (missing flags are 0)
+pub fn mask_indices(mask: &[u8]) -> Vec<usize> {
+    (0..N_PARAMS).filter(|&i| mask.get(i).copied().unwrap_or(0) != 0).collect()
+}
+
+// Rate-constant indices (< 6) selected by a mask
+pub fn rate_mask_indices(mask: &[u8]) -> Vec<usize> {
+    mask_indices(mask).into_iter().filter(|&i| i < 6).collect()
+}
+
+// Gaussian priors on log(k) per parameter; sd <= 0 or NaN leaves a parameter free
+#[derive(Clone)]
+pub struct Priors {
+    log_mean: Params,
+    sd: Params,
+}
+
+impl Priors {
+    // mean in linear units, sd in log space, both per Params entry
+    pub fn new(mean: &[f64], sd: &[f64]) -> Priors {
+        let mut pr = Priors { log_mean: [0.0; N_PARAMS], sd: [0.0; N_PARAMS] };
+        for i in 0..N_PARAMS {
+            let m = mean.get(i).copied().unwrap_or(f64::NAN);
+            pr.log_mean[i] = if m > 0.0 { m.ln() } else { f64::NAN };
+            pr.sd[i] = sd.get(i).copied().unwrap_or(0.0);
+        }
+        pr
+    }
+
+    // Sum of squared log-space z-scores, added to the SSE
+    pub fn penalty(&self, k: &Params) -> f64 {
+        let mut pen = 0.0;
+        for (i, &ki) in k.iter().enumerate() {
+            let sd = self.sd[i];
+            if !(sd.is_finite() && sd > 0.0 && self.log_mean[i].is_finite()) { continue; }
+            let z = (ki.max(1e-300).ln() - self.log_mean[i]) / sd;
+            pen += z * z;
+        }
+        pen
+    }
+
+    // Derivative of penalty over each Params entry
+    pub fn gradient(&self, k: &Params) -> Params {
+        let mut g = [0.0; N_PARAMS];
+        for i in 0..N_PARAMS {
+            let sd = self.sd[i];
+            if !(sd.is_finite() && sd > 0.0 && self.log_mean[i].is_finite()) { continue; }
+            let x = k[i].max(1e-300);
+            g[i] = 2.0 * (x.ln() - self.log_mean[i]) / (sd * sd * x);
+        }
+        g
+    }
+}
+
+// Equality constraint held during fitting: one parameter is solved from the
+// others at every evaluation, so the optimizer only moves the rest. Leave
+// the dependent parameter out of the fit mask; moves of it are overwritten.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Constraint {
+    // params[num] = ratio * params[den]
+    Ratio { num: usize, den: usize, ratio: f64 },
+    // Haldane relation k1 k2 k3 / (k-1 k-2 k-3) = keq, solved for the rate
+    // constant params[solve] (index < 6)
+    Haldane { keq: f64, solve: usize },
+    // params[param] = params[to], e.g. k-1 = k-2 for a symmetric mechanism;
+    // tie a group to one free member
+    Tie { param: usize, to: usize },
+}
+
+// Forward and reverse rate constants of the Haldane relation, in Params order
+const FORWARD: [usize; 3] = [0, 3, 5]; // k1, k2, k3
+const REVERSE: [usize; 3] = [2, 4, 1]; // k-1, k-2, k-3
+
+impl Constraint {
+    // Index of the parameter the constraint sets
+    pub fn dependent(&self) -> usize {
+        match *self {
+            Constraint::Ratio { num, .. } => num,
+            Constraint::Haldane { solve, .. } => solve,
+            Constraint::Tie { param, .. } => param,
+        }
+    }
+
+    pub fn check(&self) -> Result<(), String> {
+        let ok = match *self {
+            Constraint::Ratio { num, den, ratio } => num < N_PARAMS && den < N_PARAMS && num != den && ratio.is_finite(),
+            Constraint::Haldane { keq, solve } => solve < 6 && keq.is_finite() && keq > 0.0,
+            Constraint::Tie { param, to } => param < N_PARAMS && to < N_PARAMS && param != to,
+        };
+        if ok { Ok(()) } else { Err(format!("invalid constraint {:?}", self)) }
+    }
+
+    // Set the dependent parameter of p; non-finite where the relation cannot
+    // be solved (a zero rate on the other side)
+    pub fn apply(&self, p: &mut Params) {
+        match *self {
+            Constraint::Ratio { num, den, ratio } => p[num] = ratio * p[den],
+            Constraint::Haldane { keq, solve } => {
+                let prod = |idx: &[usize]| idx.iter().filter(|&&i| i != solve).map(|&i| p[i]).product::<f64>();
+                let (fwd, rev) = (prod(&FORWARD), prod(&REVERSE));
+                p[solve] = if FORWARD.contains(&solve) { keq * rev / fwd } else { fwd / (keq * rev) };
+            }
+            Constraint::Tie { param, to } => p[param] = p[to],
+        }
+    }
+}
+
+// What Params slots 0..6 hold while fitting. The macroscopic spaces trade
+// k1, k-3, k-1 and k3 for the better identified Michaelis–Menten constants
+// (steady::mm_constants) in the slot of the rate each mostly replaces,
+// keeping the isomerization rates k2 and k-2; the microscopic rates follow
+// from steady::rates_from_mm at every evaluation. They need a reversible
+// isomerization (k-2 > 0).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RateSpace {
+    #[default]
+    Micro, // [k1, k-3, k-1, k2, k-2, k3]
+    Macro, // [Km_S, Km_P, kcat_r, k2, k-2, kcat_f]
+    MacroKeq, // [Km_S, Km_P, Keq, k2, k-2, kcat_f]; kcat_r = kcat_f Km_P / (Keq Km_S)
+}
+
+impl RateSpace {
+    pub fn from_code(code: u32) -> Result<RateSpace, String> {
+        match code {
+            0 => Ok(RateSpace::Micro),
+            1 => Ok(RateSpace::Macro),
+            2 => Ok(RateSpace::MacroKeq),
+            _ => Err(format!("unknown rate space code {} (expected 0: micro, 1: macro, 2: macro with Keq)", code)),
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<RateSpace, String> {
+        match name {
+            "micro" => Ok(RateSpace::Micro),
+            "macro" => Ok(RateSpace::Macro),
+            "macro_keq" => Ok(RateSpace::MacroKeq),
+            _ => Err(format!("unknown rate space '{}' (expected micro, macro or macro_keq)", name)),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RateSpace::Micro => "micro",
+            RateSpace::Macro => "macro",
+            RateSpace::MacroKeq => "macro_keq",
+        }
+    }
+
+    // Names of what slots 0..6 hold
+    pub fn slot_names(self) -> [&'static str; 6] {
+        match self {
+            RateSpace::Micro => ["k1", "k-3", "k-1", "k2", "k-2", "k3"],
+            RateSpace::Macro => ["Km_S", "Km_P", "kcat_r", "k2", "k-2", "kcat_f"],
+            RateSpace::MacroKeq => ["Km_S", "Km_P", "Keq", "k2", "k-2", "kcat_f"],
+        }
+    }
+
+    // Name of parameter slot i in this space
+    pub fn param_name(self, i: usize) -> &'static str {
+        if i < 6 { self.slot_names()[i] } else { PARAM_NAMES[i] }
+    }
+
+    // Errors for constraints that only make sense on microscopic rates
+    pub fn check(self, constraints: &[Constraint]) -> Result<(), String> {
+        if self != RateSpace::Micro && constraints.iter().any(|c| matches!(c, Constraint::Haldane { .. })) {
+            return Err(format!("Haldane constraints need rate space 'micro' (fix the Keq slot of 'macro_keq' instead), not '{}'", self.name()));
+        }
+        Ok(())
+    }
+
+    // Microscopic rates of slots c (NaN where rates_from_mm has no solution)
+    pub fn rates(self, c: &[f64]) -> Rates {
+        let (km_s, km_p, k2, k_minus2, kcat_f) = (c[0], c[1], c[3], c[4], c[5]);
+        let kcat_r = match self {
+            RateSpace::Micro => return [c[0], c[1], c[2], c[3], c[4], c[5]],
+            RateSpace::Macro => c[2],
+            RateSpace::MacroKeq => kcat_f * km_p / (c[2] * km_s),
+        };
+        steady::rates_from_mm(&[kcat_f, km_s, kcat_r, km_p], k2, k_minus2)
+    }
+
+    // Slots holding microscopic rates k; errors where k has no finite
+    // coordinates in this space
+    pub fn coordinates(self, k: &Rates) -> Result<[f64; 6], String> {
+        if self == RateSpace::Micro { return Ok(*k); }
+        if k[4].is_nan() || k[4] <= 0.0 { return Err(format!("rate space '{}' needs k-2 > 0", self.name())); }
+        let [kcat_f, km_s, kcat_r, km_p] = steady::mm_constants(k);
+        let third = if self == RateSpace::Macro { kcat_r } else { steady::keq(k) };
+        let c = [km_s, km_p, third, k[3], k[4], kcat_f];
+        if !c.iter().all(|v| v.is_finite()) || !self.rates(&c).iter().all(|v| v.is_finite()) {
+            return Err(format!("rates {:?} have no finite '{}' coordinates (needs k1, k-3 and k3 > 0)", k, self.name()));
+        }
+        Ok(c)
+    }
+}
+
+// Fit-mask indices without the parameters constraints set
+pub fn free_indices(idx: &[usize], constraints: &[Constraint]) -> Vec<usize> {
+    idx.iter().copied().filter(|&i| !constraints.iter().any(|c| c.dependent() == i)).collect()
+}
+
+// Observed trace and model setup shared by the fitters
+#[derive(Clone)]
+pub struct Problem {
+    pub init: State, // [E, ES, EP, S, P, t0]; Params carry the amounts actually simulated
+    pub times: Vec<f64>,
+    pub y_obs: Vec<f64>,
+    pub observable: Observable,
+    pub interp: Interp,
+    pub priors: Option<Priors>,
+    pub loss: Loss,
+    // Applied in order to every parameter vector the problem evaluates
+    pub constraints: Vec<Constraint>,
+    // What slots 0..6 of the parameter vectors hold
+    pub space: RateSpace,
+}
+
+impl Problem {
+    // p with the constraints applied
+    pub fn constrained(&self, p: &Params) -> Params {
+        let mut q = *p;
+        for c in &self.constraints { c.apply(&mut q); }
+        q
+    }
+
+    // Parameters actually simulated: p constrained, with slots 0..6 mapped
+    // from the problem's rate space to the microscopic rates
+    pub fn simulated(&self, p: &Params) -> Params {
+        let mut q = self.constrained(p);
+        let k = self.space.rates(&q[..6]);
+        q[..6].copy_from_slice(&k);
+        q
+    }
+
+    // Initial state for a parameter vector: its E0..P0 at the problem's t0
+    pub fn init_at(&self, p: &Params) -> State {
+        let mut st = self.init;
+        st[..5].copy_from_slice(&p[INIT..INIT + 5]);
+        st
+    }
+
+    // SSE at a full parameter vector (dt floored at 1e-12)
+    pub fn sse(&self, p: &Params) -> f64 {
+        let p = &self.simulated(p);
+        let mut k = [0.0f64; 6];
+        k.copy_from_slice(&p[..6]);
+        sse(self.init_at(p), &k, p[6].max(1e-12), Readout::from_params(p), &self.times, &self.y_obs, self.observable, self.interp)
+    }
+
+    // The problem's loss at a full parameter vector (dt floored at 1e-12);
+    // +inf where a constraint cannot be solved or the rate space has no
+    // microscopic rates
+    pub fn loss_value(&self, p: &Params) -> f64 {
+        let p = &self.simulated(p);
+        if self.constraints.iter().any(|c| !p[c.dependent()].is_finite()) || !p[..6].iter().all(|v| v.is_finite()) {
+            return f64::INFINITY;
+        }
+        let mut k = [0.0f64; 6];
+        k.copy_from_slice(&p[..6]);
+        loss(self.init_at(p), &k, p[6].max(1e-12), Readout::from_params(p), &self.times, &self.y_obs, self.observable, self.interp, self.loss)
+    }
+
+    // Observed minus predicted signal per observation, from one stochastic run
+    // (NaN where the time is not finite)
+    pub fn residuals(&self, p: &Params) -> Vec<f64> {
+        let p = &self.simulated(p);
+        let n = self.n_obs();
+        let mut k = [0.0f64; 6];
+        k.copy_from_slice(&p[..6]);
+        let pred = predict(self.init_at(p), &k, p[6].max(1e-12), Readout::from_params(p), &self.times[..n], self.observable, self.interp)
+            .unwrap_or_else(|| vec![f64::NAN; n]);
+        (0..n).map(|i| self.y_obs[i] - pred[i]).collect()
+    }
+
+    // Signal of the deterministic (RK4) model at each observation time, NaN
+    // where the time is not finite
+    pub fn ode_predictions(&self, p: &Params) -> Vec<f64> {
+        let p = &self.simulated(p);
+        let n = self.n_obs();
+        let ro = Readout::from_params(p);
+        let mut k = [0.0f64; 6];
+        k.copy_from_slice(&p[..6]);
+        let mut y0 = [0.0; 5];
+        y0.copy_from_slice(&p[INIT..INIT + 5]);
+        let shifted: Vec<f64> = self.times[..n].iter().map(|t| t + ro.t_shift).collect();
+        let states = ode::integrate(&y0, self.init[5], &k, p[6].max(1e-12), &shifted);
+        (0..n).map(|i| {
+            let t = self.times[i];
+            if t.is_finite() { ro.scale * self.observable.value(&states[i]) + ro.offset + ro.baseline(t) } else { f64::NAN }
+        }).collect()
+    }
+
+    // Derivatives of ode_predictions over params[cols] by central differences
+    // with an adaptive step per parameter: from 1% of its value (0.01 at 0),
+    // halved until successive estimates stop agreeing better (truncation
+    // error giving way to rounding), keeping the closest pair's. Forward
+    // differences where the step would cross the lower bound. Row-major
+    // n_obs x cols.len(); dt has no derivative and gives NaN.
+    pub fn prediction_jacobian(&self, p: &Params, cols: &[usize]) -> Vec<f64> {
+        let n = self.n_obs();
+        let m = cols.len();
+        let mut jac = vec![f64::NAN; n * m];
+        let base = self.ode_predictions(p);
+        for (c, &j) in cols.iter().enumerate() {
+            if j == 6 { continue; }
+            let diff = |h: f64| -> Vec<f64> {
+                let mut pp = *p;
+                pp[j] += h;
+                let yp = self.ode_predictions(&pp);
+                if p[j] - h < lower_bound(j) { return (0..n).map(|i| (yp[i] - base[i]) / h).collect(); }
+                let mut pm = *p;
+                pm[j] -= h;
+                let ym = self.ode_predictions(&pm);
+                (0..n).map(|i| (yp[i] - ym[i]) / (2.0 * h)).collect()
+            };
+            let mut h = 1e-2 * if p[j] != 0.0 { p[j].abs() } else { 1.0 };
+            let mut prev = diff(h);
+            let (mut best, mut best_err) = (prev.clone(), f64::INFINITY);
+            for _ in 0..8 {
+                h *= 0.5;
+                let cur = diff(h);
+                let err = cur.iter().zip(&prev).map(|(a, b)| (a - b).abs()).filter(|e| e.is_finite()).fold(0.0, f64::max);
+                if err < best_err {
+                    best_err = err;
+                    best = cur.clone();
+                } else if err > 2.0 * best_err {
+                    break;
+                }
+                prev = cur;
+            }
+            for i in 0..n { jac[i * m + c] = best[i]; }
+        }
+        jac
+    }
+
+    // ode_predictions and their derivatives over the rates params[0..6], from
+    // the forward sensitivities; None unless the rates are fitted directly
+    // (micro space, no constraints)
+    pub fn ode_rate_jacobian(&self, p: &Params) -> Option<(Vec<f64>, Vec<[f64; 6]>)> {
+        if self.space != RateSpace::Micro || !self.constraints.is_empty() { return None; }
+        let n = self.n_obs();
+        let ro = Readout::from_params(p);
+        let mut k = [0.0f64; 6];
+        k.copy_from_slice(&p[..6]);
+        let mut y0 = [0.0; 5];
+        y0.copy_from_slice(&p[INIT..INIT + 5]);
+        let shifted: Vec<f64> = self.times[..n].iter().map(|t| t + ro.t_shift).collect();
+        let states = ode::integrate_sensitivities(&y0, self.init[5], &k, p[6].max(1e-12), &shifted);
+        let mut pred = vec![f64::NAN; n];
+        let mut grad = vec![[f64::NAN; 6]; n];
+        for i in 0..n {
+            let t = self.times[i];
+            if !t.is_finite() { continue; }
+            let (y, sens) = &states[i];
+            pred[i] = ro.scale * self.observable.value(y) + ro.offset + ro.baseline(t);
+            for j in 0..6 { grad[i][j] = ro.scale * self.observable.value(&sens[j]); }
+        }
+        Some((pred, grad))
+    }
+
+    // SSE of the deterministic model over the finite observations and its
+    // gradient over the rates, for gradient-based fitters; None as for
+    // ode_rate_jacobian
+    pub fn ode_sse_gradient(&self, p: &Params) -> Option<(f64, [f64; 6])> {
+        let (pred, grad) = self.ode_rate_jacobian(p)?;
+        let mut sse = 0.0;
+        let mut g = [0.0; 6];
+        for i in 0..pred.len() {
+            let r = pred[i] - self.y_obs[i];
+            if !r.is_finite() { continue; }
+            sse += r * r;
+            for j in 0..6 { g[j] += 2.0 * r * grad[i][j]; }
+        }
+        Some((sse, g))
+    }
+
+    // Prior penalty, 0 without priors
+    pub fn penalty(&self, p: &Params) -> f64 {
+        self.priors.as_ref().map_or(0.0, |pr| pr.penalty(p))
+    }
+
+    // Prior term of the objective: the penalty next to an SSE-type loss, and
+    // the prior's negative log-density (penalty / 2) next to a likelihood
+    pub fn prior_term(&self, p: &Params) -> f64 {
+        let pen = self.penalty(p);
+        if self.loss.is_likelihood() { 0.5 * pen } else { pen }
+    }
+
+    // Derivative of prior_term over each Params entry
+    pub fn prior_gradient(&self, p: &Params) -> Params {
+        let mut g = self.priors.as_ref().map_or([0.0; N_PARAMS], |pr| pr.gradient(p));
+        if self.loss.is_likelihood() { for v in g.iter_mut() { *v *= 0.5; } }
+        g
+    }
+
+    // Loss plus prior term, the quantity the optimizers minimize
+    pub fn objective(&self, p: &Params) -> f64 { self.loss_value(p) + self.prior_term(p) }
+
+    // objective with the deterministic model in place of the stochastic run:
+    // the loss of ode_predictions over the finite observation times plus the
+    // prior term, +inf where the predictions are not finite
+    pub fn ode_objective(&self, p: &Params) -> f64 {
+        let pred = self.ode_predictions(p);
+        let (obs, pred): (Vec<f64>, Vec<f64>) =
+            (0..pred.len()).filter(|&i| self.times[i].is_finite()).map(|i| (self.y_obs[i], pred[i])).unzip();
+        let v = self.loss.value(&obs, &pred) + self.prior_term(p);
+        if v.is_nan() { f64::INFINITY } else { v }
+    }
+
+    // Number of (time, value) pairs
+    pub fn n_obs(&self) -> usize { self.times.len().min(self.y_obs.len()) }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::rng::seed_rng;
+    use wasm_bindgen_test::*;
+
+    // Fixture shared with the fitter tests: [P] with the SSE, no priors or
+    // constraints, microscopic rates
+    pub(crate) fn problem(init: State, times: Vec<f64>, y_obs: Vec<f64>) -> Problem {
+        Problem {
+            init, times, y_obs,
+            observable: Observable::P,
+            interp: Interp::Linear,
+            priors: None,
+            loss: Loss::Sse,
+            constraints: Vec::new(),
+            space: RateSpace::Micro,
+        }
+    }
+
+    // problem() observing the deterministic [P] curve at truth
+    pub(crate) fn synthetic_problem(init: State, truth: &Params, times: Vec<f64>) -> Problem {
+        let n = times.len();
+        let mut pr = problem(init, times, vec![0.0; n]);
+        pr.y_obs = pr.ode_predictions(truth);
+        pr
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn shift_aligns_delayed_observations() {
+        let init = [10.0, 0.0, 0.0, 1000.0, 0.0, 0.0];
+        let k = [1e-3, 0.0, 0.1, 1.0, 0.0, 1.0];
+        let (dt, shift) = (0.1, 0.5);
+        seed_rng(7);
+        let series = run_series(init, &k, dt, 30);
+        // Readings start at model time 0.5, on the observation clock's 0
+        let rows: Vec<&[f64]> = series.chunks(6).skip(5).step_by(5).collect();
+        let times: Vec<f64> = rows.iter().map(|r| r[5] - shift).collect();
+        let y: Vec<f64> = rows.iter().map(|r| r[4]).collect();
+        seed_rng(7);
+        assert!(sse(init, &k, dt, Readout::new(shift, 1.0, 0.0), &times, &y, Observable::P, Interp::Linear) < 1e-9);
+        seed_rng(7);
+        assert!(sse(init, &k, dt, Readout::default(), &times, &y, Observable::P, Interp::Linear) > 0.0);
+        // A signal of 2 [P] - 1 matches once scale and offset are applied
+        let signal: Vec<f64> = y.iter().map(|v| 2.0 * v - 1.0).collect();
+        seed_rng(7);
+        assert!(sse(init, &k, dt, Readout::new(shift, 2.0, -1.0), &times, &signal, Observable::P, Interp::Linear) < 1e-9);
+        // Plus drift on the observation clock
+        let drifting = Readout::new(shift, 2.0, -1.0).with_drift(0.3, 0.5);
+        let drifted: Vec<f64> = signal.iter().zip(&times).map(|(v, &t)| v + 0.3 * (1.0 - (-0.5 * t).exp()) / 0.5).collect();
+        seed_rng(7);
+        assert!(sse(init, &k, dt, drifting, &times, &drifted, Observable::P, Interp::Linear) < 1e-9);
+        assert!((Readout::default().with_drift(0.3, 0.0).baseline(2.0) - 0.6).abs() < 1e-15);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn gaussian_loss_is_profile_likelihood() {
+        let obs = [1.0, 2.0, 3.0, 4.0];
+        let pred = [1.5, 1.5, 3.5, 3.5];
+        // sigma^2 = 1/4 maximizes sum of -ln N(r; 0, sigma)
+        let direct: f64 = obs.iter().zip(&pred).map(|(o, p): (&f64, &f64)| {
+            0.5 * (2.0 * std::f64::consts::PI * 0.25).ln() + (o - p).powi(2) / (2.0 * 0.25)
+        }).sum();
+        assert!((Loss::Gaussian.value(&obs, &pred) - direct).abs() < 1e-12);
+        assert_eq!(Loss::Sse.value(&obs, &pred), 1.0);
+        let [aic, aicc, bic] = information_criteria(direct, 2, 4);
+        assert!((aic - (2.0 * direct + 4.0)).abs() < 1e-12);
+        assert!((aicc - (aic + 12.0)).abs() < 1e-12);
+        assert!((bic - (2.0 * direct + 2.0 * 4f64.ln())).abs() < 1e-12);
+        // Poisson: mu - y ln mu + ln y! per count
+        let counts: f64 = [(0.0, 0.5), (3.0, 2.0)].iter().map(|&(y, mu): &(f64, f64)| {
+            mu - y * mu.ln() + [1.0f64, 1.0, 2.0, 6.0][y as usize].ln()
+        }).sum();
+        assert!((Loss::Poisson.value(&[0.0, 3.0], &[0.5, 2.0]) - counts).abs() < 1e-12);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn robust_losses_damp_outliers() {
+        let obs = [0.1, -0.1, 10.0];
+        let pred = [0.0; 3];
+        let huber = Loss::Huber(1.0);
+        assert!((huber.value(&obs, &pred) - (0.02 + 19.0)).abs() < 1e-12);
+        let soft = Loss::SoftL1(1.0).value(&obs, &pred);
+        assert!(soft < Loss::Sse.value(&obs, &pred) / 4.0);
+        // Both match the SSE for small residuals
+        assert!((Loss::SoftL1(1.0).value(&obs[..2], &pred[..2]) - 0.02).abs() < 1e-4);
+        assert_eq!(Loss::from_code(3).unwrap().with_scale(2.5), Loss::Huber(2.5));
+        assert_eq!(Loss::Sse.with_scale(2.5), Loss::Sse);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn priors_penalise_log_space_z_scores() {
+        // k1 ~ LogNormal(ln 2, 0.5), k2 ~ LogNormal(ln 10, 1); the rest unset
+        let pr = Priors::new(&[2.0, f64::NAN, 0.0, 10.0], &[0.5, 1.0, 1.0, 1.0]);
+        let mut k = [1.0; N_PARAMS];
+        k[0] = 2.0 * 1f64.exp();
+        k[3] = 10.0 / 2f64.exp();
+        assert!((pr.penalty(&k) - (4.0 + 4.0)).abs() < 1e-12);
+        let g = pr.gradient(&k);
+        for i in 0..N_PARAMS {
+            let h = 1e-6 * k[i];
+            let (mut up, mut down) = (k, k);
+            up[i] += h;
+            down[i] -= h;
+            let fd = (pr.penalty(&up) - pr.penalty(&down)) / (2.0 * h);
+            assert!((g[i] - fd).abs() < 1e-6 * (1.0 + fd.abs()), "entry {}: {} vs {}", i, g[i], fd);
+        }
+        assert!((g[0] - 2.0 * 1.0 / (0.25 * k[0])).abs() < 1e-12);
+        assert_eq!(g[1], 0.0);
+        // At the prior medians the penalty and its gradient vanish
+        k[0] = 2.0;
+        k[3] = 10.0;
+        assert_eq!(pr.penalty(&k), 0.0);
+        assert!(pr.gradient(&k).iter().all(|&v| v == 0.0));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn initial_amounts_come_from_params() {
+        let problem = Problem { observable: Observable::S, ..problem([10.0, 0.0, 0.0, 1000.0, 0.0, 2.0], vec![1.0, 2.0], vec![5.0, 10.0]) };
+        let mut p = params_from_slice(&[1e-3, 0.0, 0.1, 1.0, 0.0, 1.0, 0.1], &problem.init);
+        assert_eq!(problem.init_at(&p), problem.init);
+        p[INIT + 3] = 800.0;
+        assert_eq!(problem.init_at(&p), [10.0, 0.0, 0.0, 800.0, 0.0, 2.0]);
+        seed_rng(3);
+        let a = problem.sse(&p);
+        seed_rng(3);
+        let b = sse([10.0, 0.0, 0.0, 800.0, 0.0, 2.0], &[1e-3, 0.0, 0.1, 1.0, 0.0, 1.0], 0.1, Readout::default(), &problem.times, &problem.y_obs, Observable::S, Interp::Linear);
+        assert_eq!(a, b);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn macroscopic_coordinates_map_back_to_the_rates() {
+        let k = [2.0, 0.3, 1.5, 4.0, 0.7, 3.0];
+        for space in [RateSpace::Macro, RateSpace::MacroKeq] {
+            let c = space.coordinates(&k).unwrap();
+            let back = space.rates(&c);
+            for i in 0..6 { assert!((back[i] - k[i]).abs() < 1e-12 * k[i], "{:?}: {:?} vs {:?}", space, back, k); }
+        }
+        assert!((RateSpace::MacroKeq.coordinates(&k).unwrap()[2] - steady::keq(&k)).abs() < 1e-12);
+        // kcat_f must stay below k2
+        assert!(RateSpace::Macro.rates(&[1.0, 1.0, 0.1, 4.0, 0.7, 5.0]).iter().all(|v| v.is_nan()));
+        assert!(RateSpace::Macro.coordinates(&[1.0, 0.0, 0.1, 1.0, 0.0, 1.0]).is_err());
+        // A problem in macro coordinates simulates the same model
+        let init = [10.0, 0.0, 0.0, 100.0, 0.0, 0.0];
+        let mut problem = problem(init, vec![1.0, 2.0, 5.0], vec![0.0; 3]);
+        let mut p = params_from_slice(&[k[0], k[1], k[2], k[3], k[4], k[5], 0.01], &init);
+        let micro = problem.ode_predictions(&p);
+        problem.space = RateSpace::MacroKeq;
+        p[..6].copy_from_slice(&RateSpace::MacroKeq.coordinates(&k).unwrap());
+        let mapped = problem.ode_predictions(&p);
+        for (a, b) in micro.iter().zip(&mapped) { assert!((a - b).abs() < 1e-9, "{} vs {}", a, b); }
+        p[5] = 10.0;
+        assert_eq!(problem.loss_value(&p), f64::INFINITY);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn tied_parameters_follow_their_free_member() {
+        let problem = Problem {
+            constraints: vec![Constraint::Tie { param: 2, to: 4 }, Constraint::Tie { param: 1, to: 4 }],
+            ..problem([10.0, 0.0, 0.0, 100.0, 0.0, 0.0], vec![1.0], vec![1.0])
+        };
+        assert_eq!(free_indices(&[0, 1, 2, 3, 4, 5], &problem.constraints), [0, 3, 4, 5]);
+        let p = problem.constrained(&params_from_slice(&[1.0, 0.0, 0.0, 2.0, 0.7, 3.0, 0.01], &problem.init));
+        assert_eq!(p[..6], [1.0, 0.7, 0.7, 2.0, 0.7, 3.0]);
+        assert!(Constraint::Tie { param: 4, to: 4 }.check().is_err());
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn forward_sensitivities_match_central_differences() {
+        let problem = Problem {
+            observable: Observable::parse("S+EP").unwrap(),
+            ..problem([10.0, 0.0, 0.0, 500.0, 20.0, 0.0], vec![0.5, 2.0, 5.0, 12.0, 30.0], vec![0.0; 5])
+        };
+        let mut p = params_from_slice(&[2e-3, 1e-3, 0.3, 1.5, 0.4, 0.8, 0.01], &problem.init);
+        p[SIGNAL_SCALE] = 2.0;
+        let (pred, grad) = problem.ode_rate_jacobian(&p).unwrap();
+        assert_eq!(pred, problem.ode_predictions(&p));
+        for j in 0..6 {
+            let h = 1e-5 * p[j];
+            let (mut pp, mut pm) = (p, p);
+            pp[j] += h;
+            pm[j] -= h;
+            let (yp, ym) = (problem.ode_predictions(&pp), problem.ode_predictions(&pm));
+            for i in 0..5 {
+                let fd = (yp[i] - ym[i]) / (2.0 * h);
+                assert!((grad[i][j] - fd).abs() <= 1e-5 * fd.abs().max(1e-3), "d y{} / d k{}: {} vs {}", i, j, grad[i][j], fd);
+            }
+        }
+        let (sse, g) = problem.ode_sse_gradient(&p).unwrap();
+        assert_eq!(sse, pred.iter().map(|y| y * y).sum::<f64>());
+        assert!((g[3] - 2.0 * (0..5).map(|i| pred[i] * grad[i][3]).sum::<f64>()).abs() < 1e-9 * g[3].abs());
+        let tied = Problem { constraints: vec![Constraint::Tie { param: 2, to: 4 }], ..problem };
+        assert!(tied.ode_rate_jacobian(&p).is_none());
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn adaptive_differences_match_exact_derivatives() {
+        let problem = problem([10.0, 0.0, 0.0, 500.0, 0.0, 0.0], vec![1.0, 4.0, 15.0], vec![0.0; 3]);
+        let mut p = params_from_slice(&[2e-3, 0.0, 0.3, 1.5, 0.4, 0.8, 0.05], &problem.init);
+        p[SIGNAL_SCALE] = 3.0;
+        let cols = [0, 3, 6, SIGNAL_SCALE, SIGNAL_OFFSET];
+        let jac = problem.prediction_jacobian(&p, &cols);
+        let (pred, grad) = problem.ode_rate_jacobian(&p).unwrap();
+        for i in 0..3 {
+            let row = &jac[i * cols.len()..(i + 1) * cols.len()];
+            assert!((row[0] / grad[i][0] - 1.0).abs() < 1e-6, "{} vs {}", row[0], grad[i][0]);
+            assert!((row[1] / grad[i][3] - 1.0).abs() < 1e-6, "{} vs {}", row[1], grad[i][3]);
+            assert!(row[2].is_nan());
+            assert!((row[3] - pred[i] / 3.0).abs() < 1e-9 * pred[i]);
+            assert!((row[4] - 1.0).abs() < 1e-9);
+        }
+    }
+}
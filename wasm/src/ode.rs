@@ -0,0 +1,250 @@
+// Deterministic mass-action model of the same network the stochastic stepper
+// simulates, integrated with fixed-step RK4.
+//
+//   E + S <-> ES (k1, k-1),  ES <-> EP (k2, k-2),  EP <-> E + P (k3, k-3)
+//
+// State order matches the series layout: [E, ES, EP, S, P].
+// Rate order matches the fit vector: [k1, k-3, k-1, k2, k-2, k3].
+
+use crate::dual::{Dual, Scalar};
+
+pub type State = [f64; 5];
+
+// Generic over the number type so that Dual inputs carry exact derivatives
+pub fn rhs<T: Scalar>(y: &[T; 5], k: &[T; 6]) -> [T; 5] {
+    let [e, es, ep, s, p] = *y;
+    let [k1, k_minus3, k_minus1, k2, k_minus2, k3] = *k;
+    let v_bind_s = k1 * e * s;
+    let v_unbind_s = k_minus1 * es;
+    let v_iso_f = k2 * es;
+    let v_iso_r = k_minus2 * ep;
+    let v_release_p = k3 * ep;
+    let v_bind_p = k_minus3 * e * p;
+    [
+        -v_bind_s + v_unbind_s + v_release_p - v_bind_p,
+        v_bind_s - v_unbind_s - v_iso_f + v_iso_r,
+        v_iso_f - v_iso_r - v_release_p + v_bind_p,
+        -v_bind_s + v_unbind_s,
+        v_release_p - v_bind_p,
+    ]
+}
+
+fn rk4_step<T: Scalar>(y: &[T; 5], k: &[T; 6], h: f64) -> [T; 5] {
+    let add = |a: &[T; 5], b: &[T; 5], w: f64| -> [T; 5] {
+        let mut out = *a;
+        for i in 0..5 { out[i] = out[i] + T::constant(w) * b[i]; }
+        out
+    };
+    let k1 = rhs(y, k);
+    let k2 = rhs(&add(y, &k1, 0.5 * h), k);
+    let k3 = rhs(&add(y, &k2, 0.5 * h), k);
+    let k4 = rhs(&add(y, &k3, h), k);
+    let (sixth, two) = (T::constant(h / 6.0), T::constant(2.0));
+    let mut out = *y;
+    for i in 0..5 {
+        out[i] = out[i] + sixth * (k1[i] + two * k2[i] + two * k3[i] + k4[i]);
+        if out[i].value() < 0.0 { out[i] = T::constant(0.0); }
+    }
+    out
+}
+
+// States at each requested absolute time, starting from y0 at t0. Steps of at
+// most dt, shortened to land exactly on each output time. Times before t0 (or
+// non-finite) report the initial state.
+pub fn integrate(y0: &State, t0: f64, k: &[f64; 6], dt: f64, times: &[f64]) -> Vec<State> {
+    integrate_with(y0, t0, k, dt, times)
+}
+
+// integrate in any Scalar
+pub fn integrate_with<T: Scalar>(y0: &[T; 5], t0: f64, k: &[T; 6], dt: f64, times: &[f64]) -> Vec<[T; 5]> {
+    let dt = if dt.is_finite() && dt > 0.0 { dt } else { 1.0 };
+    let mut order: Vec<usize> = (0..times.len()).collect();
+    order.sort_by(|&a, &b| times[a].partial_cmp(&times[b]).unwrap_or(std::cmp::Ordering::Equal));
+    let mut out = vec![*y0; times.len()];
+    let mut y = *y0;
+    let mut t = t0;
+    for &i in &order {
+        let target = times[i];
+        if !target.is_finite() { continue; }
+        while t < target {
+            let h = dt.min(target - t);
+            y = rk4_step(&y, k, h);
+            t += h;
+        }
+        out[i] = y;
+    }
+    out
+}
+
+// Forward sensitivities dy/dk_j of the state to each rate, in rate order
+pub type Sensitivities = [State; 6];
+
+// integrate on dual numbers seeded on each rate: the states with their exact
+// derivatives dy/dk (of the RK4 steps themselves), as integrate_sensitivities
+// gives by integrating the sensitivity equations
+pub fn integrate_dual(y0: &State, t0: f64, k: &[f64; 6], dt: f64, times: &[f64]) -> Vec<(State, Sensitivities)> {
+    let kd: [Dual<6>; 6] = std::array::from_fn(|j| Dual::variable(k[j], j));
+    let yd = y0.map(Dual::constant);
+    integrate_with(&yd, t0, &kd, dt, times).iter().map(|y| {
+        (y.map(|v| v.v), std::array::from_fn(|j| y.map(|v| v.d[j])))
+    }).collect()
+}
+
+// Reactions in rate order (E + S -> ES, E + P -> EP, ES -> E + S, ES -> EP,
+// EP -> ES, EP -> E + P): their change to [E, ES, EP, S, P] ...
+const STOICH: [State; 6] = [
+    [-1.0, 1.0, 0.0, -1.0, 0.0],
+    [-1.0, 0.0, 1.0, 0.0, -1.0],
+    [1.0, -1.0, 0.0, 1.0, 0.0],
+    [0.0, -1.0, 1.0, 0.0, 0.0],
+    [0.0, 1.0, -1.0, 0.0, 0.0],
+    [1.0, 0.0, -1.0, 0.0, 1.0],
+];
+
+// ... their rates per unit rate constant ...
+fn unit_fluxes(y: &State) -> [f64; 6] {
+    let [e, es, ep, s, p] = *y;
+    [e * s, e * p, es, es, ep, ep]
+}
+
+// ... and the gradients of their rates over the state
+fn flux_gradients(y: &State, k: &[f64; 6]) -> [State; 6] {
+    let [e, _, _, s, p] = *y;
+    let [k1, k_minus3, k_minus1, k2, k_minus2, k3] = *k;
+    [
+        [k1 * s, 0.0, 0.0, k1 * e, 0.0],
+        [k_minus3 * p, 0.0, 0.0, 0.0, k_minus3 * e],
+        [0.0, k_minus1, 0.0, 0.0, 0.0],
+        [0.0, k2, 0.0, 0.0, 0.0],
+        [0.0, 0.0, k_minus2, 0.0, 0.0],
+        [0.0, 0.0, k3, 0.0, 0.0],
+    ]
+}
+
+// d/dt of the sensitivities: J s_j + df/dk_j, J the Jacobian of rhs
+fn sensitivity_rhs(y: &State, sens: &Sensitivities, k: &[f64; 6]) -> Sensitivities {
+    let grads = flux_gradients(y, k);
+    let unit = unit_fluxes(y);
+    let mut out = [[0.0; 5]; 6];
+    for j in 0..6 {
+        for r in 0..6 {
+            let dv: f64 = (0..5).map(|i| grads[r][i] * sens[j][i]).sum::<f64>() + if r == j { unit[r] } else { 0.0 };
+            for i in 0..5 { out[j][i] += STOICH[r][i] * dv; }
+        }
+    }
+    out
+}
+
+// rk4_step carrying the sensitivities along; the state follows the same
+// arithmetic as rk4_step
+fn rk4_sensitivity_step(y: &State, sens: &Sensitivities, k: &[f64; 6], h: f64) -> (State, Sensitivities) {
+    let add = |a: &State, b: &State, w: f64| -> State {
+        let mut out = *a;
+        for i in 0..5 { out[i] += w * b[i]; }
+        out
+    };
+    let add_s = |a: &Sensitivities, b: &Sensitivities, w: f64| -> Sensitivities {
+        let mut out = *a;
+        for j in 0..6 { out[j] = add(&a[j], &b[j], w); }
+        out
+    };
+    let (k1, s1) = (rhs(y, k), sensitivity_rhs(y, sens, k));
+    let (y2, z2) = (add(y, &k1, 0.5 * h), add_s(sens, &s1, 0.5 * h));
+    let (k2, s2) = (rhs(&y2, k), sensitivity_rhs(&y2, &z2, k));
+    let (y3, z3) = (add(y, &k2, 0.5 * h), add_s(sens, &s2, 0.5 * h));
+    let (k3, s3) = (rhs(&y3, k), sensitivity_rhs(&y3, &z3, k));
+    let (y4, z4) = (add(y, &k3, h), add_s(sens, &s3, h));
+    let (k4, s4) = (rhs(&y4, k), sensitivity_rhs(&y4, &z4, k));
+    let mut out = *y;
+    for i in 0..5 {
+        out[i] += h / 6.0 * (k1[i] + 2.0 * k2[i] + 2.0 * k3[i] + k4[i]);
+        if out[i] < 0.0 { out[i] = 0.0; }
+    }
+    let mut sens_out = *sens;
+    for j in 0..6 {
+        for i in 0..5 { sens_out[j][i] += h / 6.0 * (s1[j][i] + 2.0 * s2[j][i] + 2.0 * s3[j][i] + s4[j][i]); }
+    }
+    (out, sens_out)
+}
+
+// integrate with the forward sensitivities dy/dk at each requested time
+// (zero at t0, the initial state not depending on the rates). The states
+// match integrate's.
+pub fn integrate_sensitivities(y0: &State, t0: f64, k: &[f64; 6], dt: f64, times: &[f64]) -> Vec<(State, Sensitivities)> {
+    let dt = if dt.is_finite() && dt > 0.0 { dt } else { 1.0 };
+    let mut order: Vec<usize> = (0..times.len()).collect();
+    order.sort_by(|&a, &b| times[a].partial_cmp(&times[b]).unwrap_or(std::cmp::Ordering::Equal));
+    let mut out = vec![(*y0, [[0.0; 5]; 6]); times.len()];
+    let mut y = *y0;
+    let mut sens = [[0.0; 5]; 6];
+    let mut t = t0;
+    for &i in &order {
+        let target = times[i];
+        if !target.is_finite() { continue; }
+        while t < target {
+            let h = dt.min(target - t);
+            (y, sens) = rk4_sensitivity_step(&y, &sens, k, h);
+            t += h;
+        }
+        out[i] = (y, sens);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn rk4_follows_first_order_release() {
+        // Only EP -> E + P: EP decays as e^(-k3 t)
+        let y0 = [0.0, 0.0, 10.0, 0.0, 0.0];
+        let k = [0.0, 0.0, 0.0, 0.0, 0.0, 0.5];
+        let times = [1.0, 4.0, 2.5];
+        let out = integrate(&y0, 0.0, &k, 0.05, &times);
+        for (y, &t) in out.iter().zip(&times) {
+            let ep = 10.0 * (-0.5 * t).exp();
+            assert!((y[2] - ep).abs() < 1e-7, "t={} {}", t, y[2]);
+            assert!((y[0] + y[2] - 10.0).abs() < 1e-12 && (y[4] + y[2] - 10.0).abs() < 1e-12);
+        }
+        // Times before t0 report the initial state
+        assert_eq!(integrate(&y0, 1.0, &k, 0.05, &[0.5, f64::NAN]), [y0, y0]);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn sensitivities_match_dual_numbers_and_differences() {
+        let y0 = [1.0, 0.0, 0.0, 50.0, 0.0];
+        let k = [0.2, 0.01, 1.0, 2.0, 0.5, 3.0];
+        let times = [0.5, 2.0, 6.0];
+        let forward = integrate_sensitivities(&y0, 0.0, &k, 0.01, &times);
+        let dual = integrate_dual(&y0, 0.0, &k, 0.01, &times);
+        let plain = integrate(&y0, 0.0, &k, 0.01, &times);
+        for i in 0..times.len() {
+            assert_eq!(forward[i].0, plain[i]);
+            // Mass balance: sensitivities of E + ES + EP and S + ES + EP + P are 0
+            for j in 0..6 {
+                let sf = forward[i].1[j];
+                assert!((sf[0] + sf[1] + sf[2]).abs() < 1e-9 && (sf[1] + sf[2] + sf[3] + sf[4]).abs() < 1e-9);
+                for (c, (&a, &b)) in sf.iter().zip(&dual[i].1[j]).enumerate() {
+                    assert!((a - b).abs() < 1e-6 * (1.0 + b.abs()), "t={} k{} y{}: {} vs {}", times[i], j, c, a, b);
+                }
+            }
+        }
+        // Central differences of the RK4 solution
+        for j in 0..6 {
+            let h = 1e-6 * k[j].max(1e-3);
+            let (mut up, mut down) = (k, k);
+            up[j] += h;
+            down[j] -= h;
+            let (yu, yd) = (integrate(&y0, 0.0, &up, 0.01, &times), integrate(&y0, 0.0, &down, 0.01, &times));
+            for i in 0..times.len() {
+                for c in 0..5 {
+                    let fd = (yu[i][c] - yd[i][c]) / (2.0 * h);
+                    let d = dual[i].1[j][c];
+                    assert!((fd - d).abs() < 1e-5 * (1.0 + d.abs()), "t={} k{} y{}: {} vs {}", times[i], j, c, fd, d);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,130 @@
+// Fan-out for embarrassingly parallel work (replicates, sweeps, multi-start).
+//
+// With the `threads` feature on native targets, work is split across scoped
+// OS threads; otherwise it runs sequentially in index order. The feature is
+// native-only: wasm32 builds always run sequentially, since browser threads
+// would need an atomics build of std, a worker pool on the JS side and a
+// cross-origin isolated page, none of which this crate ships.
+//
+// Every path seeds each item's stream off the caller's (one seed per item,
+// then one to move the caller on), so results depend on the seed but not on
+// the worker count or the build. Work runs sequentially while the caller is
+// recording or replaying draws, since tapes are per thread.
+
+use crate::rng::{next_seed, seed_rng};
+
+// Seeds for n items, and the one the caller's stream resumes from afterwards
+fn item_seeds(n: usize) -> (Vec<u64>, u64) {
+    let seeds = (0..n).map(|_| next_seed()).collect();
+    (seeds, next_seed())
+}
+
+fn map_seeded<T>(range: std::ops::Range<usize>, seeds: &[u64], f: &impl Fn(usize) -> T) -> Vec<T> {
+    range.map(|i| { seed_rng(seeds[i]); f(i) }).collect()
+}
+
+#[cfg(all(feature = "threads", not(target_arch = "wasm32")))]
+pub(crate) fn par_map<T: Send>(n: usize, f: impl Fn(usize) -> T + Sync) -> Vec<T> {
+    let workers = worker_count().min(n.max(1));
+    let (seeds, resume) = item_seeds(n);
+    let out = if workers <= 1 || crate::rng::tape_active() {
+        map_seeded(0..n, &seeds, &f)
+    } else {
+        let chunk = n.div_ceil(workers);
+        let (f, seeds) = (&f, &seeds);
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..workers)
+                .map(|w| scope.spawn(move || {
+                    let out = map_seeded(w * chunk..((w + 1) * chunk).min(n), seeds, f);
+                    (out, crate::warn::take())
+                }))
+                .collect();
+            handles.into_iter().flat_map(|h| {
+                let (out, raised) = h.join().expect("worker thread panicked");
+                crate::warn::raise(raised);
+                out
+            }).collect()
+        })
+    };
+    seed_rng(resume);
+    out
+}
+
+#[cfg(not(all(feature = "threads", not(target_arch = "wasm32"))))]
+pub(crate) fn par_map<T: Send>(n: usize, f: impl Fn(usize) -> T + Sync) -> Vec<T> {
+    let (seeds, resume) = item_seeds(n);
+    let out = map_seeded(0..n, &seeds, &f);
+    seed_rng(resume);
+    out
+}
+
+#[cfg(all(feature = "threads", not(target_arch = "wasm32")))]
+fn worker_count() -> usize {
+    #[cfg(test)]
+    if let Some(w) = WORKERS.with(std::cell::Cell::get) { return w; }
+    std::thread::available_parallelism().map_or(1, |w| w.get())
+}
+
+#[cfg(test)]
+thread_local! {
+    static WORKERS: std::cell::Cell<Option<usize>> = const { std::cell::Cell::new(None) };
+}
+
+// Run f with par_map capped at the given worker count on this thread (tests
+// only; without the `threads` feature every count runs sequentially)
+#[cfg(test)]
+pub(crate) fn with_workers<R>(workers: usize, f: impl FnOnce() -> R) -> R {
+    let prev = WORKERS.with(|w| w.replace(Some(workers)));
+    let out = f();
+    WORKERS.with(|w| w.set(prev));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::{finish_recording, rand_f64, start_recording, start_replay, stop_replay};
+    use wasm_bindgen_test::*;
+
+    fn draws() -> Vec<f64> { par_map(64, |i| (0..=i).map(|_| rand_f64()).sum()) }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn seeded_maps_repeat_and_replay() {
+        seed_rng(21);
+        let a = draws();
+        seed_rng(21);
+        assert_eq!(draws(), a);
+        start_recording();
+        let recorded = draws();
+        let tape = finish_recording();
+        assert_eq!(tape.len(), (1..=64).sum::<usize>());
+        seed_rng(22);
+        start_replay(tape);
+        assert_eq!(draws(), recorded);
+        assert_eq!(stop_replay(), (1..=64).sum::<usize>());
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn items_are_seeded_alike_on_every_path() {
+        seed_rng(5);
+        let (seeds, resume) = item_seeds(64);
+        let mut expect: Vec<f64> = seeds.iter().enumerate()
+            .map(|(i, &s)| { seed_rng(s); (0..=i).map(|_| rand_f64()).sum() })
+            .collect();
+        // The caller's stream moves on to the same place afterwards
+        seed_rng(resume);
+        expect.push(rand_f64());
+        for workers in [1, 3, 8] {
+            seed_rng(5);
+            let mut got = with_workers(workers, draws);
+            got.push(rand_f64());
+            assert_eq!(got, expect, "{workers} workers");
+        }
+        // A recorded map seeds its items the same way
+        seed_rng(5);
+        start_recording();
+        let recorded = draws();
+        finish_recording();
+        assert_eq!(recorded, expect[..64]);
+    }
+}
@@ -0,0 +1,239 @@
+// Multi-objective fitting of one parameter vector to several datasets. Rather
+// than the single optimum of a weighted sum, NSGA-II evolves a population
+// toward the Pareto front: the fits no other fit beats on every dataset's
+// objective at once, leaving the trade-off between datasets (S against P
+// residuals, say) to the user.
+
+use crate::fit::search_range;
+use crate::objective::{Params, Problem};
+use crate::parallel;
+use crate::rng::rand_f64;
+
+// Distribution indices of SBX crossover and polynomial mutation
+const ETA_CROSSOVER: f64 = 15.0;
+const ETA_MUTATION: f64 = 20.0;
+// Chance that a pair of parents is crossed over
+const CROSSOVER_RATE: f64 = 0.9;
+
+pub struct Pareto {
+    // Members per generation; 0 => 20 per fitted parameter (at least 20),
+    // rounded up to an even count
+    pub population: u32,
+    pub generations: u32,
+}
+
+// One fit on the front
+#[derive(Clone, Debug)]
+pub struct ParetoPoint {
+    pub params: Params,
+    pub objectives: Vec<f64>, // each problem's objective, in order
+}
+
+// a no worse than b everywhere and better somewhere
+fn dominates(a: &[f64], b: &[f64]) -> bool {
+    a.iter().zip(b).all(|(x, y)| x <= y) && a.iter().zip(b).any(|(x, y)| x < y)
+}
+
+// Fronts of the objective vectors, best first
+fn nondominated_sort(objs: &[Vec<f64>]) -> Vec<Vec<usize>> {
+    let n = objs.len();
+    let mut beaten_by = vec![0usize; n];
+    let mut beats: Vec<Vec<usize>> = vec![Vec::new(); n];
+    for i in 0..n {
+        for j in 0..n {
+            if dominates(&objs[i], &objs[j]) { beats[i].push(j); } else if dominates(&objs[j], &objs[i]) { beaten_by[i] += 1; }
+        }
+    }
+    let mut fronts = Vec::new();
+    let mut current: Vec<usize> = (0..n).filter(|&i| beaten_by[i] == 0).collect();
+    while !current.is_empty() {
+        let mut next = Vec::new();
+        for &i in &current {
+            for &j in &beats[i] {
+                beaten_by[j] -= 1;
+                if beaten_by[j] == 0 { next.push(j); }
+            }
+        }
+        fronts.push(current);
+        current = next;
+    }
+    fronts
+}
+
+// Crowding distance of each member of a front: the normalized side lengths
+// of the box its neighbours span, infinite at the ends
+fn crowding(objs: &[Vec<f64>], front: &[usize]) -> Vec<f64> {
+    let mut dist = vec![0.0; front.len()];
+    let m = objs.first().map_or(0, Vec::len);
+    for col in (0..m).map(|k| front.iter().map(|&i| objs[i][k]).collect::<Vec<f64>>()) {
+        let mut order: Vec<usize> = (0..front.len()).collect();
+        order.sort_by(|&a, &b| col[a].total_cmp(&col[b]));
+        let (lo, hi) = (col[order[0]], col[order[order.len() - 1]]);
+        dist[order[0]] = f64::INFINITY;
+        dist[order[order.len() - 1]] = f64::INFINITY;
+        if hi <= lo || !(hi - lo).is_finite() { continue; }
+        for w in 1..order.len().saturating_sub(1) {
+            dist[order[w]] += (col[order[w + 1]] - col[order[w - 1]]) / (hi - lo);
+        }
+    }
+    dist
+}
+
+// Simulated binary crossover of two genes in [0, 1]
+fn sbx(a: f64, b: f64) -> (f64, f64) {
+    let u = rand_f64();
+    let beta = if u <= 0.5 { (2.0 * u).powf(1.0 / (ETA_CROSSOVER + 1.0)) } else { (0.5 / (1.0 - u)).powf(1.0 / (ETA_CROSSOVER + 1.0)) };
+    let (c1, c2) = (0.5 * ((1.0 + beta) * a + (1.0 - beta) * b), 0.5 * ((1.0 - beta) * a + (1.0 + beta) * b));
+    (c1.clamp(0.0, 1.0), c2.clamp(0.0, 1.0))
+}
+
+// Polynomial mutation of a gene in [0, 1]
+fn mutate(x: f64) -> f64 {
+    let u = rand_f64();
+    let delta = if u < 0.5 { (2.0 * u).powf(1.0 / (ETA_MUTATION + 1.0)) - 1.0 } else { 1.0 - (2.0 * (1.0 - u)).powf(1.0 / (ETA_MUTATION + 1.0)) };
+    (x + delta).clamp(0.0, 1.0)
+}
+
+// Pareto front of params[optimize_idx] over the problems' objectives, by
+// NSGA-II within bounds (one (lower, upper) per optimize_idx entry, ranges
+// as in fit::fit_auto; log-scaled where lower > 0), the start one member of
+// the first generation. The problems share one parameter vector, and the
+// constraints and rate space of the first. progress is called as
+// (generation, current front) after each generation; returning true stops.
+// Returns the final front sorted by the first objective; each objective is
+// one stochastic run, so members near the front can trade places.
+pub fn pareto_fit(
+    problems: &[Problem],
+    params: Params,
+    optimize_idx: &[usize],
+    bounds: &[(f64, f64)],
+    opts: &Pareto,
+    mut progress: impl FnMut(u32, &[ParetoPoint]) -> bool,
+) -> Vec<ParetoPoint> {
+    let n = optimize_idx.len();
+    let Some(first) = problems.first() else { return Vec::new() };
+    let at = |u: &[f64]| -> Params {
+        let mut p = params;
+        for (j, &i) in optimize_idx.iter().enumerate() {
+            let (lo, hi) = search_range(&params, i, bounds.get(j));
+            p[i] = if lo > 0.0 { (lo.ln() + u[j] * (hi / lo).ln()).exp() } else { lo + u[j] * (hi - lo) };
+        }
+        p[6] = p[6].max(1e-12);
+        first.constrained(&p)
+    };
+    let evaluate = |pop: &[Vec<f64>]| -> Vec<Vec<f64>> {
+        parallel::par_map(pop.len(), |s| {
+            let p = at(&pop[s]);
+            problems.iter().map(|pr| { let f = pr.objective(&p); if f.is_nan() { f64::INFINITY } else { f } }).collect()
+        })
+    };
+    if n == 0 {
+        let objectives = evaluate(&[Vec::new()]).remove(0);
+        return vec![ParetoPoint { params: at(&[]), objectives }];
+    }
+    let size = if opts.population > 0 { opts.population as usize } else { (20 * n).max(20) };
+    let size = size + size % 2;
+
+    // Latin hypercube first generation, with the start in place of one member
+    let mut pop: Vec<Vec<f64>> = vec![vec![0.0; n]; size];
+    for j in 0..n {
+        let mut strata: Vec<usize> = (0..size).collect();
+        for s in (1..size).rev() { strata.swap(s, (rand_f64() * (s + 1) as f64) as usize); }
+        for (member, &stratum) in pop.iter_mut().zip(&strata) { member[j] = (stratum as f64 + rand_f64()) / size as f64; }
+    }
+    pop[0] = optimize_idx.iter().enumerate().map(|(j, &i)| {
+        let (lo, hi) = search_range(&params, i, bounds.get(j));
+        let x = params[i].clamp(lo, hi);
+        let u = if lo > 0.0 { (x / lo).ln() / (hi / lo).ln() } else { (x - lo) / (hi - lo) };
+        if u.is_finite() { u } else { 0.5 }
+    }).collect();
+    let mut objs = evaluate(&pop);
+
+    let front_of = |pop: &[Vec<f64>], objs: &[Vec<f64>]| -> Vec<ParetoPoint> {
+        let mut front: Vec<ParetoPoint> = nondominated_sort(objs).first().map_or(Vec::new(), |f| {
+            f.iter().map(|&i| ParetoPoint { params: at(&pop[i]), objectives: objs[i].clone() }).collect()
+        });
+        front.sort_by(|a, b| a.objectives[0].total_cmp(&b.objectives[0]));
+        front.dedup_by(|a, b| a.params == b.params);
+        front
+    };
+    for generation in 0..opts.generations {
+        // Rank and crowding of the current generation for the tournaments
+        let mut rank = vec![0usize; size];
+        let mut crowd = vec![0.0; size];
+        for (r, front) in nondominated_sort(&objs).iter().enumerate() {
+            for (&i, d) in front.iter().zip(crowding(&objs, front)) { rank[i] = r; crowd[i] = d; }
+        }
+        let tournament = || {
+            let (a, b) = ((rand_f64() * size as f64) as usize % size, (rand_f64() * size as f64) as usize % size);
+            if rank[a] < rank[b] || (rank[a] == rank[b] && crowd[a] > crowd[b]) { a } else { b }
+        };
+        let mut children = Vec::with_capacity(size);
+        while children.len() < size {
+            let (mut c1, mut c2) = (pop[tournament()].clone(), pop[tournament()].clone());
+            if rand_f64() < CROSSOVER_RATE {
+                for j in 0..n {
+                    if rand_f64() < 0.5 { (c1[j], c2[j]) = sbx(c1[j], c2[j]); }
+                }
+            }
+            for c in [&mut c1, &mut c2] {
+                for g in c.iter_mut() { if rand_f64() < 1.0 / n as f64 { *g = mutate(*g); } }
+            }
+            children.push(c1);
+            children.push(c2);
+        }
+        let child_objs = evaluate(&children);
+
+        // Elitist survival from parents and children: whole fronts while
+        // they fit, then the least crowded of the next
+        let all: Vec<Vec<f64>> = pop.into_iter().chain(children).collect();
+        let all_objs: Vec<Vec<f64>> = objs.into_iter().chain(child_objs).collect();
+        let mut keep = Vec::with_capacity(size);
+        for front in nondominated_sort(&all_objs) {
+            if keep.len() + front.len() <= size {
+                keep.extend_from_slice(&front);
+                continue;
+            }
+            let d = crowding(&all_objs, &front);
+            let mut order: Vec<usize> = (0..front.len()).collect();
+            order.sort_by(|&a, &b| d[b].total_cmp(&d[a]));
+            keep.extend(order.iter().take(size - keep.len()).map(|&k| front[k]));
+            break;
+        }
+        pop = keep.iter().map(|&i| all[i].clone()).collect();
+        objs = keep.iter().map(|&i| all_objs[i].clone()).collect();
+        if progress(generation + 1, &front_of(&pop, &objs)) { break; }
+    }
+    front_of(&pop, &objs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objective::params_from_slice;
+    use crate::objective::tests::synthetic_problem;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn front_spans_conflicting_datasets() {
+        crate::rng::seed_rng(11);
+        let init = [100.0, 0.0, 0.0, 5000.0, 0.0, 0.0];
+        let problem = |k2: f64| {
+            let truth = params_from_slice(&[1e-3, 0.0, 0.1, k2, 0.0, 1.0, 0.05], &init);
+            synthetic_problem(init, &truth, (1..=20).map(|i| 2.0 * i as f64).collect())
+        };
+        // Two runs that disagree on k2: no single value fits both
+        let problems = [problem(0.5), problem(1.0)];
+        let start = params_from_slice(&[1e-3, 0.0, 0.1, 0.7, 0.0, 1.0, 0.05], &init);
+        let opts = Pareto { population: 20, generations: 15 };
+        let mut generations = 0;
+        let front = pareto_fit(&problems, start, &[3], &[(0.2, 2.0)], &opts, |g, _| { generations = g; false });
+        assert_eq!(generations, 15);
+        assert!(front.len() >= 3, "{} points", front.len());
+        for a in &front {
+            assert!(front.iter().all(|b| !dominates(&b.objectives, &a.objectives)));
+        }
+        let (lo, hi) = (front[0].params[3], front[front.len() - 1].params[3]);
+        assert!((lo - 0.5).abs() < 0.15 && (hi - 1.0).abs() < 0.2, "front from k2 {} to {}", lo, hi);
+    }
+}
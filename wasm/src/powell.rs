@@ -0,0 +1,210 @@
+// Powell's conjugate-direction method, a derivative-free fitter for the
+// stochastic objective. Each iteration minimizes along every direction in
+// turn, then along the net move of the iteration, which replaces the
+// direction that gave the largest decrease. Line searches adapt the step to
+// each direction, so long narrow valleys that stall the simplex are
+// followed directly.
+
+use std::cell::Cell;
+
+use crate::fit::{FitResult, FIT_CANCELLED, FIT_CONVERGED, FIT_MAX_EVALS, FIT_MAX_ITER, FIT_NOTHING_TO_DO, FIT_X_CONVERGED};
+use crate::objective::{lower_bound, Params, Problem};
+use crate::rng::{next_seed, seed_rng};
+
+const GOLDEN: f64 = 1.618_033_988_749_895;
+// Expansions of the bracketing step, and refinements inside a bracket
+const MAX_EXPANSIONS: u32 = 10;
+const MAX_REFINEMENTS: u32 = 5;
+// Most evaluations one line search spends
+const LINE_EVALS: u32 = 2 + MAX_EXPANSIONS + MAX_REFINEMENTS;
+
+#[derive(Clone, Debug)]
+pub struct Powell {
+    pub max_iter: u32,
+    // Objective evaluations allowed in total; the fit stops before a line
+    // search (up to 17 evaluations) that could exceed it. 0 => unlimited
+    pub max_evals: u32,
+    // First trial step of each line search, relative to the starting values
+    // (absolute where they are 0)
+    pub scale: f64,
+    // Stop once an iteration lowers the objective by less than tol, relative
+    // to its magnitude
+    pub tol: f64,
+    // Stop once an iteration moves no coordinate by more than x_tol
+    // (relative); also the bracket width that ends a line search. <= 0 => 1e-6
+    pub x_tol: f64,
+    pub progress_every: u32, // 0 => every iteration
+    // Record the objective and parameters at every iteration
+    pub trace: bool,
+    // Common random numbers, as in fit::NelderMead
+    pub crn_seed: Option<u64>,
+}
+
+// Minimum of f along a line from t = 0 (where it is f0), bracketed from
+// trial step `step` and refined by safeguarded parabolic steps until the
+// bracket is narrower than tol. Returns (t, f(t)), (0, f0) when nothing
+// along the line is lower.
+fn line_min(f: &mut impl FnMut(f64) -> f64, f0: f64, step: f64, tol: f64) -> (f64, f64) {
+    let (mut a, mut fa) = (0.0, f0);
+    let (mut b, mut fb) = (step, f(step));
+    if fb.is_nan() || fb > fa {
+        // Downhill the other way, else the minimum lies within +-step
+        let fm = f(-step);
+        if fm.is_nan() || fm > fa {
+            let curv = fb + fm - 2.0 * fa;
+            if curv.is_nan() || curv <= 0.0 { return (0.0, f0); }
+            let t = (0.5 * step * (fm - fb) / curv).clamp(-step, step);
+            let ft = f(t);
+            return if ft < f0 { (t, ft) } else { (0.0, f0) };
+        }
+        (a, fa, b, fb) = (0.0, f0, -step, fm);
+    }
+    // Grow the step until the objective turns up again
+    let mut c = b + GOLDEN * (b - a);
+    let mut fc = f(c);
+    let mut expansions = 0;
+    while fc <= fb && expansions < MAX_EXPANSIONS {
+        (a, fa, b, fb) = (b, fb, c, fc);
+        c = b + GOLDEN * (b - a);
+        fc = f(c);
+        expansions += 1;
+    }
+    if fc <= fb { return (c, fc); }
+    // a, b, c bracket a minimum at b: parabolic steps, golden sections where
+    // the parabola leaves the bracket
+    let (mut lo, mut flo, mut hi, mut fhi) = if a < c { (a, fa, c, fc) } else { (c, fc, a, fa) };
+    for _ in 0..MAX_REFINEMENTS {
+        if hi - lo <= tol { break; }
+        let (p, q) = ((b - lo) * (fb - fhi), (b - hi) * (fb - flo));
+        let mut u = b - 0.5 * ((b - lo) * p - (b - hi) * q) / (p - q);
+        if u.is_nan() || u <= lo || u >= hi || (u - b).abs() < 1e-3 * (hi - lo) {
+            u = if b - lo > hi - b { b - (b - lo) / (1.0 + GOLDEN) } else { b + (hi - b) / (1.0 + GOLDEN) };
+        }
+        let fu = f(u);
+        if fu < fb {
+            if u < b { (hi, fhi) = (b, fb); } else { (lo, flo) = (b, fb); }
+            (b, fb) = (u, fu);
+        } else if u < b {
+            (lo, flo) = (u, fu);
+        } else {
+            (hi, fhi) = (u, fu);
+        }
+    }
+    (b, fb)
+}
+
+// Minimize the problem objective over params[optimize_idx] by Powell's
+// method, starting from params along the coordinate axes. progress is
+// called as (iteration, objective, params) every opts.progress_every
+// iterations; returning true stops the fit. Reasons are the fit::FIT_*
+// codes; spread is the last iteration's decrease, simplex the final point.
+pub fn powell_fit(
+    problem: &Problem,
+    params: Params,
+    optimize_idx: &[usize],
+    opts: &Powell,
+    mut progress: impl FnMut(u32, f64, &Params) -> bool,
+) -> FitResult {
+    let n = optimize_idx.len();
+    if n == 0 {
+        let f = problem.objective(&params);
+        return FitResult::single_point(params, f, 0, 1, 0.0, FIT_NOTHING_TO_DO, Vec::new());
+    }
+    let resume = opts.crn_seed.map(|_| next_seed());
+
+    // Coordinates z relative to the start: x = w z
+    let w: Vec<f64> = optimize_idx.iter().map(|&i| if params[i].abs() > 0.0 { params[i].abs() } else { 1.0 }).collect();
+    let at = |z: &[f64]| -> Params {
+        let mut trial = params;
+        for (j, &idx) in optimize_idx.iter().enumerate() { trial[idx] = (w[j] * z[j]).max(lower_bound(idx)); }
+        trial[6] = trial[6].max(1e-12);
+        problem.constrained(&trial)
+    };
+    let evals = Cell::new(0u32);
+    let objective = |z: &[f64]| -> f64 {
+        evals.set(evals.get() + 1);
+        if let Some(seed) = opts.crn_seed { seed_rng(seed); }
+        problem.objective(&at(z))
+    };
+    let step = if opts.scale.is_finite() && opts.scale > 0.0 { opts.scale } else { 0.1 };
+    let x_tol = if opts.x_tol > 0.0 { opts.x_tol } else { 1e-6 };
+    let affordable = |evals: u32| opts.max_evals == 0 || evals + LINE_EVALS <= opts.max_evals;
+
+    let mut z: Vec<f64> = (0..n).map(|j| params[optimize_idx[j]] / w[j]).collect();
+    let mut f = objective(&z);
+    let mut dirs: Vec<Vec<f64>> = (0..n).map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect()).collect();
+    let mut trace = Vec::new();
+    let mut spread = f64::NAN;
+    let mut iter = 0;
+    let mut reason = FIT_MAX_ITER;
+    'fit: while iter < opts.max_iter {
+        if opts.trace { trace.push((f, at(&z))); }
+        if iter % opts.progress_every.max(1) == 0 && progress(iter, f, &at(&z)) { reason = FIT_CANCELLED; break; }
+        let (z_start, f_start) = (z.clone(), f);
+        let (mut biggest, mut biggest_drop) = (0, 0.0);
+        for (i, u) in dirs.iter().enumerate() {
+            if !affordable(evals.get()) { reason = FIT_MAX_EVALS; break 'fit; }
+            let (t, ft) = line_min(&mut |t| objective(&z.iter().zip(u).map(|(a, b)| a + t * b).collect::<Vec<f64>>()), f, step, x_tol);
+            for j in 0..n { z[j] += t * u[j]; }
+            if f - ft > biggest_drop { (biggest, biggest_drop) = (i, f - ft); }
+            f = ft;
+        }
+        iter += 1;
+        // The net move replaces the direction of largest decrease when the
+        // extrapolated point suggests a valley along it (Powell's test)
+        let net: Vec<f64> = (0..n).map(|j| z[j] - z_start[j]).collect();
+        if affordable(evals.get() + 1) {
+            let fe = objective(&z.iter().zip(&net).map(|(a, b)| a + b).collect::<Vec<f64>>());
+            let test = 2.0 * (f_start - 2.0 * f + fe) * (f_start - f - biggest_drop).powi(2) - biggest_drop * (f_start - fe).powi(2);
+            if fe < f_start && test < 0.0 {
+                let scale = net.iter().fold(0.0, |m, v| f64::max(m, v.abs()));
+                let u: Vec<f64> = net.iter().map(|v| v / scale).collect();
+                let (t, ft) = line_min(&mut |t| objective(&z.iter().zip(&u).map(|(a, b)| a + t * b).collect::<Vec<f64>>()), f, step.min(scale), x_tol);
+                for j in 0..n { z[j] += t * u[j]; }
+                f = ft;
+                dirs.remove(biggest);
+                dirs.push(u);
+            }
+        }
+        spread = f_start - f;
+        if spread <= opts.tol * 0.5 * (f_start.abs() + f.abs()) { reason = FIT_CONVERGED; break; }
+        if (0..n).all(|j| (z[j] - z_start[j]).abs() <= x_tol) { reason = FIT_X_CONVERGED; break; }
+    }
+    if let Some(seed) = resume { seed_rng(seed); }
+    FitResult::single_point(at(&z), f, iter, evals.get(), spread, reason, trace)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objective::params_from_slice;
+    use crate::objective::tests::synthetic_problem;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn line_search_finds_a_parabola_minimum() {
+        let mut calls = 0;
+        let (t, ft) = line_min(&mut |t| { calls += 1; (t - 3.0) * (t - 3.0) + 1.0 }, 10.0, 0.1, 1e-9);
+        assert!((t - 3.0).abs() < 1e-6 && (ft - 1.0).abs() < 1e-9, "{} {}", t, ft);
+        assert!(calls <= LINE_EVALS);
+        // Uphill both ways: stay put
+        assert_eq!(line_min(&mut |t| t * t, 0.0, 0.5, 1e-9), (0.0, 0.0));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn powell_recovers_two_rates() {
+        crate::rng::seed_rng(5);
+        let init = [100.0, 0.0, 0.0, 5000.0, 0.0, 0.0];
+        let truth = params_from_slice(&[1e-3, 0.0, 0.1, 1.0, 0.0, 1.0, 0.05], &init);
+        let problem = synthetic_problem(init, &truth, (1..=20).map(|i| 2.0 * i as f64).collect());
+        let mut start = truth;
+        start[3] = 0.6;
+        start[5] = 1.4;
+        let opts = Powell { max_iter: 30, max_evals: 400, scale: 0.1, tol: 1e-6, x_tol: 1e-3, progress_every: 0, trace: true, crn_seed: Some(3) };
+        let res = powell_fit(&problem, start, &[3, 5], &opts, |_, _, _| false);
+        assert!(res.evaluations <= 400);
+        assert!(res.sse < 0.2 * problem.objective(&start), "{} vs {}", res.sse, problem.objective(&start));
+        assert!((res.params[3] - 1.0).abs() < 0.2, "k2 = {}", res.params[3]);
+        assert!(res.trace.windows(2).all(|w| w[1].0 <= w[0].0));
+    }
+}
@@ -0,0 +1,180 @@
+// Self-describing JSON fit reports: fitted parameters with standard errors,
+// the objective, residuals, convergence details and the model the fit was
+// run against, so a fit can be saved, shared and loaded back.
+
+use crate::fit::{self, FitResult};
+use crate::json::Value;
+use crate::objective::{self, Constraint, Params, Problem, RateSpace, N_PARAMS, PARAM_NAMES};
+use crate::warn;
+
+// Value of "format" in every report
+pub const FIT_REPORT_FORMAT: &str = "enzyme_plus/fit";
+// Bumped when a field changes meaning; readers accept this version and older
+pub const FIT_REPORT_VERSION: f64 = 1.0;
+
+const MECHANISM: &str = "E + S <-> ES <-> EP <-> E + P";
+
+fn member(key: &str, v: Value) -> (String, Value) { (key.to_string(), v) }
+
+fn indices(v: &[usize]) -> Value { Value::Array(v.iter().map(|&i| Value::from(i as f64)).collect()) }
+
+// Report of res, a fit of params[idx] to problem. Standard errors and
+// residuals are computed here at the fitted parameters (one stochastic run
+// each), with points res excluded left out of the standard errors; the
+// warnings are those raised on this thread so far (warn::peek).
+pub fn fit_report(problem: &Problem, res: &FitResult, idx: &[usize]) -> Value {
+    let mut kept = problem.clone();
+    for &i in &res.excluded { if i < kept.times.len() { kept.times[i] = f64::NAN; } }
+    let se = fit::standard_errors(&kept, &res.params, idx);
+    let residuals = problem.residuals(&res.params);
+
+    let name = |i: usize| problem.space.param_name(i);
+    let params = res.params.iter().enumerate().map(|(i, &v)| member(name(i), Value::from(v))).collect();
+    let fitted = idx.iter().map(|&i| Value::from(name(i))).collect();
+    let errors = idx.iter().zip(&se).map(|(&i, &s)| member(name(i), Value::from(s))).collect();
+    let mut report = vec![
+        member("format", Value::from(FIT_REPORT_FORMAT)),
+        member("version", Value::from(FIT_REPORT_VERSION)),
+        member("crate_version", Value::from(env!("CARGO_PKG_VERSION"))),
+        member("params", Value::Object(params)),
+    ];
+    if problem.space != RateSpace::Micro {
+        let k = problem.simulated(&res.params);
+        let rates = PARAM_NAMES[..6].iter().zip(&k[..6]).map(|(n, &v)| member(n, Value::from(v))).collect();
+        report.push(member("rates", Value::Object(rates)));
+    }
+    report.extend([
+        member("fitted", Value::Array(fitted)),
+        member("standard_errors", Value::Object(errors)),
+        member("sse", Value::from(res.sse)),
+        member("iterations", Value::from(res.iterations as f64)),
+        member("evaluations", Value::from(res.evaluations as f64)),
+        member("spread", Value::from(res.spread)),
+        member("reason", Value::from(fit::reason_name(res.reason))),
+        member("restarts", Value::from(res.restarts as f64)),
+    ]);
+    let n_obs = problem.n_obs() - res.excluded.len().min(problem.n_obs());
+    report.push(member("n_obs", Value::from(n_obs as f64)));
+    if problem.loss.is_likelihood() {
+        let [aic, aicc, bic] = objective::information_criteria(res.sse, idx.len(), n_obs);
+        report.push(member("aic", Value::from(aic)));
+        report.push(member("aicc", Value::from(aicc)));
+        report.push(member("bic", Value::from(bic)));
+    }
+    report.push(member("residuals", Value::from(&residuals[..])));
+    if !res.studentized.is_empty() {
+        report.push(member("excluded", indices(&res.excluded)));
+        report.push(member("studentized", Value::from(&res.studentized[..])));
+    }
+    if !res.trace.is_empty() {
+        let rows = res.trace.iter().map(|(f, p)| Value::Object(vec![
+            member("sse", Value::from(*f)),
+            member("params", Value::from(&p[..])),
+        ])).collect();
+        report.push(member("trace", Value::Array(rows)));
+    }
+    let mut model = vec![
+        member("mechanism", Value::from(MECHANISM)),
+        member("init", Value::from(&problem.init[..])),
+        member("species", Value::String(problem.observable.to_string())),
+        member("interp", Value::from(problem.interp.name())),
+        member("loss", Value::from(problem.loss.name())),
+        member("rate_space", Value::from(problem.space.name())),
+    ];
+    if problem.loss.scale().is_finite() { model.push(member("loss_scale", Value::from(problem.loss.scale()))); }
+    model.push(member("priors", Value::Bool(problem.priors.is_some())));
+    if !problem.constraints.is_empty() {
+        let rows = problem.constraints.iter().map(|c| Value::Object(match *c {
+            Constraint::Ratio { num, den, ratio } => vec![
+                member("kind", Value::from("ratio")),
+                member("param", Value::from(name(num))),
+                member("of", Value::from(name(den))),
+                member("value", Value::from(ratio)),
+            ],
+            Constraint::Haldane { keq, solve } => vec![
+                member("kind", Value::from("haldane")),
+                member("param", Value::from(name(solve))),
+                member("value", Value::from(keq)),
+            ],
+            Constraint::Tie { param, to } => vec![
+                member("kind", Value::from("tie")),
+                member("param", Value::from(name(param))),
+                member("of", Value::from(name(to))),
+            ],
+        })).collect();
+        model.push(member("constraints", Value::Array(rows)));
+    }
+    report.push(member("model", Value::Object(model)));
+    let warnings = warn::peek().names().into_iter().map(Value::from).collect();
+    report.push(member("warnings", Value::Array(warnings)));
+    Value::Object(report)
+}
+
+// Rate space a report's params are in ("micro" when the report predates it)
+pub fn rate_space_of(report: &Value) -> Result<RateSpace, String> {
+    match report.get("model").and_then(|m| m.get("rate_space")) {
+        None => Ok(RateSpace::Micro),
+        Some(v) => RateSpace::parse(v.as_str().ok_or("model.rate_space must be a string")?),
+    }
+}
+
+// Fitted parameters of a report written by fit_report (or a newer writer of
+// the same version), e.g. to warm-start another fit; slots 0..6 are in the
+// report's rate_space_of
+pub fn params_from_report(report: &Value) -> Result<Params, String> {
+    if report.get("format").and_then(Value::as_str) != Some(FIT_REPORT_FORMAT) {
+        return Err(format!("not a fit report (expected format \"{}\")", FIT_REPORT_FORMAT));
+    }
+    match report.get("version").and_then(Value::as_f64) {
+        Some(v) if v <= FIT_REPORT_VERSION => {}
+        Some(v) => return Err(format!("fit report version {} is newer than {}", v, FIT_REPORT_VERSION)),
+        None => return Err("fit report has no version".to_string()),
+    }
+    let space = rate_space_of(report)?;
+    let params = report.get("params").ok_or("fit report has no params")?;
+    let mut out = [0.0; N_PARAMS];
+    for (i, slot) in out.iter_mut().enumerate() {
+        let name = space.param_name(i);
+        *slot = match params.get(name) {
+            Some(Value::Null) => f64::NAN,
+            Some(v) => v.as_f64().ok_or_else(|| format!("param '{}' must be a number", name))?,
+            None => return Err(format!("fit report is missing param '{}'", name)),
+        };
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+    use crate::objective::tests::problem;
+    use crate::objective::Loss;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn report_round_trips_params() {
+        crate::rng::seed_rng(4);
+        let init = [10.0, 0.0, 0.0, 500.0, 0.0, 0.0];
+        let mut params = objective::params_from_slice(&[1e-3, 0.0, 0.1, 1.0, 0.0, 1.0, 0.05], &init);
+        let problem = Problem {
+            loss: Loss::Gaussian,
+            ..problem(init, (1..=20).map(|i| i as f64).collect(), (1..=20).map(|i| 10.0 * i as f64).collect())
+        };
+        let opts = fit::NelderMead {
+            max_iter: 30, tol: 1e-8, scale: 0.1, progress_every: 0, warm_simplex: None,
+            max_restarts: 0, x_tol: 0.0, max_evals: 0, trace: false, crn_seed: None,
+            adaptive: false, oriented_restarts: false,
+        };
+        params[3] = 0.5;
+        let res = fit::nelder_mead(&problem, params, &[0, 3], &opts, |_, _, _| false);
+        let text = fit_report(&problem, &res, &[0, 3]).to_string();
+        let doc = json::parse(&text).unwrap();
+        let back = params_from_report(&doc).unwrap();
+        assert_eq!(back[..], res.params[..]);
+        assert_eq!(doc.get("residuals").and_then(Value::as_f64_vec).map(|r| r.len()), Some(20));
+        assert!(doc.get("standard_errors").and_then(|s| s.get("k2")).and_then(Value::as_f64).is_some());
+        assert!(doc.get("aic").is_some());
+        assert!(params_from_report(&json::parse(r#"{"format":"other"}"#).unwrap()).is_err());
+    }
+}
@@ -0,0 +1,526 @@
+// Random sources and discrete samplers used by the stochastic stepper.
+
+use std::cell::Cell;
+
+// Uniform on [0, 1) from a per-thread xoshiro256++ stream. No JS imports are
+// needed: the stream is seeded from std's random hasher keys natively, from
+// Math.random on wasm32 with the `js-random` feature, and otherwise from a
+// fixed seed until seed_rng is called.
+// While a tape is recording or replaying, every uniform goes through it.
+#[inline]
+pub(crate) fn rand_f64() -> f64 {
+    if !tape_active() { return XOSHIRO.with(|x| x.next_f64()); }
+    TAPE.with(|t| match &mut *t.borrow_mut() {
+        Tape::Record(draws) => {
+            let u = XOSHIRO.with(|x| x.next_f64());
+            draws.push(u);
+            u
+        }
+        // Past the end of the tape the live stream takes over
+        Tape::Replay(draws, pos) => match draws.get(*pos) {
+            Some(&u) => { *pos += 1; u }
+            None => XOSHIRO.with(|x| x.next_f64()),
+        },
+        Tape::Off => XOSHIRO.with(|x| x.next_f64()),
+    })
+}
+
+// Restart the calling thread's stream from a seed; parallel::par_map seeds
+// its workers from this stream.
+pub fn seed_rng(seed: u64) {
+    XOSHIRO.with(|x| x.reseed(seed));
+    clear_spare();
+}
+
+// A seed drawn from this thread's stream, for moving it on to a fresh but
+// reproducible sequence after a run that reseeded it
+pub(crate) fn next_seed() -> u64 { XOSHIRO.with(|x| x.next_u64()) }
+
+fn clear_spare() {
+    #[cfg(feature = "normal-polar")]
+    SPARE.with(|c| c.set(None));
+}
+
+// Recorded uniforms for exact replay of a run, independent of seed and
+// sampler internals. Like the stream, tapes are per thread.
+enum Tape {
+    Off,
+    Record(Vec<f64>),
+    Replay(Vec<f64>, usize),
+}
+
+thread_local! {
+    static TAPE: std::cell::RefCell<Tape> = const { std::cell::RefCell::new(Tape::Off) };
+    static TAPE_ACTIVE: Cell<bool> = const { Cell::new(false) };
+}
+
+// Whether this thread is recording or replaying
+pub(crate) fn tape_active() -> bool { TAPE_ACTIVE.with(Cell::get) }
+
+fn set_tape(tape: Tape) {
+    TAPE_ACTIVE.with(|a| a.set(!matches!(tape, Tape::Off)));
+    TAPE.with(|t| *t.borrow_mut() = tape);
+    clear_spare();
+}
+
+// Start capturing every uniform drawn on this thread (drops any current tape)
+pub fn start_recording() { set_tape(Tape::Record(Vec::new())); }
+
+// Stop recording and return the draws; empty if nothing was recording
+pub fn finish_recording() -> Vec<f64> {
+    let tape = TAPE.with(|t| std::mem::replace(&mut *t.borrow_mut(), Tape::Off));
+    set_tape(Tape::Off);
+    match tape { Tape::Record(draws) => draws, _ => Vec::new() }
+}
+
+// Serve uniforms from a recorded tape until stop_replay
+pub fn start_replay(draws: Vec<f64>) { set_tape(Tape::Replay(draws, 0)); }
+
+// Antithetic partner of recorded draws: u -> 1 - 2^-53 - u, an exact
+// bijection of the [0, 1) grid rand_f64 draws from
+pub fn mirror_draws(draws: &mut [f64]) {
+    for u in draws { *u = (1.0 - f64::EPSILON / 2.0) - *u; }
+}
+
+// Stop replaying; returns how many recorded draws were consumed
+pub fn stop_replay() -> usize {
+    let tape = TAPE.with(|t| std::mem::replace(&mut *t.borrow_mut(), Tape::Off));
+    set_tape(Tape::Off);
+    match tape { Tape::Replay(draws, pos) => pos.min(draws.len()), _ => 0 }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn initial_seed() -> u64 {
+    use std::hash::{BuildHasher, Hasher};
+    std::collections::hash_map::RandomState::new().build_hasher().finish()
+}
+
+#[cfg(all(target_arch = "wasm32", feature = "js-random"))]
+fn initial_seed() -> u64 {
+    let word = || (js_sys::Math::random() * 4_294_967_296.0) as u64;
+    (word() << 32) | word()
+}
+
+#[cfg(all(target_arch = "wasm32", not(feature = "js-random")))]
+fn initial_seed() -> u64 { 0x853c_49e6_748f_ea9b }
+
+thread_local! {
+    static XOSHIRO: Xoshiro256 = Xoshiro256::seeded(initial_seed());
+}
+
+struct Xoshiro256 {
+    s: [Cell<u64>; 4],
+}
+
+impl Xoshiro256 {
+    fn seeded(seed: u64) -> Xoshiro256 {
+        let x = Xoshiro256 { s: [Cell::new(0), Cell::new(0), Cell::new(0), Cell::new(0)] };
+        x.reseed(seed);
+        x
+    }
+
+    // State expanded from one word with splitmix64
+    fn reseed(&self, seed: u64) {
+        let mut z = seed;
+        for cell in &self.s {
+            z = z.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut x = z;
+            x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            cell.set(x ^ (x >> 31));
+        }
+    }
+
+    fn next_u64(&self) -> u64 {
+        let s = &self.s;
+        let result = (s[0].get().wrapping_add(s[3].get())).rotate_left(23).wrapping_add(s[0].get());
+        let t = s[1].get() << 17;
+        s[2].set(s[2].get() ^ s[0].get());
+        s[3].set(s[3].get() ^ s[1].get());
+        s[1].set(s[1].get() ^ s[2].get());
+        s[0].set(s[0].get() ^ s[3].get());
+        s[2].set(s[2].get() ^ t);
+        s[3].set(s[3].get().rotate_left(45));
+        result
+    }
+
+    // Top 53 bits as a double in [0, 1)
+    fn next_f64(&self) -> f64 { (self.next_u64() >> 11) as f64 * (1.0 / 9_007_199_254_740_992.0) }
+}
+
+// Standard normal: ziggurat by default, Marsaglia polar with the
+// `normal-polar` feature (no tables, smaller binary).
+#[cfg(not(feature = "normal-polar"))]
+pub(crate) fn rand_std_normal() -> f64 { ziggurat::ZIGGURAT.with(|z| z.sample()) }
+
+#[cfg(feature = "normal-polar")]
+pub(crate) fn rand_std_normal() -> f64 { rand_std_normal_polar() }
+
+#[cfg(feature = "normal-polar")]
+thread_local! {
+    static SPARE: Cell<Option<f64>> = const { Cell::new(None) };
+}
+
+// Marsaglia polar method; both values of each accepted pair are used
+#[cfg(feature = "normal-polar")]
+fn rand_std_normal_polar() -> f64 {
+    if let Some(z) = SPARE.with(|c| c.take()) { return z; }
+    loop {
+        let u = 2.0 * rand_f64() - 1.0;
+        let v = 2.0 * rand_f64() - 1.0;
+        let s = u * u + v * v;
+        if s > 0.0 && s < 1.0 {
+            let f = (-2.0 * s.ln() / s).sqrt();
+            SPARE.with(|c| c.set(Some(v * f)));
+            return u * f;
+        }
+    }
+}
+
+// Marsaglia–Tsang ziggurat with 128 layers
+#[cfg(not(feature = "normal-polar"))]
+mod ziggurat {
+    use super::rand_f64;
+
+    // Uniform 32-bit integer from the f64 source
+    #[inline]
+    fn rand_u32() -> u32 { (rand_f64() * 4_294_967_296.0) as u32 }
+
+    // Uniform in (0, 1), safe for ln()
+    #[inline]
+    fn rand_open01() -> f64 { rand_f64().max(1e-300) }
+
+    pub(super) struct Ziggurat {
+        kn: [u32; 128],
+        wn: [f64; 128],
+        fn_: [f64; 128],
+    }
+
+    thread_local! {
+        pub(super) static ZIGGURAT: Ziggurat = Ziggurat::new();
+    }
+
+    impl Ziggurat {
+        const R: f64 = 3.442_619_855_899;
+
+        fn new() -> Ziggurat {
+            let m1 = 2_147_483_648.0;
+            let vn = 9.912_563_035_262_17e-3;
+            let mut dn = Self::R;
+            let mut tn = dn;
+            let q = vn / (-0.5 * dn * dn).exp();
+            let mut z = Ziggurat { kn: [0; 128], wn: [0.0; 128], fn_: [0.0; 128] };
+            z.kn[0] = ((dn / q) * m1) as u32;
+            z.kn[1] = 0;
+            z.wn[0] = q / m1;
+            z.wn[127] = dn / m1;
+            z.fn_[0] = 1.0;
+            z.fn_[127] = (-0.5 * dn * dn).exp();
+            for i in (1..=126).rev() {
+                dn = (-2.0 * (vn / dn + (-0.5 * dn * dn).exp()).ln()).sqrt();
+                z.kn[i + 1] = ((dn / tn) * m1) as u32;
+                tn = dn;
+                z.fn_[i] = (-0.5 * dn * dn).exp();
+                z.wn[i] = dn / m1;
+            }
+            z
+        }
+
+        pub(super) fn sample(&self) -> f64 {
+            let mut hz = rand_u32() as i32;
+            let mut iz = (hz & 127) as usize;
+            loop {
+                if hz.unsigned_abs() < self.kn[iz] { return hz as f64 * self.wn[iz]; }
+                let x = hz as f64 * self.wn[iz];
+                if iz == 0 {
+                    // Base strip: sample the tail beyond R
+                    loop {
+                        let x = -rand_open01().ln() / Self::R;
+                        let y = -rand_open01().ln();
+                        if y + y >= x * x { return if hz > 0 { Self::R + x } else { -Self::R - x }; }
+                    }
+                }
+                if self.fn_[iz] + rand_f64() * (self.fn_[iz - 1] - self.fn_[iz]) < (-0.5 * x * x).exp() { return x; }
+                hz = rand_u32() as i32;
+                iz = (hz & 127) as usize;
+            }
+        }
+    }
+}
+
+// ln Γ(x) for x > 0 (Lanczos, g = 7, n = 9; ~1e-15 relative)
+pub(crate) fn ln_gamma(x: f64) -> f64 {
+    const G: f64 = 7.0;
+    const COEF: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        // Reflection
+        let pi = std::f64::consts::PI;
+        return (pi / (pi * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let mut a = COEF[0];
+    let t = x + G + 0.5;
+    for (i, c) in COEF.iter().enumerate().skip(1) { a += c / (x + i as f64); }
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + a.ln()
+}
+
+// Poisson sampler, e.g. for count noise on synthetic data: Knuth
+// multiplication for small lambda, PTRS (Hörmann's transformed rejection with
+// squeeze) above, which is O(1) and avoids the exp(-lambda) underflow of the
+// multiplicative method.
+pub fn sample_poisson(lambda: f64) -> i64 {
+    if lambda <= 0.0 { return 0; }
+    if lambda < 10.0 {
+        let l = (-lambda).exp();
+        let mut k: i64 = 0;
+        let mut p = 1.0;
+        loop {
+            k += 1;
+            p *= rand_f64();
+            if p <= l { break; }
+        }
+        return k - 1;
+    }
+    let slam = lambda.sqrt();
+    let loglam = lambda.ln();
+    let b = 0.931 + 2.53 * slam;
+    let a = -0.059 + 0.02483 * b;
+    let invalpha = 1.1239 + 1.1328 / (b - 3.4);
+    let vr = 0.9277 - 3.6224 / (b - 2.0);
+    loop {
+        let u = rand_f64() - 0.5;
+        let v = rand_f64();
+        let us = 0.5 - u.abs();
+        let k = ((2.0 * a / us + b) * u + lambda + 0.43).floor();
+        if us >= 0.07 && v <= vr { return k as i64; }
+        if k < 0.0 || (us < 0.013 && v > us) { continue; }
+        if v.ln() + invalpha.ln() - (a / (us * us) + b).ln() <= -lambda + k * loglam - ln_gamma(k + 1.0) {
+            return k as i64;
+        }
+    }
+}
+
+pub(crate) fn sample_binomial(n: i64, mut p: f64) -> i64 {
+    if n <= 0 { return 0; }
+    if p <= 0.0 { return 0; }
+    if p >= 1.0 { return n; }
+    // Use symmetry to keep p <= 0.5
+    let mutate = p > 0.5;
+    if mutate { p = 1.0 - p; }
+
+    let mean = n as f64 * p;
+
+    let k = if n < 50 {
+        // Direct Bernoulli sum for small n
+        let mut c = 0i64;
+        for _ in 0..n { if rand_f64() < p { c += 1; } }
+        c
+    } else if mean < 30.0 {
+        binomial_inversion(n, p)
+    } else {
+        binomial_btpe(n, p)
+    };
+
+    if mutate { n - k } else { k }
+}
+
+// Sequential inversion of the CDF; exact, O(n*p) draws on average. p <= 0.5.
+fn binomial_inversion(n: i64, p: f64) -> i64 {
+    let q = 1.0 - p;
+    let nn = n as f64;
+    let qn = (nn * q.ln()).exp();
+    let np = nn * p;
+    let bound = (n as f64).min(np + 10.0 * (np * q + 1.0).sqrt());
+    let mut x: i64 = 0;
+    let mut px = qn;
+    let mut u = rand_f64();
+    while u > px {
+        x += 1;
+        if x as f64 > bound {
+            x = 0;
+            px = qn;
+            u = rand_f64();
+        } else {
+            u -= px;
+            px = ((nn - x as f64 + 1.0) * p * px) / (x as f64 * q);
+        }
+    }
+    x
+}
+
+// BTPE (Kachitvichyanukul & Schmeiser 1988): exact triangle/parallelogram/
+// exponential rejection sampler, O(1) expected time. Requires p <= 0.5, n*p >= 30.
+fn binomial_btpe(n: i64, p: f64) -> i64 {
+    let nn = n as f64;
+    let r = p;
+    let q = 1.0 - r;
+    let fm = nn * r + r;
+    let m = fm.floor();
+    let p1 = (2.195 * (nn * r * q).sqrt() - 4.6 * q).floor() + 0.5;
+    let xm = m + 0.5;
+    let xl = xm - p1;
+    let xr = xm + p1;
+    let c = 0.134 + 20.5 / (15.3 + m);
+    let a = (fm - xl) / (fm - xl * r);
+    let laml = a * (1.0 + a / 2.0);
+    let a = (xr - fm) / (xr * q);
+    let lamr = a * (1.0 + a / 2.0);
+    let p2 = p1 * (1.0 + 2.0 * c);
+    let p3 = p2 + c / laml;
+    let p4 = p3 + c / lamr;
+    let nrq = nn * r * q;
+
+    loop {
+        let u = rand_f64() * p4;
+        let mut v = rand_f64();
+        let y: f64;
+        if u <= p1 {
+            // Triangular region: accept immediately
+            return (xm - p1 * v + u).floor() as i64;
+        } else if u <= p2 {
+            // Parallelogram
+            let x = xl + (u - p1) / c;
+            v = v * c + 1.0 - (m - x + 0.5).abs() / p1;
+            if v > 1.0 { continue; }
+            y = x.floor();
+        } else if u <= p3 {
+            // Left exponential tail
+            y = (xl + v.ln() / laml).floor();
+            if y < 0.0 { continue; }
+            v *= (u - p2) * laml;
+        } else {
+            // Right exponential tail
+            y = (xr - v.ln() / lamr).floor();
+            if y > nn { continue; }
+            v *= (u - p3) * lamr;
+        }
+
+        let k = (y - m).abs();
+        if k <= 20.0 || k >= nrq / 2.0 - 1.0 {
+            // Explicit evaluation of f(y) / f(m) by recursion
+            let s = r / q;
+            let a = s * (nn + 1.0);
+            let mut f = 1.0;
+            if m < y {
+                let mut i = m + 1.0;
+                while i <= y { f *= a / i - s; i += 1.0; }
+            } else if m > y {
+                let mut i = y + 1.0;
+                while i <= m { f /= a / i - s; i += 1.0; }
+            }
+            if v > f { continue; }
+            return y as i64;
+        }
+
+        // Squeeze using upper and lower bounds on log f(y)
+        let rho = (k / nrq) * ((k * (k / 3.0 + 0.625) + 0.166_666_666_666_6) / nrq + 0.5);
+        let t = -k * k / (2.0 * nrq);
+        let big_a = v.ln();
+        if big_a < t - rho { return y as i64; }
+        if big_a > t + rho { continue; }
+
+        // Final acceptance with Stirling corrections
+        let x1 = y + 1.0;
+        let f1 = m + 1.0;
+        let z = nn + 1.0 - m;
+        let w = nn - y + 1.0;
+        let (x2, f2, z2, w2) = (x1 * x1, f1 * f1, z * z, w * w);
+        let stirling = |a: f64, a2: f64| (13680.0 - (462.0 - (132.0 - (99.0 - 140.0 / a2) / a2) / a2) / a2) / a / 166320.0;
+        let bound = xm * (f1 / x1).ln()
+            + (nn - m + 0.5) * (z / w).ln()
+            + (y - m) * (w * r / (x1 * q)).ln()
+            + stirling(f1, f2) + stirling(z, z2) + stirling(x1, x2) + stirling(w, w2);
+        if big_a > bound { continue; }
+        return y as i64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn seeded_streams_repeat() {
+        seed_rng(42);
+        let a: Vec<f64> = (0..8).map(|_| rand_std_normal() + sample_binomial(1000, 0.3) as f64).collect();
+        seed_rng(42);
+        let b: Vec<f64> = (0..8).map(|_| rand_std_normal() + sample_binomial(1000, 0.3) as f64).collect();
+        assert_eq!(a, b);
+        assert!((0..1000).map(|_| rand_f64()).all(|u| (0.0..1.0).contains(&u)));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn replay_reproduces_a_recorded_run() {
+        let st0 = [50.0, 0.0, 0.0, 2_000.0, 0.0, 0.0];
+        let k = [1e-3, 1e-4, 0.1, 1.0, 0.05, 1.0];
+        start_recording();
+        let first = crate::model::run_series(st0, &k, 0.01, 300);
+        let tape = finish_recording();
+        assert!(!tape.is_empty());
+        seed_rng(7); // the live stream no longer matters
+        start_replay(tape.clone());
+        let again = crate::model::run_series(st0, &k, 0.01, 300);
+        assert_eq!(stop_replay(), tape.len());
+        assert_eq!(first, again);
+    }
+
+    // Sample mean and variance of n draws
+    fn moments(n: usize, mut draw: impl FnMut() -> f64) -> (f64, f64) {
+        let xs: Vec<f64> = (0..n).map(|_| draw()).collect();
+        let mean = xs.iter().sum::<f64>() / n as f64;
+        (mean, xs.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1) as f64)
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn binomial_draws_have_binomial_moments() {
+        seed_rng(11);
+        // Bernoulli sum, inversion (including rare events), BTPE, and p > 0.5 by symmetry
+        for &(n, p) in &[(40, 0.3), (100_000, 1e-4), (5_000, 2e-3), (200, 0.1), (1_000_000, 0.3), (500, 0.8)] {
+            let (mean, var) = moments(20_000, || sample_binomial(n, p) as f64);
+            let (mu, sigma2) = (n as f64 * p, n as f64 * p * (1.0 - p));
+            assert!((mean - mu).abs() < 4.0 * (sigma2 / 20_000.0).sqrt(), "n={} p={}: mean {}", n, p, mean);
+            assert!((var / sigma2 - 1.0).abs() < 0.06, "n={} p={}: variance {}", n, p, var);
+        }
+        // Binomial, not Poisson, variance where n p q differs from n p by 10%
+        let (_, var) = moments(20_000, || sample_binomial(200, 0.1) as f64);
+        assert!((var - 18.0).abs() < (var - 20.0).abs());
+        assert!((0..1000).all(|_| (0..=60).contains(&sample_binomial(60, 0.97))));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn poisson_draws_have_poisson_moments() {
+        seed_rng(12);
+        for &lambda in &[3.0, 10.0, 1e3, 1e5] {
+            let (mean, var) = moments(20_000, || sample_poisson(lambda) as f64);
+            assert!((mean - lambda).abs() < 4.0 * (lambda / 20_000.0).sqrt(), "lambda={}: mean {}", lambda, mean);
+            assert!((var / lambda - 1.0).abs() < 0.06, "lambda={}: variance {}", lambda, var);
+        }
+        assert_eq!(sample_poisson(0.0), 0);
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn normal_draws_have_normal_tails() {
+        seed_rng(14);
+        let n = 400_000;
+        let xs: Vec<f64> = (0..n).map(|_| rand_std_normal()).collect();
+        let mean = xs.iter().sum::<f64>() / n as f64;
+        let var = xs.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n as f64;
+        assert!(mean.abs() < 0.01 && (var - 1.0).abs() < 0.01, "mean {} variance {}", mean, var);
+        // P(|Z| > 3) = 0.0026998; 1080 expected, sd about 33
+        let tail = xs.iter().filter(|x| x.abs() > 3.0).count() as f64;
+        assert!((tail / (n as f64 * 0.0026998) - 1.0).abs() < 0.12, "{} beyond 3 sd", tail);
+        let far = xs.iter().filter(|x| x.abs() > 4.0).count();
+        assert!((5..=60).contains(&far), "{} beyond 4 sd", far); // 25 expected
+    }
+}
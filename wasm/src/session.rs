@@ -0,0 +1,169 @@
+// Versioned save files for the web app: the model configuration, the
+// datasets loaded against it and the fits made to them, validated on the way
+// in so a hand-edited or stale file fails with a message instead of
+// misbehaving later.
+//
+//   {"format": "enzyme_plus/session", "version": 1,
+//    "model": {"init": [E, ES, EP, S, P, t0], "rates": [k1, k-3, k-1, k2, k-2, k3],
+//              "dt": 0.01, "steps": 1000},
+//    "datasets": [{"name": "run1", "species": "P", "times": [...], "values": [...]}],
+//    "fits": [{"dataset": "run1", "report": <report::fit_report document>}]}
+
+use crate::json::Value;
+use crate::model::{Observable, Rates, State};
+use crate::report;
+
+pub const SESSION_FORMAT: &str = "enzyme_plus/session";
+// Bumped when a field changes meaning; from_json accepts this version and older
+pub const SESSION_VERSION: f64 = 1.0;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ModelConfig {
+    pub init: State,
+    pub rates: Rates,
+    pub dt: f64,
+    pub steps: u32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dataset {
+    pub name: String,
+    pub species: Observable,
+    pub times: Vec<f64>,
+    pub values: Vec<f64>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SessionFit {
+    pub dataset: String, // name of the dataset fitted
+    pub report: Value,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Session {
+    pub model: ModelConfig,
+    pub datasets: Vec<Dataset>,
+    pub fits: Vec<SessionFit>,
+}
+
+fn member(key: &str, v: Value) -> (String, Value) { (key.to_string(), v) }
+
+fn numbers<const N: usize>(v: Option<&Value>, what: &str) -> Result<[f64; N], String> {
+    let vals = v.and_then(Value::as_f64_vec).ok_or_else(|| format!("{} must be an array of numbers", what))?;
+    if vals.len() != N { return Err(format!("{} needs {} entries", what, N)); }
+    if let Some(i) = vals.iter().position(|v| !v.is_finite()) { return Err(format!("{} entry {} is not finite", what, i)); }
+    let mut out = [0.0; N];
+    out.copy_from_slice(&vals);
+    Ok(out)
+}
+
+fn model_from_json(v: &Value) -> Result<ModelConfig, String> {
+    let init = numbers::<6>(v.get("init"), "model.init")?;
+    if init[..5].iter().any(|&c| c < 0.0) { return Err("model.init counts must be non-negative".to_string()); }
+    let rates = numbers::<6>(v.get("rates"), "model.rates")?;
+    if rates.iter().any(|&k| k < 0.0) { return Err("model.rates must be non-negative".to_string()); }
+    let dt = v.get("dt").and_then(Value::as_f64).ok_or("model.dt must be a number")?;
+    if !(dt.is_finite() && dt > 0.0) { return Err("model.dt must be positive".to_string()); }
+    let steps = v.get("steps").and_then(Value::as_f64).ok_or("model.steps must be a number")?;
+    if !(steps >= 0.0 && steps.fract() == 0.0 && steps <= u32::MAX as f64) {
+        return Err("model.steps must be a non-negative integer".to_string());
+    }
+    Ok(ModelConfig { init, rates, dt, steps: steps as u32 })
+}
+
+fn dataset_from_json(v: &Value, i: usize) -> Result<Dataset, String> {
+    let name = v.get("name").and_then(Value::as_str).ok_or_else(|| format!("datasets[{}].name must be a string", i))?;
+    let species = Observable::parse(v.get("species").and_then(Value::as_str).unwrap_or("P"))
+        .map_err(|e| format!("datasets[{}].species: {}", i, e))?;
+    let series = |key: &str| v.get(key).and_then(Value::as_f64_vec).ok_or_else(|| format!("datasets[{}].{} must be an array of numbers", i, key));
+    let (times, values) = (series("times")?, series("values")?);
+    if times.len() != values.len() {
+        return Err(format!("datasets[{}] has {} times but {} values", i, times.len(), values.len()));
+    }
+    Ok(Dataset { name: name.to_string(), species, times, values })
+}
+
+impl Session {
+    pub fn to_json(&self) -> Value {
+        let m = &self.model;
+        let model = Value::Object(vec![
+            member("init", Value::from(&m.init[..])),
+            member("rates", Value::from(&m.rates[..])),
+            member("dt", Value::from(m.dt)),
+            member("steps", Value::from(m.steps as f64)),
+        ]);
+        let datasets = self.datasets.iter().map(|d| Value::Object(vec![
+            member("name", Value::from(d.name.as_str())),
+            member("species", Value::String(d.species.to_string())),
+            member("times", Value::from(&d.times[..])),
+            member("values", Value::from(&d.values[..])),
+        ])).collect();
+        let fits = self.fits.iter().map(|f| Value::Object(vec![
+            member("dataset", Value::from(f.dataset.as_str())),
+            member("report", f.report.clone()),
+        ])).collect();
+        Value::Object(vec![
+            member("format", Value::from(SESSION_FORMAT)),
+            member("version", Value::from(SESSION_VERSION)),
+            member("model", model),
+            member("datasets", Value::Array(datasets)),
+            member("fits", Value::Array(fits)),
+        ])
+    }
+
+    // Validated session; errors name the offending field. Dataset names must
+    // be unique, and every fit must name one of them and carry a readable
+    // fit report.
+    pub fn from_json(doc: &Value) -> Result<Session, String> {
+        if doc.get("format").and_then(Value::as_str) != Some(SESSION_FORMAT) {
+            return Err(format!("not a session file (expected format \"{}\")", SESSION_FORMAT));
+        }
+        match doc.get("version").and_then(Value::as_f64) {
+            Some(v) if v <= SESSION_VERSION => {}
+            Some(v) => return Err(format!("session version {} is newer than {}", v, SESSION_VERSION)),
+            None => return Err("session has no version".to_string()),
+        }
+        let model = model_from_json(doc.get("model").ok_or("session has no model")?)?;
+        let list = |key: &str| match doc.get(key) {
+            None | Some(Value::Null) => Ok(&[][..]),
+            Some(v) => v.as_array().ok_or_else(|| format!("{} must be an array", key)),
+        };
+        let datasets = list("datasets")?.iter().enumerate().map(|(i, d)| dataset_from_json(d, i)).collect::<Result<Vec<_>, _>>()?;
+        for (i, d) in datasets.iter().enumerate() {
+            if datasets[..i].iter().any(|o| o.name == d.name) { return Err(format!("duplicate dataset name '{}'", d.name)); }
+        }
+        let mut fits = Vec::new();
+        for (i, f) in list("fits")?.iter().enumerate() {
+            let dataset = f.get("dataset").and_then(Value::as_str).ok_or_else(|| format!("fits[{}].dataset must be a string", i))?;
+            if !datasets.iter().any(|d| d.name == dataset) { return Err(format!("fits[{}] names unknown dataset '{}'", i, dataset)); }
+            let report = f.get("report").ok_or_else(|| format!("fits[{}] has no report", i))?;
+            report::params_from_report(report).map_err(|e| format!("fits[{}].report: {}", i, e))?;
+            fits.push(SessionFit { dataset: dataset.to_string(), report: report.clone() });
+        }
+        Ok(Session { model, datasets, fits })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn session_round_trip_and_validation() {
+        let text = r#"{"format":"enzyme_plus/session","version":1,
+            "model":{"init":[10,0,0,500,0,0],"rates":[0.001,0,0.1,1,0,1],"dt":0.01,"steps":100},
+            "datasets":[{"name":"a","species":"S+P","times":[1,2],"values":[5,6]}],"fits":[]}"#;
+        let session = Session::from_json(&json::parse(text).unwrap()).unwrap();
+        assert_eq!(session.datasets[0].species, Observable::parse("S+P").unwrap());
+        let again = Session::from_json(&json::parse(&session.to_json().to_string()).unwrap()).unwrap();
+        assert_eq!(again, session);
+
+        let broken = |from: &str, to: &str| Session::from_json(&json::parse(&text.replace(from, to)).unwrap());
+        assert!(broken(r#""times":[1,2]"#, r#""times":[1]"#).unwrap_err().contains("datasets[0]"));
+        assert!(broken(r#""dt":0.01"#, r#""dt":-1"#).unwrap_err().contains("model.dt"));
+        assert!(broken(r#""version":1"#, r#""version":2"#).unwrap_err().contains("newer"));
+        assert!(broken(r#""fits":[]"#, r#""fits":[{"dataset":"b","report":{}}]"#).unwrap_err().contains("unknown dataset"));
+    }
+}
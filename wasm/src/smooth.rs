@@ -0,0 +1,82 @@
+// Savitzky–Golay smoothing and differentiation by local least-squares
+// polynomials. Works on non-uniform time grids; near the edges the window is
+// shifted inward so every point uses the same number of samples.
+
+use crate::linalg;
+
+// Smoothed values and first derivatives dy/dt at every sample
+pub fn savitzky_golay(t: &[f64], y: &[f64], half_window: usize, order: usize) -> (Vec<f64>, Vec<f64>) {
+    let n = t.len().min(y.len());
+    let mut smooth = y[..n].to_vec();
+    let mut deriv = vec![0.0; n];
+    let width = (2 * half_window + 1).min(n);
+    let order = order.min(width.saturating_sub(1));
+    if n < 2 || width < 2 { return (smooth, deriv); }
+    let m = order + 1;
+    for i in 0..n {
+        let start = i.saturating_sub(half_window).min(n - width);
+        let win = start..start + width;
+        // Centre and scale the abscissa for conditioning
+        let scale = (t[win.end - 1] - t[win.start]).abs().max(1e-300);
+        let mut ata = vec![0.0; m * m];
+        let mut aty = vec![0.0; m];
+        for j in win {
+            let x = (t[j] - t[i]) / scale;
+            let mut pw = vec![1.0; m];
+            for d in 1..m { pw[d] = pw[d - 1] * x; }
+            for a in 0..m {
+                aty[a] += pw[a] * y[j];
+                for b in 0..m { ata[a * m + b] += pw[a] * pw[b]; }
+            }
+        }
+        let Some(inv) = linalg::invert(&ata, m) else { continue; };
+        let coef: Vec<f64> = (0..m).map(|a| (0..m).map(|b| inv[a * m + b] * aty[b]).sum()).collect();
+        smooth[i] = coef[0];
+        deriv[i] = if m > 1 { coef[1] / scale } else { 0.0 };
+    }
+    (smooth, deriv)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn polynomials_up_to_the_order_pass_through() {
+        let cubic = |x: f64| 1.0 - 2.0 * x + 0.5 * x * x - 0.05 * x * x * x;
+        let slope = |x: f64| -2.0 + x - 0.15 * x * x;
+        // Uniform and uneven grids, edges included
+        let uniform: Vec<f64> = (0..30).map(|i| 0.2 * i as f64).collect();
+        let uneven: Vec<f64> = (0..30).map(|i| 0.01 * (i * i) as f64 + 0.1 * i as f64).collect();
+        for t in [uniform, uneven] {
+            let y: Vec<f64> = t.iter().map(|&x| cubic(x)).collect();
+            let (s, d) = savitzky_golay(&t, &y, 3, 3);
+            for i in 0..t.len() {
+                assert!((s[i] - y[i]).abs() < 1e-9, "value at {}", t[i]);
+                assert!((d[i] - slope(t[i])).abs() < 1e-7, "slope at {}: {} vs {}", t[i], d[i], slope(t[i]));
+            }
+        }
+        // A lower order smooths the cubic instead, but keeps a line's slope
+        let t: Vec<f64> = (0..20).map(|i| i as f64).collect();
+        let line: Vec<f64> = t.iter().map(|x| 4.0 + 0.5 * x).collect();
+        let (s, d) = savitzky_golay(&t, &line, 2, 1);
+        assert!(s.iter().zip(&line).all(|(a, b)| (a - b).abs() < 1e-12));
+        assert!(d.iter().all(|v| (v - 0.5).abs() < 1e-12));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn smoothing_shrinks_noise() {
+        let t: Vec<f64> = (0..200).map(|i| 0.05 * i as f64).collect();
+        // Deterministic +-1 alternation on a line
+        let y: Vec<f64> = t.iter().enumerate().map(|(i, x)| 2.0 * x + if i % 2 == 0 { 1.0 } else { -1.0 }).collect();
+        let (s, d) = savitzky_golay(&t, &y, 5, 2);
+        let rms = |r: Vec<f64>| (r.iter().map(|v| v * v).sum::<f64>() / r.len() as f64).sqrt();
+        let interior = 5..195;
+        let residual = rms(interior.clone().map(|i| s[i] - 2.0 * t[i]).collect());
+        assert!(residual < 0.2, "{}", residual);
+        // An odd-width centred window cancels the alternation in the slope
+        let worst = interior.map(|i| (d[i] - 2.0).abs()).fold(0.0, f64::max);
+        assert!(worst < 1e-9, "{}", worst);
+    }
+}
@@ -0,0 +1,179 @@
+// Closed-form steady states of the mechanism, without time-stepping.
+//
+//   E + S <-> ES (k1, k-1),  ES <-> EP (k2, k-2),  EP <-> E + P (k3, k-3)
+//
+// With S and P held fixed the enzyme forms cycle among E, ES and EP with
+// pseudo-first-order rates, whose stationary distribution follows from the
+// King–Altman spanning trees of the three-state graph. Chemical equilibrium
+// follows from detailed balance and the enzyme and substrate totals, and the
+// reversible Michaelis–Menten law is the QSSA rate of the same cycle.
+
+use crate::model::Rates;
+use crate::ode;
+
+// Steady-state [E, ES, EP, v] for enzyme total e_total at fixed s and p, with
+// v = k3 EP - k-3 E P the net rate of product formation. All NaN when every
+// King–Altman weight vanishes (no transitions).
+pub fn steady_state(k: &Rates, e_total: f64, s: f64, p: f64) -> [f64; 4] {
+    let [k1, k_minus3, k_minus1, k2, k_minus2, k3] = *k;
+    let (bind_s, bind_p) = (k1 * s, k_minus3 * p);
+    // Trees directed into each state
+    let w_e = k_minus1 * k3 + k2 * k3 + k_minus2 * k_minus1;
+    let w_es = bind_s * k_minus2 + bind_p * k_minus2 + bind_s * k3;
+    let w_ep = bind_p * k2 + bind_s * k2 + bind_p * k_minus1;
+    let total = w_e + w_es + w_ep;
+    if !(total.is_finite() && total > 0.0) { return [f64::NAN; 4]; }
+    let (e, es, ep) = (e_total * w_e / total, e_total * w_es / total, e_total * w_ep / total);
+    [e, es, ep, k3 * ep - bind_p * e]
+}
+
+// Overall equilibrium constant [P]/[S] = k1 k2 k3 / (k-1 k-2 k-3)
+pub fn keq(k: &Rates) -> f64 {
+    let [k1, k_minus3, k_minus1, k2, k_minus2, k3] = *k;
+    k1 * k2 * k3 / (k_minus1 * k_minus2 * k_minus3)
+}
+
+// Equilibrium [E, ES, EP, S, P, Keq] with the enzyme total E + ES + EP and
+// substrate total S + ES + EP + P of y. Detailed balance gives ES = K1 E S,
+// EP = K1 K2 E S and P = Keq S (K1 = k1/k-1, K2 = k2/k-2), leaving a
+// quadratic in S. All NaN unless every constant is positive and finite: an
+// irreversible step has no equilibrium in this sense.
+pub fn equilibrium(k: &Rates, y: &ode::State) -> [f64; 6] {
+    if !k.iter().all(|v| v.is_finite() && *v > 0.0) { return [f64::NAN; 6]; }
+    let [k1, _, k_minus1, k2, k_minus2, _] = *k;
+    let [e, es, ep, s, p] = *y;
+    let (e_total, s_total) = (e + es + ep, s + es + ep + p);
+    let keq = keq(k);
+    // E = e_total / (1 + a S) with a = K1 (1 + K2)
+    let k_1 = k1 / k_minus1;
+    let a = k_1 * (1.0 + k2 / k_minus2);
+    // (1 + Keq) a S^2 + b S - s_total = 0, solved without cancellation
+    let qa = (1.0 + keq) * a;
+    let b = (1.0 + keq) + a * (e_total - s_total);
+    let disc = (b * b + 4.0 * qa * s_total).max(0.0).sqrt();
+    let s_eq = if b >= 0.0 { 2.0 * s_total / (b + disc) } else { (disc - b) / (2.0 * qa) };
+    let e_eq = e_total / (1.0 + a * s_eq);
+    let es_eq = k_1 * e_eq * s_eq;
+    [e_eq, es_eq, e_total - e_eq - es_eq, s_eq, keq * s_eq, keq]
+}
+
+// Reversible Michaelis–Menten constants [kcat_f, Km_S, kcat_r, Km_P] of the
+// three-step mechanism
+pub fn mm_constants(k: &Rates) -> [f64; 4] {
+    let [k1, k_minus3, k_minus1, k2, k_minus2, k3] = *k;
+    let num = k_minus1 * k_minus2 + k_minus1 * k3 + k2 * k3;
+    let (fwd, rev) = (k2 + k_minus2 + k3, k_minus1 + k2 + k_minus2);
+    [k2 * k3 / fwd, num / (k1 * fwd), k_minus1 * k_minus2 / rev, num / (k_minus3 * rev)]
+}
+
+// Rates [k1, k-3, k-1, k2, k-2, k3] with the constants c = [kcat_f, Km_S,
+// kcat_r, Km_P] of mm_constants and the given isomerization rates k2 and
+// k-2: the two kcats fix k3 and k-1, then the Kms fix k1 and k-3 (Km_P
+// infinite => k-3 = 0). Needs kcat_f < k2 and kcat_r < k-2, since neither
+// direction turns over faster than its isomerization; NaN otherwise.
+pub fn rates_from_mm(c: &[f64; 4], k2: f64, k_minus2: f64) -> Rates {
+    let [kcat_f, km_s, kcat_r, km_p] = *c;
+    let feasible = kcat_f >= 0.0 && kcat_f < k2 && kcat_r >= 0.0 && kcat_r < k_minus2 && km_s > 0.0 && km_p > 0.0;
+    if !feasible { return [f64::NAN; 6]; }
+    let k3 = kcat_f * (k2 + k_minus2) / (k2 - kcat_f);
+    let k_minus1 = kcat_r * (k2 + k_minus2) / (k_minus2 - kcat_r);
+    let num = k_minus1 * k_minus2 + k_minus1 * k3 + k2 * k3;
+    let k1 = num / (km_s * (k2 + k_minus2 + k3));
+    let k_minus3 = num / (km_p * (k_minus1 + k2 + k_minus2));
+    [k1, k_minus3, k_minus1, k2, k_minus2, k3]
+}
+
+// Net rate E_t (kcat_f S / Km_S - kcat_r P / Km_P) / (1 + S / Km_S + P / Km_P)
+pub fn mm_rate(c: &[f64; 4], e_total: f64, s: f64, p: f64) -> f64 {
+    let [kcat_f, km_s, kcat_r, km_p] = *c;
+    // 0 / inf terms for an unbound ligand (k1 or k-3 of 0) vanish
+    let term = |kcat: f64, km: f64, x: f64| if km.is_infinite() { (0.0, 0.0) } else { (kcat * x / km, x / km) };
+    let (vf, bs) = term(kcat_f, km_s, s);
+    let (vr, bp) = term(kcat_r, km_p, p);
+    e_total * (vf - vr) / (1.0 + bs + bp)
+}
+
+// QSSA comparison for a series whose rows start with [E, ES, EP, S, P, t]
+// (stride is the row width, 0 => 6, use 12 for flux series). Output:
+// [kcat_f, Km_S, kcat_r, Km_P] then per row [t, v_mm, v_obs, P_mm]: the
+// reversible MM rate at the row's S and P, dP/dt of the series by differences
+// (one-sided at the ends), and P of the QSSA model started from the first row
+// with S = S_total - P (complexes neglected), by RK4 between row times.
+pub fn qssa_comparison(data: &[f64], k: &Rates, stride: u32) -> Vec<f64> {
+    let w = if stride == 0 { 6 } else { (stride as usize).max(6) };
+    let rows = data.len() / w;
+    let c = mm_constants(k);
+    let mut out = c.to_vec();
+    if rows == 0 { return out; }
+    let row = |r: usize| &data[r * w..r * w + 6];
+    let e_total = |r: &[f64]| r[0] + r[1] + r[2];
+    let first = row(0);
+    let (e0, s_total) = (e_total(first), first[1] + first[2] + first[3] + first[4]);
+    let dp = |p: f64| mm_rate(&c, e0, s_total - p, p);
+    let mut p_mm = first[4];
+    out.reserve(4 * rows);
+    for r in 0..rows {
+        let cur = row(r);
+        if r > 0 {
+            // Fixed RK4 substeps over the gap since the previous row
+            let h = (cur[5] - row(r - 1)[5]) / 8.0;
+            for _ in 0..8 {
+                let (a, b) = (dp(p_mm), dp(p_mm + 0.5 * h * dp(p_mm)));
+                let cc = dp(p_mm + 0.5 * h * b);
+                let d = dp(p_mm + h * cc);
+                p_mm += h / 6.0 * (a + 2.0 * b + 2.0 * cc + d);
+            }
+        }
+        let (lo, hi) = (r.saturating_sub(1), (r + 1).min(rows - 1));
+        let span = row(hi)[5] - row(lo)[5];
+        let v_obs = if span > 0.0 { (row(hi)[4] - row(lo)[4]) / span } else { f64::NAN };
+        out.extend_from_slice(&[cur[5], mm_rate(&c, e_total(cur), cur[3], cur[4]), v_obs, p_mm]);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn steady_state_is_a_fixed_point() {
+        let k = [2.0, 0.3, 1.5, 4.0, 0.7, 3.0];
+        let (s, p) = (5.0, 2.0);
+        let [e, es, ep, v] = steady_state(&k, 1.0, s, p);
+        assert!((e + es + ep - 1.0).abs() < 1e-12);
+        let d = ode::rhs(&[e, es, ep, s, p], &k);
+        assert!(d[..3].iter().all(|x| x.abs() < 1e-12), "{:?}", d);
+        assert!((v - d[4]).abs() < 1e-12);
+        // Irreversible limit: the Michaelis–Menten rate with Km = (k-1 + k2) / k1 when k3 is fast
+        let mm = [1.0, 0.0, 1.0, 2.0, 0.0, 1e9];
+        let (km, s_mm) = (3.0, 3.0);
+        let v_mm = steady_state(&mm, 1.0, s_mm, 0.0)[3];
+        assert!((v_mm - 2.0 * s_mm / (km + s_mm)).abs() < 1e-6);
+        // The closed-form rate law is the same QSSA rate
+        assert!((mm_rate(&mm_constants(&k), 1.0, s, p) - v).abs() < 1e-12);
+        assert!((mm_rate(&mm_constants(&mm), 1.0, s_mm, 0.0) - v_mm).abs() < 1e-9);
+        // With little enzyme the QSSA trajectory tracks the full model
+        let y0 = [0.01, 0.0, 0.0, 10.0, 0.0];
+        let times: Vec<f64> = (1..=40).map(|i| i as f64 * 5.0).collect();
+        let series: Vec<f64> = ode::integrate(&y0, 0.0, &k, 0.01, &times).iter().zip(&times)
+            .flat_map(|(y, &t)| [y[0], y[1], y[2], y[3], y[4], t]).collect();
+        let cmp = qssa_comparison(&series, &k, 0);
+        assert_eq!(cmp.len(), 4 + 4 * times.len());
+        for (r, row) in cmp[4..].chunks(4).enumerate() {
+            assert!((row[3] - series[r * 6 + 4]).abs() < 0.01, "{:?} vs {}", row, series[r * 6 + 4]);
+        }
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn long_runs_end_at_equilibrium() {
+        let k = [0.5, 0.2, 1.0, 2.0, 0.5, 3.0];
+        let y0 = [2.0, 0.0, 0.0, 10.0, 0.0];
+        let eq = equilibrium(&k, &y0);
+        let end = ode::integrate(&y0, 0.0, &k, 0.01, &[200.0])[0];
+        for i in 0..5 { assert!((end[i] - eq[i]).abs() < 1e-6, "{:?} vs {:?}", end, eq); }
+        assert!((eq[4] / eq[3] - 0.5 * 2.0 * 3.0 / (1.0 * 0.5 * 0.2)).abs() < 1e-9);
+        assert!(equilibrium(&[1.0, 0.0, 1.0, 1.0, 1.0, 1.0], &y0)[0].is_nan());
+    }
+}
@@ -0,0 +1,308 @@
+// Summary statistics of simulated series buffers whose rows start with
+// [E, ES, EP, S, P, t], for front-ends plotting large runs, and diagnostics
+// of sets of progress curves.
+
+use crate::model::Observable;
+
+// Quadrature rule for auc
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AucRule {
+    Trapezoid,
+    // Composite Simpson on pairs of (possibly unequal) intervals; an odd
+    // interval left at the end is a trapezoid
+    Simpson,
+}
+
+impl AucRule {
+    // 0: trapezoid, 1: Simpson
+    pub fn from_code(code: u32) -> Result<AucRule, String> {
+        match code {
+            0 => Ok(AucRule::Trapezoid),
+            1 => Ok(AucRule::Simpson),
+            _ => Err(format!("unknown AUC rule {} (expected 0: trapezoid or 1: Simpson)", code)),
+        }
+    }
+}
+
+// Row width of a series buffer (0 => 6, at least 6)
+fn row_width(stride: u32) -> usize { if stride == 0 { 6 } else { (stride as usize).max(6) } }
+
+// Width of the summarize_series output: four values per species, then t_half(P)
+pub const SUMMARY_LEN: usize = 21;
+
+// Per species E, ES, EP, S, P: [max, time of max, final value, trapezoid AUC
+// over the series' time span], then the time P first reaches halfway from its
+// first-row value to its maximum (interpolated between rows; NaN if P never
+// rises). NaN throughout for an empty buffer.
+pub fn summarize_series(data: &[f64], stride: u32) -> [f64; SUMMARY_LEN] {
+    let w = row_width(stride);
+    let rows = data.len() / w;
+    let mut out = [f64::NAN; SUMMARY_LEN];
+    if rows == 0 { return out; }
+    let at = |r: usize, i: usize| data[r * w + i];
+    let species = [Observable::E, Observable::ES, Observable::EP, Observable::S, Observable::P];
+    for (sp, &obs) in species.iter().enumerate() {
+        let (mut max, mut t_max) = (at(0, sp), at(0, 5));
+        for r in 1..rows {
+            let v = at(r, sp);
+            if v > max { max = v; t_max = at(r, 5); }
+        }
+        let area = auc(data, stride, obs, f64::NAN, f64::NAN, AucRule::Trapezoid);
+        out[sp * 4..sp * 4 + 4].copy_from_slice(&[max, t_max, at(rows - 1, sp), area]);
+    }
+    let (p0, p_max) = (at(0, 4), out[16]);
+    if p_max > p0 {
+        let half = 0.5 * (p0 + p_max);
+        for r in 1..rows {
+            let (v0, v1) = (at(r - 1, 4), at(r, 4));
+            if v1 >= half {
+                let w = if v1 != v0 { ((half - v0) / (v1 - v0)).clamp(0.0, 1.0) } else { 1.0 };
+                out[20] = at(r - 1, 5) + w * (at(r, 5) - at(r - 1, 5));
+                break;
+            }
+        }
+    }
+    out
+}
+
+// Area under an observable over [t_from, t_to] (a non-finite bound means the
+// series' own end), with the endpoints interpolated linearly between rows.
+// 0 for an empty buffer or window.
+pub fn auc(data: &[f64], stride: u32, obs: Observable, t_from: f64, t_to: f64, rule: AucRule) -> f64 {
+    let w = row_width(stride);
+    let rows = data.len() / w;
+    if rows == 0 { return 0.0; }
+    let t = |r: usize| data[r * w + 5];
+    let v = |r: usize| obs.value(&data[r * w..r * w + 5]);
+    let lo = if t_from.is_finite() { t_from.max(t(0)) } else { t(0) };
+    let hi = if t_to.is_finite() { t_to.min(t(rows - 1)) } else { t(rows - 1) };
+    if hi.is_nan() || lo.is_nan() || hi <= lo { return 0.0; }
+    // Value at x inside the series, linearly between rows
+    let value_at = |x: f64| -> f64 {
+        let r = (1..rows).find(|&r| t(r) >= x).unwrap_or(rows - 1);
+        let h = t(r) - t(r - 1);
+        if h > 0.0 { v(r - 1) + (x - t(r - 1)) / h * (v(r) - v(r - 1)) } else { v(r) }
+    };
+    let mut pts: Vec<(f64, f64)> = vec![(lo, value_at(lo))];
+    pts.extend((0..rows).filter(|&r| t(r) > lo && t(r) < hi).map(|r| (t(r), v(r))));
+    pts.push((hi, value_at(hi)));
+
+    let trapezoid = |a: (f64, f64), b: (f64, f64)| 0.5 * (a.1 + b.1) * (b.0 - a.0);
+    match rule {
+        AucRule::Trapezoid => pts.windows(2).map(|p| trapezoid(p[0], p[1])).sum(),
+        AucRule::Simpson => {
+            let mut area = 0.0;
+            let mut i = 0;
+            while i + 2 < pts.len() {
+                let ((t0, f0), (t1, f1), (t2, f2)) = (pts[i], pts[i + 1], pts[i + 2]);
+                let (h0, h1) = (t1 - t0, t2 - t1);
+                if h0 > 0.0 && h1 > 0.0 {
+                    // Exact for the quadratic through the three points
+                    area += (h0 + h1) / 6.0
+                        * ((2.0 - h1 / h0) * f0 + (h0 + h1) * (h0 + h1) / (h0 * h1) * f1 + (2.0 - h0 / h1) * f2);
+                } else {
+                    area += trapezoid(pts[i], pts[i + 1]) + trapezoid(pts[i + 1], pts[i + 2]);
+                }
+                i += 2;
+            }
+            if i + 1 < pts.len() { area += trapezoid(pts[i], pts[i + 1]); }
+            area
+        }
+    }
+}
+
+// Times at which substrate conversion 1 - S / S0 (S0 from the first row)
+// first reaches each fraction, interpolated between rows; NaN for fractions
+// never reached, or for every fraction if S0 is not positive.
+pub fn conversion_times(data: &[f64], stride: u32, fractions: &[f64]) -> Vec<f64> {
+    let w = row_width(stride);
+    let rows = data.len() / w;
+    let mut out = vec![f64::NAN; fractions.len()];
+    if rows == 0 || data[3].is_nan() || data[3] <= 0.0 { return out; }
+    let s0 = data[3];
+    for (slot, &f) in out.iter_mut().zip(fractions) {
+        let target = (1.0 - f) * s0;
+        if data[3] <= target { *slot = data[5]; continue; }
+        for r in 1..rows {
+            let (a, b) = (&data[(r - 1) * w..], &data[r * w..]);
+            if b[3] <= target {
+                let ds = b[3] - a[3];
+                let u = if ds != 0.0 { ((target - a[3]) / ds).clamp(0.0, 1.0) } else { 1.0 };
+                *slot = a[5] + u * (b[5] - a[5]);
+                break;
+            }
+        }
+    }
+    out
+}
+
+// Lag time from the steepest tangent of P(t): the least-squares slope over
+// each run of `window` consecutive rows (at least 2; wider windows tame
+// stochastic noise), the steepest taken, and its line through the run's mean
+// point extrapolated back to the first-row P. [lag, max slope, time of the
+// tangent point]; NaN throughout if P never rises.
+pub fn lag_time(data: &[f64], stride: u32, window: u32) -> [f64; 3] {
+    let w = row_width(stride);
+    let rows = data.len() / w;
+    let n = (window as usize).max(2);
+    let mut best = [f64::NAN; 3];
+    if rows < n { return best; }
+    let (t, p) = (|r: usize| data[r * w + 5], |r: usize| data[r * w + 4]);
+    let mut max_slope = 0.0;
+    for start in 0..=rows - n {
+        let (t_mean, p_mean) = (start..start + n).fold((0.0, 0.0), |(a, b), r| (a + t(r), b + p(r)));
+        let (t_mean, p_mean) = (t_mean / n as f64, p_mean / n as f64);
+        let (sxy, sxx) = (start..start + n).fold((0.0, 0.0), |(a, b), r| {
+            let dt = t(r) - t_mean;
+            (a + dt * (p(r) - p_mean), b + dt * dt)
+        });
+        if sxx > 0.0 && sxy / sxx > max_slope {
+            max_slope = sxy / sxx;
+            best = [t_mean - (p_mean - p(0)) / max_slope, max_slope, t_mean];
+        }
+    }
+    best
+}
+
+// Selwyn's test for enzyme inactivation: progress curves run at different
+// E0 collapse onto one curve against E0 t when the enzyme is stable over the
+// assay. Curve c has lengths[c] (time, value) points of the concatenated
+// times and values and enzyme e0[c]. Each is resampled (linearly) at
+// `points` (0 => 50, at least 2) evenly spaced E0 t across the range all
+// cover. Output: [score, deviation per curve, then per grid point [E0 t,
+// value per curve]]; the deviation is the rms distance of a curve from the
+// mean curve relative to the mean curve's span, the score the largest. A few
+// percent is noise; low-E0 curves (the longest assays) falling below the
+// rest point to inactivation. All NaN with fewer than two curves or no
+// common range.
+pub fn selwyn(e0: &[f64], times: &[f64], values: &[f64], lengths: &[u32], points: u32) -> Vec<f64> {
+    let n = e0.len().min(lengths.len());
+    let points = if points == 0 { 50 } else { (points as usize).max(2) };
+    let mut out = vec![f64::NAN; 1 + n + points * (1 + n)];
+    let mut curves: Vec<Vec<(f64, f64)>> = Vec::with_capacity(n);
+    let mut at = 0;
+    for c in 0..n {
+        let len = lengths[c] as usize;
+        let end = (at + len).min(times.len()).min(values.len());
+        let mut pts: Vec<(f64, f64)> = (at.min(end)..end)
+            .map(|i| (e0[c] * times[i], values[i]))
+            .filter(|p| p.0.is_finite() && p.1.is_finite())
+            .collect();
+        pts.sort_by(|a, b| a.0.total_cmp(&b.0));
+        curves.push(pts);
+        at += len;
+    }
+    if n < 2 || curves.iter().any(|c| c.len() < 2) { return out; }
+    let lo = curves.iter().map(|c| c[0].0).fold(f64::NEG_INFINITY, f64::max);
+    let hi = curves.iter().map(|c| c[c.len() - 1].0).fold(f64::INFINITY, f64::min);
+    if hi.is_nan() || hi <= lo { return out; }
+
+    let sample = |c: &[(f64, f64)], x: f64| {
+        let j = c.partition_point(|p| p.0 < x).clamp(1, c.len() - 1);
+        let (a, b) = (c[j - 1], c[j]);
+        if b.0 > a.0 { a.1 + (x - a.0) / (b.0 - a.0) * (b.1 - a.1) } else { b.1 }
+    };
+    let grid: Vec<f64> = (0..points).map(|g| lo + (hi - lo) * g as f64 / (points - 1) as f64).collect();
+    let vals: Vec<Vec<f64>> = curves.iter().map(|c| grid.iter().map(|&x| sample(c, x)).collect()).collect();
+    let mean: Vec<f64> = (0..points).map(|g| vals.iter().map(|v| v[g]).sum::<f64>() / n as f64).collect();
+    let span = mean.iter().fold(f64::NEG_INFINITY, |a, &b| a.max(b)) - mean.iter().fold(f64::INFINITY, |a, &b| a.min(b));
+    let mut score: f64 = 0.0;
+    for c in 0..n {
+        let ms = (0..points).map(|g| (vals[c][g] - mean[g]).powi(2)).sum::<f64>() / points as f64;
+        let dev = if span > 0.0 { ms.sqrt() / span } else { 0.0 };
+        out[1 + c] = dev;
+        score = score.max(dev);
+    }
+    out[0] = score;
+    for g in 0..points {
+        let row = &mut out[1 + n + g * (1 + n)..1 + n + (g + 1) * (1 + n)];
+        row[0] = grid[g];
+        for c in 0..n { row[1 + c] = vals[c][g]; }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn summary_of_a_linear_ramp() {
+        // P rises 0, 2, 4, 6 over t = 0..3 while S falls; 8-wide rows carry extra columns
+        let data: Vec<f64> = (0..4).flat_map(|i| {
+            let t = i as f64;
+            [1.0, 0.0, 0.0, 6.0 - 2.0 * t, 2.0 * t, t, 99.0, 99.0]
+        }).collect();
+        let s = summarize_series(&data, 8);
+        assert_eq!(s[12..16], [6.0, 0.0, 0.0, 9.0]);
+        assert_eq!(s[16..20], [6.0, 3.0, 6.0, 9.0]);
+        assert_eq!(s[0..4], [1.0, 0.0, 1.0, 3.0]);
+        assert_eq!(s[20], 1.5);
+        assert!(summarize_series(&[], 0)[0].is_nan());
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn simpson_is_exact_for_quadratics() {
+        // P = t^2 on an uneven grid; 1..3 has two rows past the clipped start
+        let times = [0.0, 0.5, 1.25, 2.0, 3.0, 3.5];
+        let data: Vec<f64> = times.iter().flat_map(|&t| [0.0, 0.0, 0.0, 1.0, t * t, t]).collect();
+        let simpson = auc(&data, 0, Observable::P, f64::NAN, f64::NAN, AucRule::Simpson);
+        assert!((simpson - 3.5f64.powi(3) / 3.0).abs() < 0.05, "{}", simpson);
+        let full = auc(&data[..30], 0, Observable::P, f64::NAN, f64::NAN, AucRule::Simpson);
+        assert!((full - 9.0).abs() < 1e-12, "{}", full);
+        let s_plus_p = auc(&data, 0, Observable::parse("S+P").unwrap(), 1.0, 2.0, AucRule::Trapezoid);
+        assert!(s_plus_p > 1.0 + 7.0 / 3.0);
+        assert_eq!(auc(&data, 0, Observable::P, 2.0, 1.0, AucRule::Trapezoid), 0.0);
+        assert!(AucRule::from_code(2).is_err());
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn conversion_times_interpolate_falling_substrate() {
+        // S falls 100, 80, 40, 20 at t = 0, 1, 2, 3
+        let data: Vec<f64> = [100.0, 80.0, 40.0, 20.0].iter().enumerate()
+            .flat_map(|(i, &s)| [1.0, 0.0, 0.0, s, 100.0 - s, i as f64]).collect();
+        let t = conversion_times(&data, 0, &[0.0, 0.1, 0.5, 0.9]);
+        assert_eq!(t[..3], [0.0, 0.5, 1.75]);
+        assert!(t[3].is_nan());
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn lag_from_a_delayed_ramp() {
+        // P flat until t = 4, then rising 3 per unit time
+        let data: Vec<f64> = (0..12).flat_map(|i| {
+            let t = i as f64;
+            [1.0, 0.0, 0.0, 0.0, 10.0 + 3.0 * (t - 4.0).max(0.0), t]
+        }).collect();
+        let [lag, slope, _] = lag_time(&data, 0, 3);
+        assert!((lag - 4.0).abs() < 1e-12 && (slope - 3.0).abs() < 1e-12, "{} {}", lag, slope);
+        assert!(lag_time(&data[..24], 0, 3)[0].is_nan());
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn selwyn_separates_unstable_enzyme() {
+        let k = [1e-2, 0.0, 1.0, 2.0, 0.0, 5.0];
+        let curve = |e0: f64, decay: f64| -> (Vec<f64>, Vec<f64>) {
+            // Decaying activity runs the stable kinetics on the clock (1 - e^-at) / a
+            let times: Vec<f64> = (1..=40).map(|i| i as f64 * 2.0 / e0).collect();
+            let clock: Vec<f64> = times.iter().map(|&t| if decay > 0.0 { -(-decay * t).exp_m1() / decay } else { t }).collect();
+            let p = crate::ode::integrate(&[e0, 0.0, 0.0, 100.0, 0.0], 0.0, &k, 1e-3, &clock).iter().map(|y| y[4]).collect();
+            (times, p)
+        };
+        let run = |decay: f64| {
+            let (mut times, mut values) = (Vec::new(), Vec::new());
+            for e0 in [0.5, 1.0, 2.0] {
+                let (t, v) = curve(e0, decay);
+                times.extend(t);
+                values.extend(v);
+            }
+            selwyn(&[0.5, 1.0, 2.0], &times, &values, &[40, 40, 40], 20)
+        };
+        let stable = run(0.0);
+        assert_eq!(stable.len(), 1 + 3 + 20 * 4);
+        assert!(stable[0] < 0.02, "{}", stable[0]);
+        let unstable = run(0.05);
+        assert!(unstable[0] > 5.0 * stable[0].max(1e-3), "{} vs {}", unstable[0], stable[0]);
+        assert!(selwyn(&[1.0], &[1.0, 2.0], &[0.0, 1.0], &[2], 0)[0].is_nan());
+    }
+}
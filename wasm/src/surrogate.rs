@@ -0,0 +1,266 @@
+// Surrogate-assisted local fitting for the noisy stochastic objective. A
+// quadratic regression on the points evaluated so far stands in for the
+// objective inside a trust region and each iteration spends one forward
+// simulation at its minimizer. Regressing over many points averages out the
+// run-to-run noise that makes Nelder–Mead's pairwise comparisons unreliable,
+// so a fit needs far fewer simulations.
+
+use crate::fit::{FitResult, FIT_CANCELLED, FIT_MAX_EVALS, FIT_MAX_ITER, FIT_NOTHING_TO_DO, FIT_X_CONVERGED};
+use crate::linalg;
+use crate::objective::{lower_bound, Params, Problem};
+use crate::parallel;
+use crate::rng::rand_f64;
+
+// Largest trust radius, relative to the starting values
+const MAX_RADIUS: f64 = 4.0;
+
+#[derive(Clone, Debug)]
+pub struct Surrogate {
+    pub max_iter: u32,
+    // Forward simulations allowed in total, including the initial design
+    // (1 + n + n(n+1)/2 + n points for n fitted parameters); 0 => unlimited
+    pub max_evals: u32,
+    // Initial trust radius, relative to each starting value (absolute where it is 0)
+    pub scale: f64,
+    // Stop once the trust radius shrinks below x_tol (relative); <= 0 => 1e-4
+    pub x_tol: f64,
+    pub progress_every: u32, // 0 => every iteration
+    // Record the center objective and parameters at every iteration
+    pub trace: bool,
+}
+
+// Quadratic model f(center + d) ~ c + g.d + d.H.d / 2
+struct Quadratic {
+    c: f64,
+    g: Vec<f64>,
+    h: Vec<f64>, // row-major n x n
+    rms: f64, // residual rms of the regression, an estimate of the noise in f
+}
+
+impl Quadratic {
+    fn at(&self, d: &[f64]) -> f64 {
+        let n = d.len();
+        let mut v = self.c;
+        for i in 0..n {
+            v += self.g[i] * d[i];
+            for j in 0..n { v += 0.5 * d[i] * self.h[i * n + j] * d[j]; }
+        }
+        v
+    }
+}
+
+fn norm(v: &[f64]) -> f64 { v.iter().map(|x| x * x).sum::<f64>().sqrt() }
+
+// Least-squares quadratic through the points nearest center, in coordinates
+// scaled by radius for conditioning; None with fewer points than terms
+fn fit_quadratic(points: &[(Vec<f64>, f64)], center: &[f64], radius: f64) -> Option<Quadratic> {
+    let n = center.len();
+    let terms = 1 + n + n * (n + 1) / 2;
+    if points.len() < terms { return None; }
+    let dist = |z: &[f64]| z.iter().zip(center).map(|(a, b)| (a - b) * (a - b)).sum::<f64>();
+    let mut near: Vec<&(Vec<f64>, f64)> = points.iter().filter(|p| p.1.is_finite()).collect();
+    if near.len() < terms { return None; }
+    near.sort_by(|a, b| dist(&a.0).partial_cmp(&dist(&b.0)).unwrap_or(std::cmp::Ordering::Equal));
+    near.truncate(4 * terms);
+
+    let m = near.len();
+    let mut phi = vec![0.0; m * terms];
+    for (r, (z, _)) in near.iter().enumerate() {
+        let d: Vec<f64> = (0..n).map(|j| (z[j] - center[j]) / radius).collect();
+        let row = &mut phi[r * terms..(r + 1) * terms];
+        row[0] = 1.0;
+        row[1..=n].copy_from_slice(&d);
+        let mut c = 1 + n;
+        for i in 0..n {
+            for j in i..n {
+                row[c] = if i == j { 0.5 * d[i] * d[i] } else { d[i] * d[j] };
+                c += 1;
+            }
+        }
+    }
+    let mut a = linalg::gram(&phi, m, terms);
+    let ridge = 1e-10 * (0..terms).map(|i| a[i * terms + i]).sum::<f64>() / terms as f64;
+    for i in 0..terms { a[i * terms + i] += ridge; }
+    let inv = linalg::invert(&a, terms)?;
+    let mut rhs = vec![0.0; terms];
+    for (r, (_, f)) in near.iter().enumerate() {
+        for t in 0..terms { rhs[t] += phi[r * terms + t] * f; }
+    }
+    let beta: Vec<f64> = (0..terms).map(|i| (0..terms).map(|j| inv[i * terms + j] * rhs[j]).sum()).collect();
+    let sse: f64 = near.iter().enumerate()
+        .map(|(r, (_, f))| (f - (0..terms).map(|t| phi[r * terms + t] * beta[t]).sum::<f64>()).powi(2))
+        .sum();
+
+    // Back to unscaled steps
+    let g = (0..n).map(|j| beta[1 + j] / radius).collect();
+    let mut h = vec![0.0; n * n];
+    let mut c = 1 + n;
+    for i in 0..n {
+        for j in i..n {
+            h[i * n + j] = beta[c] / (radius * radius);
+            h[j * n + i] = h[i * n + j];
+            c += 1;
+        }
+    }
+    Some(Quadratic { c: beta[0], g, h, rms: (sse / (m - terms).max(1) as f64).sqrt() })
+}
+
+// Step minimizing the model within radius: the Newton step when the model is
+// convex and it fits, else steepest descent to the boundary
+fn trust_step(q: &Quadratic, radius: f64) -> Vec<f64> {
+    let n = q.g.len();
+    if linalg::cholesky(&q.h, n).is_some() {
+        if let Some(inv) = linalg::invert(&q.h, n) {
+            let d: Vec<f64> = (0..n).map(|i| -(0..n).map(|j| inv[i * n + j] * q.g[j]).sum::<f64>()).collect();
+            let len = norm(&d);
+            if len <= radius { return d; }
+            if len.is_finite() { return d.iter().map(|v| v * radius / len).collect(); }
+        }
+    }
+    let len = norm(&q.g);
+    if len > 0.0 && len.is_finite() { q.g.iter().map(|v| -v * radius / len).collect() } else { random_direction(n, radius) }
+}
+
+fn random_direction(n: usize, radius: f64) -> Vec<f64> {
+    let d: Vec<f64> = (0..n).map(|_| 2.0 * rand_f64() - 1.0).collect();
+    let len = norm(&d).max(1e-300);
+    d.iter().map(|v| v * radius / len).collect()
+}
+
+// Minimize the problem objective over params[optimize_idx] with a quadratic
+// surrogate in a trust region, starting from params. progress is called as
+// (iteration, center objective, center params) every opts.progress_every
+// iterations; returning true stops the fit. Reasons are the fit::FIT_*
+// codes; spread is the surrogate's residual rms, simplex the final center.
+pub fn surrogate_fit(
+    problem: &Problem,
+    params: Params,
+    optimize_idx: &[usize],
+    opts: &Surrogate,
+    mut progress: impl FnMut(u32, f64, &Params) -> bool,
+) -> FitResult {
+    let n = optimize_idx.len();
+    if n == 0 {
+        let sse = problem.objective(&params);
+        return FitResult::single_point(params, sse, 0, 1, 0.0, FIT_NOTHING_TO_DO, Vec::new());
+    }
+
+    // Coordinates z relative to the start: x = x0 + w z
+    let x0: Vec<f64> = optimize_idx.iter().map(|&i| params[i]).collect();
+    let w: Vec<f64> = x0.iter().map(|v| if v.abs() > 0.0 { v.abs() } else { 1.0 }).collect();
+    let at = |z: &[f64]| -> Params {
+        let mut trial = params;
+        for (j, &idx) in optimize_idx.iter().enumerate() { trial[idx] = (x0[j] + w[j] * z[j]).max(lower_bound(idx)); }
+        trial[6] = trial[6].max(1e-12);
+        problem.constrained(&trial)
+    };
+    let lo: Vec<f64> = optimize_idx.iter().enumerate().map(|(j, &i)| (lower_bound(i) - x0[j]) / w[j]).collect();
+    let budget = if opts.max_evals > 0 { opts.max_evals as usize } else { usize::MAX };
+    let x_tol = if opts.x_tol > 0.0 { opts.x_tol } else { 1e-4 };
+    let mut radius = if opts.scale.is_finite() && opts.scale > 0.0 { opts.scale } else { 0.1 };
+
+    // Initial design: the start, the axis points at +-radius, then random
+    // points in the box up to n more than the model has terms
+    let terms = 1 + n + n * (n + 1) / 2;
+    let mut design = vec![vec![0.0; n]];
+    for j in 0..n {
+        for sign in [1.0, -1.0] {
+            let mut z = vec![0.0; n];
+            z[j] = sign * radius;
+            design.push(z);
+        }
+    }
+    while design.len() < terms + n { design.push((0..n).map(|_| (2.0 * rand_f64() - 1.0) * radius).collect()); }
+    design.truncate(budget);
+    let fs = parallel::par_map(design.len(), |i| problem.objective(&at(&design[i])));
+    let mut points: Vec<(Vec<f64>, f64)> = design.into_iter().zip(fs).collect();
+
+    let mut center = vec![0.0; n];
+    let mut f_center = points[0].1;
+    let mut spread = f64::NAN;
+    let mut trace = Vec::new();
+    let mut iter = 0;
+    let mut reason = FIT_MAX_ITER;
+    while iter < opts.max_iter {
+        let reporting = iter % opts.progress_every.max(1) == 0;
+        if opts.trace { trace.push((f_center, at(&center))); }
+        if reporting && progress(iter, f_center, &at(&center)) { reason = FIT_CANCELLED; break; }
+        if radius < x_tol { reason = FIT_X_CONVERGED; break; }
+        if points.len() >= budget { reason = FIT_MAX_EVALS; break; }
+
+        let model = fit_quadratic(&points, &center, radius);
+        let d = match &model {
+            Some(q) => trust_step(q, radius),
+            None => random_direction(n, radius),
+        };
+        // Stay inside the parameter bounds
+        let z: Vec<f64> = (0..n).map(|j| (center[j] + d[j]).max(lo[j])).collect();
+        let d: Vec<f64> = (0..n).map(|j| z[j] - center[j]).collect();
+        let f = problem.objective(&at(&z));
+        points.push((z.clone(), f));
+
+        // Judge the step on the refitted (noise-averaged) model rather than
+        // on single noisy values, turning down samples clearly worse than
+        // the center; only a step that also sampled better grows the region.
+        // An accepted center keeps the model's value there, not its sample.
+        let (improved, f_new) = match fit_quadratic(&points, &center, radius) {
+            Some(q) => {
+                spread = q.rms;
+                let f_model = q.at(&d);
+                (f_model < q.c && f <= f_center + q.rms, f_model)
+            }
+            None => (f < f_center, f),
+        };
+        if improved && f.is_finite() && f_new.is_finite() {
+            if f < f_center && norm(&d) >= 0.99 * radius { radius = (2.0 * radius).min(MAX_RADIUS); }
+            center = z;
+            f_center = f_new;
+        } else {
+            radius *= 0.7;
+        }
+        iter += 1;
+    }
+    let evaluations = points.len() as u32;
+    FitResult::single_point(at(&center), f_center, iter, evaluations, spread, reason, trace)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objective::tests::synthetic_problem;
+    use crate::objective::{params_from_slice, SIGNAL_OFFSET};
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn surrogate_recovers_kcat_within_budget() {
+        crate::rng::seed_rng(21);
+        let init = [100.0, 0.0, 0.0, 5000.0, 0.0, 0.0];
+        let truth = params_from_slice(&[1e-3, 0.0, 0.1, 1.0, 0.0, 1.0, 0.05], &init);
+        let problem = synthetic_problem(init, &truth, (1..=20).map(|i| 2.0 * i as f64).collect());
+        let mut start = truth;
+        start[3] = 0.6;
+        let opts = Surrogate { max_iter: 200, max_evals: 60, scale: 0.3, x_tol: 1e-3, progress_every: 0, trace: false };
+        let res = surrogate_fit(&problem, start, &[3], &opts, |_, _, _| false);
+        assert!(res.evaluations <= 60);
+        assert!((res.params[3] - 1.0).abs() < 0.15, "k2 = {}", res.params[3]);
+        assert!(res.sse < problem.objective(&start));
+    }
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn reported_sse_is_the_model_value_at_the_center() {
+        // No rates, so the loss is exactly quadratic in the offset: the
+        // model value the fit reports is then the objective there, up to
+        // the regression's ridge
+        crate::rng::seed_rng(4);
+        let init = [10.0, 0.0, 0.0, 1000.0, 0.0, 0.0];
+        let y: Vec<f64> = [19.5, 20.8, 20.1, 19.2, 20.4].to_vec();
+        let problem = crate::objective::tests::problem(init, (1..=5).map(f64::from).collect(), y);
+        let mut start = params_from_slice(&[0.0; 7], &init);
+        start[6] = 0.1;
+        start[SIGNAL_OFFSET] = 25.0;
+        let opts = Surrogate { max_iter: 40, max_evals: 0, scale: 0.2, x_tol: 1e-6, progress_every: 0, trace: false };
+        let res = surrogate_fit(&problem, start, &[SIGNAL_OFFSET], &opts, |_, _, _| false);
+        assert!((res.params[SIGNAL_OFFSET] - 20.0).abs() < 1e-3, "offset = {}", res.params[SIGNAL_OFFSET]);
+        assert!((res.sse - problem.objective(&res.params)).abs() < 1e-4 * res.sse, "{} vs {}", res.sse, problem.objective(&res.params));
+    }
+}
@@ -0,0 +1,101 @@
+// Unit parsing and conversion at the API boundary. The engine's canonical
+// units are seconds and µM: first-order constants in s^-1, second-order
+// constants (k1, k-3) in µM^-1 s^-1.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TimeUnit { Second, Minute, Hour }
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConcUnit { Molar, Millimolar, Micromolar, Nanomolar }
+
+impl TimeUnit {
+    pub fn parse(s: &str) -> Result<TimeUnit, String> {
+        match s.trim() {
+            "s" | "sec" | "second" | "seconds" => Ok(TimeUnit::Second),
+            "min" | "minute" | "minutes" => Ok(TimeUnit::Minute),
+            "h" | "hr" | "hour" | "hours" => Ok(TimeUnit::Hour),
+            other => Err(format!("unknown time unit '{}' (expected s, min or h)", other)),
+        }
+    }
+
+    // Seconds per unit
+    pub fn seconds(self) -> f64 {
+        match self { TimeUnit::Second => 1.0, TimeUnit::Minute => 60.0, TimeUnit::Hour => 3600.0 }
+    }
+}
+
+impl ConcUnit {
+    pub fn parse(s: &str) -> Result<ConcUnit, String> {
+        match s.trim() {
+            "M" => Ok(ConcUnit::Molar),
+            "mM" => Ok(ConcUnit::Millimolar),
+            "uM" | "µM" | "μM" => Ok(ConcUnit::Micromolar),
+            "nM" => Ok(ConcUnit::Nanomolar),
+            other => Err(format!("unknown concentration unit '{}' (expected M, mM, uM or nM)", other)),
+        }
+    }
+
+    // µM per unit
+    pub fn micromolar(self) -> f64 {
+        match self {
+            ConcUnit::Molar => 1e6,
+            ConcUnit::Millimolar => 1e3,
+            ConcUnit::Micromolar => 1.0,
+            ConcUnit::Nanomolar => 1e-3,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Units {
+    pub time: TimeUnit,
+    pub conc: ConcUnit,
+}
+
+impl Units {
+    pub fn parse(time: &str, conc: &str) -> Result<Units, String> {
+        Ok(Units { time: TimeUnit::parse(time)?, conc: ConcUnit::parse(conc)? })
+    }
+
+    // [k1,k-3,k-1,k2,k-2,k3,dt] in these units -> canonical (s, µM).
+    // Rejects negative or non-finite constants so unit mix-ups surface early.
+    pub fn canonical_params(&self, p: &[f64; 7]) -> Result<[f64; 7], String> {
+        for (i, v) in p.iter().enumerate() {
+            if !v.is_finite() || *v < 0.0 {
+                return Err(format!("parameter {} must be finite and non-negative, got {}", i, v));
+            }
+        }
+        let per_s = 1.0 / self.time.seconds();
+        let per_um = 1.0 / self.conc.micromolar();
+        let mut out = *p;
+        for (i, v) in out.iter_mut().enumerate() {
+            *v *= match i {
+                0 | 1 => per_s * per_um, // second-order: conc^-1 time^-1
+                6 => self.time.seconds(), // dt
+                _ => per_s,
+            };
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn second_order_constants_scale_with_concentration() {
+        let units = Units::parse("min", "mM").unwrap();
+        let out = units.canonical_params(&[6.0, 12.0, 60.0, 120.0, 0.0, 30.0, 0.5]).unwrap();
+        // mM^-1 min^-1 -> µM^-1 s^-1 divides by 1e3 * 60; min^-1 -> s^-1 by 60
+        let expected = [1e-4, 2e-4, 1.0, 2.0, 0.0, 0.5, 30.0];
+        for (o, e) in out.iter().zip(&expected) { assert!((o - e).abs() < 1e-15 * e.max(1.0), "{:?}", out); }
+        let nano = Units::parse("s", "nM").unwrap().canonical_params(&[1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0]).unwrap();
+        assert_eq!(nano, [1e3, 1e3, 1.0, 1.0, 1.0, 1.0, 1.0]);
+        assert!(units.canonical_params(&[-1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.1]).is_err());
+        assert!(units.canonical_params(&[f64::NAN, 0.0, 0.0, 0.0, 0.0, 0.0, 0.1]).is_err());
+        assert!(Units::parse("day", "uM").is_err());
+        assert_eq!(ConcUnit::parse("µM"), Ok(ConcUnit::Micromolar));
+    }
+}
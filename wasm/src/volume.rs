@@ -0,0 +1,66 @@
+// Concentration <-> molecule-count conversion for a reaction volume.
+//
+// Concentrations are in µM and second-order constants (k1, k-3) in µM^-1 s^-1;
+// the stochastic engine works in molecules and per-molecule propensities, so
+// both are scaled by NA·V.
+
+pub const AVOGADRO: f64 = 6.022_140_76e23;
+
+#[derive(Clone, Copy)]
+pub struct Volume {
+    molecules_per_um: f64,
+}
+
+impl Volume {
+    // None for a non-positive or non-finite volume
+    pub fn litres(v: f64) -> Option<Volume> {
+        if v.is_finite() && v > 0.0 { Some(Volume { molecules_per_um: 1e-6 * AVOGADRO * v }) } else { None }
+    }
+
+    pub fn to_count(self, c_um: f64) -> f64 { (c_um * self.molecules_per_um).round().max(0.0) }
+
+    pub fn to_conc(self, n: f64) -> f64 { n / self.molecules_per_um }
+
+    // [E, ES, EP, S, P, t] in µM -> molecules (time untouched)
+    pub fn state_to_counts(self, st: &[f64; 6]) -> [f64; 6] {
+        let mut out = *st;
+        for v in out.iter_mut().take(5) { *v = self.to_count(*v); }
+        out
+    }
+
+    // [E, ES, EP, S, P, t] in molecules -> µM (time untouched)
+    pub fn state_to_conc(self, st: &[f64]) -> Vec<f64> {
+        st.iter().enumerate().map(|(i, &v)| if i < 5 { self.to_conc(v) } else { v }).collect()
+    }
+
+    // Macroscopic [k1, k-3, k-1, k2, k-2, k3] -> stochastic per-molecule constants
+    pub fn rates_to_stochastic(self, k: &[f64; 6]) -> [f64; 6] {
+        let mut out = *k;
+        out[0] /= self.molecules_per_um;
+        out[1] /= self.molecules_per_um;
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn second_order_constants_scale_with_the_volume() {
+        // 1 fL holds about 602 molecules per µM
+        let v = Volume::litres(1e-15).unwrap();
+        let per_um = 1e-21 * AVOGADRO;
+        let k = v.rates_to_stochastic(&[2.0, 0.5, 1.0, 3.0, 0.1, 4.0]);
+        assert!((k[0] - 2.0 / per_um).abs() < 1e-15 && (k[1] - 0.5 / per_um).abs() < 1e-15);
+        assert_eq!(k[2..], [1.0, 3.0, 0.1, 4.0]);
+        // A bimolecular propensity is the same rate in either unit system
+        let (e, s) = (0.1, 5.0);
+        let (ne, ns) = (v.to_count(e), v.to_count(s));
+        assert!((k[0] * ne * ns / per_um - 2.0 * (ne / per_um) * (ns / per_um)).abs() < 1e-12);
+        assert_eq!(v.state_to_counts(&[0.1, 0.0, 0.0, 5.0, 0.0, 7.0]), [60.0, 0.0, 0.0, 3011.0, 0.0, 7.0]);
+        assert!((v.to_conc(v.to_count(5.0)) - 5.0).abs() < 1e-3);
+        assert!(Volume::litres(0.0).is_none() && Volume::litres(f64::NAN).is_none());
+    }
+}
@@ -0,0 +1,88 @@
+// Non-fatal conditions met while simulating, collected as flags on the calling
+// thread (parallel::par_map forwards its workers' flags) until taken, so
+// results stay unchanged and callers ask for the warnings next to them.
+
+use std::cell::Cell;
+
+// Set of raised conditions
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Warnings(u32);
+
+impl Warnings {
+    pub const DT_CLAMPED: Warnings = Warnings(1); // invalid dt replaced by 1
+    pub const P_TOT_HIGH: Warnings = Warnings(1 << 1); // a channel p_tot above P_TOT_WARN
+    pub const COUNT_ZERO: Warnings = Warnings(1 << 2); // a negative count clamped to 0, or a draw cut by its pool
+    pub const RESOURCE_CAPPED: Warnings = Warnings(1 << 3); // binding draws capped by the S or P available
+
+    const NAMES: [&'static str; 4] = ["dt_clamped", "p_tot_high", "count_zero", "resource_capped"];
+
+    pub fn bits(self) -> u32 { self.0 }
+
+    pub fn from_bits(bits: u32) -> Warnings { Warnings(bits & ((1 << Self::NAMES.len()) - 1)) }
+
+    pub fn is_empty(self) -> bool { self.0 == 0 }
+
+    pub fn contains(self, other: Warnings) -> bool { self.0 & other.0 == other.0 }
+
+    // Names of the raised conditions, in flag order
+    pub fn names(self) -> Vec<&'static str> {
+        (0..Self::NAMES.len()).filter(|i| self.0 & (1 << i) != 0).map(|i| Self::NAMES[i]).collect()
+    }
+}
+
+// Per-channel p_tot above which a step is reported as coarse
+pub const P_TOT_WARN: f64 = 0.1;
+
+thread_local! {
+    static RAISED: Cell<u32> = const { Cell::new(0) };
+}
+
+#[inline]
+pub(crate) fn raise(w: Warnings) { RAISED.with(|r| r.set(r.get() | w.0)); }
+
+// Conditions raised on this thread since the last take
+pub fn peek() -> Warnings { Warnings(RAISED.with(Cell::get)) }
+
+// peek, clearing them
+pub fn take() -> Warnings { Warnings(RAISED.with(|r| r.replace(0))) }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{clamp_dt, run_final, run_final_integer, Overflow};
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test(unsupported = test)]
+    fn runs_raise_flags_until_taken() {
+        take();
+        assert_eq!(clamp_dt(f64::NAN), 1.0);
+        assert_eq!(clamp_dt(0.5), 0.5);
+        assert_eq!(peek(), Warnings::DT_CLAMPED);
+        assert_eq!(take().names(), ["dt_clamped"]);
+        assert!(take().is_empty());
+        // Flags raised on par_map workers reach the caller
+        crate::parallel::par_map(8, |_| clamp_dt(-1.0));
+        assert_eq!(take(), Warnings::DT_CLAMPED);
+        // A coarse step on 3 substrate molecules: p_tot is high and binding
+        // is capped by S, which runs out exactly, so nothing is clamped
+        let k = [1.0, 0.0, 0.0, 0.0, 0.0, 0.0];
+        crate::rng::seed_rng(3);
+        run_final([1000.0, 0.0, 0.0, 3.0, 0.0, 0.0], &k, 1.0, 1);
+        assert_eq!(take().names(), ["p_tot_high", "resource_capped"]);
+        // 2.6 E rounds to a pool of 3: all bind and E goes negative
+        crate::rng::seed_rng(3);
+        assert_eq!(run_final([2.6, 0.0, 0.0, 1000.0, 0.0, 0.0], &k, 1.0, 1)[0], 0.0);
+        assert_eq!(take().names(), ["p_tot_high", "count_zero"]);
+        // On exact counts the capped draw is the one reported
+        crate::rng::seed_rng(3);
+        run_final_integer([1000.0, 0.0, 0.0, 3.0, 0.0, 0.0], &k, 1.0, 1, Overflow::default());
+        assert_eq!(take().names(), ["p_tot_high", "count_zero", "resource_capped"]);
+        // ES and then EP running out in a complete integral run is not reported
+        crate::rng::seed_rng(3);
+        let end = run_final([0.0, 10.0, 0.0, 0.0, 0.0, 0.0], &[0.0, 0.0, 0.0, 1.0, 0.0, 1.0], 0.01, 5000);
+        assert_eq!(end[..5], [10.0, 0.0, 0.0, 0.0, 10.0]);
+        assert!(take().is_empty());
+        assert_eq!(Warnings::from_bits(0xff).bits(), 0b1111);
+        assert_eq!(Warnings::from_bits(0b101).names(), ["dt_clamped", "count_zero"]);
+    }
+}
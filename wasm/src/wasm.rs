@@ -0,0 +1,2084 @@
+// wasm-bindgen exports: flat f64 argument lists and Float64Array outputs over
+// the native core. Rate arguments are always k1, k-3, k-1, k2, k-2, k3.
+
+// The flat argument lists mirror the JS call sites
+#![allow(clippy::too_many_arguments)]
+
+use wasm_bindgen::prelude::*;
+use js_sys::Float64Array;
+
+use crate::bench;
+use crate::design;
+use crate::ensemble;
+use crate::fit::{self, FitResult};
+use crate::interp::Interp;
+use crate::model::{self, run_final, run_final_counts, run_series_fluxes, Observable, Rates};
+use crate::objective::{self, Constraint, Priors, Problem, RateSpace};
+use crate::{branched, burst, cascade, compartment, competition, conditions, coupled, guess, inhibition, json, lbfgs, limits, ode, pareto, powell, report, session, smooth, steady, summary, surrogate, units, volume};
+
+// Split a [k1,k-3,k-1,k2,k-2,k3,dt, ..] vector into rates and dt
+fn split_params(params_in: &Float64Array) -> (Rates, f64) {
+    let mut params = [0.0f64; 7];
+    let n = (params_in.length() as usize).min(7);
+    params_in.subarray(0, n as u32).copy_to(&mut params[..n]);
+    let mut k = [0.0f64; 6];
+    k.copy_from_slice(&params[..6]);
+    (k, params[6])
+}
+
+// Observable from a species name or sum ("P", "S+P", "ES+EP") or one of the
+// original numeric codes (0:S, 1:P, 2:E, 3:ES, 4:EP)
+fn observable_from_js(species: &JsValue) -> Result<Observable, JsValue> {
+    let parsed = match (species.as_string(), species.as_f64()) {
+        (Some(name), _) => Observable::parse(&name),
+        (None, Some(code)) if code >= 0.0 && code.fract() == 0.0 => Observable::from_code(code as u32),
+        _ => Err("species must be a name such as \"P\" or \"S+P\", or a code 0-4".to_string()),
+    };
+    parsed.map_err(|e| JsValue::from_str(&e))
+}
+
+fn loss_from_code(code: u32) -> Result<objective::Loss, JsValue> {
+    objective::Loss::from_code(code).map_err(|e| JsValue::from_str(&e))
+}
+
+// Fitting constraints from rows of [kind, a, b, value]: kind 0 holds
+// params[a] = value * params[b], kind 1 the Haldane relation with Keq value
+// solved for rate constant a, kind 2 ties params[a] to params[b]
+fn constraints_from_rows(rows: &[f64]) -> Result<Vec<Constraint>, JsValue> {
+    rows.chunks(4).map(|r| {
+        let c = match *r {
+            [0.0, a, b, value] => Constraint::Ratio { num: a as usize, den: b as usize, ratio: value },
+            [1.0, a, _, value] => Constraint::Haldane { keq: value, solve: a as usize },
+            [2.0, a, b, _] => Constraint::Tie { param: a as usize, to: b as usize },
+            _ => return Err(JsValue::from_str("constraints must be rows of [kind (0: ratio, 1: Haldane, 2: tie), a, b, value]")),
+        };
+        c.check().map_err(|e| JsValue::from_str(&e))?;
+        Ok(c)
+    }).collect()
+}
+
+fn space_from_js(code: u32) -> Result<RateSpace, JsValue> {
+    RateSpace::from_code(code).map_err(|e| JsValue::from_str(&e))
+}
+
+// Settings of the fit exports, read once from a plain JS object whose keys
+// are the snake_case names below; undefined or null (the whole object or one
+// key) leaves the default, and unknown keys are errors. Each export reads
+// the keys of its fitter and ignores the rest.
+//
+// The problem, shared by every fit export:
+//   interp_code   0: linear (default), 1: monotone cubic (PCHIP), 2: exact
+//                 (simulate onto the times)
+//   loss_code     0: sse (default), 1: Gaussian (sigma profiled out), 2:
+//                 Poisson NLL, 3: Huber, 4: soft-L1
+//   loss_scale    residual scale delta of the robust losses (default 1)
+//   prior_mean, prior_sd   log-normal priors per parameter: means in linear
+//                 units, log-space sds; the objective includes the penalty
+//   constraints   rows of 4: [0, num, den, ratio] fixes params[num] /
+//                 params[den]; [1, solve, 0, keq] the Haldane Keq, solved for
+//                 rate solve; [2, param, to, 0] ties params[param] to
+//                 params[to]
+//   rate_space    what params slots 0..6 hold: 0: the rates (default), 1:
+//                 [Km_S, Km_P, kcat_r, k2, k-2, kcat_f], 2: the same with Keq
+//                 for kcat_r
+// Fitters (see each export for the ones it reads):
+//   max_iter (500), tol (1e-8), scale (0.1), x_tol (0 = the fitter's
+//   default), max_evals (0 = unlimited), progress_every (0 = every
+//   iteration), trace (false), crn_seed (common random numbers: restart the
+//   rng from this seed at every evaluation; absent or < 0 => off)
+//   warm_start, max_restarts, adaptive, oriented_restarts, outlier_threshold,
+//   outlier_rounds    Nelder–Mead (fit_nelder_mead)
+//   lower, upper      bounds over the 17 parameters, NaN where unset
+//   n_starts, n_polish   fit_auto; memory   fit_lbfgs
+//   population, generations (50)   fit_pareto
+//   sigma, step, n_samples (1000), burn_in, thin, n_chains   mcmc_sample
+struct FitOptions {
+    interp_code: u32,
+    loss_code: u32,
+    loss_scale: f64,
+    prior_mean: Option<Vec<f64>>,
+    prior_sd: Option<Vec<f64>>,
+    constraints: Vec<Constraint>,
+    rate_space: u32,
+    max_iter: u32,
+    tol: f64,
+    scale: f64,
+    x_tol: f64,
+    max_evals: u32,
+    progress_every: u32,
+    trace: bool,
+    crn_seed: Option<u64>,
+    warm_start: Option<Vec<f64>>,
+    max_restarts: u32,
+    adaptive: bool,
+    oriented_restarts: bool,
+    outlier_threshold: f64,
+    outlier_rounds: u32,
+    lower: Vec<f64>,
+    upper: Vec<f64>,
+    n_starts: u32,
+    n_polish: u32,
+    memory: u32,
+    population: u32,
+    generations: u32,
+    sigma: f64,
+    step: f64,
+    n_samples: u32,
+    burn_in: u32,
+    thin: u32,
+    n_chains: u32,
+}
+
+impl Default for FitOptions {
+    fn default() -> Self {
+        FitOptions {
+            interp_code: 0, loss_code: 0, loss_scale: 1.0, prior_mean: None, prior_sd: None,
+            constraints: Vec::new(), rate_space: 0,
+            max_iter: 500, tol: 1e-8, scale: 0.1, x_tol: 0.0, max_evals: 0, progress_every: 0, trace: false, crn_seed: None,
+            warm_start: None, max_restarts: 0, adaptive: false, oriented_restarts: false, outlier_threshold: 0.0, outlier_rounds: 0,
+            lower: Vec::new(), upper: Vec::new(), n_starts: 0, n_polish: 0, memory: 0, population: 0, generations: 50,
+            sigma: 0.0, step: 0.0, n_samples: 1000, burn_in: 0, thin: 1, n_chains: 1,
+        }
+    }
+}
+
+impl FitOptions {
+    fn from_js(options: &JsValue) -> Result<FitOptions, JsValue> {
+        let mut o = FitOptions::default();
+        if options.is_undefined() || options.is_null() { return Ok(o); }
+        if !options.is_object() { return Err(JsValue::from_str("fit options must be an object")); }
+        for key in js_sys::Object::keys(options.unchecked_ref()).iter() {
+            let name = key.as_string().unwrap_or_default();
+            let v = js_sys::Reflect::get(options, &key)?;
+            if v.is_undefined() || v.is_null() { continue; }
+            let number = || v.as_f64().ok_or_else(|| JsValue::from_str(&format!("fit option '{}' must be a number", name)));
+            let count = || number().and_then(|x| {
+                if x >= 0.0 && x.fract() == 0.0 && x <= u32::MAX as f64 { Ok(x as u32) } else { Err(JsValue::from_str(&format!("fit option '{}' must be a whole number >= 0", name))) }
+            });
+            let flag = || v.as_bool().ok_or_else(|| JsValue::from_str(&format!("fit option '{}' must be true or false", name)));
+            let array = || {
+                if v.is_instance_of::<Float64Array>() || js_sys::Array::is_array(&v) { Ok(Float64Array::new(&v).to_vec()) } else { Err(JsValue::from_str(&format!("fit option '{}' must be an array of numbers", name))) }
+            };
+            match name.as_str() {
+                "interp_code" => o.interp_code = count()?,
+                "loss_code" => o.loss_code = count()?,
+                "loss_scale" => o.loss_scale = number()?,
+                "prior_mean" => o.prior_mean = Some(array()?),
+                "prior_sd" => o.prior_sd = Some(array()?),
+                "constraints" => o.constraints = constraints_from_rows(&array()?)?,
+                "rate_space" => o.rate_space = count()?,
+                "max_iter" => o.max_iter = count()?,
+                "tol" => o.tol = number()?,
+                "scale" => o.scale = number()?,
+                "x_tol" => o.x_tol = number()?,
+                "max_evals" => o.max_evals = count()?,
+                "progress_every" => o.progress_every = count()?,
+                "trace" => o.trace = flag()?,
+                "crn_seed" => o.crn_seed = Some(number()?).filter(|x| x.is_finite() && *x >= 0.0).map(|x| x as u64),
+                "warm_start" => o.warm_start = Some(array()?),
+                "max_restarts" => o.max_restarts = count()?,
+                "adaptive" => o.adaptive = flag()?,
+                "oriented_restarts" => o.oriented_restarts = flag()?,
+                "outlier_threshold" => o.outlier_threshold = number()?,
+                "outlier_rounds" => o.outlier_rounds = count()?,
+                "lower" => o.lower = array()?,
+                "upper" => o.upper = array()?,
+                "n_starts" => o.n_starts = count()?,
+                "n_polish" => o.n_polish = count()?,
+                "memory" => o.memory = count()?,
+                "population" => o.population = count()?,
+                "generations" => o.generations = count()?,
+                "sigma" => o.sigma = number()?,
+                "step" => o.step = number()?,
+                "n_samples" => o.n_samples = count()?,
+                "burn_in" => o.burn_in = count()?,
+                "thin" => o.thin = count()?,
+                "n_chains" => o.n_chains = count()?,
+                _ => return Err(JsValue::from_str(&format!("unknown fit option '{}'", name))),
+            }
+        }
+        Ok(o)
+    }
+
+    // (lower, upper) of each fitted parameter, NaN where unset
+    fn bounds(&self, optimize_idx: &[usize]) -> Vec<(f64, f64)> {
+        optimize_idx.iter()
+            .map(|&i| (self.lower.get(i).copied().unwrap_or(f64::NAN), self.upper.get(i).copied().unwrap_or(f64::NAN)))
+            .collect()
+    }
+}
+
+// The fitting problem of the fit exports, checked that its constraints can
+// apply in its rate space
+fn problem_from_js(init: [f64; 6], times: &Float64Array, y_obs: &Float64Array, species: &JsValue, opts: &FitOptions) -> Result<Problem, JsValue> {
+    let priors = match (&opts.prior_mean, &opts.prior_sd) {
+        (Some(mean), Some(sd)) => Some(Priors::new(mean, sd)),
+        _ => None,
+    };
+    let problem = Problem {
+        init,
+        times: times.to_vec(),
+        y_obs: y_obs.to_vec(),
+        observable: observable_from_js(species)?,
+        interp: Interp::from_code(opts.interp_code),
+        priors,
+        loss: loss_from_code(opts.loss_code)?.with_scale(opts.loss_scale),
+        constraints: opts.constraints.clone(),
+        space: space_from_js(opts.rate_space)?,
+    };
+    problem.space.check(&problem.constraints).map_err(|e| JsValue::from_str(&e))?;
+    Ok(problem)
+}
+
+// Fit progress for an optional JS callback, called as (iteration, objective,
+// params); a truthy return stops the fit
+fn js_progress(progress: &Option<js_sys::Function>) -> impl Fn(u32, f64, &objective::Params) -> bool + '_ {
+    move |iter, f, best| {
+        let Some(cb) = progress.as_ref() else { return false };
+        let arr = Float64Array::from(&best[..]);
+        let ret = cb.call3(&JsValue::NULL, &JsValue::from(iter), &JsValue::from_f64(f), &arr);
+        matches!(ret, Ok(v) if v.is_truthy())
+    }
+}
+
+// Restart the random stream from a seed (integral part of a non-negative
+// number), for reproducible runs or hosts without Math.random
+#[wasm_bindgen]
+pub fn seed_rng(seed: f64) {
+    crate::rng::seed_rng(seed.max(0.0) as u64);
+}
+
+// Record every random draw from here on, e.g. around one simulation call
+#[wasm_bindgen]
+pub fn start_recording() { crate::rng::start_recording() }
+
+// Stop recording and return the draws (keep them to replay the run)
+#[wasm_bindgen]
+pub fn finish_recording() -> Float64Array {
+    let draws = crate::rng::finish_recording();
+    Float64Array::from(&draws[..])
+}
+
+// Replay recorded draws: repeating the recorded calls with the same inputs
+// reproduces the trajectory exactly. Draws past the end come from the stream.
+#[wasm_bindgen]
+pub fn start_replay(draws: &Float64Array) { crate::rng::start_replay(draws.to_vec()) }
+
+// Stop replaying; returns how many recorded draws were consumed
+#[wasm_bindgen]
+pub fn stop_replay() -> u32 { crate::rng::stop_replay() as u32 }
+
+// Names of the non-fatal conditions met by simulations since the last call
+// (dt_clamped, p_tot_high, count_zero, resource_capped), and clear them.
+// count_zero means a step drove a species negative and it was clamped to 0
+// (or, on exact counts, a draw was cut by its pool), not that a species ran
+// out. Call before and after a run to get that run's warnings.
+#[wasm_bindgen]
+pub fn take_warnings() -> js_sys::Array {
+    crate::warn::take().names().into_iter().map(JsValue::from_str).collect()
+}
+
+// Run-length and output-size caps for the simulate_* exports, per module
+// instance: max_steps per run and max_output_bytes per call, 0 => unlimited,
+// non-finite => the default (no step cap, 1 GiB). Exceeding them throws a
+// RangeError before anything is allocated.
+#[wasm_bindgen]
+pub fn set_limits(max_steps: f64, max_output_bytes: f64) {
+    let pick = |v: f64, default: u64| if v.is_finite() { v.max(0.0) as u64 } else { default };
+    limits::set(limits::Limits {
+        max_steps: pick(max_steps, limits::DEFAULT_LIMITS.max_steps),
+        max_output_bytes: pick(max_output_bytes, limits::DEFAULT_LIMITS.max_output_bytes),
+    });
+}
+
+fn limit_error(e: limits::LimitError) -> JsValue { js_sys::RangeError::new(&e.to_string()).into() }
+
+fn check_limits(steps: u64, values: u64) -> Result<(), JsValue> { limits::check(steps, values).map_err(limit_error) }
+
+fn check_series(steps: u32, width: usize) -> Result<(), JsValue> { limits::check_series(steps, width).map_err(limit_error) }
+
+// Steps of a run from t0 to the latest finite time at dt
+fn steps_to(t0: f64, dt: f64, times: &[f64]) -> u64 {
+    let last = times.iter().copied().filter(|t| t.is_finite()).fold(t0, f64::max);
+    ((last - t0) / model::clamp_dt(dt)).ceil() as u64
+}
+
+// Final [E, ES, EP, S, P, t] after `steps` steps of dt. _ns and _np are
+// unused; they stay only so existing JS callers keep their argument positions.
+#[wasm_bindgen]
+pub fn simulate_steps_final(
+    e: f64,
+    es: f64,
+    ep: f64,
+    s: f64,
+    p: f64,
+    tiempo: f64,
+    _ns: f64,
+    _np: f64,
+    k1: f64,
+    k_minus3: f64,
+    k_minus1: f64,
+    k2: f64,
+    k_minus2: f64,
+    k3: f64,
+    dt: f64,
+    steps: u32,
+) -> Result<Float64Array, JsValue> {
+    check_limits(steps as u64, 6)?;
+    let k = [k1, k_minus3, k_minus1, k2, k_minus2, k3];
+    let st = model::run_final([e, es, ep, s, p, tiempo], &k, dt, steps);
+    Ok(Float64Array::from(&st[..]))
+}
+
+// One [E, ES, EP, S, P, t] row per step; _ns and _np as in simulate_steps_final
+#[wasm_bindgen]
+pub fn simulate_steps_series(
+    e: f64,
+    es: f64,
+    ep: f64,
+    s: f64,
+    p: f64,
+    tiempo: f64,
+    _ns: f64,
+    _np: f64,
+    k1: f64,
+    k_minus3: f64,
+    k_minus1: f64,
+    k2: f64,
+    k_minus2: f64,
+    k3: f64,
+    dt: f64,
+    steps: u32,
+) -> Result<Float64Array, JsValue> {
+    check_series(steps, 6)?;
+    let k = [k1, k_minus3, k_minus1, k2, k_minus2, k3];
+    let data = model::run_series([e, es, ep, s, p, tiempo], &k, dt, steps);
+    let arr = Float64Array::new_with_length(data.len() as u32);
+    arr.copy_from(&data);
+    Ok(arr)
+}
+
+// simulate_steps_final with each step refined into substeps keeping every
+// channel p_tot <= p_max; NaN or >= 1 => fixed dt
+#[wasm_bindgen]
+pub fn simulate_steps_final_adaptive(
+    e: f64, es: f64, ep: f64, s: f64, p: f64, tiempo: f64,
+    k1: f64, k_minus3: f64, k_minus1: f64, k2: f64, k_minus2: f64, k3: f64,
+    dt: f64,
+    steps: u32,
+    p_max: f64,
+) -> Result<Float64Array, JsValue> {
+    check_limits(steps as u64, 6)?;
+    let k = [k1, k_minus3, k_minus1, k2, k_minus2, k3];
+    let st = model::run_final_adaptive([e, es, ep, s, p, tiempo], &k, dt, steps, p_max);
+    Ok(Float64Array::from(&st[..]))
+}
+
+// simulate_steps_series refined as in simulate_steps_final_adaptive; rows stay
+// on the dt grid
+#[wasm_bindgen]
+pub fn simulate_steps_series_adaptive(
+    e: f64, es: f64, ep: f64, s: f64, p: f64, tiempo: f64,
+    k1: f64, k_minus3: f64, k_minus1: f64, k2: f64, k_minus2: f64, k3: f64,
+    dt: f64,
+    steps: u32,
+    p_max: f64,
+) -> Result<Float64Array, JsValue> {
+    check_series(steps, 6)?;
+    let k = [k1, k_minus3, k_minus1, k2, k_minus2, k3];
+    let data = model::run_series_adaptive([e, es, ep, s, p, tiempo], &k, dt, steps, p_max);
+    Ok(Float64Array::from(&data[..]))
+}
+
+// simulate_steps_series at two resolutions for burst experiments: fine_steps
+// of dt_fine, then coarse_steps of dt_coarse. Output: one [E, ES, EP, S, P,
+// t] row per step at either resolution.
+#[wasm_bindgen]
+pub fn simulate_steps_series_dual(
+    e: f64, es: f64, ep: f64, s: f64, p: f64, tiempo: f64,
+    k1: f64, k_minus3: f64, k_minus1: f64, k2: f64, k_minus2: f64, k3: f64,
+    dt_fine: f64,
+    fine_steps: u32,
+    dt_coarse: f64,
+    coarse_steps: u32,
+    p_max: f64,
+) -> Result<Float64Array, JsValue> {
+    check_series(fine_steps.saturating_add(coarse_steps), 6)?;
+    let k = [k1, k_minus3, k_minus1, k2, k_minus2, k3];
+    let data = model::run_series_dual([e, es, ep, s, p, tiempo], &k, dt_fine, fine_steps, dt_coarse, coarse_steps, p_max);
+    Ok(Float64Array::from(&data[..]))
+}
+
+// simulate_steps_series over a piecewise dt schedule of flat [until_time,
+// dt, ..] pairs (absolute times, increasing): steps of each dt until its
+// time, the last landing on it exactly. Output: one [E, ES, EP, S, P, t]
+// row per step.
+#[wasm_bindgen]
+pub fn simulate_steps_series_scheduled(
+    e: f64, es: f64, ep: f64, s: f64, p: f64, tiempo: f64,
+    k1: f64, k_minus3: f64, k_minus1: f64, k2: f64, k_minus2: f64, k3: f64,
+    schedule: &Float64Array,
+    p_max: f64,
+) -> Result<Float64Array, JsValue> {
+    let schedule = model::dt_schedule(&schedule.to_vec()).map_err(|e| JsValue::from_str(&e))?;
+    let steps = model::schedule_steps(tiempo, &schedule);
+    check_limits(steps, steps.saturating_mul(6))?;
+    let k = [k1, k_minus3, k_minus1, k2, k_minus2, k3];
+    let data = model::run_series_scheduled([e, es, ep, s, p, tiempo], &k, &schedule, p_max);
+    Ok(Float64Array::from(&data[..]))
+}
+
+// Derivatives of the deterministic model's signal (as fitted: observable
+// through the readout parameters) at each time over every parameter of
+// params_in (the 17-entry fit layout, shorter inputs padded as in
+// fit_nelder_mead), by adaptive central differences. Output: one row per
+// time of 17 derivatives; the dt column is NaN.
+#[wasm_bindgen]
+pub fn jacobian(
+    e0: f64, es0: f64, ep0: f64, s0: f64, p0: f64, t0: f64,
+    params_in: &Float64Array,
+    times: &Float64Array,
+    species: &JsValue,
+) -> Result<Float64Array, JsValue> {
+    let init = [e0, es0, ep0, s0, p0, t0];
+    let params = objective::params_from_slice(&params_in.to_vec(), &init);
+    let times = times.to_vec();
+    check_limits(steps_to(t0, params[6], &times), objective::N_PARAMS as u64 * times.len() as u64)?;
+    let problem = Problem {
+        init,
+        y_obs: vec![0.0; times.len()],
+        times,
+        observable: observable_from_js(species)?,
+        interp: Interp::Linear,
+        priors: None,
+        loss: objective::Loss::Sse,
+        constraints: Vec::new(),
+        space: RateSpace::Micro,
+    };
+    let cols: Vec<usize> = (0..objective::N_PARAMS).collect();
+    Ok(Float64Array::from(&problem.prediction_jacobian(&params, &cols)[..]))
+}
+
+// Deterministic (RK4) states with their forward sensitivities to the rates
+// at each time. Output: one row per time of [E, ES, EP, S, P] then d[E, ES,
+// EP, S, P]/dk for k1, k-3, k-1, k2, k-2, k3 in turn (35 values).
+#[wasm_bindgen]
+pub fn ode_sensitivities(
+    e: f64, es: f64, ep: f64, s: f64, p: f64, tiempo: f64,
+    k1: f64, k_minus3: f64, k_minus1: f64, k2: f64, k_minus2: f64, k3: f64,
+    dt: f64,
+    times: &Float64Array,
+) -> Result<Float64Array, JsValue> {
+    let times = times.to_vec();
+    check_limits(steps_to(tiempo, dt, &times), 35 * times.len() as u64)?;
+    let k = [k1, k_minus3, k_minus1, k2, k_minus2, k3];
+    let rows = ode::integrate_sensitivities(&[e, es, ep, s, p], tiempo, &k, dt, &times);
+    let mut data = Vec::with_capacity(35 * rows.len());
+    for (y, sens) in &rows {
+        data.extend_from_slice(y);
+        for d in sens { data.extend_from_slice(d); }
+    }
+    Ok(Float64Array::from(&data[..]))
+}
+
+// Burst amplitude, rate and steady rate fitted to product p at times after
+// mixing (burst::fit_burst). Output: [A, se, kb, se, v, se, rms].
+#[wasm_bindgen]
+pub fn fit_burst(times: &Float64Array, p: &Float64Array) -> Float64Array {
+    Float64Array::from(&burst::fit_burst(&times.to_vec(), &p.to_vec())[..])
+}
+
+// Expected [A, kb, v] at saturating S with irreversible steps
+#[wasm_bindgen]
+pub fn burst_constants(k1: f64, k_minus3: f64, k_minus1: f64, k2: f64, k_minus2: f64, k3: f64, e_total: f64) -> Float64Array {
+    Float64Array::from(&burst::burst_constants(&[k1, k_minus3, k_minus1, k2, k_minus2, k3], e_total)[..])
+}
+
+// simulate_steps_series with compact f32 rows for live previews (feature
+// "compact-output"): same row layout at half the memory and transfer, same
+// f64 draws and about the same run time; counts exact only up to 2^24.
+// overflow_code as in simulate_steps_series_policy.
+#[cfg(feature = "compact-output")]
+#[wasm_bindgen]
+pub fn simulate_steps_series_f32(
+    e: f64, es: f64, ep: f64, s: f64, p: f64, tiempo: f64,
+    k1: f64, k_minus3: f64, k_minus1: f64, k2: f64, k_minus2: f64, k3: f64,
+    dt: f64,
+    steps: u32,
+    overflow_code: u32,
+) -> Result<js_sys::Float32Array, JsValue> {
+    check_series(steps, 3)?; // 6 f32 per row, the bytes of 3 f64
+    let overflow = model::Overflow::from_code(overflow_code).map_err(|e| JsValue::from_str(&e))?;
+    let k = [k1, k_minus3, k_minus1, k2, k_minus2, k3];
+    let data = crate::compact::run_series_f32(&[e, es, ep, s, p, tiempo], &k, dt, steps, overflow);
+    Ok(js_sys::Float32Array::from(&data[..]))
+}
+
+// simulate_steps_final writing [E, ES, EP, S, P, t] into a caller-provided
+// buffer (length >= 6) instead of allocating a new array; returns values written.
+#[wasm_bindgen]
+pub fn simulate_steps_final_into(
+    out: &Float64Array,
+    e: f64, es: f64, ep: f64, s: f64, p: f64, tiempo: f64,
+    k1: f64, k_minus3: f64, k_minus1: f64, k2: f64, k_minus2: f64, k3: f64,
+    dt: f64,
+    steps: u32,
+) -> Result<u32, JsValue> {
+    check_limits(steps as u64, 0)?;
+    let k = [k1, k_minus3, k_minus1, k2, k_minus2, k3];
+    let st = run_final([e, es, ep, s, p, tiempo], &k, dt, steps);
+    let n = out.length().min(6);
+    out.subarray(0, n).copy_from(&st[..n as usize]);
+    Ok(n)
+}
+
+// simulate_steps_series writing rows into a caller-provided buffer. Runs
+// min(steps, out.length / 6) steps; returns the number of rows written.
+#[wasm_bindgen]
+pub fn simulate_steps_series_into(
+    out: &Float64Array,
+    e: f64, es: f64, ep: f64, s: f64, p: f64, tiempo: f64,
+    k1: f64, k_minus3: f64, k_minus1: f64, k2: f64, k_minus2: f64, k3: f64,
+    dt: f64,
+    steps: u32,
+) -> Result<u32, JsValue> {
+    let rows = steps.min(out.length() / 6);
+    check_series(rows, 6)?;
+    let k = [k1, k_minus3, k_minus1, k2, k_minus2, k3];
+    // Stream through a fixed block rather than a run-sized copy
+    let mut stream = model::SeriesStream::new([e, es, ep, s, p, tiempo], k, dt, rows);
+    let mut block = [0.0; 6 * 512];
+    let mut at = 0;
+    loop {
+        let len = 6 * stream.fill(&mut block) as u32;
+        if len == 0 { break; }
+        out.subarray(at, at + len).copy_from(&block[..len as usize]);
+        at += len;
+    }
+    Ok(rows)
+}
+
+thread_local! {
+    // Reused output buffer for simulate_steps_series_in_memory
+    static SERIES_BUF: std::cell::RefCell<Vec<f64>> = const { std::cell::RefCell::new(Vec::new()) };
+}
+
+// simulate_steps_series into a buffer owned by the module and reused across
+// calls; returns the number of f64 values. View it without copying via
+// new Float64Array(memory.buffer, series_buffer_ptr(), len). The view is
+// invalidated by the next call or by memory growth.
+#[wasm_bindgen]
+pub fn simulate_steps_series_in_memory(
+    e: f64, es: f64, ep: f64, s: f64, p: f64, tiempo: f64,
+    k1: f64, k_minus3: f64, k_minus1: f64, k2: f64, k_minus2: f64, k3: f64,
+    dt: f64,
+    steps: u32,
+) -> Result<u32, JsValue> {
+    check_series(steps, 6)?;
+    let k = [k1, k_minus3, k_minus1, k2, k_minus2, k3];
+    Ok(SERIES_BUF.with(|buf| {
+        let mut buf = buf.borrow_mut();
+        buf.resize(6 * steps as usize, 0.0);
+        model::run_series_into(&mut buf, [e, es, ep, s, p, tiempo], &k, dt, steps);
+        buf.len() as u32
+    }))
+}
+
+// Address of the buffer filled by simulate_steps_series_in_memory
+#[wasm_bindgen]
+pub fn series_buffer_ptr() -> *const f64 {
+    SERIES_BUF.with(|buf| buf.borrow().as_ptr())
+}
+
+// Streaming series: simulate a long run in bounded-memory chunks so the
+// caller can yield between them.
+#[wasm_bindgen]
+pub struct SeriesStream {
+    inner: model::SeriesStream,
+}
+
+#[wasm_bindgen]
+impl SeriesStream {
+    // Up to n further rows of [E, ES, EP, S, P, t]; empty once the run is done
+    pub fn next_chunk(&mut self, n: u32) -> Result<Float64Array, JsValue> {
+        check_series(n.min(self.inner.remaining()), 6)?;
+        let data = self.inner.next_chunk(n);
+        let arr = Float64Array::new_with_length(data.len() as u32);
+        arr.copy_from(&data);
+        Ok(arr)
+    }
+
+    // Steps not yet simulated
+    pub fn remaining(&self) -> u32 { self.inner.remaining() }
+
+    // Current [E, ES, EP, S, P, t]
+    pub fn state(&self) -> Float64Array { Float64Array::from(&self.inner.state()[..]) }
+}
+
+// Begin a streaming run of `steps` steps; pull rows with next_chunk(n)
+#[wasm_bindgen]
+pub fn start_series(
+    e: f64, es: f64, ep: f64, s: f64, p: f64, tiempo: f64,
+    k1: f64, k_minus3: f64, k_minus1: f64, k2: f64, k_minus2: f64, k3: f64,
+    dt: f64,
+    steps: u32,
+) -> Result<SeriesStream, JsValue> {
+    // Chunks are checked for output size as they are pulled
+    check_limits(steps as u64, 0)?;
+    let k = [k1, k_minus3, k_minus1, k2, k_minus2, k3];
+    Ok(SeriesStream { inner: model::SeriesStream::new([e, es, ep, s, p, tiempo], k, dt, steps) })
+}
+
+// simulate_steps_series with species kept as exact integer counts: the
+// initial values are rounded once and counts are converted to f64 only in
+// the output, so both conservation totals hold exactly in long runs.
+// overflow_code as in simulate_steps_series_policy.
+// Output: rows of [E, ES, EP, S, P, t], one per step.
+#[wasm_bindgen]
+pub fn simulate_steps_series_integer(
+    e: f64, es: f64, ep: f64, s: f64, p: f64, tiempo: f64,
+    k1: f64, k_minus3: f64, k_minus1: f64, k2: f64, k_minus2: f64, k3: f64,
+    dt: f64,
+    steps: u32,
+    overflow_code: u32,
+) -> Result<Float64Array, JsValue> {
+    check_series(steps, 6)?;
+    let overflow = model::Overflow::from_code(overflow_code).map_err(|e| JsValue::from_str(&e))?;
+    let k = [k1, k_minus3, k_minus1, k2, k_minus2, k3];
+    let data = model::run_series_integer([e, es, ep, s, p, tiempo], &k, dt, steps, overflow);
+    Ok(Float64Array::from(&data[..]))
+}
+
+// simulate_steps_series under an explicit step policy. rounding_code picks
+// how fractional values become counts: 0: enzyme pools rounded, S and P caps
+// floored (the default scheme), 1: all rounded to nearest, 2: all floored,
+// 3: stochastic rounding (unbiased). overflow_code picks what happens to
+// binding draws beyond the S or P available: 0: moved to the other binding
+// channel (default), 1: dropped (stay free E), 2: redrawn by the rate split.
+// Output: rows of [E, ES, EP, S, P, t], then the total binding events moved
+// to the other channel.
+#[wasm_bindgen]
+pub fn simulate_steps_series_policy(
+    e: f64, es: f64, ep: f64, s: f64, p: f64, tiempo: f64,
+    k1: f64, k_minus3: f64, k_minus1: f64, k2: f64, k_minus2: f64, k3: f64,
+    dt: f64,
+    steps: u32,
+    rounding_code: u32,
+    overflow_code: u32,
+) -> Result<Float64Array, JsValue> {
+    check_series(steps, 6)?;
+    let policy = model::StepPolicy {
+        rounding: model::Rounding::from_code(rounding_code).map_err(|e| JsValue::from_str(&e))?,
+        overflow: model::Overflow::from_code(overflow_code).map_err(|e| JsValue::from_str(&e))?,
+    };
+    let k = [k1, k_minus3, k_minus1, k2, k_minus2, k3];
+    let (mut data, moved) = model::run_series_with([e, es, ep, s, p, tiempo], &k, dt, steps, &policy);
+    data.push(moved as f64);
+    Ok(Float64Array::from(&data[..]))
+}
+
+// Final [E, ES, EP, S, P, t] of simulate_steps_series_integer
+#[wasm_bindgen]
+pub fn simulate_steps_final_integer(
+    e: f64, es: f64, ep: f64, s: f64, p: f64, tiempo: f64,
+    k1: f64, k_minus3: f64, k_minus1: f64, k2: f64, k_minus2: f64, k3: f64,
+    dt: f64,
+    steps: u32,
+    overflow_code: u32,
+) -> Result<Float64Array, JsValue> {
+    check_limits(steps as u64, 6)?;
+    let overflow = model::Overflow::from_code(overflow_code).map_err(|e| JsValue::from_str(&e))?;
+    let k = [k1, k_minus3, k_minus1, k2, k_minus2, k3];
+    Ok(Float64Array::from(&model::run_final_integer([e, es, ep, s, p, tiempo], &k, dt, steps, overflow)[..]))
+}
+
+// Like simulate_steps_final, with cumulative channel counts over the whole run:
+// [E, ES, EP, S, P, t, E->ES, E->EP, ES->E, ES->EP, EP->ES, EP->E].
+// Net turnovers = (EP->E) - (E->EP).
+#[wasm_bindgen]
+pub fn simulate_steps_final_counts(
+    e: f64, es: f64, ep: f64, s: f64, p: f64, tiempo: f64,
+    k1: f64, k_minus3: f64, k_minus1: f64, k2: f64, k_minus2: f64, k3: f64,
+    dt: f64,
+    steps: u32,
+) -> Result<Float64Array, JsValue> {
+    check_limits(steps as u64, 12)?;
+    let k = [k1, k_minus3, k_minus1, k2, k_minus2, k3];
+    let (st, total) = run_final_counts([e, es, ep, s, p, tiempo], &k, dt, steps);
+    let mut data = st.to_vec();
+    data.extend(total.iter().map(|&v| v as f64));
+    Ok(Float64Array::from(&data[..]))
+}
+
+// Like simulate_steps_series, with the six per-step reaction fluxes appended:
+// rows of [E, ES, EP, S, P, t, E->ES, E->EP, ES->E, ES->EP, EP->ES, EP->E].
+#[wasm_bindgen]
+pub fn simulate_steps_series_fluxes(
+    e: f64, es: f64, ep: f64, s: f64, p: f64, tiempo: f64,
+    k1: f64, k_minus3: f64, k_minus1: f64, k2: f64, k_minus2: f64, k3: f64,
+    dt: f64,
+    steps: u32,
+) -> Result<Float64Array, JsValue> {
+    check_series(steps, 12)?;
+    let k = [k1, k_minus3, k_minus1, k2, k_minus2, k3];
+    let data = run_series_fluxes([e, es, ep, s, p, tiempo], &k, dt, steps);
+    let arr = Float64Array::new_with_length(data.len() as u32);
+    arr.copy_from(&data);
+    Ok(arr)
+}
+
+// Like simulate_steps_series, with derived columns appended to each row as
+// selected by the channels flags, in this order: 1 => [dP/dt, -dS/dt], the net
+// rates of P formation and S consumption over the step; 2 => [ES, EP] /
+// (E + ES + EP), the fractional saturation. p_max as in
+// simulate_steps_final_adaptive. Output: rows of 6 + 2 * (channels selected)
+// values; pass the row width as the stride of audit_conservation or
+// qssa_comparison.
+#[wasm_bindgen]
+pub fn simulate_steps_series_channels(
+    e: f64, es: f64, ep: f64, s: f64, p: f64, tiempo: f64,
+    k1: f64, k_minus3: f64, k_minus1: f64, k2: f64, k_minus2: f64, k3: f64,
+    dt: f64,
+    steps: u32,
+    p_max: f64,
+    channels: u32,
+) -> Result<Float64Array, JsValue> {
+    check_series(steps, model::channels_width(channels))?;
+    let k = [k1, k_minus3, k_minus1, k2, k_minus2, k3];
+    let data = model::run_series_channels([e, es, ep, s, p, tiempo], &k, dt, steps, p_max, channels);
+    Ok(Float64Array::from(&data[..]))
+}
+
+// Two-compartment run: the enzyme reacts in the inner compartment while S and
+// P cross to and from an outer one (s_out, p_out) with first-order constants.
+// Output: rows of [E, ES, EP, S, P, t, S_out, P_out], one per step.
+#[wasm_bindgen]
+pub fn simulate_two_compartment_series(
+    e: f64, es: f64, ep: f64, s: f64, p: f64, tiempo: f64, s_out: f64, p_out: f64,
+    k1: f64, k_minus3: f64, k_minus1: f64, k2: f64, k_minus2: f64, k3: f64,
+    ks_out: f64, ks_in: f64, kp_out: f64, kp_in: f64, // per-molecule transport: S in->out, S out->in, P in->out, P out->in
+    dt: f64,
+    steps: u32,
+) -> Result<Float64Array, JsValue> {
+    check_series(steps, 8)?;
+    let k = [k1, k_minus3, k_minus1, k2, k_minus2, k3];
+    let data = compartment::run_series([e, es, ep, s, p, tiempo, s_out, p_out], &k, &[ks_out, ks_in, kp_out, kp_in], dt, steps);
+    Ok(Float64Array::from(&data[..]))
+}
+
+// Two enzymes with their own rate constants (the second's suffixed _b) on one
+// shared S and P pool. Output: rows of [E, ES, EP, S, P, t, E2, ES2, EP2, P
+// formed by the first enzyme, P formed by the second], one per step.
+#[wasm_bindgen]
+pub fn simulate_two_enzyme_series(
+    e: f64, es: f64, ep: f64, s: f64, p: f64, tiempo: f64, e_b: f64, es_b: f64, ep_b: f64,
+    k1: f64, k_minus3: f64, k_minus1: f64, k2: f64, k_minus2: f64, k3: f64,
+    k1_b: f64, k_minus3_b: f64, k_minus1_b: f64, k2_b: f64, k_minus2_b: f64, k3_b: f64,
+    dt: f64,
+    steps: u32,
+) -> Result<Float64Array, JsValue> {
+    check_series(steps, competition::ROW_LEN)?;
+    let k = [k1, k_minus3, k_minus1, k2, k_minus2, k3];
+    let k_b = [k1_b, k_minus3_b, k_minus1_b, k2_b, k_minus2_b, k3_b];
+    let data = competition::run_series([e, es, ep, s, p, tiempo, e_b, es_b, ep_b], &k, &k_b, dt, steps);
+    Ok(Float64Array::from(&data[..]))
+}
+
+// Branched mechanism: ES also releases a second product P2 at first-order kb.
+// Output: rows of [E, ES, EP, S, P, t, P2, P / P2], one per step.
+#[wasm_bindgen]
+pub fn simulate_branched_series(
+    e: f64, es: f64, ep: f64, s: f64, p: f64, tiempo: f64, p2: f64,
+    k1: f64, k_minus3: f64, k_minus1: f64, k2: f64, k_minus2: f64, k3: f64,
+    kb: f64,
+    dt: f64,
+    steps: u32,
+) -> Result<Float64Array, JsValue> {
+    check_series(steps, branched::ROW_LEN)?;
+    let k = [k1, k_minus3, k_minus1, k2, k_minus2, k3];
+    let data = branched::run_series([e, es, ep, s, p, tiempo, p2], &k, kb, dt, steps);
+    Ok(Float64Array::from(&data[..]))
+}
+
+// Expected P / P2 of the branched mechanism while no product rebinds
+#[wasm_bindgen]
+pub fn branched_partition_ratio(k1: f64, k_minus3: f64, k_minus1: f64, k2: f64, k_minus2: f64, k3: f64, kb: f64) -> f64 {
+    branched::partition_ratio(&[k1, k_minus3, k_minus1, k2, k_minus2, k3], kb)
+}
+
+// Linear pathway M0 -> .. -> Mn of n enzyme stages (cascade): enzymes holds
+// [E, ES, EP] per stage, metabolites the n + 1 pools M0..Mn and rates the six
+// [k1, k-3, k-1, k2, k-2, k3] of each stage. Output: rows of [E1, ES1, EP1,
+// .., En, ESn, EPn, M0, .., Mn, t], one per step.
+#[wasm_bindgen]
+pub fn simulate_cascade_series(
+    enzymes: &Float64Array,
+    metabolites: &Float64Array,
+    tiempo: f64,
+    rates: &Float64Array,
+    dt: f64,
+    steps: u32,
+) -> Result<Float64Array, JsValue> {
+    let rates = rates.to_vec();
+    if !rates.len().is_multiple_of(6) { return Err(JsValue::from_str("rates must hold 6 values per stage")); }
+    let k: Vec<[f64; 6]> = rates.chunks(6).map(|c| [c[0], c[1], c[2], c[3], c[4], c[5]]).collect();
+    check_series(steps, cascade::row_len(k.len()))?;
+    let mut st0 = enzymes.to_vec();
+    st0.extend(metabolites.to_vec());
+    st0.push(tiempo);
+    let data = cascade::run_series(&st0, &k, dt, steps).map_err(|e| JsValue::from_str(&e))?;
+    Ok(Float64Array::from(&data[..]))
+}
+
+// Coupled assay: P is turned into the detected Q by a coupling enzyme with
+// vmax_c and km_c (in the state's units). Output: rows of [E, ES, EP, S, P,
+// t, Q], one per step.
+#[wasm_bindgen]
+pub fn simulate_coupled_series(
+    e: f64, es: f64, ep: f64, s: f64, p: f64, tiempo: f64, q: f64,
+    k1: f64, k_minus3: f64, k_minus1: f64, k2: f64, k_minus2: f64, k3: f64,
+    vmax_c: f64, km_c: f64,
+    dt: f64,
+    steps: u32,
+) -> Result<Float64Array, JsValue> {
+    check_series(steps, 7)?;
+    let k = [k1, k_minus3, k_minus1, k2, k_minus2, k3];
+    let data = coupled::run_series([e, es, ep, s, p, tiempo, q], &k, &[vmax_c, km_c], dt, steps);
+    Ok(Float64Array::from(&data[..]))
+}
+
+// Whether a coupling enzyme keeps up with primary rate v
+// (coupled::coupling_check). Output: [steady P, lag, time for the detected
+// rate to reach fraction of v, v / vmax_c].
+#[wasm_bindgen]
+pub fn coupling_check(v: f64, vmax_c: f64, km_c: f64, fraction: f64) -> Float64Array {
+    Float64Array::from(&coupled::coupling_check(v, &[vmax_c, km_c], fraction)[..])
+}
+
+// State at each requested absolute time, stepping onto the times exactly
+// (no interpolation); p_max as in simulate_steps_final_adaptive. Output: one
+// [E, ES, EP, S, P, t] row per requested time, in request order; times
+// before tiempo (or non-finite) report the initial state.
+#[wasm_bindgen]
+pub fn simulate_at_times(
+    e: f64, es: f64, ep: f64, s: f64, p: f64, tiempo: f64,
+    k1: f64, k_minus3: f64, k_minus1: f64, k2: f64, k_minus2: f64, k3: f64,
+    dt: f64,
+    times: &Float64Array,
+    p_max: f64,
+) -> Result<Float64Array, JsValue> {
+    let times = times.to_vec();
+    check_limits(steps_to(tiempo, dt, &times), 6 * times.len() as u64)?;
+    let k = [k1, k_minus3, k_minus1, k2, k_minus2, k3];
+    let data = model::run_at_times([e, es, ep, s, p, tiempo], &k, dt, &times, p_max);
+    Ok(Float64Array::from(&data[..]))
+}
+
+// simulate_at_times on n_points times log-spaced from tiempo + t_first to
+// tiempo + t_end (empty unless 0 < t_first < t_end). Output: one
+// [E, ES, EP, S, P, t] row per time.
+#[wasm_bindgen]
+pub fn simulate_log_series(
+    e: f64, es: f64, ep: f64, s: f64, p: f64, tiempo: f64,
+    k1: f64, k_minus3: f64, k_minus1: f64, k2: f64, k_minus2: f64, k3: f64,
+    dt: f64,
+    t_first: f64,
+    t_end: f64,
+    n_points: u32,
+    p_max: f64,
+) -> Result<Float64Array, JsValue> {
+    check_limits(0, 6 * n_points as u64)?;
+    let times = model::log_spaced_times(tiempo, t_first, t_end, n_points);
+    check_limits(steps_to(tiempo, dt, &times), 0)?;
+    let k = [k1, k_minus3, k_minus1, k2, k_minus2, k3];
+    let data = model::run_at_times([e, es, ep, s, p, tiempo], &k, dt, &times, p_max);
+    Ok(Float64Array::from(&data[..]))
+}
+
+// Independent replicate runs from the same initial state.
+// Output: n_replicates rows of the final [E, ES, EP, S, P, t].
+#[wasm_bindgen]
+pub fn simulate_replicates_final(
+    e: f64, es: f64, ep: f64, s: f64, p: f64, tiempo: f64,
+    k1: f64, k_minus3: f64, k_minus1: f64, k2: f64, k_minus2: f64, k3: f64,
+    dt: f64,
+    steps: u32,
+    n_replicates: u32,
+    p_max: f64, // as in simulate_steps_final_adaptive
+    antithetic: bool, // run replicates in pairs on mirrored random draws (variance reduction for means)
+) -> Result<Float64Array, JsValue> {
+    check_limits(steps as u64, 6 * n_replicates as u64)?;
+    let k = [k1, k_minus3, k_minus1, k2, k_minus2, k3];
+    let rows = ensemble::replicates_final([e, es, ep, s, p, tiempo], &k, dt, steps, n_replicates, p_max, antithetic);
+    let data: Vec<f64> = rows.iter().flatten().copied().collect();
+    Ok(Float64Array::from(&data[..]))
+}
+
+// Titration series: one run per entry of amounts with the enzyme (titrant 0;
+// ES and EP cleared) or substrate (1) initial amount set to it. Output per
+// amount, stacked: steps [E, ES, EP, S, P, t] rows when series is set, else
+// the final state.
+#[wasm_bindgen]
+pub fn simulate_titration(
+    e: f64, es: f64, ep: f64, s: f64, p: f64, tiempo: f64,
+    k1: f64, k_minus3: f64, k_minus1: f64, k2: f64, k_minus2: f64, k3: f64,
+    dt: f64,
+    steps: u32,
+    amounts: &Float64Array,
+    titrant: u32,
+    series: bool,
+) -> Result<Float64Array, JsValue> {
+    let amounts = amounts.to_vec();
+    let rows = if series { steps as u64 } else { 1 };
+    check_limits(steps as u64, 6 * rows * amounts.len() as u64)?;
+    let titrant = ensemble::Titrant::from_code(titrant).map_err(|e| JsValue::from_str(&e))?;
+    let k = [k1, k_minus3, k_minus1, k2, k_minus2, k3];
+    Ok(Float64Array::from(&ensemble::titration([e, es, ep, s, p, tiempo], &k, dt, steps, &amounts, titrant, series)[..]))
+}
+
+// Replicate mean and spread per step, for ensemble bands.
+// Output: steps rows of [t, mean E, ES, EP, S, P, sd E, ES, EP, S, P].
+#[wasm_bindgen]
+pub fn simulate_replicate_band(
+    e: f64, es: f64, ep: f64, s: f64, p: f64, tiempo: f64,
+    k1: f64, k_minus3: f64, k_minus1: f64, k2: f64, k_minus2: f64, k3: f64,
+    dt: f64,
+    steps: u32,
+    n_replicates: u32,
+    antithetic: bool, // as in simulate_replicates_final
+) -> Result<Float64Array, JsValue> {
+    // Every replicate series is held until the rows are reduced
+    check_limits(steps as u64 * n_replicates.max(1) as u64, (6 * n_replicates.max(1) as u64 + 11) * steps as u64)?;
+    let k = [k1, k_minus3, k_minus1, k2, k_minus2, k3];
+    let data = ensemble::replicate_band([e, es, ep, s, p, tiempo], &k, dt, steps, n_replicates, antithetic);
+    Ok(Float64Array::from(&data[..]))
+}
+
+// First-passage times of an observable across a threshold, one per replicate.
+// falling => first time value <= threshold (e.g. S at 50% conversion),
+// otherwise first time value >= threshold. Crossing times are interpolated
+// within the step; NaN marks replicates that never crossed within max_steps.
+#[wasm_bindgen]
+pub fn first_passage_times(
+    e: f64, es: f64, ep: f64, s: f64, p: f64, tiempo: f64,
+    k1: f64, k_minus3: f64, k_minus1: f64, k2: f64, k_minus2: f64, k3: f64,
+    dt: f64,
+    max_steps: u32,
+    species: &JsValue,
+    threshold: f64,
+    falling: bool,
+    n_replicates: u32,
+) -> Result<Float64Array, JsValue> {
+    check_limits(max_steps as u64, n_replicates as u64)?;
+    let obs = observable_from_js(species)?;
+    let k = [k1, k_minus3, k_minus1, k2, k_minus2, k3];
+    let out = ensemble::first_passage_times(
+        [e, es, ep, s, p, tiempo], &k, dt, max_steps, obs, threshold, falling, n_replicates,
+    );
+    Ok(Float64Array::from(&out[..]))
+}
+
+// Histogram of an observable after `steps` steps across independent replicates,
+// computed in WASM so only the bins cross to JS.
+// Output: [edges (n_bins + 1)..., counts (n_bins)...].
+#[wasm_bindgen]
+pub fn final_state_histogram(
+    e: f64, es: f64, ep: f64, s: f64, p: f64, tiempo: f64,
+    k1: f64, k_minus3: f64, k_minus1: f64, k2: f64, k_minus2: f64, k3: f64,
+    dt: f64,
+    steps: u32,
+    n_replicates: u32,
+    species: &JsValue,
+    n_bins: u32,
+) -> Result<Float64Array, JsValue> {
+    let obs = observable_from_js(species)?;
+    let k = [k1, k_minus3, k_minus1, k2, k_minus2, k3];
+    let (mut data, counts) = ensemble::final_state_histogram(
+        [e, es, ep, s, p, tiempo], &k, dt, steps, n_replicates, obs, n_bins,
+    );
+    data.extend_from_slice(&counts);
+    Ok(Float64Array::from(&data[..]))
+}
+
+// Conservation audit of a series buffer whose rows start with [E, ES, EP, S, P].
+// Checks E+ES+EP and S+P+ES+EP against the given totals (NaN => first row).
+// stride is the row width (0 => 6, use 12 for flux series).
+// Output: [max |enzyme drift|, row of max, max |substrate drift|, row of max].
+#[wasm_bindgen]
+pub fn audit_conservation(series: &Float64Array, enzyme_total: f64, substrate_total: f64, stride: u32) -> Float64Array {
+    let out = ensemble::audit_conservation(&series.to_vec(), enzyme_total, substrate_total, stride);
+    Float64Array::from(&out[..])
+}
+
+// Steady-state enzyme distribution at fixed substrate and product levels,
+// solved algebraically. Output: [E, ES, EP, v] with v the net rate of product
+// formation; NaN when no transition has a positive rate.
+#[wasm_bindgen]
+pub fn steady_state(
+    e_total: f64, s: f64, p: f64,
+    k1: f64, k_minus3: f64, k_minus1: f64, k2: f64, k_minus2: f64, k3: f64,
+) -> Float64Array {
+    let k = [k1, k_minus3, k_minus1, k2, k_minus2, k3];
+    Float64Array::from(&steady::steady_state(&k, e_total, s, p)[..])
+}
+
+// Chemical equilibrium reached from the given state, from Keq and the enzyme
+// and substrate totals. Output: [E, ES, EP, S, P, Keq]; NaN unless all six
+// constants are positive.
+#[wasm_bindgen]
+pub fn equilibrium_state(
+    e: f64, es: f64, ep: f64, s: f64, p: f64,
+    k1: f64, k_minus3: f64, k_minus1: f64, k2: f64, k_minus2: f64, k3: f64,
+) -> Float64Array {
+    let k = [k1, k_minus3, k_minus1, k2, k_minus2, k3];
+    Float64Array::from(&steady::equilibrium(&k, &[e, es, ep, s, p])[..])
+}
+
+// Eyring analysis of rate constants k (s^-1) fitted at temperatures t (K),
+// optionally weighted by their standard errors sd (empty => unweighted).
+// Output: [dH‡, se, dS‡, se, dG‡ at 298.15 K, se, rms residual in
+// ln(k / T)], in J/mol and J/(mol K); NaN with fewer than two temperatures.
+#[wasm_bindgen]
+pub fn eyring_fit(t: &Float64Array, k: &Float64Array, sd: &Float64Array) -> Float64Array {
+    let sd = sd.to_vec();
+    Float64Array::from(&conditions::eyring(&t.to_vec(), &k.to_vec(), (!sd.is_empty()).then_some(&sd[..]))[..])
+}
+
+// Two-pKa (bell-shaped) fit of kcat or kcat/Km values y against pH. Output:
+// [pKa1, se, pKa2, se, pH-independent limit, se, rms residual in ln y];
+// NaN with fewer than three positive values.
+#[wasm_bindgen]
+pub fn ph_profile_fit(ph: &Float64Array, y: &Float64Array) -> Float64Array {
+    Float64Array::from(&conditions::ph_profile(&ph.to_vec(), &y.to_vec())[..])
+}
+
+// Global Ki fit of initial rates v at substrate s and inhibitor i to mode 0:
+// competitive, 1: uncompetitive, 2: noncompetitive or 3: mixed inhibition.
+// Output: [V, se, Km, se, Ki, se, alpha, se (mixed only, else NaN), rms].
+#[wasm_bindgen]
+pub fn fit_inhibition(s: &Float64Array, i: &Float64Array, v: &Float64Array, mode: u32) -> Result<Float64Array, JsValue> {
+    let mode = inhibition::Inhibition::from_code(mode).map_err(|e| JsValue::from_str(&e))?;
+    Ok(Float64Array::from(&inhibition::fit_inhibition(&s.to_vec(), &i.to_vec(), &v.to_vec(), mode)[..]))
+}
+
+// Four-parameter logistic fit of a dose-response curve. Output: [IC50, se,
+// Hill slope, se, top, se, bottom, se, rms residual].
+#[wasm_bindgen]
+pub fn fit_dose_response(x: &Float64Array, y: &Float64Array) -> Float64Array {
+    Float64Array::from(&inhibition::fit_dose_response(&x.to_vec(), &y.to_vec())[..])
+}
+
+// Ki from an IC50 measured at substrate s (Cheng–Prusoff) for inhibition
+// mode 0: competitive, 1: uncompetitive, 2: noncompetitive, 3: mixed (with
+// alpha); e_total > 0 adds the tight-binding correction IC50 - E_t / 2.
+#[wasm_bindgen]
+pub fn cheng_prusoff_ki(ic50: f64, s: f64, km: f64, e_total: f64, mode: u32, alpha: f64) -> Result<f64, JsValue> {
+    let mode = inhibition::Inhibition::from_code(mode).map_err(|e| JsValue::from_str(&e))?;
+    Ok(inhibition::cheng_prusoff(ic50, s, km, e_total, mode, alpha))
+}
+
+// Morrison tight-binding fit of rates v at total inhibitor i. fit_enzyme
+// also fits the active enzyme total, starting from e_total. Output:
+// [Ki_app, se, v0, se, E_t, se (NaN when fixed), rms residual].
+#[wasm_bindgen]
+pub fn fit_morrison(i: &Float64Array, v: &Float64Array, e_total: f64, fit_enzyme: bool) -> Float64Array {
+    Float64Array::from(&inhibition::fit_morrison(&i.to_vec(), &v.to_vec(), e_total, fit_enzyme)[..])
+}
+
+// Rate of Morrison's quadratic at total inhibitor i (inhibition::morrison_rate)
+#[wasm_bindgen]
+pub fn morrison_rate(v0: f64, e_total: f64, i: f64, ki_app: f64) -> f64 {
+    inhibition::morrison_rate(v0, e_total, i, ki_app)
+}
+
+// Slow-binding progress curve at inhibitor i (inhibition::slow_binding_curve:
+// rapid E + I <-> EI with Ki_app, then EI <-> EI* with k5 and k6, from
+// uninhibited rate v_u). Output: [v0, vs, kobs] then P at each time.
+#[wasm_bindgen]
+pub fn slow_binding_curve(v_u: f64, ki_app: f64, k5: f64, k6: f64, i: f64, p0: f64, times: &Float64Array) -> Float64Array {
+    let (head, p) = inhibition::slow_binding_curve(v_u, ki_app, k5, k6, i, p0, &times.to_vec());
+    let data: Vec<f64> = head.iter().chain(&p).copied().collect();
+    Float64Array::from(&data[..])
+}
+
+// kobs of one slow-onset progress curve. Output: [kobs, se, v0, se, vs, se,
+// p0, se, rms residual].
+#[wasm_bindgen]
+pub fn fit_slow_onset(times: &Float64Array, p: &Float64Array) -> Float64Array {
+    Float64Array::from(&inhibition::fit_slow_onset(&times.to_vec(), &p.to_vec())[..])
+}
+
+// k5 and k6 from kobs at inhibitor concentrations i: two_step fits k6 + k5 I
+// / (Ki_app + I), else k6 + k5 I. Output: [k5, se, k6, se, Ki_app, se (NaN
+// for one step), rms residual].
+#[wasm_bindgen]
+pub fn fit_kobs(i: &Float64Array, kobs: &Float64Array, two_step: bool) -> Float64Array {
+    Float64Array::from(&inhibition::fit_kobs(&i.to_vec(), &kobs.to_vec(), two_step)[..])
+}
+
+// Progress curve under irreversible inactivation at inhibitor i
+// (inhibition::inactivation_curve). Output: [v0, kobs] then P at each time.
+#[wasm_bindgen]
+pub fn inactivation_curve(v_u: f64, kinact: f64, ki: f64, i: f64, times: &Float64Array) -> Float64Array {
+    let (head, p) = inhibition::inactivation_curve(v_u, kinact, ki, i, &times.to_vec());
+    let data: Vec<f64> = head.iter().chain(&p).copied().collect();
+    Float64Array::from(&data[..])
+}
+
+// kinact and KI from kobs at inhibitor concentrations i (kobs of each curve
+// from fit_slow_onset). Output: [kinact, se, KI, se, kinact/KI, se, rms].
+#[wasm_bindgen]
+pub fn fit_inactivation(i: &Float64Array, kobs: &Float64Array) -> Float64Array {
+    Float64Array::from(&inhibition::fit_inactivation(&i.to_vec(), &kobs.to_vec())[..])
+}
+
+// Starting rates guessed from a P or S progress curve (guess::initial_rates).
+// Output: [k1, k-3, k-1, k2, k-2, k3, kcat_f, Km_S, Keq].
+#[wasm_bindgen]
+pub fn guess_initial_rates(
+    e0: f64, es0: f64, ep0: f64, s0: f64, p0: f64, t0: f64,
+    times: &Float64Array,
+    y_obs: &Float64Array,
+    species: &JsValue,
+) -> Result<Float64Array, JsValue> {
+    let g = guess::initial_rates(&[e0, es0, ep0, s0, p0, t0], &times.to_vec(), &y_obs.to_vec(), observable_from_js(species)?)
+        .map_err(|e| JsValue::from_str(&e))?;
+    Ok(Float64Array::from(&g[..]))
+}
+
+// Fitting coordinates of the rates in a rate space (0: micro, 1: [Km_S, Km_P,
+// kcat_r, k2, k-2, kcat_f], 2: the same with Keq for kcat_r), e.g. to turn a
+// rate guess into the params_in of a macroscopic fit
+#[wasm_bindgen]
+pub fn rate_space_coordinates(
+    k1: f64, k_minus3: f64, k_minus1: f64, k2: f64, k_minus2: f64, k3: f64,
+    rate_space: u32,
+) -> Result<Float64Array, JsValue> {
+    let c = space_from_js(rate_space)?.coordinates(&[k1, k_minus3, k_minus1, k2, k_minus2, k3]).map_err(|e| JsValue::from_str(&e))?;
+    Ok(Float64Array::from(&c[..]))
+}
+
+// Rates [k1, k-3, k-1, k2, k-2, k3] at the six coordinates of a rate space
+// (inverse of rate_space_coordinates); NaN where they have no rates
+#[wasm_bindgen]
+pub fn rate_space_rates(coordinates: &Float64Array, rate_space: u32) -> Result<Float64Array, JsValue> {
+    let c = coordinates.to_vec();
+    if c.len() != 6 { return Err(JsValue::from_str("rate space coordinates need 6 values")); }
+    Ok(Float64Array::from(&space_from_js(rate_space)?.rates(&c)[..]))
+}
+
+// Reversible Michaelis–Menten (QSSA) prediction alongside a simulated series
+// whose rows start with [E, ES, EP, S, P, t]; stride is the row width (0 => 6,
+// use 12 for flux series). Output: [kcat_f, Km_S, kcat_r, Km_P] then per row
+// [t, v_mm at the row's S and P, dP/dt of the series, P of the QSSA model
+// started from the first row]. The QSSA holds while E0 << S0 + Km_S.
+#[wasm_bindgen]
+pub fn qssa_comparison(
+    series: &Float64Array,
+    k1: f64, k_minus3: f64, k_minus1: f64, k2: f64, k_minus2: f64, k3: f64,
+    stride: u32,
+) -> Float64Array {
+    let k = [k1, k_minus3, k_minus1, k2, k_minus2, k3];
+    Float64Array::from(&steady::qssa_comparison(&series.to_vec(), &k, stride)[..])
+}
+
+// Largest dt keeping every channel p_tot at or below target over a run from
+// the given state (model::suggest_dt; target NaN or outside (0, 1) => 0.1).
+// Infinite when nothing can react.
+#[wasm_bindgen]
+pub fn suggest_dt(
+    e: f64, es: f64, ep: f64, s: f64, p: f64,
+    k1: f64, k_minus3: f64, k_minus1: f64, k2: f64, k_minus2: f64, k3: f64,
+    target: f64,
+) -> f64 {
+    model::suggest_dt(&[e, es, ep, s, p, 0.0], &[k1, k_minus3, k_minus1, k2, k_minus2, k3], target)
+}
+
+// dt-convergence study: reruns to t_end at dt, dt/2, ... (levels in all), each
+// from seed and averaged over n_replicates runs. Output: one row per level,
+// coarsest first, of [dt, mean E, ES, EP, S, P at t_end, |difference| of each
+// from the finest level, largest channel p_tot, 1 if above p_warn (NaN => 0.1)].
+#[wasm_bindgen]
+pub fn dt_convergence(
+    e: f64, es: f64, ep: f64, s: f64, p: f64, tiempo: f64,
+    k1: f64, k_minus3: f64, k_minus1: f64, k2: f64, k_minus2: f64, k3: f64,
+    dt: f64,
+    t_end: f64,
+    levels: u32,
+    n_replicates: u32,
+    seed: f64,
+    p_warn: f64,
+) -> Float64Array {
+    let k = [k1, k_minus3, k_minus1, k2, k_minus2, k3];
+    let data = ensemble::dt_convergence([e, es, ep, s, p, tiempo], &k, dt, t_end, levels, n_replicates, seed.max(0.0) as u64, p_warn);
+    Float64Array::from(&data[..])
+}
+
+// Summary of a series whose rows start with [E, ES, EP, S, P, t]; stride is
+// the row width (0 => 6). Output: for each of E, ES, EP, S, P [max, time of
+// max, final value, AUC (trapezoid)], then the time P first reaches halfway
+// from its initial value to its maximum (NaN if it never rises).
+#[wasm_bindgen]
+pub fn summarize_series(series: &Float64Array, stride: u32) -> Float64Array {
+    Float64Array::from(&summary::summarize_series(&series.to_vec(), stride)[..])
+}
+
+// Times at which substrate conversion 1 - S / S0 (S0 = S of the first row)
+// first reaches each of `fractions` (e.g. [0.1, 0.5, 0.9]), interpolated
+// between rows; NaN where never reached. stride is the row width (0 => 6).
+#[wasm_bindgen]
+pub fn conversion_times(series: &Float64Array, fractions: &Float64Array, stride: u32) -> Float64Array {
+    Float64Array::from(&summary::conversion_times(&series.to_vec(), stride, &fractions.to_vec())[..])
+}
+
+// Conversion times across independent replicates, each run until every
+// fraction is reached or max_steps pass.
+// Output per fraction: [fraction, mean time, sd, replicates that reached it].
+#[wasm_bindgen]
+pub fn conversion_time_stats(
+    e: f64, es: f64, ep: f64, s: f64, p: f64, tiempo: f64,
+    k1: f64, k_minus3: f64, k_minus1: f64, k2: f64, k_minus2: f64, k3: f64,
+    dt: f64,
+    max_steps: u32,
+    fractions: &Float64Array,
+    n_replicates: u32,
+) -> Result<Float64Array, JsValue> {
+    // Each replicate buffers its series until the conversion times are read
+    check_series(max_steps.saturating_add(1), 6)?;
+    let k = [k1, k_minus3, k_minus1, k2, k_minus2, k3];
+    let out = ensemble::conversion_time_stats(
+        [e, es, ep, s, p, tiempo], &k, dt, max_steps, &fractions.to_vec(), n_replicates,
+    );
+    Ok(Float64Array::from(&out[..]))
+}
+
+// Selwyn's test: progress curves at enzyme amounts e0, concatenated in times
+// and values with their lengths in `lengths`, resampled on `points` (0 =>
+// 50) common values of E0 t. Output: [score, deviation per curve, then per
+// point [E0 t, value per curve]] (summary::selwyn); overlapping curves (a
+// score of a few percent) mean the enzyme is stable over the assays.
+#[wasm_bindgen]
+pub fn selwyn_test(
+    e0: &Float64Array,
+    times: &Float64Array,
+    values: &Float64Array,
+    lengths: &js_sys::Uint32Array,
+    points: u32,
+) -> Float64Array {
+    Float64Array::from(&summary::selwyn(&e0.to_vec(), &times.to_vec(), &values.to_vec(), &lengths.to_vec(), points)[..])
+}
+
+// Lag time of P(t) from its steepest tangent (least squares over `window`
+// consecutive rows, at least 2) extrapolated back to the initial P, for
+// coupled or slowly activating systems. Output: [lag, max slope, time of the
+// tangent point], NaN if P never rises. stride is the row width (0 => 6).
+#[wasm_bindgen]
+pub fn lag_time(series: &Float64Array, window: u32, stride: u32) -> Float64Array {
+    Float64Array::from(&summary::lag_time(&series.to_vec(), stride, window)[..])
+}
+
+fn auc_rule_from_code(code: u32) -> Result<summary::AucRule, JsValue> {
+    summary::AucRule::from_code(code).map_err(|e| JsValue::from_str(&e))
+}
+
+// Area under a species or sum ("P", "S+P") of a series over [t_from, t_to],
+// with non-finite bounds meaning the series' start or end. rule_code 0:
+// trapezoid, 1: Simpson. stride is the row width (0 => 6).
+#[wasm_bindgen]
+pub fn series_auc(
+    series: &Float64Array,
+    species: &JsValue,
+    t_from: f64,
+    t_to: f64,
+    rule_code: u32,
+    stride: u32,
+) -> Result<f64, JsValue> {
+    let obs = observable_from_js(species)?;
+    let rule = auc_rule_from_code(rule_code)?;
+    Ok(summary::auc(&series.to_vec(), stride, obs, t_from, t_to, rule))
+}
+
+// series_auc of a fresh `steps`-step run from the given state (which counts
+// as the first row), without copying the series to JS
+#[wasm_bindgen]
+pub fn simulate_auc(
+    e: f64, es: f64, ep: f64, s: f64, p: f64, tiempo: f64,
+    k1: f64, k_minus3: f64, k_minus1: f64, k2: f64, k_minus2: f64, k3: f64,
+    dt: f64,
+    steps: u32,
+    species: &JsValue,
+    t_from: f64,
+    t_to: f64,
+    rule_code: u32,
+) -> Result<f64, JsValue> {
+    check_series(steps.saturating_add(1), 6)?;
+    let obs = observable_from_js(species)?;
+    let rule = auc_rule_from_code(rule_code)?;
+    let k = [k1, k_minus3, k_minus1, k2, k_minus2, k3];
+    let st0 = [e, es, ep, s, p, tiempo];
+    let mut data = st0.to_vec();
+    data.extend(model::run_series(st0, &k, dt, steps));
+    Ok(summary::auc(&data, 0, obs, t_from, t_to, rule))
+}
+
+// Molecules in volume_l litres at c_um µM (rounded); NaN for an invalid volume
+#[wasm_bindgen]
+pub fn concentration_to_count(c_um: f64, volume_l: f64) -> f64 {
+    volume::Volume::litres(volume_l).map_or(f64::NAN, |v| v.to_count(c_um))
+}
+
+// µM of n molecules in volume_l litres; NaN for an invalid volume
+#[wasm_bindgen]
+pub fn count_to_concentration(n: f64, volume_l: f64) -> f64 {
+    volume::Volume::litres(volume_l).map_or(f64::NAN, |v| v.to_conc(n))
+}
+
+// simulate_steps_final with an explicit unit mode and volume (empty on an invalid volume)
+#[wasm_bindgen]
+pub fn simulate_steps_final_mode(
+    e: f64, es: f64, ep: f64, s: f64, p: f64, tiempo: f64,
+    k1: f64, k_minus3: f64, k_minus1: f64, k2: f64, k_minus2: f64, k3: f64,
+    dt: f64,
+    steps: u32,
+    concentration_mode: bool,
+    volume_l: f64,
+) -> Float64Array {
+    let k = [k1, k_minus3, k_minus1, k2, k_minus2, k3];
+    let data = model::run_mode([e, es, ep, s, p, tiempo], k, dt, steps, concentration_mode, volume_l, false);
+    Float64Array::from(&data[..])
+}
+
+// simulate_steps_series with an explicit unit mode and volume (empty on an invalid volume)
+#[wasm_bindgen]
+pub fn simulate_steps_series_mode(
+    e: f64, es: f64, ep: f64, s: f64, p: f64, tiempo: f64,
+    k1: f64, k_minus3: f64, k_minus1: f64, k2: f64, k_minus2: f64, k3: f64,
+    dt: f64,
+    steps: u32,
+    concentration_mode: bool,
+    volume_l: f64,
+) -> Result<Float64Array, JsValue> {
+    check_series(steps, 6)?;
+    let k = [k1, k_minus3, k_minus1, k2, k_minus2, k3];
+    let data = model::run_mode([e, es, ep, s, p, tiempo], k, dt, steps, concentration_mode, volume_l, true);
+    let arr = Float64Array::new_with_length(data.len() as u32);
+    arr.copy_from(&data);
+    Ok(arr)
+}
+
+// Convert [k1,k-3,k-1,k2,k-2,k3,dt] given in time_unit (s, min, h) and
+// conc_unit (M, mM, uM, nM) to the canonical seconds / µM used by the
+// concentration-mode simulators. Errors on unknown units or invalid values.
+#[wasm_bindgen]
+pub fn convert_rate_params(params_in: &Float64Array, time_unit: &str, conc_unit: &str) -> Result<Float64Array, JsValue> {
+    if params_in.length() != 7 {
+        return Err(JsValue::from_str(&format!("expected 7 parameters [k1,k-3,k-1,k2,k-2,k3,dt], got {}", params_in.length())));
+    }
+    let mut params = [0.0f64; 7];
+    params_in.copy_to(&mut params);
+    let units = units::Units::parse(time_unit, conc_unit).map_err(|e| JsValue::from_str(&e))?;
+    let out = units.canonical_params(&params).map_err(|e| JsValue::from_str(&e))?;
+    Ok(Float64Array::from(&out[..]))
+}
+
+// Convert a concentration between units (M, mM, uM, nM)
+#[wasm_bindgen]
+pub fn convert_concentration(value: f64, from: &str, to: &str) -> Result<f64, JsValue> {
+    let f = units::ConcUnit::parse(from).map_err(|e| JsValue::from_str(&e))?;
+    let t = units::ConcUnit::parse(to).map_err(|e| JsValue::from_str(&e))?;
+    Ok(value * f.micromolar() / t.micromolar())
+}
+
+// Convert a time between units (s, min, h)
+#[wasm_bindgen]
+pub fn convert_time(value: f64, from: &str, to: &str) -> Result<f64, JsValue> {
+    let f = units::TimeUnit::parse(from).map_err(|e| JsValue::from_str(&e))?;
+    let t = units::TimeUnit::parse(to).map_err(|e| JsValue::from_str(&e))?;
+    Ok(value * f.seconds() / t.seconds())
+}
+
+// Savitzky–Golay smoothing of an observed trace with dY/dt estimates, for
+// velocity analyses on noisy data. window is the full odd width in samples;
+// times may be non-uniform. Output: [smoothed (n)..., dY/dt (n)...].
+#[wasm_bindgen]
+pub fn savitzky_golay(times: &Float64Array, y: &Float64Array, window: u32, poly_order: u32) -> Float64Array {
+    let t_vec = times.to_vec();
+    let y_vec = y.to_vec();
+    let half = (window.max(3) as usize - 1) / 2;
+    let (mut data, deriv) = smooth::savitzky_golay(&t_vec, &y_vec, half, poly_order as usize);
+    data.extend_from_slice(&deriv);
+    Float64Array::from(&data[..])
+}
+
+// Loss (SSE by default) of the simulated signal against observations. _ns and
+// _np are unused and kept for the argument positions of existing JS callers.
+#[wasm_bindgen]
+pub fn objective_sse(
+    e0: f64,
+    es0: f64,
+    ep0: f64,
+    s0: f64,
+    p0: f64,
+    t0: f64,
+    _ns: f64,
+    _np: f64,
+    k1: f64,
+    k_minus3: f64,
+    k_minus1: f64,
+    k2: f64,
+    k_minus2: f64,
+    k3: f64,
+    dt: f64,
+    times: &Float64Array,
+    y_obs: &Float64Array,
+    species: &JsValue,
+    interp_code: u32, // 0: linear, 1: monotone cubic (PCHIP), 2: exact (simulate onto the times)
+    t_shift: f64, // dead time: observation t compares to model t + t_shift
+    signal_scale: f64, // measured = signal_scale * observable + signal_offset;
+    signal_offset: f64, // non-finite => 1 and 0
+    drift: f64, // baseline drift: drift * (1 - exp(-drift_decay t)) / drift_decay,
+    drift_decay: f64, // linear drift * t at 0; non-finite => no drift
+    loss_code: u32, // 0: sse, 1: Gaussian (sigma profiled out) or 2: Poisson NLL, 3: Huber, 4: soft-L1
+    loss_scale: f64, // residual scale delta of the robust losses (default 1)
+) -> Result<f64, JsValue> {
+    let obs = observable_from_js(species)?;
+    let t_vec = times.to_vec();
+    let y_vec = y_obs.to_vec();
+    Ok(objective::loss(
+        [e0, es0, ep0, s0, p0, t0],
+        &[k1, k_minus3, k_minus1, k2, k_minus2, k3],
+        dt, objective::Readout::new(t_shift, signal_scale, signal_offset).with_drift(drift, drift_decay), &t_vec, &y_vec, obs, Interp::from_code(interp_code),
+        loss_from_code(loss_code)?.with_scale(loss_scale),
+    ))
+}
+
+// sse is the minimized objective: the loss plus any prior term.
+// Output: [k1,k-3,k-1,k2,k-2,k3,dt, sse, iterations, evaluations, spread, reason,
+// restarts, t_shift, E0, ES0, EP0, S0, P0, signal_scale, signal_offset, drift,
+// drift_decay, n_vertices, final simplex as n_vertices rows of the 17
+// parameters [k1,k-3,k-1,k2,k-2,k3,dt,t_shift,E0,ES0,EP0,S0,P0,signal_scale,
+// signal_offset,drift,drift_decay], n_trace, n_trace rows of [objective,
+// 17 parameters] (empty unless traced), n_excluded, excluded observation
+// indices, n_studentized, studentized residuals (empty without outlier
+// screening)]
+fn fit_output(res: &FitResult) -> Float64Array {
+    let v = res.to_vec();
+    let arr = Float64Array::new_with_length(v.len() as u32);
+    arr.copy_from(&v);
+    arr
+}
+
+// JSON report of a fit_nelder_mead output against the same problem: named
+// parameters, standard errors of the masked ones, objective, residuals,
+// convergence details and the model settings (report::fit_report). options
+// are those of the fit (FitOptions; only the problem keys are read).
+#[wasm_bindgen]
+pub fn fit_report_json(
+    fit_output: &Float64Array,
+    e0: f64, es0: f64, ep0: f64, s0: f64, p0: f64, t0: f64,
+    mask: &js_sys::Uint8Array,
+    times: &Float64Array,
+    y_obs: &Float64Array,
+    species: &JsValue,
+    options: &JsValue,
+) -> Result<String, JsValue> {
+    let res = fit::result_from_output(&fit_output.to_vec()).ok_or_else(|| JsValue::from_str("truncated fit output"))?;
+    let problem = problem_from_js([e0, es0, ep0, s0, p0, t0], times, y_obs, species, &FitOptions::from_js(options)?)?;
+    let idx = objective::free_indices(&objective::mask_indices(&mask.to_vec()), &problem.constraints);
+    Ok(report::fit_report(&problem, &res, &idx).to_string())
+}
+
+// The 17 fitted parameters of a fit_report_json document, in the order of
+// the fit_nelder_mead parameter block (usable as params_in)
+#[wasm_bindgen]
+pub fn fit_report_params(report_json: &str) -> Result<Float64Array, JsValue> {
+    let doc = json::parse(report_json).map_err(|e| JsValue::from_str(&e))?;
+    let params = report::params_from_report(&doc).map_err(|e| JsValue::from_str(&e))?;
+    Ok(Float64Array::from(&params[..]))
+}
+
+// Validates a saved session (session::Session) and returns it re-serialized in
+// the current layout; errors name the offending field. Run save files through
+// it both when writing and when loading them.
+#[wasm_bindgen]
+pub fn normalize_session(session_json: &str) -> Result<String, JsValue> {
+    let doc = json::parse(session_json).map_err(|e| JsValue::from_str(&e))?;
+    let s = session::Session::from_json(&doc).map_err(|e| JsValue::from_str(&e))?;
+    Ok(s.to_json().to_string())
+}
+
+// Nelder–Mead fit of params_in (optionally the rest of the 17 output
+// parameters after [k1,k-3,k-1,k2,k-2,k3,dt]; E0..P0 default to e0..p0)
+// over the parameters mask selects (1 => optimize, over the 17). options
+// (FitOptions): the problem keys, max_iter, tol, scale, x_tol (all vertices
+// within x_tol, relative, of the best; <= 0 off), max_evals (never
+// exceeded), progress_every, trace (append the best objective and
+// parameters of every iteration), crn_seed, warm_start (a previous
+// fit_nelder_mead output), max_restarts (fresh simplexes around the best
+// point on convergence or stagnation), adaptive (Gao–Han coefficients for
+// the number of fitted parameters), oriented_restarts (restarts along
+// Kelley's oriented simplex, also on a lack of sufficient decrease),
+// outlier_threshold (refit without points whose |studentized residual|
+// exceeds it; <= 0 off) and outlier_rounds (refits allowed, 0 => 1).
+// progress is called as (iteration, best_sse, params); return true to stop.
+#[wasm_bindgen]
+pub fn fit_nelder_mead(
+    e0: f64, es0: f64, ep0: f64, s0: f64, p0: f64, t0: f64,
+    params_in: &Float64Array,
+    mask: &js_sys::Uint8Array,
+    times: &Float64Array,
+    y_obs: &Float64Array,
+    species: &JsValue,
+    options: &JsValue,
+    progress: Option<js_sys::Function>,
+) -> Result<Float64Array, JsValue> {
+    let options = FitOptions::from_js(options)?;
+    let (problem, params, optimize_idx, opts) = nelder_mead_setup([e0, es0, ep0, s0, p0, t0], params_in, mask, times, y_obs, species, &options)?;
+    let outliers = fit::Outliers { threshold: options.outlier_threshold, max_rounds: options.outlier_rounds };
+    let res = fit::refit_without_outliers(&problem, params, &optimize_idx, &opts, &outliers, js_progress(&progress));
+    Ok(fit_output(&res))
+}
+
+// The problem, start, fitted indices and settings of a fit_nelder_mead call
+fn nelder_mead_setup(
+    init: [f64; 6],
+    params_in: &Float64Array,
+    mask: &js_sys::Uint8Array,
+    times: &Float64Array,
+    y_obs: &Float64Array,
+    species: &JsValue,
+    options: &FitOptions,
+) -> Result<(Problem, objective::Params, Vec<usize>, fit::NelderMead), JsValue> {
+    let problem = problem_from_js(init, times, y_obs, species, options)?;
+    let mut params = objective::params_from_slice(&params_in.to_vec(), &init);
+    let optimize_idx = objective::free_indices(&objective::mask_indices(&mask.to_vec()), &problem.constraints);
+    // Warm start: the previous best replaces the fitted entries of params_in, and
+    // its simplex is reused when the mask still selects as many parameters
+    let warm = options.warm_start.as_deref();
+    if let Some(w) = warm.and_then(fit::params_from_output) {
+        for &i in &optimize_idx { params[i] = w[i]; }
+    }
+    let warm_simplex = warm.and_then(fit::simplex_from_output).map(|vs| {
+        vs.into_iter().map(|v| {
+            let mut p = params;
+            for &i in &optimize_idx { p[i] = v[i]; }
+            p
+        }).collect()
+    });
+    let opts = fit::NelderMead {
+        max_iter: options.max_iter, tol: options.tol, scale: options.scale, progress_every: options.progress_every,
+        warm_simplex, max_restarts: options.max_restarts, x_tol: options.x_tol, max_evals: options.max_evals,
+        trace: options.trace, crn_seed: options.crn_seed, adaptive: options.adaptive, oriented_restarts: options.oriented_restarts,
+    };
+    Ok((problem, params, optimize_idx, opts))
+}
+
+// A fit_nelder_mead fit taken in slices (fit_begin, fit_step, fit_finish), so
+// a worker can report progress and yield between them
+#[wasm_bindgen]
+pub struct FitHandle {
+    inner: fit::NelderMeadRun<'static>,
+}
+
+// Start a resumable fit: the arguments of fit_nelder_mead without the
+// progress callback (the caller sees progress after every fit_step);
+// progress_every and the outlier keys are ignored. Evaluates the starting
+// simplex.
+#[wasm_bindgen]
+pub fn fit_begin(
+    e0: f64, es0: f64, ep0: f64, s0: f64, p0: f64, t0: f64,
+    params_in: &Float64Array,
+    mask: &js_sys::Uint8Array,
+    times: &Float64Array,
+    y_obs: &Float64Array,
+    species: &JsValue,
+    options: &JsValue,
+) -> Result<FitHandle, JsValue> {
+    let options = FitOptions::from_js(options)?;
+    let (problem, params, optimize_idx, opts) = nelder_mead_setup([e0, es0, ep0, s0, p0, t0], params_in, mask, times, y_obs, species, &options)?;
+    Ok(FitHandle { inner: fit::NelderMeadRun::new(std::borrow::Cow::Owned(problem), params, &optimize_idx, &opts) })
+}
+
+// Up to n_iters further iterations of a resumable fit. Output: [done (1 once
+// the fit has stopped), iterations, evaluations, best objective, best params
+// (17)].
+#[wasm_bindgen]
+pub fn fit_step(handle: &mut FitHandle, n_iters: u32) -> Float64Array {
+    let done = handle.inner.step(n_iters, |_, _, _| false);
+    let (best, params) = handle.inner.best();
+    let mut out = vec![if done { 1.0 } else { 0.0 }, handle.inner.iterations() as f64, handle.inner.evaluations() as f64, best];
+    out.extend_from_slice(&params);
+    Float64Array::from(&out[..])
+}
+
+// Result of a resumable fit in the fit_nelder_mead layout; a fit finished
+// before it stopped reports reason max_iter. Consumes the handle.
+#[wasm_bindgen]
+pub fn fit_finish(handle: FitHandle) -> Float64Array {
+    fit_output(&handle.inner.finish())
+}
+
+// Global-then-local fit of the same problem (fit::fit_auto): Latin hypercube
+// starts over [lower, upper] (a NaN pair => a decade either side of the
+// start), then Nelder–Mead from the n_polish best (0 => 1). options: the
+// problem keys, n_starts (0 => 10 per fitted parameter), lower, upper and,
+// per polishing run, max_iter, tol, scale, x_tol, adaptive, progress_every
+// and trace; max_evals caps the polishing evaluations in total, on top of
+// the starts. Output as fit_nelder_mead; with trace, the running best over
+// the starts comes before the polishing iterations.
+#[wasm_bindgen]
+pub fn fit_auto(
+    e0: f64, es0: f64, ep0: f64, s0: f64, p0: f64, t0: f64,
+    params_in: &Float64Array, // as in fit_nelder_mead
+    mask: &js_sys::Uint8Array,
+    times: &Float64Array,
+    y_obs: &Float64Array,
+    species: &JsValue,
+    options: &JsValue,
+    progress: Option<js_sys::Function>, // as in fit_nelder_mead, per polishing run
+) -> Result<Float64Array, JsValue> {
+    let options = FitOptions::from_js(options)?;
+    let problem = problem_from_js([e0, es0, ep0, s0, p0, t0], times, y_obs, species, &options)?;
+    let params = objective::params_from_slice(&params_in.to_vec(), &problem.init);
+    let optimize_idx = objective::free_indices(&objective::mask_indices(&mask.to_vec()), &problem.constraints);
+    let polish = fit::NelderMead {
+        max_iter: options.max_iter, tol: options.tol, scale: options.scale, progress_every: options.progress_every,
+        warm_simplex: None, max_restarts: 0, x_tol: options.x_tol, max_evals: options.max_evals, trace: options.trace,
+        crn_seed: None, adaptive: options.adaptive, oriented_restarts: false,
+    };
+    let opts = fit::AutoFit { n_starts: options.n_starts, n_polish: options.n_polish, polish };
+    let res = fit::fit_auto(&problem, params, &optimize_idx, &options.bounds(&optimize_idx), &opts, js_progress(&progress));
+    Ok(fit_output(&res))
+}
+
+// Surrogate-assisted fit of the same problem (surrogate::surrogate_fit): a
+// quadratic model of the noisy objective in a trust region proposes each
+// step, so fits need far fewer forward simulations. options: the problem
+// keys, max_iter, max_evals (forward simulations including the initial
+// design), scale (initial trust radius, relative to the starting values),
+// x_tol (stop when the trust radius falls below it, relative; <= 0 =>
+// 1e-4), progress_every and trace. Output as fit_nelder_mead (spread is the
+// surrogate's residual rms, the simplex the final point).
+#[wasm_bindgen]
+pub fn fit_surrogate(
+    e0: f64, es0: f64, ep0: f64, s0: f64, p0: f64, t0: f64,
+    params_in: &Float64Array, // as in fit_nelder_mead
+    mask: &js_sys::Uint8Array,
+    times: &Float64Array,
+    y_obs: &Float64Array,
+    species: &JsValue,
+    options: &JsValue,
+    progress: Option<js_sys::Function>, // called as (iteration, center objective, params); return true to stop
+) -> Result<Float64Array, JsValue> {
+    let options = FitOptions::from_js(options)?;
+    let problem = problem_from_js([e0, es0, ep0, s0, p0, t0], times, y_obs, species, &options)?;
+    let params = objective::params_from_slice(&params_in.to_vec(), &problem.init);
+    let optimize_idx = objective::free_indices(&objective::mask_indices(&mask.to_vec()), &problem.constraints);
+    let opts = surrogate::Surrogate {
+        max_iter: options.max_iter, max_evals: options.max_evals, scale: options.scale, x_tol: options.x_tol,
+        progress_every: options.progress_every, trace: options.trace,
+    };
+    let res = surrogate::surrogate_fit(&problem, params, &optimize_idx, &opts, js_progress(&progress));
+    Ok(fit_output(&res))
+}
+
+// Powell's conjugate-direction fit of the same problem (powell::powell_fit),
+// derivative-free like fit_nelder_mead and on the same stochastic
+// objective, with a line search along each direction. options: the problem
+// keys, max_iter, max_evals (the fit stops before a line search that could
+// exceed it), scale (first trial step of each line search, relative to the
+// starting values), tol (relative decrease per iteration below which the
+// fit stops), x_tol (relative move per iteration; <= 0 => 1e-6),
+// progress_every, trace and crn_seed. Output as fit_nelder_mead (spread is
+// the last iteration's decrease, the simplex the final point).
+#[wasm_bindgen]
+pub fn fit_powell(
+    e0: f64, es0: f64, ep0: f64, s0: f64, p0: f64, t0: f64,
+    params_in: &Float64Array, // as in fit_nelder_mead
+    mask: &js_sys::Uint8Array,
+    times: &Float64Array,
+    y_obs: &Float64Array,
+    species: &JsValue,
+    options: &JsValue,
+    progress: Option<js_sys::Function>, // called as (iteration, objective, params); return true to stop
+) -> Result<Float64Array, JsValue> {
+    let options = FitOptions::from_js(options)?;
+    let problem = problem_from_js([e0, es0, ep0, s0, p0, t0], times, y_obs, species, &options)?;
+    let params = objective::params_from_slice(&params_in.to_vec(), &problem.init);
+    let optimize_idx = objective::free_indices(&objective::mask_indices(&mask.to_vec()), &problem.constraints);
+    let opts = powell::Powell {
+        max_iter: options.max_iter, max_evals: options.max_evals, scale: options.scale, tol: options.tol, x_tol: options.x_tol,
+        progress_every: options.progress_every, trace: options.trace, crn_seed: options.crn_seed,
+    };
+    let res = powell::powell_fit(&problem, params, &optimize_idx, &opts, js_progress(&progress));
+    Ok(fit_output(&res))
+}
+
+// Pareto front of one parameter vector fitted to several datasets
+// (pareto::pareto_fit, NSGA-II): the datasets' observations run back to back
+// in times and y_obs, lengths[d] points each, with species[d] naming each
+// one's observable. options: the problem keys, lower and upper (search
+// range per parameter; NaN => as in fit_auto), population (0 => 20 per
+// fitted parameter) and generations. progress is called as (generation,
+// front size); return true to stop. Output: [n_points, n_datasets], then
+// per point the objective on each dataset followed by its 17 parameters,
+// sorted by the first dataset's objective.
+#[wasm_bindgen]
+pub fn fit_pareto(
+    e0: f64, es0: f64, ep0: f64, s0: f64, p0: f64, t0: f64,
+    params_in: &Float64Array, // as in fit_nelder_mead
+    mask: &js_sys::Uint8Array,
+    times: &Float64Array,
+    y_obs: &Float64Array,
+    lengths: &js_sys::Uint32Array,
+    species: &js_sys::Array,
+    options: &JsValue,
+    progress: Option<js_sys::Function>,
+) -> Result<Float64Array, JsValue> {
+    let options = FitOptions::from_js(options)?;
+    let lengths = lengths.to_vec();
+    if lengths.is_empty() || species.length() as usize != lengths.len() {
+        return Err(JsValue::from_str("fit_pareto needs one species per dataset length"));
+    }
+    let total: u64 = lengths.iter().map(|&l| l as u64).sum();
+    if total != times.length() as u64 || total != y_obs.length() as u64 {
+        return Err(JsValue::from_str("dataset lengths must add up to the times and y_obs lengths"));
+    }
+    let mut problems = Vec::with_capacity(lengths.len());
+    let mut at = 0;
+    for (d, &len) in lengths.iter().enumerate() {
+        let (t, y) = (times.subarray(at, at + len), y_obs.subarray(at, at + len));
+        problems.push(problem_from_js([e0, es0, ep0, s0, p0, t0], &t, &y, &species.get(d as u32), &options)?);
+        at += len;
+    }
+    let params = objective::params_from_slice(&params_in.to_vec(), &problems[0].init);
+    let optimize_idx = objective::free_indices(&objective::mask_indices(&mask.to_vec()), &problems[0].constraints);
+    let opts = pareto::Pareto { population: options.population, generations: options.generations };
+    let report = |generation: u32, front: &[pareto::ParetoPoint]| -> bool {
+        let Some(cb) = progress.as_ref() else { return false };
+        let ret = cb.call2(&JsValue::NULL, &JsValue::from(generation), &JsValue::from(front.len() as u32));
+        matches!(ret, Ok(v) if v.is_truthy())
+    };
+    let front = pareto::pareto_fit(&problems, params, &optimize_idx, &options.bounds(&optimize_idx), &opts, report);
+    let mut out = vec![front.len() as f64, problems.len() as f64];
+    for point in &front {
+        out.extend_from_slice(&point.objectives);
+        out.extend_from_slice(&point.params);
+    }
+    Ok(Float64Array::from(&out[..]))
+}
+
+// Gradient-based fit of the deterministic model (lbfgs::lbfgs_fit): L-BFGS-B
+// on the ODE objective with the sensitivity gradients, inside box bounds.
+// dt only sets the integration step and is never fitted, and the ODE
+// objective compares at the observation times directly (interp_code is
+// unused). options: the problem keys, max_iter, max_evals (objective plus
+// gradient evaluations), memory (correction pairs kept; 0 => 7), tol
+// (relative decrease per iteration; <= 0 off), x_tol (relative step; <= 0
+// off), lower (NaN => only the parameter's floor), upper (NaN =>
+// unbounded), progress_every and trace. Output as fit_nelder_mead (spread
+// is the final projected gradient, the simplex the final point).
+#[wasm_bindgen]
+pub fn fit_lbfgs(
+    e0: f64, es0: f64, ep0: f64, s0: f64, p0: f64, t0: f64,
+    params_in: &Float64Array, // as in fit_nelder_mead
+    mask: &js_sys::Uint8Array,
+    times: &Float64Array,
+    y_obs: &Float64Array,
+    species: &JsValue,
+    options: &JsValue,
+    progress: Option<js_sys::Function>, // called as (iteration, objective, params); return true to stop
+) -> Result<Float64Array, JsValue> {
+    let options = FitOptions::from_js(options)?;
+    let problem = problem_from_js([e0, es0, ep0, s0, p0, t0], times, y_obs, species, &options)?;
+    let params = objective::params_from_slice(&params_in.to_vec(), &problem.init);
+    let optimize_idx = objective::free_indices(&objective::mask_indices(&mask.to_vec()), &problem.constraints);
+    let opts = lbfgs::Lbfgs {
+        max_iter: options.max_iter, max_evals: options.max_evals, memory: options.memory, tol: options.tol, x_tol: options.x_tol,
+        progress_every: options.progress_every, trace: options.trace,
+    };
+    let res = lbfgs::lbfgs_fit(&problem, params, &optimize_idx, &options.bounds(&optimize_idx), &opts, js_progress(&progress));
+    Ok(fit_output(&res))
+}
+
+// [AIC, AICc, BIC] from a negative log-likelihood (a fit's objective under
+// loss 1 or 2) with n_params fitted parameters over n_obs points
+#[wasm_bindgen]
+pub fn information_criteria(nll: f64, n_params: u32, n_obs: u32) -> Float64Array {
+    Float64Array::from(&objective::information_criteria(nll, n_params as usize, n_obs as usize)[..])
+}
+
+// Random-walk Metropolis–Hastings over log(k) for the masked parameters
+// (1 => sample, over the 17 parameters of the output; params_in as in
+// fit_nelder_mead), under Gaussian noise: log L = -SSE / (2 sigma^2) (or
+// -loss for a likelihood loss), flat prior on log(k) unless log-normal
+// priors are given. options: the problem keys, sigma (noise sd; <= 0
+// estimates it from the starting SSE), step (proposal sd in log space; <= 0
+// => 0.05), n_samples, burn_in, thin and n_chains. Each chain starts at
+// params_in. Output: [acceptance rate per chain] followed by n_chains *
+// n_samples rows of [k1,k-3,k-1,k2,k-2,k3,dt, loss, t_shift, E0, ES0, EP0,
+// S0, P0, signal_scale, signal_offset, drift, drift_decay], chain-major.
+#[wasm_bindgen]
+pub fn mcmc_sample(
+    e0: f64, es0: f64, ep0: f64, s0: f64, p0: f64, t0: f64,
+    params_in: &Float64Array,
+    mask: &js_sys::Uint8Array,
+    times: &Float64Array,
+    y_obs: &Float64Array,
+    species: &JsValue,
+    options: &JsValue,
+) -> Result<Float64Array, JsValue> {
+    let options = FitOptions::from_js(options)?;
+    let problem = problem_from_js([e0, es0, ep0, s0, p0, t0], times, y_obs, species, &options)?;
+    let params = objective::params_from_slice(&params_in.to_vec(), &problem.init);
+    let opts = fit::Mcmc {
+        sigma: options.sigma, step: options.step, n_samples: options.n_samples, burn_in: options.burn_in,
+        thin: options.thin, n_chains: options.n_chains,
+    };
+    let sample_idx = objective::free_indices(&objective::mask_indices(&mask.to_vec()), &problem.constraints);
+    let (mut data, rows) = fit::mcmc(&problem, params, &sample_idx, &opts);
+    data.extend_from_slice(&rows);
+    let arr = Float64Array::new_with_length(data.len() as u32);
+    arr.copy_from(&data);
+    Ok(arr)
+}
+
+// Local sensitivities dY/dk of the deterministic model's observable at each
+// time, exact by dual numbers carried through the RK4 steps (no step size
+// to tune). relative => scaled to d ln Y / d ln k (0 where Y is 0).
+// Output: n_times rows of [dY/dk1, dY/dk-3, dY/dk-1, dY/dk2, dY/dk-2, dY/dk3].
+#[wasm_bindgen]
+pub fn sensitivities(
+    e0: f64, es0: f64, ep0: f64, s0: f64, p0: f64, t0: f64,
+    params_in: &Float64Array, // [k1,k-3,k-1,k2,k-2,k3,dt]
+    times: &Float64Array,
+    species: &JsValue,
+    relative: bool,
+) -> Result<Float64Array, JsValue> {
+    let obs = observable_from_js(species)?;
+    let (k, dt) = split_params(params_in);
+    let data = design::sensitivities(&[e0, es0, ep0, s0, p0], t0, &k, dt, &times.to_vec(), obs, relative);
+    let arr = Float64Array::new_with_length(data.len() as u32);
+    arr.copy_from(&data);
+    Ok(arr)
+}
+
+// Fisher information matrix of the deterministic model for a sampling schedule.
+// Output: m x m FIM (row-major, m = masked rate constants) followed by log det(FIM).
+#[wasm_bindgen]
+pub fn fisher_information(
+    e0: f64, es0: f64, ep0: f64, s0: f64, p0: f64, t0: f64,
+    params_in: &Float64Array, // [k1,k-3,k-1,k2,k-2,k3,dt]
+    mask: &js_sys::Uint8Array, // 1 => include, first 6 entries
+    times: &Float64Array,
+    species: &JsValue,
+    sigma: f64,
+    log_params: bool,
+) -> Result<Float64Array, JsValue> {
+    let obs = observable_from_js(species)?;
+    let (k, dt) = split_params(params_in);
+    let idx = objective::rate_mask_indices(&mask.to_vec());
+    let data = design::fisher_information(
+        &[e0, es0, ep0, s0, p0], t0, &k, dt, &times.to_vec(), obs, &idx, sigma, log_params,
+    );
+    Ok(Float64Array::from(&data[..]))
+}
+
+// D-optimality score log det(FIM) for each candidate schedule. Schedules are
+// concatenated in `schedules` with their lengths in `lengths`; higher is better.
+#[wasm_bindgen]
+pub fn d_optimal_scores(
+    e0: f64, es0: f64, ep0: f64, s0: f64, p0: f64, t0: f64,
+    params_in: &Float64Array, // [k1,k-3,k-1,k2,k-2,k3,dt]
+    mask: &js_sys::Uint8Array, // 1 => include, first 6 entries
+    schedules: &Float64Array,
+    lengths: &js_sys::Uint32Array,
+    species: &JsValue,
+    sigma: f64,
+    log_params: bool,
+) -> Result<Float64Array, JsValue> {
+    let obs = observable_from_js(species)?;
+    let (k, dt) = split_params(params_in);
+    let idx = objective::rate_mask_indices(&mask.to_vec());
+    let scores = design::d_optimal_scores(
+        &[e0, es0, ep0, s0, p0], t0, &k, dt, &schedules.to_vec(), &lengths.to_vec(),
+        obs, &idx, sigma, log_params,
+    );
+    Ok(Float64Array::from(&scores[..]))
+}
+
+// Parameter correlations from the asymptotic covariance sigma^2 (J^T J)^-1 of
+// the deterministic model at params_in, with sigma^2 = SSE / (n - m) from the
+// observations. Pairs with |r| above threshold (default 0.95) are flagged.
+// Output: [m x m correlation][m standard errors][n_flagged][i, j, r] * n_flagged,
+// with i, j indexing [k1,k-3,k-1,k2,k-2,k3]. Empty if J^T J is singular.
+#[wasm_bindgen]
+pub fn parameter_correlations(
+    e0: f64, es0: f64, ep0: f64, s0: f64, p0: f64, t0: f64,
+    params_in: &Float64Array, // [k1,k-3,k-1,k2,k-2,k3,dt]
+    mask: &js_sys::Uint8Array, // 1 => fitted, first 6 entries
+    times: &Float64Array,
+    y_obs: &Float64Array,
+    species: &JsValue,
+    threshold: f64,
+) -> Result<Float64Array, JsValue> {
+    let obs = observable_from_js(species)?;
+    let (k, dt) = split_params(params_in);
+    let idx = objective::rate_mask_indices(&mask.to_vec());
+    let data = design::parameter_correlations(
+        &[e0, es0, ep0, s0, p0], t0, &k, dt, &times.to_vec(), &y_obs.to_vec(), obs, &idx, threshold,
+    );
+    Ok(Float64Array::from(&data[..]))
+}
+
+// Sobol first-order (S) and total-effect (ST) indices of an observable at
+// t0 + steps*dt over per-rate-constant ranges. Output from either the
+// deterministic model or a single stochastic run per sample.
+// Output: [S_k1, ST_k1, S_k-3, ST_k-3, ..., S_k3, ST_k3].
+#[wasm_bindgen]
+pub fn sobol_indices(
+    e0: f64, es0: f64, ep0: f64, s0: f64, p0: f64, t0: f64,
+    lower: &Float64Array, // [k1,k-3,k-1,k2,k-2,k3]
+    upper: &Float64Array, // upper <= lower holds that constant fixed
+    dt: f64,
+    steps: u32,
+    species: &JsValue,
+    n_base: u32,
+    log_scale: bool,
+    stochastic: bool,
+) -> Result<Float64Array, JsValue> {
+    let obs = observable_from_js(species)?;
+    let data = design::sobol_indices(
+        &[e0, es0, ep0, s0, p0], t0, &lower.to_vec(), &upper.to_vec(), dt, steps,
+        obs, n_base, log_scale, stochastic,
+    );
+    Ok(Float64Array::from(&data[..]))
+}
+
+// Times the simulation and sampler hot paths inside the module.
+// params: [E, ES, EP, S, P, t0, k1, k-3, k-1, k2, k-2, k3, dt]; the ensemble
+// is `replicates` runs of `steps` steps from that state.
+// Output: [total_ms, ms_per_replicate, ns_per_step, steps_per_second,
+// ns_uniform, ns_normal, ns_binomial_bernoulli, ns_binomial_poisson,
+// ns_binomial_inversion, ns_binomial_btpe]. The clock is Date.now, so
+// ensembles much shorter than a millisecond read as 0.
+#[wasm_bindgen]
+pub fn benchmark(params: &Float64Array, steps: u32, replicates: u32) -> Float64Array {
+    let mut p = [0.0f64; 13];
+    let n = (params.length() as usize).min(13);
+    params.subarray(0, n as u32).copy_to(&mut p[..n]);
+    let mut st = [0.0f64; 6];
+    st.copy_from_slice(&p[..6]);
+    let mut k = [0.0f64; 6];
+    k.copy_from_slice(&p[6..12]);
+    let data = bench::benchmark(st, &k, p[12], steps, replicates).to_vec();
+    Float64Array::from(&data[..])
+}
+
+// JS values only exist on wasm32; run with wasm-pack test --node
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    const K: [f64; 6] = [1e-3, 0.0, 0.1, 1.0, 0.0, 1.0];
+
+    // f under a 100-step cap and a 4 KiB (512 value) output cap
+    fn limited<T>(f: impl FnOnce() -> T) -> T {
+        set_limits(100.0, 4096.0);
+        let out = f();
+        set_limits(f64::NAN, f64::NAN);
+        out
+    }
+
+    fn is_range_error<T>(r: Result<T, JsValue>) -> bool {
+        r.err().is_some_and(|e| e.is_instance_of::<js_sys::RangeError>())
+    }
+
+    #[wasm_bindgen_test]
+    fn final_state_exports_enforce_the_step_cap() {
+        let [k1, km3, km1, k2, km2, k3] = K;
+        limited(|| {
+            assert!(simulate_steps_final(10.0, 0.0, 0.0, 100.0, 0.0, 0.0, 0.0, 0.0, k1, km3, km1, k2, km2, k3, 0.1, 50).is_ok());
+            assert!(is_range_error(simulate_steps_final(10.0, 0.0, 0.0, 100.0, 0.0, 0.0, 0.0, 0.0, k1, km3, km1, k2, km2, k3, 0.1, 101)));
+            assert!(is_range_error(simulate_steps_final_adaptive(10.0, 0.0, 0.0, 100.0, 0.0, 0.0, k1, km3, km1, k2, km2, k3, 0.1, 101, 0.1)));
+            assert!(is_range_error(simulate_steps_final_counts(10.0, 0.0, 0.0, 100.0, 0.0, 0.0, k1, km3, km1, k2, km2, k3, 0.1, 101)));
+            let out = Float64Array::new_with_length(6);
+            assert!(is_range_error(simulate_steps_final_into(&out, 10.0, 0.0, 0.0, 100.0, 0.0, 0.0, k1, km3, km1, k2, km2, k3, 0.1, 101)));
+        });
+    }
+
+    #[wasm_bindgen_test]
+    fn series_exports_enforce_the_output_cap() {
+        let [k1, km3, km1, k2, km2, k3] = K;
+        limited(|| {
+            // 80 rows of 6 fit in 512 values, 90 do not
+            assert!(simulate_steps_series(10.0, 0.0, 0.0, 100.0, 0.0, 0.0, 0.0, 0.0, k1, km3, km1, k2, km2, k3, 0.1, 80).is_ok());
+            assert!(is_range_error(simulate_steps_series(10.0, 0.0, 0.0, 100.0, 0.0, 0.0, 0.0, 0.0, k1, km3, km1, k2, km2, k3, 0.1, 90)));
+            assert!(is_range_error(simulate_steps_series_adaptive(10.0, 0.0, 0.0, 100.0, 0.0, 0.0, k1, km3, km1, k2, km2, k3, 0.1, 90, 0.1)));
+            assert!(is_range_error(simulate_auc(10.0, 0.0, 0.0, 100.0, 0.0, 0.0, k1, km3, km1, k2, km2, k3, 0.1, 90, &"P".into(), f64::NAN, f64::NAN, 0)));
+        });
+    }
+
+    #[wasm_bindgen_test]
+    fn streams_check_the_run_and_each_chunk() {
+        let [k1, km3, km1, k2, km2, k3] = K;
+        limited(|| {
+            assert!(is_range_error(start_series(10.0, 0.0, 0.0, 100.0, 0.0, 0.0, k1, km3, km1, k2, km2, k3, 0.1, 101)));
+            let mut stream = start_series(10.0, 0.0, 0.0, 100.0, 0.0, 0.0, k1, km3, km1, k2, km2, k3, 0.1, 100).unwrap();
+            assert!(is_range_error(stream.next_chunk(90)));
+            assert_eq!(stream.next_chunk(40).unwrap().length(), 240);
+            assert_eq!(stream.remaining(), 60);
+        });
+    }
+
+    #[wasm_bindgen_test]
+    fn replicate_exports_enforce_the_step_cap() {
+        let [k1, km3, km1, k2, km2, k3] = K;
+        limited(|| {
+            let fractions = Float64Array::from(&[0.5][..]);
+            assert!(is_range_error(first_passage_times(10.0, 0.0, 0.0, 100.0, 0.0, 0.0, k1, km3, km1, k2, km2, k3, 0.1, 101, &"S".into(), 50.0, true, 4)));
+            assert!(is_range_error(conversion_time_stats(10.0, 0.0, 0.0, 100.0, 0.0, 0.0, k1, km3, km1, k2, km2, k3, 0.1, 101, &fractions, 4)));
+            assert!(conversion_time_stats(10.0, 0.0, 0.0, 100.0, 0.0, 0.0, k1, km3, km1, k2, km2, k3, 0.1, 80, &fractions, 4).is_ok());
+        });
+    }
+
+    #[wasm_bindgen_test]
+    fn buffer_exports_match_the_allocating_ones() {
+        let [k1, km3, km1, k2, km2, k3] = K;
+        crate::rng::seed_rng(9);
+        let series = simulate_steps_series(10.0, 0.0, 0.0, 100.0, 0.0, 0.0, 0.0, 0.0, k1, km3, km1, k2, km2, k3, 0.1, 20).unwrap().to_vec();
+        // The buffer holds 15 rows: only those are run and written
+        let out = Float64Array::new_with_length(6 * 15 + 2);
+        out.fill(-1.0, 0, out.length());
+        crate::rng::seed_rng(9);
+        assert_eq!(simulate_steps_series_into(&out, 10.0, 0.0, 0.0, 100.0, 0.0, 0.0, k1, km3, km1, k2, km2, k3, 0.1, 20).unwrap(), 15);
+        let written = out.to_vec();
+        assert_eq!(written[..90], series[..90]);
+        assert_eq!(written[90..], [-1.0, -1.0]);
+        // Without a ceiling the adaptive export runs the same fixed steps
+        crate::rng::seed_rng(9);
+        assert_eq!(simulate_steps_series_adaptive(10.0, 0.0, 0.0, 100.0, 0.0, 0.0, k1, km3, km1, k2, km2, k3, 0.1, 20, f64::NAN).unwrap().to_vec(), series);
+        // Longer runs are written block by block
+        crate::rng::seed_rng(9);
+        let long = simulate_steps_series(10.0, 0.0, 0.0, 100.0, 0.0, 0.0, 0.0, 0.0, k1, km3, km1, k2, km2, k3, 0.1, 1200).unwrap().to_vec();
+        let out = Float64Array::new_with_length(6 * 1200);
+        crate::rng::seed_rng(9);
+        assert_eq!(simulate_steps_series_into(&out, 10.0, 0.0, 0.0, 100.0, 0.0, 0.0, k1, km3, km1, k2, km2, k3, 0.1, 1200).unwrap(), 1200);
+        assert_eq!(out.to_vec(), long);
+        crate::rng::seed_rng(9);
+        let len = simulate_steps_series_in_memory(10.0, 0.0, 0.0, 100.0, 0.0, 0.0, k1, km3, km1, k2, km2, k3, 0.1, 20).unwrap() as usize;
+        assert_eq!(SERIES_BUF.with(|b| b.borrow()[..len].to_vec()), series);
+        let last = Float64Array::new_with_length(6);
+        crate::rng::seed_rng(9);
+        assert_eq!(simulate_steps_final_into(&last, 10.0, 0.0, 0.0, 100.0, 0.0, 0.0, k1, km3, km1, k2, km2, k3, 0.1, 20).unwrap(), 6);
+        assert_eq!(last.to_vec(), series[series.len() - 6..]);
+    }
+
+    #[wasm_bindgen_test]
+    fn rate_conversion_needs_all_seven_parameters() {
+        assert!(convert_rate_params(&Float64Array::from(&[1.0; 6][..]), "s", "uM").is_err());
+        let out = convert_rate_params(&Float64Array::from(&[1.0; 7][..]), "s", "mM").unwrap().to_vec();
+        assert_eq!(out, [1e-3, 1e-3, 1.0, 1.0, 1.0, 1.0, 1.0]);
+    }
+}